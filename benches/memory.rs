@@ -0,0 +1,70 @@
+/*!
+  Benchmarks for reading memory, to check how much the way reads are done matters.
+
+  The read benchmarks read this process's own memory, so they don't need the game. To also time
+  scanning the hierarchy of a running game, set `WALKOFLIFE_BENCH_PID` to its PID.
+  */
+
+use std::env;
+use criterion::{criterion_group,criterion_main,Criterion,BenchmarkId,Throughput,black_box};
+use nix::{libc::pid_t,unistd::{Pid,getpid}};
+use walkoflife::{memory::{read_prims,read_into},process::Rayman2Process};
+
+fn read_throughput(c: &mut Criterion) {
+    let pid = getpid();
+    let mut group = c.benchmark_group("read_prims");
+    for &n_bytes in [4usize, 64, 4096, 65536].iter() {
+        let buf = vec![0x5Au8; n_bytes];
+        group.throughput(Throughput::Bytes(n_bytes as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n_bytes), &buf, |b, buf| {
+            b.iter(|| read_prims::<u8>(pid, black_box(buf.as_ptr() as usize), buf.len()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn single_vs_separate_reads(c: &mut Criterion) {
+    let pid = getpid();
+    let values: Vec<u32> = (0..64).collect();
+    let address = values.as_ptr() as usize;
+
+    let mut group = c.benchmark_group("64_u32s");
+    group.bench_function("one_read", |b| {
+        b.iter(|| read_prims::<u32>(pid, black_box(address), values.len()).unwrap())
+    });
+    group.bench_function("one_read_into", |b| {
+        let mut buf = [0u32; 64];
+        b.iter(|| read_into(pid, black_box(address), &mut buf).unwrap())
+    });
+    group.bench_function("separate_reads", |b| {
+        b.iter(|| {
+            (0..values.len())
+                .map(|i| read_prims::<u32>(pid, black_box(address + 4 * i), 1).unwrap()[0])
+                .collect::<Vec<u32>>()
+        })
+    });
+    group.finish();
+}
+
+fn hierarchy_scan(c: &mut Criterion) {
+    let pid = match env::var("WALKOFLIFE_BENCH_PID").ok().and_then(|pid| pid.parse::<pid_t>().ok()) {
+        Some(pid) => Pid::from_raw(pid),
+        None => {return;},
+    };
+    let r2 = Rayman2Process::from_pid(pid);
+
+    c.bench_function("object_types", |b| {
+        b.iter(|| {
+            r2.invalidate_cache();
+            r2.object_types().unwrap()
+        })
+    });
+    // Read the object types once, so that only the hierarchy itself is timed.
+    r2.object_types().unwrap();
+    c.bench_function("active_super_objects", |b| {
+        b.iter(|| r2.active_super_objects().unwrap())
+    });
+}
+
+criterion_group!(benches, read_throughput, single_vs_separate_reads, hierarchy_scan);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+// With the `capi` feature, generate the C header for the `ffi` module with cbindgen. Otherwise,
+// there's nothing to do.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+// The header is always written to OUT_DIR, so that building never changes the source tree (and
+// `cargo package` still works). The committed copy in include/ is only rewritten when
+// WALKOFLIFE_UPDATE_HEADER is set - a test checks that it matches the one in OUT_DIR.
+#[cfg(feature = "capi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=WALKOFLIFE_UPDATE_HEADER");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = match cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)) {
+        Ok(config) => config,
+        Err(err) => panic!("Couldn't read cbindgen.toml: {}", err),
+    };
+    // Only src/ffi.rs is parsed, so that nothing else in the crate ends up in the header.
+    let bindings = match cbindgen::Builder::new().with_config(config).with_src(format!("{}/src/ffi.rs", crate_dir)).generate() {
+        Ok(bindings) => bindings,
+        Err(err) => panic!("Couldn't generate walkoflife.h: {}", err),
+    };
+    bindings.write_to_file(format!("{}/walkoflife.h", out_dir));
+    if std::env::var_os("WALKOFLIFE_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(format!("{}/include/walkoflife.h", crate_dir));
+    }
+}
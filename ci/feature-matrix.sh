@@ -0,0 +1,14 @@
+#!/bin/sh
+# Check that the library builds and passes clippy with no features, with each feature on its own,
+# and with everything, so that nothing in the core accidentally depends on an optional module.
+set -e
+cd "$(dirname "$0")/.."
+
+FEATURES=$(sed -n '/^\[features\]/,/^\[/p' Cargo.toml | grep -o '^[a-z-]* =' | cut -d' ' -f1 | grep -v '^default$')
+
+cargo clippy --lib --no-default-features -- -D warnings
+for feature in $FEATURES; do
+    echo "== $feature"
+    cargo clippy --lib --no-default-features --features "$feature" -- -D warnings
+done
+cargo clippy --workspace --all-targets --all-features -- -D warnings
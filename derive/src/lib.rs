@@ -0,0 +1,117 @@
+/*!
+  Derive macros for [walkoflife](../walkoflife/index.html). These are re-exported from there, so
+  this crate shouldn't need to be used directly.
+  */
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote,format_ident};
+use syn::{parse_macro_input,Data,DeriveInput,Error,Fields,LitInt};
+
+/// Derive `EngineField` and `EngineStruct` for a struct describing a structure in the game's
+/// memory, along with a `<Name>Fields` trait giving pointers to each field from a
+/// `RemotePtr<Name>`.
+///
+/// ## Requirements:
+/// * The struct must have named fields and no generic parameters.
+/// * Every field needs an `#[offset(...)]` attribute giving its byte offset in the structure.
+/// * Every field's type needs to implement `EngineField`.
+#[proc_macro_derive(EngineStruct, attributes(offset))]
+pub fn derive_engine_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "EngineStruct can't be derived for generic structs"));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {return Err(Error::new(Span::call_site(), "EngineStruct needs a struct with named fields"));},
+        },
+        _ => {return Err(Error::new(Span::call_site(), "EngineStruct can only be derived for structs"));},
+    };
+
+    let mut idents = Vec::new();
+    let mut types = Vec::new();
+    let mut offsets = Vec::new();
+    for field in fields.iter() {
+        let offset = match field.attrs.iter().find(|attr| attr.path().is_ident("offset")) {
+            Some(attr) => attr.parse_args::<LitInt>()?.base10_parse::<usize>()?,
+            None => {return Err(Error::new_spanned(field, "Field needs an #[offset(...)] attribute"));},
+        };
+        idents.push(field.ident.clone().unwrap());
+        types.push(field.ty.clone());
+        offsets.push(offset);
+    }
+
+    let fields_trait = format_ident!("{}Fields", name);
+    let fields_doc = format!("Pointers to the fields of a [`{}`](struct.{}.html) in another process.", name, name);
+
+    Ok(quote! {
+        impl ::walkoflife::layout::EngineField for #name {
+            const SIZE: usize = {
+                let mut size = 0;
+                #(
+                    let end = #offsets + <#types as ::walkoflife::layout::EngineField>::SIZE;
+                    if end > size {
+                        size = end;
+                    }
+                )*
+                size
+            };
+
+            fn from_bytes(bytes: &[u8]) -> #name {
+                #name {
+                    #(
+                        #idents: <#types as ::walkoflife::layout::EngineField>::from_bytes(
+                            &bytes[#offsets..#offsets + <#types as ::walkoflife::layout::EngineField>::SIZE]
+                        ),
+                    )*
+                }
+            }
+
+            fn to_bytes(&self, bytes: &mut [u8]) {
+                #(
+                    ::walkoflife::layout::EngineField::to_bytes(
+                        &self.#idents,
+                        &mut bytes[#offsets..#offsets + <#types as ::walkoflife::layout::EngineField>::SIZE]
+                    );
+                )*
+            }
+        }
+
+        impl ::walkoflife::layout::EngineStruct for #name {
+            fn write_to(&self, pid: ::walkoflife::layout::Pid, ptr: ::walkoflife::remote::RemotePtr<#name>) -> ::walkoflife::Result<()> {
+                #(
+                    ::walkoflife::layout::write_field(pid, ptr.field::<#types>(#offsets), &self.#idents)?;
+                )*
+                Ok(())
+            }
+        }
+
+        #[doc = #fields_doc]
+        #vis trait #fields_trait {
+            #(
+                fn #idents(self) -> ::walkoflife::remote::RemotePtr<#types>;
+            )*
+        }
+
+        impl #fields_trait for ::walkoflife::remote::RemotePtr<#name> {
+            #(
+                fn #idents(self) -> ::walkoflife::remote::RemotePtr<#types> {
+                    self.field(#offsets)
+                }
+            )*
+        }
+    })
+}
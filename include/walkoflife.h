@@ -0,0 +1,151 @@
+#ifndef WALKOFLIFE_H
+#define WALKOFLIFE_H
+
+/* Generated by cbindgen from src/ffi.rs - don't edit this by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <sys/types.h>
+
+// A handle on a running game, for the C bindings.
+typedef struct WolProcess WolProcess;
+
+// Get a description of what went wrong in the last call which failed on this thread, or `NULL`
+// if nothing has failed yet. The string belongs to the library, and stays valid until the next
+// call which fails on this thread.
+const char *wol_last_error(void);
+
+// Free a string returned by one of the other functions. Passing `NULL` does nothing.
+//
+// ## Safety
+// * `string` must have come from this library, and mustn't be used (or freed) afterwards.
+void wol_free_string(char *string);
+
+// Attach to the game running as the process `pid`, or find `Rayman2.exe` if `pid` is 0.
+//
+// ## Returns:
+// * On success, returns a handle on the game, to be freed with `wol_detach()`.
+// * Returns `NULL` if the game isn't running.
+struct WolProcess *wol_attach(pid_t pid);
+
+// Free a handle from `wol_attach()`. Passing `NULL` does nothing.
+//
+// ## Safety
+// * `handle` must have come from `wol_attach()`, and mustn't be used (or freed) afterwards.
+void wol_detach(struct WolProcess *handle);
+
+// Get the process ID of the game, or -1 if `handle` is `NULL`.
+//
+// ## Safety
+// * `handle` must be `NULL` or a live handle from `wol_attach()`.
+pid_t wol_pid(const struct WolProcess *handle);
+
+// Read up to `len` bytes from `addr` in the game into `buf` - see `memory::read_into()`.
+//
+// ## Returns:
+// * On success, returns the number of bytes read (which may be less than `len` at the end of
+//   the readable memory).
+// * Returns -1 if nothing could be read.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+// * `buf` must point to at least `len` writable bytes.
+intptr_t wol_read(const struct WolProcess *handle, uintptr_t addr, uint8_t *buf, uintptr_t len);
+
+// Write the `len` bytes at `buf` to `addr` in the game - see `memory::write_prims()`.
+//
+// ## Returns:
+// * On success, returns 0.
+// * Returns -1 on failure.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+// * `buf` must point to at least `len` readable bytes.
+int32_t wol_write(const struct WolProcess *handle,
+                  uintptr_t addr,
+                  const uint8_t *buf,
+                  uintptr_t len);
+
+// Get the name of the level currently open.
+//
+// ## Returns:
+// * On success, returns the name, to be freed with `wol_free_string()`.
+// * Returns `NULL` on failure.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+char *wol_level_name(const struct WolProcess *handle);
+
+// Get the active super-objects, as a JSON object mapping each name to its address.
+//
+// ## Returns:
+// * On success, returns the JSON, to be freed with `wol_free_string()`.
+// * Returns `NULL` on failure.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+char *wol_active_super_objects_json(const struct WolProcess *handle);
+
+// Walk the active part of the engine hierarchy (the dynamic world) - see
+// `Rayman2Process::walk_hierarchy()`.
+//
+// ## Returns:
+// * On success, returns the root as JSON, to be freed with `wol_free_string()`. Each node is an
+//   object with `name`, `ptr`, `ai_model` and `family` (`null` if it isn't an actor), and
+//   `children` (an array of nodes).
+// * Returns `NULL` on failure.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+char *wol_hierarchy_json(const struct WolProcess *handle);
+
+// Read every DSG variable on the super-object at `super_object` - see `utils::dump_dsg_mem()`.
+//
+// ## Returns:
+// * On success, returns a JSON array, to be freed with `wol_free_string()`, with an object for
+//   each variable: its `index`, `type` (as in `DsgVarType`), `offset` and `value`.
+// * Returns `NULL` on failure.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+char *wol_dsg_json(const struct WolProcess *handle, uintptr_t super_object);
+
+// Read the raw bytes of the DSG variable at `index` on the super-object at `super_object` into
+// `buf` (little-endian, as the game stores it).
+//
+// ## Returns:
+// * On success, returns the size of the variable, in bytes.
+// * Returns -1 on failure, including if `len` is less than the size of the variable.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+// * `buf` must point to at least `len` writable bytes.
+intptr_t wol_dsg_read(const struct WolProcess *handle,
+                      uintptr_t super_object,
+                      uintptr_t index,
+                      uint8_t *buf,
+                      uintptr_t len);
+
+// Write the DSG variable at `index` on the super-object at `super_object`, as the `len` bytes at
+// `buf` (little-endian, as the game stores it).
+//
+// ## Returns:
+// * On success, returns 0.
+// * Returns -1 on failure, including if `len` isn't the size of the variable.
+//
+// ## Safety
+// * `handle` must be a live handle from `wol_attach()`.
+// * `buf` must point to at least `len` readable bytes.
+int32_t wol_dsg_write(const struct WolProcess *handle,
+                      uintptr_t super_object,
+                      uintptr_t index,
+                      const uint8_t *buf,
+                      uintptr_t len);
+
+// Get the version of this library, e.g. `0.1.0`. The string belongs to the library, and never
+// needs to be freed.
+const char *wol_version(void);
+
+#endif  /* WALKOFLIFE_H */
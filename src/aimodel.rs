@@ -0,0 +1,315 @@
+/*!
+  AI Models: the behaviours (comports) and DSG variable layout shared by all the objects of a
+  given kind in Rayman 2. Since these don't change during a level, they're read once per model and
+  kept in an [`AiModelCache`](struct.AiModelCache.html).
+
+  The behaviours in the released PC version don't have names in memory (each one is just 12 bytes:
+  its scripts, its schedule script and the number of scripts), so their names come from a
+  [`BehaviourNames`](struct.BehaviourNames.html) table, e.g. written down from Raymap.
+  */
+
+extern crate nix;
+
+use std::{fs,path::Path,collections::HashMap,sync::Arc};
+use nix::unistd::Pid;
+use crate::{memory::{read_prims,get_pointer_path},superobject::{SuperObject,AiModelPtr},dsg::{DsgVarInfo,read_dsg_var},error::{Error,Result}};
+
+/// A behaviour (comport) of an AI Model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Behaviour {
+    /// The name of the behaviour, from a [`BehaviourNames`](struct.BehaviourNames.html) table, or
+    /// `<AI Model>#<index>` if it isn't in one.
+    pub name: String,
+    /// Pointer to the behaviour in Rayman 2's memory.
+    pub ptr: usize,
+    /// The index of the behaviour in its AI Model.
+    pub index: usize,
+}
+
+/// A way of picking out one of the behaviours (comports) of an AI Model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BehaviourId<'a> {
+    /// The behaviour at this index.
+    Index(usize),
+    /// The behaviour with this name (from a [`BehaviourNames`](struct.BehaviourNames.html) table,
+    /// or of the form `<AI Model>#<index>`).
+    Name(&'a str),
+}
+
+impl From<usize> for BehaviourId<'_> {
+    fn from(index: usize) -> Self {
+        BehaviourId::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for BehaviourId<'a> {
+    fn from(name: &'a str) -> Self {
+        BehaviourId::Name(name)
+    }
+}
+
+/// Names for the behaviours of AI Models, which aren't in the game's memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BehaviourNames {
+    names: HashMap<String, HashMap<usize, String>>,
+}
+
+impl BehaviourNames {
+    /// Make an empty table.
+    pub fn new() -> BehaviourNames {
+        BehaviourNames::default()
+    }
+
+    /// Give the behaviour at `index` in the AI Model called `ai_model` the name `name`.
+    pub fn insert(&mut self, ai_model: &str, index: usize, name: &str) {
+        self.names.entry(ai_model.to_string()).or_default().insert(index, name.to_string());
+    }
+
+    /// Get the name of the behaviour at `index` in the AI Model called `ai_model`, if it's known.
+    pub fn get(&self, ai_model: &str, index: usize) -> Option<&str> {
+        self.names.get(ai_model)?.get(&index).map(String::as_str)
+    }
+
+    /// Read a table from `text`, which has one behaviour per line: the AI Model's name, the
+    /// behaviour's index and its name, separated by spaces, e.g.:
+    ///
+    /// ```text
+    /// # Comments start with a hash
+    /// GRP_TimerCourse 0 waiting_for_race_start
+    /// ```
+    ///
+    /// ## Returns:
+    /// * On success, returns the table.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if there's a line which doesn't make sense.
+    pub fn parse(text: &str) -> Result<BehaviourNames> {
+        let mut names = BehaviourNames::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                [ai_model, index, name] => match index.parse() {
+                    Ok(index) => names.insert(ai_model, index, name),
+                    Err(_) => {return Err(format!("Line {} of behaviour names has a bad index: {}", i + 1, index).into());},
+                },
+                _ => {return Err(format!("Line {} of behaviour names isn't `<AI Model> <index> <name>`", i + 1).into());},
+            }
+        }
+        Ok(names)
+    }
+
+    /// Read a table from the file at `path` - see [`parse()`](#method.parse).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<BehaviourNames> {
+        match fs::read_to_string(&path) {
+            Ok(text) => BehaviourNames::parse(&text),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't read behaviour names {}", path.as_ref().display()))),
+        }
+    }
+}
+
+/// Everything known about an AI Model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiModel {
+    /// Pointer to the AI Model in Rayman 2's memory.
+    pub ptr: AiModelPtr,
+    /// The name of the AI Model, from the object-type tables (or `unknown_<pointer>`).
+    pub name: String,
+    /// Pointers to the normal behaviours (comports), in index order.
+    pub normal_behaviours: Vec<usize>,
+    /// Pointers to the reflex behaviours, in index order.
+    pub reflex_behaviours: Vec<usize>,
+    /// Size of the DSG memory buffer of each object using this AI Model, in bytes.
+    pub dsg_size: usize,
+    /// The DSG variables of each object using this AI Model, in index order.
+    pub dsg_vars: Vec<DsgVarInfo>,
+}
+
+impl AiModel {
+    /// Read the AI Model used by the given `super_object` in the Rayman 2 process given by
+    /// `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    /// * You need to give a pointer to a valid super-object, which has a mind.
+    /// * `ai_model_names` is the second table returned by
+    ///   [`read_object_types()`](../utils/fn.read_object_types.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `AiModel`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn read(r2pid: Pid, super_object: usize, ai_model_names: &[String]) -> Result<AiModel> {
+        let ptr = SuperObject::new(r2pid, super_object).ai_model()?;
+        let name = get_ai_model_name(r2pid, super_object, ai_model_names)?;
+        AiModel::read_ptr(r2pid, ptr, name)
+    }
+
+    fn read_ptr(r2pid: Pid, ptr: AiModelPtr, name: String) -> Result<AiModel> {
+        let read_model = || -> Result<AiModel> {
+            let normal_behaviours = read_behaviour_list(r2pid, ptr.normal_behaviours_ptr(r2pid)?)?;
+            let reflex_behaviours = read_behaviour_list(r2pid, ptr.reflex_behaviours_ptr(r2pid)?)?;
+            let (dsg_size, dsg_vars) = match ptr.dsg_var(r2pid)? {
+                0 => (0, Vec::new()),
+                off_dsg_var => read_dsg_var(r2pid, off_dsg_var)?,
+            };
+            Ok(AiModel{ptr, name: name.clone(), normal_behaviours, reflex_behaviours, dsg_size, dsg_vars})
+        };
+        match read_model() {
+            Ok(model) => Ok(model),
+            Err(err) => Err(err.context(format!("Unable to read AI Model {}", name))),
+        }
+    }
+
+    /// Get a pointer to the normal behaviour (comport) at `index`, e.g. as returned by
+    /// [`SuperObject::active_normal_behaviour()`](../superobject/struct.SuperObject.html#method.active_normal_behaviour).
+    pub fn normal_behaviour(&self, index: usize) -> Option<usize> {
+        self.normal_behaviours.get(index).copied()
+    }
+
+    /// Get the normal behaviours (comports), with their names from `names` (if given).
+    pub fn normal_behaviour_list(&self, names: Option<&BehaviourNames>) -> Vec<Behaviour> {
+        self.normal_behaviours.iter().enumerate()
+            .map(|(index, &ptr)| Behaviour{name: self.behaviour_name(index, names), ptr, index})
+            .collect()
+    }
+
+    /// Get the normal behaviour (comport) given by `active`, as returned by
+    /// [`SuperObject::active_normal_behaviour()`](../superobject/struct.SuperObject.html#method.active_normal_behaviour),
+    /// with its name from `names` (if given).
+    ///
+    /// ## Details:
+    /// * `active` may be either the behaviour's index or a pointer to it.
+    pub fn active_normal_behaviour(&self, active: usize, names: Option<&BehaviourNames>) -> Option<Behaviour> {
+        let index = match active < self.normal_behaviours.len() {
+            true => active,
+            false => self.normal_behaviours.iter().position(|&ptr| ptr == active)?,
+        };
+        Some(Behaviour{name: self.behaviour_name(index, names), ptr: self.normal_behaviours[index], index})
+    }
+
+    /// Find the normal behaviour (comport) picked out by `behaviour`, using the names in `names`
+    /// (if given).
+    pub fn find_normal_behaviour(&self, behaviour: BehaviourId, names: Option<&BehaviourNames>) -> Option<Behaviour> {
+        match behaviour {
+            BehaviourId::Index(index) => self.normal_behaviour_list(names).into_iter().nth(index),
+            BehaviourId::Name(name) => self.normal_behaviour_list(names).into_iter().find(|behaviour| behaviour.name == name),
+        }
+    }
+
+    fn behaviour_name(&self, index: usize, names: Option<&BehaviourNames>) -> String {
+        match names.and_then(|names| names.get(&self.name, index)) {
+            Some(name) => name.to_string(),
+            None => format!("{}#{}", self.name, index),
+        }
+    }
+}
+
+/// AI Models which have already been read, so that each one is only read once.
+///
+/// ## Details:
+/// * AI Models are only valid for the level they were read in, so the cache should be
+///   [`clear()`](#method.clear)ed when the level changes.
+#[derive(Debug, Default)]
+pub struct AiModelCache {
+    models: HashMap<AiModelPtr, Arc<AiModel>>,
+}
+
+impl AiModelCache {
+    /// Make an empty cache.
+    pub fn new() -> AiModelCache {
+        AiModelCache::default()
+    }
+
+    /// Get the AI Model used by the given `super_object` in the Rayman 2 process given by
+    /// `r2pid`, reading it if it hasn't been seen before - see
+    /// [`AiModel::read()`](struct.AiModel.html#method.read).
+    pub fn get(&mut self, r2pid: Pid, super_object: usize, ai_model_names: &[String]) -> Result<Arc<AiModel>> {
+        let ptr = SuperObject::new(r2pid, super_object).ai_model()?;
+        if let Some(model) = self.models.get(&ptr) {
+            return Ok(model.clone());
+        }
+
+        let name = get_ai_model_name(r2pid, super_object, ai_model_names)?;
+        let model = Arc::new(AiModel::read_ptr(r2pid, ptr, name)?);
+        self.models.insert(ptr, model.clone());
+        Ok(model)
+    }
+
+    /// Forget all the AI Models read so far.
+    pub fn clear(&mut self) {
+        self.models.clear();
+    }
+}
+
+/// Get the name of the AI Model used by the given `super_object` in the Rayman 2 process given by
+/// `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+/// * `ai_model_names` is the second table returned by
+///   [`read_object_types()`](../utils/fn.read_object_types.html).
+///
+/// ## Returns:
+/// * On success, returns the name, or `unknown_<super_object>` if it isn't in the table.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_ai_model_name(r2pid: Pid, super_object: usize, ai_model_names: &[String]) -> Result<String> {
+    // Super-object -> perso -> standard game info, which has the model type at 0x4.
+    match get_pointer_path(r2pid, super_object + 4, Some(&vec![4, 4])) {
+        Ok(name_index) => Ok(match ai_model_names.get(name_index) {
+            Some(name) => name.to_string(),
+            None => format!("unknown_{}", super_object),
+        }),
+        Err(err) => Err(err.context("Unable to get AI Model name index")),
+    }
+}
+
+/// Read the pointers to the behaviours in the behaviour vector at `vector_ptr` (which may be
+/// null, if there are none).
+pub(crate) fn read_behaviour_list(r2pid: Pid, vector_ptr: usize) -> Result<Vec<usize>> {
+    if vector_ptr == 0 {
+        return Ok(Vec::new());
+    }
+    let (off_first_entry, num_entries) = match read_prims::<u32>(r2pid, vector_ptr, 2) {
+        Ok(vec) => (vec[0] as usize, vec[1] as usize),
+        Err(err) => {return Err(err.context("Unable to get entries in AI Model Behaviours List"));},
+    };
+
+    // Each entry takes up 12 bytes.
+    Ok((0..num_entries).map(|i| off_first_entry + 12*i).collect())
+}
+
+#[cfg(test)]
+mod aimodel_tests {
+    use super::*;
+
+    #[test]
+    fn can_name_behaviours() {
+        let names = BehaviourNames::parse("# Timer\nGRP_TimerCourse 0 waiting_for_race_start\nGRP_TimerCourse 2 racing # the race\n").unwrap();
+        let model = AiModel{
+            ptr: AiModelPtr(0x1000),
+            name: "GRP_TimerCourse".to_string(),
+            normal_behaviours: vec![0x2000, 0x200C, 0x2018],
+            reflex_behaviours: Vec::new(),
+            dsg_size: 0,
+            dsg_vars: Vec::new(),
+        };
+
+        let list = model.normal_behaviour_list(Some(&names));
+        assert_eq!(list[0], Behaviour{name: "waiting_for_race_start".to_string(), ptr: 0x2000, index: 0});
+        assert_eq!(list[1].name, "GRP_TimerCourse#1");
+        assert_eq!(model.active_normal_behaviour(2, Some(&names)).unwrap().name, "racing");
+        assert_eq!(model.active_normal_behaviour(0x200C, None).unwrap().index, 1);
+        assert_eq!(model.active_normal_behaviour(0x3000, None), None);
+        assert_eq!(model.find_normal_behaviour("racing".into(), Some(&names)).unwrap().ptr, 0x2018);
+        assert_eq!(model.find_normal_behaviour("GRP_TimerCourse#1".into(), None).unwrap().ptr, 0x200C);
+        assert_eq!(model.find_normal_behaviour(3.into(), None), None);
+
+        assert!(BehaviourNames::parse("GRP_TimerCourse zero start").is_err());
+        assert!(BehaviourNames::parse("GRP_TimerCourse 0").is_err());
+    }
+}
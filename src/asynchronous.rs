@@ -0,0 +1,263 @@
+/*!
+  Async versions of the polling APIs, for tools built on [tokio](https://tokio.rs/) (e.g. an
+  overlay server), so that the game can be `select!`ed alongside network tasks rather than
+  needing threads of its own.
+
+  [`wait_for_next_frame()`](fn.wait_for_next_frame.html) and
+  [`AsyncFrameTicker`](struct.AsyncFrameTicker.html) wait for frames without blocking the
+  runtime. [`LevelStream`](struct.LevelStream.html) and [`ValueStream`](struct.ValueStream.html)
+  are the [`watch`](../watch/index.html) module's watchers as `Stream`s, and
+  [`RaceStream`](struct.RaceStream.html) polls a [`RaceTimer`](../races/struct.RaceTimer.html).
+
+  Everything here needs a tokio runtime with the timer enabled. The other polling functions
+  (e.g. [`SplitRecorder::poll()`](../splits/struct.SplitRecorder.html#method.poll)) only read the game's
+  memory once and return, so they can be called from async code as they are.
+  */
+
+extern crate nix;
+
+use std::{collections::VecDeque,pin::Pin,task::{Context,Poll},time::{Duration,Instant}};
+use futures_core::Stream;
+use nix::unistd::Pid;
+use tokio::{sync::mpsc::{unbounded_channel,UnboundedReceiver},time::{self,Interval,MissedTickBehavior}};
+use crate::{timing::{get_frame_counter,Frame,FRAME_POLL_INTERVAL,DEFAULT_FRAME_TIMEOUT},watch::{LevelWatcher,LevelEvent,ValueWatcher,ValueEvent,AddressSource},
+            races::{RaceTimer,RaceEvent},error::Result};
+
+/// Wait until the engine starts a new frame, in the Rayman 2 process given by `r2pid`, for up to
+/// [`DEFAULT_FRAME_TIMEOUT`](../timing/constant.DEFAULT_FRAME_TIMEOUT.html) - see
+/// [`timing::wait_for_next_frame()`](../timing/fn.wait_for_next_frame.html).
+///
+/// ## Returns:
+/// * On success, returns the new frame number.
+/// * Returns an `Err` variant describing what went wrong,
+///   if no new frame starts in time, or if the memory read fails.
+///
+/// ## Details:
+/// * tokio's timer only counts whole milliseconds, so the frame may be seen up to a millisecond
+///   after it starts.
+pub async fn wait_for_next_frame(r2pid: Pid) -> Result<u32> {
+    let frame = get_frame_counter(r2pid)?;
+    wait_for_frame_after(r2pid, frame, FRAME_POLL_INTERVAL, DEFAULT_FRAME_TIMEOUT).await
+}
+
+// Wait for the frame counter to move on from `frame`.
+async fn wait_for_frame_after(r2pid: Pid, frame: u32, poll_interval: Duration, timeout: Duration) -> Result<u32> {
+    let start = Instant::now();
+    loop {
+        let now = get_frame_counter(r2pid)?;
+        if now != frame {
+            return Ok(now);
+        }
+        if start.elapsed() >= timeout {
+            return Err(format!("No new frame in {} ms", timeout.as_millis()).into());
+        }
+        time::sleep(poll_interval).await;
+    }
+}
+
+/// Yields once per frame of the game, like a [`FrameTicker`](../timing/struct.FrameTicker.html),
+/// but without blocking.
+pub struct AsyncFrameTicker {
+    r2pid: Pid,
+    last: Option<u32>,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl AsyncFrameTicker {
+    /// Start ticking with the frames of the Rayman 2 process given by `r2pid`. The first tick is
+    /// the next frame to start after this.
+    pub fn new(r2pid: Pid) -> AsyncFrameTicker {
+        AsyncFrameTicker{r2pid, last: None, poll_interval: FRAME_POLL_INTERVAL, timeout: DEFAULT_FRAME_TIMEOUT}
+    }
+
+    /// Set how long to wait for each frame before giving up - see
+    /// [`FrameTicker::with_timeout()`](../timing/struct.FrameTicker.html#method.with_timeout).
+    pub fn with_timeout(mut self, timeout: Duration) -> AsyncFrameTicker {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait between reads of the frame counter - see
+    /// [`FrameTicker::with_poll_interval()`](../timing/struct.FrameTicker.html#method.with_poll_interval).
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> AsyncFrameTicker {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Wait for the next frame - see [`FrameTicker::tick()`](../timing/struct.FrameTicker.html#method.tick).
+    pub async fn tick(&mut self) -> Result<Frame> {
+        let from = match self.last {
+            Some(last) => last,
+            None => get_frame_counter(self.r2pid)?,
+        };
+        let number = wait_for_frame_after(self.r2pid, from, self.poll_interval, self.timeout).await?;
+        let frame = Frame::after(self.last, number);
+        self.last = Some(number);
+        Ok(frame)
+    }
+}
+
+/// A [`LevelWatcher`](../watch/struct.LevelWatcher.html) whose events come as a `Stream`.
+///
+/// ## Details:
+/// * The stream ends when the watcher stops by itself (e.g. because the game has quit). Call
+///   [`stop()`](#method.stop) afterwards to find out why.
+/// * The watcher still runs on its own thread, so this doesn't need a multi-threaded runtime.
+pub struct LevelStream {
+    events: UnboundedReceiver<LevelEvent>,
+    watcher: LevelWatcher,
+}
+
+impl LevelStream {
+    /// Start watching the level in the Rayman 2 process given by `r2pid`, checking every
+    /// `interval` - see [`LevelWatcher::spawn()`](../watch/struct.LevelWatcher.html#method.spawn).
+    pub fn spawn(r2pid: Pid, interval: Duration) -> LevelStream {
+        let (sender, events) = unbounded_channel();
+        let watcher = LevelWatcher::with_callback(r2pid, interval, move |event| {
+            let _ = sender.send(event);
+        });
+        LevelStream{events, watcher}
+    }
+
+    /// Choose whether to keep watching when the game goes away - see
+    /// [`LevelWatcher::set_reattach()`](../watch/struct.LevelWatcher.html#method.set_reattach).
+    pub fn set_reattach(&self, reattach: bool) {
+        self.watcher.set_reattach(reattach);
+    }
+
+    /// Stop watching - see [`LevelWatcher::stop()`](../watch/struct.LevelWatcher.html#method.stop).
+    pub fn stop(self) -> Result<()> {
+        self.watcher.stop()
+    }
+}
+
+impl Stream for LevelStream {
+    type Item = LevelEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LevelEvent>> {
+        self.get_mut().events.poll_recv(cx)
+    }
+}
+
+/// A [`ValueWatcher`](../watch/struct.ValueWatcher.html) whose events come as a `Stream`.
+///
+/// ## Details:
+/// * As for [`LevelStream`](struct.LevelStream.html).
+pub struct ValueStream<T> {
+    events: UnboundedReceiver<ValueEvent<T>>,
+    watcher: ValueWatcher<T>,
+}
+
+impl<T: Copy + PartialEq + Send + 'static> ValueStream<T> {
+    /// Start watching the value at `address` in the Rayman 2 process given by `r2pid`, checking
+    /// every `interval` - see [`ValueWatcher::at()`](../watch/struct.ValueWatcher.html#method.at).
+    pub fn at(r2pid: Pid, address: usize, interval: Duration) -> ValueStream<T> {
+        ValueStream::spawn(r2pid, AddressSource::Fixed(address), interval)
+    }
+
+    /// Start watching the value at `source` in the Rayman 2 process given by `r2pid`, checking
+    /// every `interval` - see [`ValueWatcher::spawn()`](../watch/struct.ValueWatcher.html#method.spawn).
+    pub fn spawn(r2pid: Pid, source: AddressSource, interval: Duration) -> ValueStream<T> {
+        let (sender, events) = unbounded_channel();
+        let watcher = ValueWatcher::with_callback(r2pid, source, interval, move |event| {
+            let _ = sender.send(event);
+        });
+        ValueStream{events, watcher}
+    }
+
+    /// Choose whether to keep watching when the game goes away - see
+    /// [`ValueWatcher::set_reattach()`](../watch/struct.ValueWatcher.html#method.set_reattach).
+    pub fn set_reattach(&self, reattach: bool) {
+        self.watcher.set_reattach(reattach);
+    }
+
+    /// Get the last value read, if any - see
+    /// [`ValueWatcher::last_value()`](../watch/struct.ValueWatcher.html#method.last_value).
+    pub fn last_value(&self) -> Option<T> {
+        self.watcher.last_value()
+    }
+
+    /// Stop watching - see [`ValueWatcher::stop()`](../watch/struct.ValueWatcher.html#method.stop).
+    pub fn stop(self) -> Result<()> {
+        self.watcher.stop()
+    }
+}
+
+impl<T> Stream for ValueStream<T> {
+    type Item = ValueEvent<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ValueEvent<T>>> {
+        self.get_mut().events.poll_recv(cx)
+    }
+}
+
+/// Polls a [`RaceTimer`](../races/struct.RaceTimer.html) every so often, and yields its
+/// [`RaceEvent`](../races/enum.RaceEvent.html)s as a `Stream`.
+///
+/// ## Details:
+/// * Each item is an event, or an `Err` variant if the timers couldn't be read. The stream never
+///   ends by itself, so it can keep going after an error (e.g. while a level is loading).
+/// * Ticks which are missed (e.g. because the task was busy) are skipped rather than made up.
+pub struct RaceStream {
+    timer: RaceTimer,
+    interval: Interval,
+    pending: VecDeque<RaceEvent>,
+}
+
+impl RaceStream {
+    /// Start polling `timer` every `interval`, beginning straight away.
+    ///
+    /// ## Requirements:
+    /// * This needs to be called from within a tokio runtime.
+    pub fn new(timer: RaceTimer, interval: Duration) -> RaceStream {
+        let mut interval = time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        RaceStream{timer, interval, pending: VecDeque::new()}
+    }
+
+    /// Stop polling, and get the timer back.
+    pub fn into_timer(self) -> RaceTimer {
+        self.timer
+    }
+}
+
+impl Stream for RaceStream {
+    type Item = Result<RaceEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<RaceEvent>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.interval.poll_tick(cx).is_pending() {
+                return Poll::Pending;
+            }
+            match this.timer.poll() {
+                Ok(events) => this.pending.extend(events),
+                Err(err) => {return Poll::Ready(Some(Err(err)));},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod asynchronous_tests {
+    use super::*;
+    use std::future::poll_fn;
+    use nix::unistd::getpid;
+
+    #[test]
+    fn streams_end_with_watchers() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            // This process isn't the game, so the level name can't be read, and the watcher stops.
+            let mut levels = LevelStream::spawn(getpid(), Duration::from_millis(1));
+            assert_eq!(poll_fn(|cx| Pin::new(&mut levels).poll_next(cx)).await, None);
+            assert!(levels.stop().is_err());
+
+            assert!(time::timeout(Duration::from_secs(1), wait_for_next_frame(getpid())).await.unwrap().is_err());
+        });
+    }
+}
@@ -0,0 +1,147 @@
+/*!
+  The platform-specific part of reading and writing another process's memory, behind the
+  [`MemoryBackend`](trait.MemoryBackend.html) trait, so that everything built on top of it can
+  stay the same from one operating system to the next.
+
+  On Linux, [`ProcessVm`](struct.ProcessVm.html) uses `process_vm_readv` and
+  `process_vm_writev`. [`NativeBackend`](type.NativeBackend.html) is whichever one suits the
+  platform being built for, which is only Linux for now.
+  */
+
+extern crate nix;
+
+use crate::error::Result;
+#[cfg(target_os = "linux")]
+use nix::{unistd::Pid,errno::Errno,sys::uio::{process_vm_readv,process_vm_writev,IoVec,RemoteIoVec}};
+#[cfg(target_os = "linux")]
+use crate::error::Error;
+
+/// The most `iovec`s the kernel accepts in one call (`UIO_MAXIOV`).
+#[cfg(target_os = "linux")]
+pub(crate) const MAX_IOVECS: usize = 1024;
+
+/// A way of reading and writing the memory of another process.
+pub trait MemoryBackend {
+    /// Read as many bytes as will fit in `buf`, starting from `addr`.
+    ///
+    /// ## Returns:
+    /// * On success, returns the number of bytes read, which may be less than `buf.len()` if the
+    ///   end of the readable memory was reached.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<usize>;
+
+    /// Write all of `data`, starting from `addr`.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    fn write_bytes(&self, addr: usize, data: &[u8]) -> Result<()>;
+
+    /// Read several regions, each given as `(address, length)` in `regions`, into the
+    /// corresponding `bufs` (which must be the same lengths), as quickly as possible.
+    ///
+    /// ## Details:
+    /// * A region which can't be read doesn't stop the others from being read.
+    /// * By default, this reads the regions one at a time. Backends which can read several
+    ///   regions at once should do so.
+    ///
+    /// ## Returns:
+    /// * On success, returns the number of bytes read into each buffer.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the process can't be read at all.
+    fn read_regions(&self, regions: &[(usize, usize)], bufs: &mut [Vec<u8>]) -> Result<Vec<usize>> {
+        Ok(regions.iter().zip(bufs.iter_mut())
+            .map(|(&(addr, _), buf)| self.read_bytes(addr, buf).unwrap_or(0))
+            .collect())
+    }
+}
+
+/// Reads and writes the memory of a process on Linux, with `process_vm_readv` and
+/// `process_vm_writev`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug the process (e.g. with `CAP_SYS_PTRACE`).
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessVm {
+    pid: Pid,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessVm {
+    /// Use the memory of the process given by `pid`.
+    pub fn new(pid: Pid) -> ProcessVm {
+        ProcessVm{pid}
+    }
+
+    /// Get the PID of the process.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl MemoryBackend for ProcessVm {
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len();
+        let iovec = IoVec::from_mut_slice(buf);
+        let iovec_rem = RemoteIoVec{base: addr, len};
+
+        match process_vm_readv(self.pid, &[iovec], &[iovec_rem]) {
+            Ok(bytes_copied) => Ok(bytes_copied),
+            Err(err) => Err(Error::from_read(err, self.pid, addr, len)),
+        }
+    }
+
+    fn write_bytes(&self, addr: usize, data: &[u8]) -> Result<()> {
+        let iovec = IoVec::from_slice(data);
+        let iovec_rem = RemoteIoVec{base: addr, len: data.len()};
+
+        match process_vm_writev(self.pid, &[iovec], &[iovec_rem]) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::from_write(err, self.pid, addr, data.len())),
+        }
+    }
+
+    fn read_regions(&self, regions: &[(usize, usize)], bufs: &mut [Vec<u8>]) -> Result<Vec<usize>> {
+        let mut lens_read = vec![0usize; regions.len()];
+
+        let mut start = 0;
+        while start < regions.len() {
+            let end = (start + MAX_IOVECS).min(regions.len());
+            let iovecs: Vec<IoVec<&mut [u8]>> = bufs[start..end].iter_mut()
+                .map(|buf| IoVec::from_mut_slice(&mut buf[..]))
+                .collect();
+            let iovecs_rem: Vec<RemoteIoVec> = regions[start..end].iter()
+                .map(|&(base, len)| RemoteIoVec{base, len})
+                .collect();
+
+            let mut bytes_copied = match process_vm_readv(self.pid, &iovecs, &iovecs_rem) {
+                Ok(n_bytes) => n_bytes,
+                // The first region couldn't be read at all - skip it below.
+                Err(nix::Error::Sys(Errno::EFAULT)) => 0,
+                Err(err) => {return Err(Error::from_read(err, self.pid, regions[start].0, regions[start].1));},
+            };
+
+            // The regions are filled in order, so everything up to the first short one was read.
+            let mut i = start;
+            while i < end && bytes_copied >= regions[i].1 {
+                lens_read[i] = regions[i].1;
+                bytes_copied -= regions[i].1;
+                i += 1;
+            }
+            if i < end {
+                // This is where the read stopped, so carry on after it.
+                lens_read[i] = bytes_copied;
+                i += 1;
+            }
+            start = i;
+        }
+
+        Ok(lens_read)
+    }
+}
+
+/// The backend for the platform being built for.
+#[cfg(target_os = "linux")]
+pub type NativeBackend = ProcessVm;
@@ -0,0 +1,169 @@
+/*!
+  Functions for driving Rayman 2's camera, using the forced camera position and target which
+  Robin's FunBox uses for its FPS mode. With the `input` feature,
+  [`set_first_person()`](fn.set_first_person.html) turns that FPS mode on and off.
+  */
+
+extern crate nix;
+
+#[cfg(feature = "effects")]
+use std::time::Duration;
+#[cfg(feature = "input")]
+use std::sync::Mutex;
+use nix::unistd::Pid;
+use crate::{remote::RemotePtr,relocation::address,constants::*,error::Result};
+#[cfg(feature = "input")]
+use crate::{fpsmode::{FpsController,FpsConfig,StrafeInput},input::MouseMotion};
+#[cfg(feature = "effects")]
+use crate::easing::{Easing,Tween,DEFAULT_STEP};
+
+// The forced camera position and target, in the process given by `r2pid`.
+fn forced_camera(r2pid: Pid) -> (RemotePtr<[f32; 3]>, RemotePtr<[f32; 3]>) {
+    (RemotePtr::new(address(r2pid, OFF_FORCE_CAMERA_POS)), RemotePtr::new(address(r2pid, OFF_FORCE_CAMERA_TGT)))
+}
+
+// The first-person controllers turned on with `set_first_person()`, for each process.
+#[cfg(feature = "input")]
+static FIRST_PERSON: Mutex<Vec<(Pid, FpsController)>> = Mutex::new(Vec::new());
+
+/// The state of the camera, as read by [`get_camera_state()`](fn.get_camera_state.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraState {
+    /// The position the camera is forced to (x, y, z).
+    pub forced_position: [f32; 3],
+    /// The point the camera is forced to look at (x, y, z).
+    pub forced_target: [f32; 3],
+    /// A pointer to the dynamics used for the camera's mechanics.
+    pub mechanics: RemotePtr<()>,
+}
+
+impl CameraState {
+    /// Check if the camera is being forced anywhere, i.e. if the forced position or target has
+    /// been set.
+    pub fn is_forced(&self) -> bool {
+        self.forced_position != [0.0; 3] || self.forced_target != [0.0; 3]
+    }
+}
+
+/// Read the state of the camera in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the `CameraState`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_camera_state(r2pid: Pid) -> Result<CameraState> {
+    let (forced_position, forced_target) = forced_camera(r2pid);
+    let read_state = || -> Result<CameraState> {
+        Ok(CameraState{
+            forced_position: forced_position.read(r2pid)?,
+            forced_target: forced_target.read(r2pid)?,
+            mechanics: RemotePtr::<RemotePtr<()>>::new(address(r2pid, OFF_DNM_P_ST_DYNAMICS_CAMERA_MECHANICS)).follow(r2pid)?,
+        })
+    };
+    match read_state() {
+        Ok(state) => Ok(state),
+        Err(err) => Err(err.context("Couldn't read camera state")),
+    }
+}
+
+/// Force the camera to `position`, looking at `target` (both x, y, z), in the Rayman 2 process
+/// given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory write fails.
+pub fn force_camera(r2pid: Pid, position: [f32; 3], target: [f32; 3]) -> Result<()> {
+    let (forced_position, forced_target) = forced_camera(r2pid);
+    let write_state = || -> Result<()> {
+        forced_position.write(r2pid, position)?;
+        forced_target.write(r2pid, target)
+    };
+    match write_state() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't force camera")),
+    }
+}
+
+/// Stop forcing the camera, in the Rayman 2 process given by `r2pid`, by clearing the forced
+/// position and target.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory write fails.
+pub fn release_camera(r2pid: Pid) -> Result<()> {
+    force_camera(r2pid, [0.0; 3], [0.0; 3])
+}
+
+/// Move the forced camera smoothly from where it is now to `position`, looking at `target`, over
+/// `duration`, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * The camera should already be forced (see [`force_camera()`](fn.force_camera.html)).
+///
+/// ## Returns:
+/// * On success, returns the [`Tween`](../easing/struct.Tween.html)s moving the position and the
+///   target, which stop if they're dropped.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the current state couldn't be read.
+#[cfg(feature = "effects")]
+pub fn ease_camera(r2pid: Pid, position: [f32; 3], target: [f32; 3], duration: Duration, easing: Easing) -> Result<[Tween; 2]> {
+    let state = get_camera_state(r2pid)?;
+    Ok([
+        Tween::start(r2pid, address(r2pid, OFF_FORCE_CAMERA_POS), state.forced_position.to_vec(), position.to_vec(), duration, easing, DEFAULT_STEP),
+        Tween::start(r2pid, address(r2pid, OFF_FORCE_CAMERA_TGT), state.forced_target.to_vec(), target.to_vec(), duration, easing, DEFAULT_STEP),
+    ])
+}
+
+/// Turn first-person mode on or off (depending on `enabled`) in the Rayman 2 process given by
+/// `r2pid`: while it's on, the camera is locked to Rayman's head, looking where he's facing, and
+/// the mouse looks around.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * For mouse-look, we need read permission on the mouse in `/dev/input/by-id` (see
+///   [`MouseMotion::find()`](../input/struct.MouseMotion.html#method.find)).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the camera can't be released when turning it off.
+///
+/// ## Details:
+/// * This uses a [`FpsController`](../fpsmode/struct.FpsController.html) with the default
+///   [`FpsConfig`](../fpsmode/struct.FpsConfig.html), in a background thread, which keeps running
+///   until this is called again with `enabled` set to `false`. Turning it on when it's already on
+///   (or off when it's already off) does nothing.
+/// * It doesn't strafe, since the game's strafe button isn't known here. Use a `FpsController`
+///   directly for that.
+/// * If no mouse can be opened, the camera is still locked, just without mouse-look.
+#[cfg(feature = "input")]
+pub fn set_first_person(r2pid: Pid, enabled: bool) -> Result<()> {
+    // A panic while the lock was held can't have left the list half-changed.
+    let mut first_person = FIRST_PERSON.lock().unwrap_or_else(|err| err.into_inner());
+    let idx = first_person.iter().position(|(pid, _)| *pid == r2pid);
+    match (idx, enabled) {
+        (None, true) => {
+            let mut controller = FpsController::new(r2pid, FpsConfig::default(), StrafeInput::None);
+            if let Ok(mouse) = MouseMotion::find() {
+                controller = controller.with_mouse_look(mouse);
+            }
+            controller.start();
+            first_person.push((r2pid, controller));
+            Ok(())
+        },
+        (Some(idx), false) => first_person.remove(idx).1.stop(),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,361 @@
+/*!
+  Settings for the `walkoflife` program, which can be kept in a file (see
+  [`Config::parse()`](struct.Config.html#method.parse)), so that it can be pointed at other levels
+  (e.g. the Walk of Power, or a custom map) and other variables without recompiling.
+  */
+
+use std::{fs,path::Path,time::Duration};
+use crate::{races::DEFAULT_RACES,dsg::DsgVarType,registry::DsgLocation,error::{Error,Result}};
+
+/// How often to sample the game by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// How the samples are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OutputFormat {
+    /// Lines of text for people to read.
+    #[default]
+    Text,
+    /// One JSON object per line (see
+    /// [`TelemetryFrame::to_json()`](../telemetry/struct.TelemetryFrame.html#method.to_json)).
+    Json,
+}
+
+impl OutputFormat {
+    /// Get the format called `name` (`text` or `json`).
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A DSG variable to sample along with the race timers, and to look up by name in a
+/// [`Registry`](../registry/struct.Registry.html).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WatchedVariable {
+    /// What to call it in the output.
+    pub name: String,
+    /// The name of the super-object it belongs to, or `global` for the global object (whatever
+    /// it's called - see [`utils::find_global_object()`](../utils/fn.find_global_object.html)).
+    pub object: String,
+    /// Where it is in the object's DSG memory.
+    pub location: DsgLocation,
+    /// How to read it, if it isn't the type the object's DSG variables say.
+    pub var_type: Option<DsgVarType>,
+}
+
+/// What to watch, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// How often to sample the game.
+    pub poll_interval: Duration,
+    /// The levels to watch (in any case). The race timers are only read in the Ly races.
+    pub levels: Vec<String>,
+    /// How the samples are printed.
+    pub format: OutputFormat,
+    /// Other DSG variables to sample.
+    pub variables: Vec<WatchedVariable>,
+}
+
+impl Default for Config {
+    /// Watch the Walk of Life once a second, as text - see
+    /// [`DEFAULT_RACES`](../races/constant.DEFAULT_RACES.html).
+    fn default() -> Config {
+        Config{
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            levels: DEFAULT_RACES.iter().map(|race| race.level_name().to_string()).collect(),
+            format: OutputFormat::Text,
+            variables: Vec::new(),
+        }
+    }
+}
+
+// A value in a config file (or in anything else written in the same subset of TOML, like an
+// offset profile).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    String(String),
+    Integer(usize),
+    Array(Vec<Value>),
+}
+
+// A line of a config file which isn't blank or a comment.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TomlLine {
+    // The header of a table (e.g. `[[variable]]`), as it's written.
+    Table(String),
+    KeyValue(String, Value),
+}
+
+impl Config {
+    /// Check if `level_name` is one of the levels to watch.
+    pub fn watches_level(&self, level_name: &str) -> bool {
+        self.levels.iter().any(|level| level.eq_ignore_ascii_case(level_name))
+    }
+
+    /// Read the settings from `text`, which is a subset of TOML, e.g.:
+    ///
+    /// ```text
+    /// poll_interval_ms = 500
+    /// levels = ["ly_20"]
+    /// format = "json"
+    ///
+    /// # Each variable goes in its own table.
+    /// [[variable]]
+    /// name = "countdown"
+    /// object = "global"
+    /// dsg = 30
+    ///
+    /// [[variable]]
+    /// name = "race_timer"
+    /// object = "GRP_TimerCourse_I3"
+    /// offset = 84
+    /// type = "f32"
+    /// ```
+    ///
+    /// ## Details:
+    /// * Values can be strings (in double quotes), whole numbers (in decimal, or in hex starting
+    ///   with `0x`), or arrays of those on one line.
+    /// * Anything which isn't given is taken from the [default](#impl-Default-for-Config).
+    /// * A variable is given by its index in the object's DSG variables (`dsg`), or by its offset
+    ///   in bytes in the DSG memory buffer (`offset`). Its `type` (see
+    ///   [`DsgVarType::from_name()`](../dsg/enum.DsgVarType.html#method.from_name)) only needs to
+    ///   be given if it isn't the start of a variable, or to read it as something else.
+    ///
+    /// ## Returns:
+    /// * On success, returns the settings.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if there's a line which doesn't make sense, or a key which isn't known.
+    pub fn parse(text: &str) -> Result<Config> {
+        let mut config = Config::default();
+        // The variable whose table we're in, if any.
+        let mut variable: Option<WatchedVariable> = None;
+
+        for line in parse_lines(text, "config") {
+            let (line_number, line) = line?;
+            let error = |message: &str| line_error("config", line_number, message);
+
+            let (key, value) = match line {
+                TomlLine::Table(table) if table == "[[variable]]" => {
+                    if let Some(variable) = variable.take() {
+                        config.variables.push(check_variable(variable, line_number - 1)?);
+                    }
+                    variable = Some(WatchedVariable{name: String::new(), object: String::new(), location: DsgLocation::Index(usize::MAX), var_type: None});
+                    continue;
+                },
+                TomlLine::Table(table) => {return Err(error(&format!("unknown table {}", table)));},
+                TomlLine::KeyValue(key, value) => (key, value),
+            };
+            match (&mut variable, key.as_str(), value) {
+                (Some(variable), "name", Value::String(name)) => {variable.name = name;},
+                (Some(variable), "object", Value::String(object)) => {variable.object = object;},
+                (Some(variable), "dsg", Value::Integer(index)) => {variable.location = DsgLocation::Index(index);},
+                (Some(variable), "offset", Value::Integer(offset)) => {variable.location = DsgLocation::Offset(offset);},
+                (Some(variable), "type", Value::String(var_type)) => match DsgVarType::from_name(&var_type) {
+                    Some(var_type) => {variable.var_type = Some(var_type);},
+                    None => {return Err(error(&format!("unknown type {}", var_type)));},
+                },
+                (None, "poll_interval_ms", Value::Integer(ms)) => {config.poll_interval = Duration::from_millis(ms as u64);},
+                (None, "format", Value::String(format)) => match OutputFormat::from_name(&format) {
+                    Some(format) => {config.format = format;},
+                    None => {return Err(error("format needs to be \"text\" or \"json\""));},
+                },
+                (None, "levels", Value::Array(levels)) => {
+                    config.levels = levels.into_iter()
+                        .map(|level| match level {
+                            Value::String(level) => Ok(level),
+                            _ => Err(error("levels need to be strings")),
+                        })
+                        .collect::<Result<_>>()?;
+                },
+                (_, key, _) => {return Err(error(&format!("unknown key {}, or the wrong type of value for it", key)));},
+            }
+        }
+        if let Some(variable) = variable {
+            config.variables.push(check_variable(variable, text.lines().count())?);
+        }
+
+        Ok(config)
+    }
+
+    /// Read the settings from the file at `path` - see [`parse()`](#method.parse).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {return Err(Error::from(err).context(format!("Couldn't read config {}", path.as_ref().display())));},
+        };
+        match Config::parse(&text) {
+            Ok(config) => Ok(config),
+            Err(err) => Err(err.context(format!("Bad config {}", path.as_ref().display()))),
+        }
+    }
+}
+
+// Check that a variable's table (which ended at line `end`) said everything it needs to. The name
+// defaults to the object's.
+fn check_variable(mut variable: WatchedVariable, end: usize) -> Result<WatchedVariable> {
+    if variable.object.is_empty() || variable.location == DsgLocation::Index(usize::MAX) {
+        return Err(format!("The variable ending at line {} of config needs an object, and a dsg index or an offset", end).into());
+    }
+    if variable.name.is_empty() {
+        variable.name = match variable.location {
+            DsgLocation::Index(index) => format!("{}.{}", variable.object, index),
+            DsgLocation::Offset(offset) => format!("{}+{:#x}", variable.object, offset),
+        };
+    }
+    Ok(variable)
+}
+
+/// Parse a whole number as it's written in a config file: in decimal, or in hex starting with
+/// `0x`, with `_` allowed between the digits.
+pub fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) => parse_hex(digits),
+        None => text.replace('_', "").parse().ok(),
+    }
+}
+
+// Parse hex digits (without the `0x`), with `_` allowed between them.
+pub(crate) fn parse_hex(digits: &str) -> Option<usize> {
+    usize::from_str_radix(&digits.replace('_', ""), 16).ok()
+}
+
+// Make the error for something wrong on line `line_number` (from 1) of a file, which is described
+// as `what`.
+pub(crate) fn line_error(what: &str, line_number: usize, message: &str) -> Error {
+    format!("Line {} of {}: {}", line_number, what, message).into()
+}
+
+// Go through the lines of `text` (in the subset of TOML read by `Config::parse()`) which aren't
+// blank or comments, giving each one's line number (from 1) along with what's on it. The errors
+// describe the file as `what`.
+pub(crate) fn parse_lines<'a>(text: &'a str, what: &'a str) -> impl Iterator<Item = Result<(usize, TomlLine)>> + 'a {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(move |(line_number, line)| {
+            if line.starts_with('[') {
+                return Ok((line_number, TomlLine::Table(line.to_string())));
+            }
+            match line.split_once('=') {
+                Some((key, value)) => match parse_value(value.trim()) {
+                    Ok(value) => Ok((line_number, TomlLine::KeyValue(key.trim().to_string(), value))),
+                    Err(err) => Err(line_error(what, line_number, &err)),
+                },
+                None => Err(line_error(what, line_number, "isn't `key = value`")),
+            }
+        })
+}
+
+// Cut off a comment (from a `#` which isn't in a string).
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            _ if escaped => {escaped = false;},
+            '\\' if in_string => {escaped = true;},
+            '"' => {in_string = !in_string;},
+            '#' if !in_string => {return &line[..idx];},
+            _ => {},
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> std::result::Result<Value, String> {
+    let (value, rest) = parse_value_prefix(text)?;
+    match rest.trim() {
+        "" => Ok(value),
+        rest => Err(format!("unexpected {} after the value", rest)),
+    }
+}
+
+// Parse a value from the start of `text`, giving it and whatever comes after it.
+fn parse_value_prefix(text: &str) -> std::result::Result<(Value, &str), String> {
+    let text = text.trim_start();
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '"' => {return Ok((Value::String(string), &rest[idx + 1..]));},
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, c @ ('"' | '\\'))) => string.push(c),
+                    _ => {return Err("unknown escape in string".to_string());},
+                },
+                c => string.push(c),
+            }
+        }
+        Err("string isn't closed".to_string())
+    } else if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), after));
+            }
+            let (value, after) = parse_value_prefix(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            match (rest.strip_prefix(','), rest.starts_with(']')) {
+                (Some(after), _) => {rest = after;},
+                (None, true) => {},
+                (None, false) => {return Err("array isn't closed".to_string());},
+            }
+        }
+    } else {
+        let end = text.find(|c: char| c == ',' || c == ']' || c.is_whitespace()).unwrap_or(text.len());
+        match parse_number(&text[..end]) {
+            Some(number) => Ok((Value::Integer(number), &text[end..])),
+            None => Err(format!("{} isn't a string, a whole number or an array", &text[..end])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_config() {
+        let config = Config::parse("poll_interval_ms = 250 # four times a second\n\
+                                    levels = [\"ly_20\", \"my#map\"]\n\
+                                    format = \"json\"\n\
+                                    \n\
+                                    [[variable]]\n\
+                                    name = \"countdown\"\n\
+                                    object = \"global\"\n\
+                                    dsg = 30\n\
+                                    [[variable]]\n\
+                                    object = \"GRP_TimerCourse_I3\"\n\
+                                    dsg = 0x10\n\
+                                    [[variable]]\n\
+                                    name = \"race_timer\"\n\
+                                    object = \"GRP_TimerCourse_I3\"\n\
+                                    offset = 84\n\
+                                    type = \"f32\"\n").unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(250));
+        assert_eq!(config.levels, vec!["ly_20".to_string(), "my#map".to_string()]);
+        assert!(config.watches_level("LY_20") && !config.watches_level("ly_10"));
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.variables, vec![
+            WatchedVariable{name: "countdown".to_string(), object: "global".to_string(), location: DsgLocation::Index(30), var_type: None},
+            WatchedVariable{name: "GRP_TimerCourse_I3.16".to_string(), object: "GRP_TimerCourse_I3".to_string(), location: DsgLocation::Index(16), var_type: None},
+            WatchedVariable{name: "race_timer".to_string(), object: "GRP_TimerCourse_I3".to_string(), location: DsgLocation::Offset(84), var_type: Some(DsgVarType::Float)},
+        ]);
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("level = \"ly_10\"").is_err());
+        assert!(Config::parse("format = \"xml\"").is_err());
+        assert!(Config::parse("levels = [\"ly_10\"").is_err());
+        assert!(Config::parse("[[variable]]\nname = \"x\"").is_err());
+        assert!(Config::parse("[[variable]]\nobject = \"x\"\noffset = 4\ntype = \"f64\"").is_err());
+    }
+}
@@ -3,6 +3,17 @@
   [Constants.cs](https://github.com/rtsonneveld/Rayman2FunBox/blob/master/Rayman2FunBox/Constants.cs).
   */
 
+/// Where `Rayman2.exe` is normally loaded. All the `OFF_*` addresses assume it's loaded here - see
+/// [`resolved()`](fn.resolved.html) for when it isn't.
+pub const IMAGE_BASE: usize = 0x400000;
+
+/// Move the address `offset` (one of the `OFF_*` constants) to where it really is, if
+/// `Rayman2.exe` has been loaded at `base` rather than at `IMAGE_BASE` - see also
+/// [`AddressResolver`](../relocation/struct.AddressResolver.html).
+pub const fn resolved(offset: usize, base: usize) -> usize {
+    offset.wrapping_sub(IMAGE_BASE).wrapping_add(base)
+}
+
 pub const OFF_DNM_P_ST_DYNAMICS_CAMERA_MECHANICS: usize = 0x4359D0;
 pub const OFF_FORCE_CAMERA_POS: usize = 0x473420;
 pub const OFF_FORCE_CAMERA_TGT: usize = 0x473480;
@@ -10,7 +21,14 @@ pub const OFF_FORCE_CAMERA_TGT: usize = 0x473480;
 pub const OFF_ENGINE_STRUCTURE: usize = 0x500380;
 pub const OFF_ENGINE_MODE: usize = OFF_ENGINE_STRUCTURE;
 pub const OFF_LEVEL_NAME: usize = OFF_ENGINE_STRUCTURE + 0x1F;
+// The engine timer's frame number, which comes 0xC before its delta time (not yet checked
+// against every build).
+pub const OFF_FRAME_COUNTER: usize = 0x500428;
+pub const OFF_DELTA_T: usize = 0x500434;
+pub const OFF_FRAME_LENGTH: usize = 0x50043C;
+pub const OFF_FRAMERATE: usize = 0x5036A8;
 pub const OFF_HEALTH_PTR_1: usize = 0x500584;
+// The layout of what these two point at isn't known yet, so nothing writes through them.
 pub const OFF_VOID_PTR: usize = 0x4B9BC8;
 pub const OFF_BRIGHTNESS_PTR: usize = 0x4A0488;
 pub const OFF_CAMERA_ARRAY_PTR: usize = 0x500550;
@@ -21,3 +39,46 @@ pub const OFF_INPUT_X: usize = 0x4B9BA0;
 pub const OFF_INPUT_Y: usize = 0x4B9BA4;
 
 pub const OFF_OBJECT_TYPES: usize = 0x005013E0;
+pub const OFF_DYNAM_WORLD: usize = 0x500FD0;
+pub const OFF_INACTIVE_DYNAM_WORLD: usize = 0x500FC4;
+pub const OFF_FATHER_SECTOR: usize = 0x500FC0;
+
+/// Well-known [`PointerPath`](../../memory/struct.PointerPath.html)s through the engine's
+/// structures. The relative ones start at a super-object, and should be followed with
+/// [`resolve_from()`](../../memory/struct.PointerPath.html#method.resolve_from).
+pub mod paths {
+    use super::*;
+    use crate::memory::PointerPath;
+
+    /// The main character's super-object.
+    pub const MAIN_CHAR: PointerPath = PointerPath::new(OFF_MAIN_CHAR).deref();
+    /// The main character's position (three `f32`s: x, y, z).
+    pub const MAIN_CHAR_POSITION: PointerPath = MAIN_CHAR.off(0x20).deref().off(0x4);
+    /// The first super-object in the dynamic world.
+    pub const DYNAMIC_WORLD_FIRST: PointerPath = PointerPath::new(OFF_DYNAM_WORLD).deref().off(0x8).deref();
+    /// The first super-object in the inactive dynamic world.
+    pub const INACTIVE_DYNAMIC_WORLD_FIRST: PointerPath = PointerPath::new(OFF_INACTIVE_DYNAM_WORLD).deref().off(0x8).deref();
+    /// The first sector in the father sector (the static world).
+    pub const FATHER_SECTOR_FIRST: PointerPath = PointerPath::new(OFF_FATHER_SECTOR).deref().off(0x8).deref();
+    /// Rayman's health (a `u8`, followed by the maximum health).
+    pub const HEALTH: PointerPath = PointerPath::new(OFF_HEALTH_PTR_1).deref().off(0x245);
+
+    /// A super-object's perso.
+    pub const PERSO: PointerPath = PointerPath::relative().off(0x4).deref();
+    /// A super-object's standard game info, which has the family, AI Model and name indices.
+    pub const STD_GAME: PointerPath = PERSO.off(0x4).deref();
+    /// A super-object's custom bits.
+    pub const CUSTOM_BITS: PointerPath = STD_GAME.off(0x24);
+    /// A super-object's dynamics.
+    pub const DYNAMICS: PointerPath = PERSO.off(0x8).deref().deref();
+    /// A super-object's mind (perso -> brain -> mind).
+    pub const MIND: PointerPath = PERSO.off(0xC).deref().deref();
+    /// A super-object's AI Model.
+    pub const AI_MODEL: PointerPath = MIND.deref();
+    /// A super-object's DSG memory.
+    pub const DSG_MEM: PointerPath = MIND.off(0xC).deref();
+    /// A super-object's current DSG memory buffer.
+    pub const DSG_BUFFER: PointerPath = DSG_MEM.off(0x8).deref();
+    /// A super-object's position (three `f32`s: x, y, z) in its global matrix.
+    pub const POSITION: PointerPath = PointerPath::relative().off(0x20).deref().off(0x4);
+}
@@ -0,0 +1,220 @@
+/*!
+  A live dashboard in the terminal, for practice streams and the like: the race countdown and
+  timer, Rayman's position and speed, the frame rate, and a scrollable list of the active
+  super-objects, redrawn every frame.
+
+  Start it with [`run()`](fn.run.html) (or `walkoflife dashboard`). Up and Down, Page Up and Page
+  Down, Home and End scroll the list of super-objects, and `q` or Esc quits.
+  */
+
+extern crate nix;
+
+use std::time::Duration;
+use ratatui::{DefaultTerminal,Frame,crossterm::event::{self,Event,KeyCode,KeyEventKind},layout::{Constraint,Layout},
+              text::Line,widgets::{Block,List,ListState,Paragraph}};
+use crate::{process::Rayman2Process,races::{Race,RaceTimer},telemetry::TelemetryFrame,timing::FrameTicker,error::{Error,Result}};
+
+// How long to wait for a frame before redrawing anyway (e.g. while the game is paused), so that
+// the keys still work.
+const FRAME_TIMEOUT: Duration = Duration::from_millis(100);
+// How often to read the list of super-objects again, in redraws. Walking the hierarchy every
+// frame would be wasteful, since it hardly ever changes.
+const OBJECTS_REFRESH: u32 = 30;
+// How far Page Up and Page Down scroll the list.
+const PAGE: u16 = 10;
+
+/// Show the dashboard for the game `r2`, until the user quits it.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+/// * Standard output needs to be a terminal.
+///
+/// ## Returns:
+/// * When the user quits, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the terminal can't be drawn on, or if the game goes away.
+///
+/// ## Details:
+/// * Anything which can't be read from the game (e.g. while a level is loading) is left blank,
+///   and the error is shown at the bottom, rather than stopping the dashboard.
+pub fn run(r2: &Rayman2Process) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_on(&mut terminal, r2);
+    ratatui::restore();
+    result
+}
+
+fn run_on(terminal: &mut DefaultTerminal, r2: &Rayman2Process) -> Result<()> {
+    let mut dashboard = Dashboard::default();
+    let mut frames = FrameTicker::new(r2.pid()).with_timeout(FRAME_TIMEOUT);
+    loop {
+        // Running out of time only means that the game isn't drawing frames right now.
+        if frames.tick().is_err() {
+            r2.check_alive()?;
+        }
+        dashboard.update(r2);
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        while event::poll(Duration::ZERO)? {
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {return Ok(());},
+                KeyCode::Up => dashboard.objects_state.scroll_up_by(1),
+                KeyCode::Down => dashboard.objects_state.scroll_down_by(1),
+                KeyCode::PageUp => dashboard.objects_state.scroll_up_by(PAGE),
+                KeyCode::PageDown => dashboard.objects_state.scroll_down_by(PAGE),
+                KeyCode::Home => dashboard.objects_state.select_first(),
+                KeyCode::End => dashboard.objects_state.select_last(),
+                _ => {},
+            }
+        }
+    }
+}
+
+// What's on the dashboard, as of the last update.
+#[derive(Default)]
+struct Dashboard {
+    // The timer for the race in the current level (if it's a race), and the level it's for.
+    race: Option<(String, RaceTimer)>,
+    telemetry: Option<TelemetryFrame>,
+    speed: Option<[f32; 3]>,
+    // The active super-objects, sorted by name.
+    objects: Vec<(String, usize)>,
+    objects_state: ListState,
+    updates: u32,
+    // The last thing which couldn't be read.
+    error: Option<String>,
+}
+
+impl Dashboard {
+    // Read everything again from the game.
+    fn update(&mut self, r2: &Rayman2Process) {
+        self.error = None;
+        let level = match r2.level_name() {
+            Ok(level) => level,
+            Err(err) => {
+                self.fail(err);
+                return;
+            },
+        };
+        if self.race.as_ref().map(|(race_level, _)| race_level) != Some(&level) {
+            self.race = Race::from_level(&level).map(|race| (level.clone(), RaceTimer::attach(r2.pid(), race)));
+            // The objects are all different in the new level.
+            self.updates = 0;
+        }
+
+        let state = match self.race.as_mut().map(|(_, timer)| timer.read()) {
+            Some(Ok(state)) => state,
+            Some(Err(err)) => {
+                self.fail(err);
+                None
+            },
+            None => None,
+        };
+        self.telemetry = match TelemetryFrame::read(r2.pid(), state) {
+            Ok(frame) => Some(frame),
+            Err(err) => {
+                self.fail(err);
+                None
+            },
+        };
+        self.speed = r2.main_character().and_then(|main_character| main_character.speed()).ok();
+
+        if self.updates.is_multiple_of(OBJECTS_REFRESH) {
+            match r2.active_super_objects() {
+                Ok(objects) => {
+                    self.objects = objects.into_iter().collect();
+                    self.objects.sort();
+                },
+                Err(err) => self.fail(err),
+            }
+        }
+        self.updates = self.updates.wrapping_add(1);
+    }
+
+    fn fail(&mut self, err: Error) {
+        self.error = Some(err.to_string());
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [race_area, character_area, objects_area, status_area] = Layout::vertical([
+            Constraint::Length(4), Constraint::Length(5), Constraint::Min(3), Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let telemetry = self.telemetry.as_ref();
+        let level = telemetry.map_or("?", |telemetry| telemetry.level.as_str());
+        let (countdown, timer) = match telemetry.map(|telemetry| (telemetry.countdown, telemetry.timer)) {
+            Some((Some(countdown), Some(timer))) => (format!("{} s", countdown), format_timer(timer)),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+        frame.render_widget(Paragraph::new(vec![
+            Line::from(format!("Countdown: {}", countdown)),
+            Line::from(format!("Timer:     {}", timer)),
+        ]).block(Block::bordered().title(format!(" {} ", level))), race_area);
+
+        let position = telemetry.and_then(|telemetry| telemetry.positions.iter().find(|(name, _)| name == "main_character"));
+        let framerate = telemetry.map_or("-".to_string(), |telemetry| format!("{:.1}", telemetry.framerate));
+        frame.render_widget(Paragraph::new(vec![
+            Line::from(format!("Position:   {}", position.map_or("-".to_string(), |(_, position)| format_vector(position)))),
+            Line::from(format!("Speed:      {}", self.speed.map_or("-".to_string(), |speed| {
+                format!("{} ({:.2}/s)", format_vector(&speed), speed.iter().map(|v| v * v).sum::<f32>().sqrt())
+            }))),
+            Line::from(format!("Frame rate: {}", framerate)),
+        ]).block(Block::bordered().title(" Rayman ")), character_area);
+
+        let items: Vec<String> = self.objects.iter().map(|(name, ptr)| format!("{:#010x}  {}", ptr, name)).collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" Active super-objects ({}) ", self.objects.len())))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, objects_area, &mut self.objects_state);
+
+        let status = match &self.error {
+            Some(err) => format!("Error: {}", err),
+            None => "q: quit  Up/Down/PgUp/PgDn/Home/End: scroll".to_string(),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+// Format a race timer (in milliseconds) as minutes, seconds and milliseconds.
+fn format_timer(ms: f32) -> String {
+    let ms = ms.max(0.0) as u64;
+    format!("{}:{:02}.{:03}", ms / 60000, ms / 1000 % 60, ms % 1000)
+}
+
+fn format_vector(vector: &[f32; 3]) -> String {
+    format!("({:.2}, {:.2}, {:.2})", vector[0], vector[1], vector[2])
+}
+
+#[cfg(test)]
+mod dashboard_tests {
+    use super::*;
+    use ratatui::{Terminal,backend::TestBackend};
+
+    #[test]
+    fn can_draw_dashboard() {
+        let mut dashboard = Dashboard{
+            telemetry: Some(TelemetryFrame{
+                level: "ly_10".to_string(),
+                countdown: Some(87),
+                timer: Some(72345.6),
+                framerate: 60.0,
+                positions: vec![("main_character".to_string(), [1.5, -2.0, 30.25])],
+                ..TelemetryFrame::default()
+            }),
+            speed: Some([3.0, 4.0, 0.0]),
+            objects: vec![("YLT_RaymanModel".to_string(), 0x1234)],
+            ..Dashboard::default()
+        };
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+
+        for expected in ["ly_10", "87 s", "1:12.345", "(1.50, -2.00, 30.25)", "(5.00/s)", "60.0", "0x00001234  YLT_RaymanModel"] {
+            assert!(screen.contains(expected), "{} isn't on the screen", expected);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+/*!
+  Functions for finding the Rayman 2 process, by looking through `/proc` directly rather than
+  relying on `pidof` or `pgrep` being available.
+  */
+
+extern crate nix;
+
+use std::{fs,path::{Path,PathBuf}};
+use nix::{libc::pid_t,unistd::{Pid,getpid}};
+use crate::error::{Error,Result};
+
+/// The names Rayman 2 is usually run as.
+pub const RAYMAN2_NAMES: &[&str] = &["Rayman2.exe"];
+
+// The kernel truncates process names in `comm` to this many bytes.
+const COMM_LEN: usize = 15;
+
+// Get the file name from a path, which may be a Windows path if it's from a Wine process.
+pub(crate) fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+// Check if a process called `comm` matches one of `names` (ignoring case). Since `comm` may have
+// been truncated by the kernel, a long name only has to start with it.
+fn comm_matches(comm: &str, names: &[&str]) -> bool {
+    let comm = comm.trim_end().to_lowercase();
+    names.iter().map(|name| name.to_lowercase()).any(|name| {
+        name == comm || (comm.len() == COMM_LEN && name.starts_with(&comm))
+    })
+}
+
+// Get the name and state of a process from the contents of its `/proc/<pid>/stat`. The name is
+// in brackets, and may itself contain spaces or brackets, so look for the last closing bracket.
+pub(crate) fn parse_stat(stat: &str) -> Option<(&str, char)> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let state = stat.get(close + 1..)?.trim_start().chars().next()?;
+    Some((stat.get(open + 1..close)?, state))
+}
+
+// Check if any argument in the null-separated `cmdline` of a process is a path to one of `names`
+// (ignoring case).
+fn cmdline_matches(cmdline: &[u8], names: &[&str]) -> bool {
+    cmdline
+        .split(|&x| x == 0)
+        .map(String::from_utf8_lossy)
+        .any(|arg| names.iter().any(|name| file_name(&arg).eq_ignore_ascii_case(name)))
+}
+
+// Check if any file mapped into a process, as listed in its `/proc/<pid>/maps`, is one of `names`
+// (ignoring case). Under Wine, the game's executable is mapped like any other file.
+fn maps_match(maps: &str, names: &[&str]) -> bool {
+    maps
+        .lines()
+        // The path is the last field, but may contain spaces.
+        .filter_map(|line| line.find('/').map(|idx| &line[idx..]))
+        .any(|path| names.iter().any(|name| file_name(path).eq_ignore_ascii_case(name)))
+}
+
+// Get the address at which the start of a file called one of `names` (ignoring case) is mapped,
+// and its path, according to a process's `/proc/<pid>/maps`. Each line starts with
+// `<start>-<end> <perms> <file offset>`.
+fn maps_module(maps: &str, names: &[&str]) -> Option<(usize, String)> {
+    maps
+        .lines()
+        .filter_map(|line| {
+            let path = &line[line.find('/')?..];
+            if !names.iter().any(|name| file_name(path).eq_ignore_ascii_case(name)) {
+                return None;
+            }
+            let mut fields = line.split_whitespace();
+            let start = fields.next()?.split('-').next()?;
+            let file_offset = fields.nth(1)?;
+            match usize::from_str_radix(file_offset, 16).ok()? {
+                0 => Some((usize::from_str_radix(start, 16).ok()?, path.to_string())),
+                _ => None,
+            }
+        })
+        .min()
+}
+
+pub(crate) fn read_maps(pid: Pid) -> Result<String> {
+    match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(maps) => Ok(maps),
+        Err(err) => Err(Error::from(err).context(format!("Unable to read memory map of process {}", pid))),
+    }
+}
+
+/// Find the address at which the executable with one of the given `names` (e.g. `Rayman2.exe`)
+/// is loaded in the process given by `pid`, which may not be where it usually is under newer
+/// versions of Wine.
+///
+/// ## Requirements:
+/// * This program needs to have permission to read `/proc/<pid>/maps`.
+///
+/// ## Returns:
+/// * On success, returns the base address of the executable.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the executable isn't mapped, or if the maps can't be read.
+pub fn module_base(pid: Pid, names: &[&str]) -> Result<usize> {
+    match maps_module(&read_maps(pid)?, names) {
+        Some((base, _)) => Ok(base),
+        None => Err(format!("{} isn't mapped into process {}", names.join(" or "), pid).into()),
+    }
+}
+
+/// Find the path of the executable with one of the given `names` (e.g. `Rayman2.exe`) which is
+/// loaded in the process given by `pid` - see [`module_base()`](fn.module_base.html).
+///
+/// ## Requirements:
+/// * This program needs to have permission to read `/proc/<pid>/maps`.
+///
+/// ## Returns:
+/// * On success, returns the (Unix) path of the executable.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the executable isn't mapped, or if the maps can't be read.
+pub fn module_path(pid: Pid, names: &[&str]) -> Result<PathBuf> {
+    match maps_module(&read_maps(pid)?, names) {
+        Some((_, path)) => Ok(PathBuf::from(path)),
+        None => Err(format!("{} isn't mapped into process {}", names.join(" or "), pid).into()),
+    }
+}
+
+/// Check if the process given by `pid` has a file with one of the given `names` (e.g.
+/// `Rayman2.exe`) mapped into its memory, i.e. if it's really running that program.
+///
+/// ## Requirements:
+/// * This program needs to have permission to read `/proc/<pid>/maps`.
+pub fn maps_executable(pid: Pid, names: &[&str]) -> bool {
+    match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(maps) => maps_match(&maps, names),
+        Err(_) => false,
+    }
+}
+
+// Get the PIDs of all processes in `/proc` (except this one), in ascending order.
+fn all_pids() -> Result<Vec<Pid>> {
+    let our_pid = getpid();
+    let mut pids: Vec<Pid> = match fs::read_dir("/proc") {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<pid_t>().ok())
+            .map(Pid::from_raw)
+            .filter(|&pid| pid != our_pid)
+            .collect(),
+        Err(err) => {return Err(Error::from(err).context("Unable to list processes in /proc"));},
+    };
+    pids.sort_by_key(|pid| pid.as_raw());
+    Ok(pids)
+}
+
+/// Find the PID of a running process with one of the given `names` (e.g. `Rayman2.exe`).
+///
+/// ## Details:
+/// * Names are matched case-insensitively, so `Rayman2.exe` will also find `rayman2.exe`.
+/// * Zombie processes are ignored.
+/// * Processes whose name (in `/proc/<pid>/stat`) matches are preferred.
+/// * If there aren't any, processes with a matching path anywhere in their command line are used
+///   instead, as long as they actually have the executable mapped into memory (see
+///   [`maps_executable()`](fn.maps_executable.html)). This skips wrappers (e.g. Proton's scripts)
+///   which just have the game's path as an argument.
+/// * As a last resort, any process with the executable mapped into memory is used (e.g. when the
+///   game is shown as `wine-preloader`).
+/// * If there are several matches, the one with the lowest PID is returned.
+///
+/// ## Returns:
+/// * On success, returns the PID of the process.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn find_attach_by_name(names: &[&str]) -> Result<Pid> {
+    // Processes can disappear while we look, so unreadable ones are just skipped.
+    let procs: Vec<(Pid, String)> = all_pids()?
+        .into_iter()
+        .filter_map(|pid| {
+            let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+            match parse_stat(&stat)? {
+                (_, 'Z') => None,
+                (comm, _) => Some((pid, comm.to_string())),
+            }
+        })
+        .collect();
+
+    if let Some(&(pid, _)) = procs.iter().find(|(_, comm)| comm_matches(comm, names)) {
+        return Ok(pid);
+    }
+
+    let by_cmdline = procs.iter().find(|&&(pid, _)| match fs::read(format!("/proc/{}/cmdline", pid)) {
+        Ok(cmdline) => cmdline_matches(&cmdline, names) && maps_executable(pid, names),
+        Err(_) => false,
+    });
+    if let Some(&(pid, _)) = by_cmdline {
+        return Ok(pid);
+    }
+
+    match procs.iter().find(|&&(pid, _)| maps_executable(pid, names)) {
+        Some(&(pid, _)) => Ok(pid),
+        None => Err(Error::ProcessNotFound(format!("no process called {}", names.join(" or ")))),
+    }
+}
+
+/// Check if the process given by `pid` is still running, i.e. if it exists and hasn't exited
+/// (even if its parent hasn't reaped it yet).
+pub fn is_alive(pid: Pid) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => !matches!(parse_stat(&stat), Some((_, 'Z')) | Some((_, 'X')) | None),
+        Err(_) => false,
+    }
+}
+
+/// Use the process given by `pid`, e.g. as given by the user.
+///
+/// ## Returns:
+/// * On success (i.e. if the process exists), returns `pid`.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn attach_pid(pid: Pid) -> Result<Pid> {
+    if Path::new(&format!("/proc/{}", pid)).is_dir() {
+        Ok(pid)
+    } else {
+        Err(Error::ProcessNotFound(format!("no process with PID {}", pid)))
+    }
+}
+
+#[cfg(test)]
+mod discovery_tests {
+    use super::*;
+
+    #[test]
+    fn can_match_process_names() {
+        assert!(comm_matches("rayman2.exe\n", RAYMAN2_NAMES));
+        assert!(comm_matches("Rayman2Practice", &["Rayman2Practice.exe"]));
+        assert!(!comm_matches("Rayman2", RAYMAN2_NAMES));
+        assert!(cmdline_matches(b"/usr/bin/wine\0C:\\GOG Games\\Rayman 2\\RAYMAN2.EXE\0", RAYMAN2_NAMES));
+        assert!(!cmdline_matches(b"/usr/bin/wine\0Rayman3.exe\0", RAYMAN2_NAMES));
+        assert_eq!(parse_stat("1234 (Rayman2 (1).exe) S 1 2 3"), Some(("Rayman2 (1).exe", 'S')));
+        assert!(maps_match("00400000-00401000 r--p 00000000 00:2a 123 /games/Rayman 2/rayman2.exe\n", RAYMAN2_NAMES));
+        let maps = "00410000-00411000 r-xp 00001000 00:2a 123 /games/Rayman 2/Rayman2.exe\n\
+                    00400000-00401000 r--p 00000000 00:2a 123 /games/Rayman 2/Rayman2.exe\n\
+                    7f0000000000-7f0000001000 r--p 00000000 00:2a 456 /usr/lib/wine/ntdll.so\n";
+        assert_eq!(maps_module(maps, RAYMAN2_NAMES), Some((0x400000, "/games/Rayman 2/Rayman2.exe".to_string())));
+        assert_eq!(maps_module(maps, &["Rayman3.exe"]), None);
+    }
+
+    #[test]
+    fn can_attach_to_self() {
+        assert_eq!(attach_pid(getpid()).ok(), Some(getpid()));
+        assert!(attach_pid(Pid::from_raw(-1)).is_err());
+        assert!(is_alive(getpid()));
+        assert!(!is_alive(Pid::from_raw(-1)));
+    }
+}
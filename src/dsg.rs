@@ -0,0 +1,480 @@
+/*!
+  Functions for making sense of the DSG variables ("designer variables") of objects in Rayman 2,
+  using the layout of the DsgMem and DsgVar structures as found in
+  [Raymap](https://github.com/byvar/raymap).
+  */
+
+extern crate nix;
+
+use std::{collections::HashMap,fmt};
+use nix::unistd::Pid;
+use crate::{memory::read_prims,superobject::SuperObject,utils::{read_object_types,get_active_super_object_names,World},error::Result};
+
+/// The types of DSG variable used by Rayman 2 (PC), in the order of their type numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DsgVarType {
+    Boolean,
+    Byte,
+    UByte,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    WayPoint,
+    Perso,
+    List,
+    Vector,
+    Comport,
+    Action,
+    Text,
+    GameMaterial,
+    Caps,
+    Graph,
+    PersoArray,
+    VectorArray,
+    FloatArray,
+    IntegerArray,
+    WayPointArray,
+    TextArray,
+    Unknown(u32),
+}
+
+impl From<u32> for DsgVarType {
+    fn from(type_number: u32) -> DsgVarType {
+        use DsgVarType::*;
+        match type_number {
+            0 => Boolean,
+            1 => Byte,
+            2 => UByte,
+            3 => Short,
+            4 => UShort,
+            5 => Int,
+            6 => UInt,
+            7 => Float,
+            8 => WayPoint,
+            9 => Perso,
+            10 => List,
+            11 => Vector,
+            12 => Comport,
+            13 => Action,
+            14 => Text,
+            15 => GameMaterial,
+            16 => Caps,
+            17 => Graph,
+            18 => PersoArray,
+            19 => VectorArray,
+            20 => FloatArray,
+            21 => IntegerArray,
+            22 => WayPointArray,
+            23 => TextArray,
+            other => Unknown(other),
+        }
+    }
+}
+
+impl DsgVarType {
+    /// Get the type whose value is the kind of Rust value called `name`: `bool`, `i8`, `u8`,
+    /// `i16`, `u16`, `i32`, `u32`, `f32`, `vector` (three `f32`s) or `ptr` (a perso, or anything
+    /// else pointed to).
+    pub fn from_name(name: &str) -> Option<DsgVarType> {
+        use DsgVarType::*;
+        match name {
+            "bool" => Some(Boolean),
+            "i8" => Some(Byte),
+            "u8" => Some(UByte),
+            "i16" => Some(Short),
+            "u16" => Some(UShort),
+            "i32" => Some(Int),
+            "u32" => Some(UInt),
+            "f32" => Some(Float),
+            "vector" => Some(Vector),
+            "ptr" => Some(Perso),
+            _ => None,
+        }
+    }
+
+    /// Get the number of bytes a variable of this type takes up, if it's always the same.
+    pub fn size(self) -> Option<usize> {
+        use DsgVarType::*;
+        match self {
+            Boolean | Byte | UByte => Some(1),
+            Short | UShort => Some(2),
+            Int | UInt | Float | WayPoint | Perso | Comport | Action | GameMaterial | Graph => Some(4),
+            Vector => Some(12),
+            _ => None,
+        }
+    }
+}
+
+/// The value of a DSG variable, decoded according to its type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DsgValue {
+    Boolean(bool),
+    Byte(i8),
+    UByte(u8),
+    Short(i16),
+    UShort(u16),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    Vector([f32; 3]),
+    /// A pointer into Rayman 2's memory (waypoints, persos, comports, actions, etc.).
+    Pointer(usize),
+    /// Anything we don't know how to decode (lists, arrays, etc.), as raw bytes.
+    Raw(Vec<u8>),
+}
+
+impl DsgValue {
+    /// Decode a value of type `var_type` from `bytes`, which should start at the variable's
+    /// offset in the DSG memory buffer. If there aren't enough bytes for the type, the value
+    /// is returned `Raw`.
+    pub fn decode(var_type: DsgVarType, bytes: &[u8]) -> DsgValue {
+        use DsgVarType::*;
+        fn word(bytes: &[u8], i: usize) -> [u8; 4] {
+            [bytes[i], bytes[i+1], bytes[i+2], bytes[i+3]]
+        }
+        match (var_type, bytes.len()) {
+            (Boolean, 1..) => DsgValue::Boolean(bytes[0] != 0),
+            (Byte, 1..) => DsgValue::Byte(bytes[0] as i8),
+            (UByte, 1..) => DsgValue::UByte(bytes[0]),
+            (Short, 2..) => DsgValue::Short(i16::from_le_bytes([bytes[0], bytes[1]])),
+            (UShort, 2..) => DsgValue::UShort(u16::from_le_bytes([bytes[0], bytes[1]])),
+            (Int, 4..) => DsgValue::Int(i32::from_le_bytes(word(bytes, 0))),
+            (UInt, 4..) => DsgValue::UInt(u32::from_le_bytes(word(bytes, 0))),
+            (Float, 4..) => DsgValue::Float(f32::from_le_bytes(word(bytes, 0))),
+            (Vector, 12..) => DsgValue::Vector([
+                f32::from_le_bytes(word(bytes, 0)),
+                f32::from_le_bytes(word(bytes, 4)),
+                f32::from_le_bytes(word(bytes, 8)),
+            ]),
+            (WayPoint, 4..) | (Perso, 4..) | (Comport, 4..) | (Action, 4..) | (GameMaterial, 4..) | (Graph, 4..) =>
+                DsgValue::Pointer(u32::from_le_bytes(word(bytes, 0)) as usize),
+            _ => DsgValue::Raw(bytes.to_vec()),
+        }
+    }
+}
+
+impl DsgValue {
+    /// Encode the value as the bytes it takes up in the DSG memory buffer. `Raw` values are
+    /// returned as they are.
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            DsgValue::Boolean(val) => vec![*val as u8],
+            DsgValue::Byte(val) => val.to_le_bytes().to_vec(),
+            DsgValue::UByte(val) => vec![*val],
+            DsgValue::Short(val) => val.to_le_bytes().to_vec(),
+            DsgValue::UShort(val) => val.to_le_bytes().to_vec(),
+            DsgValue::Int(val) => val.to_le_bytes().to_vec(),
+            DsgValue::UInt(val) => val.to_le_bytes().to_vec(),
+            DsgValue::Float(val) => val.to_le_bytes().to_vec(),
+            DsgValue::Vector(vec) => vec.iter().flat_map(|val| val.to_le_bytes()).collect(),
+            DsgValue::Pointer(ptr) => (*ptr as u32).to_le_bytes().to_vec(),
+            DsgValue::Raw(bytes) => bytes.clone(),
+        }
+    }
+
+    /// Check if this is the kind of value that a variable of type `var_type` holds, i.e. if
+    /// [`decode()`](#method.decode) would give back the same variant.
+    pub fn is_type(&self, var_type: DsgVarType) -> bool {
+        std::mem::discriminant(&DsgValue::decode(var_type, &self.encode())) == std::mem::discriminant(self)
+    }
+}
+
+impl fmt::Display for DsgValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DsgValue::Boolean(val) => write!(f, "{}", val),
+            DsgValue::Byte(val) => write!(f, "{}", val),
+            DsgValue::UByte(val) => write!(f, "{}", val),
+            DsgValue::Short(val) => write!(f, "{}", val),
+            DsgValue::UShort(val) => write!(f, "{}", val),
+            DsgValue::Int(val) => write!(f, "{}", val),
+            DsgValue::UInt(val) => write!(f, "{}", val),
+            DsgValue::Float(val) => write!(f, "{}", val),
+            DsgValue::Vector([x, y, z]) => write!(f, "({}, {}, {})", x, y, z),
+            DsgValue::Pointer(ptr) => write!(f, "{:#x}", ptr),
+            DsgValue::Raw(bytes) => write!(f, "{:02x?}", bytes),
+        }
+    }
+}
+
+/// The layout of one DSG variable, as given in an AI Model's DsgVar info table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DsgVarInfo {
+    /// The offset of the variable in the DSG memory buffer (i.e. what
+    /// [`get_dsg_var_ptr()`](../utils/fn.get_dsg_var_ptr.html) wants).
+    pub offset: usize,
+    /// The type of the variable.
+    pub var_type: DsgVarType,
+}
+
+/// The DSG memory of a super-object: where its variables live, and how they are laid out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DsgMemLayout {
+    /// Pointer to the current DSG memory buffer.
+    pub buffer: usize,
+    /// Size of the DSG memory buffer, in bytes.
+    pub size: usize,
+    /// The variables in the buffer, in index order.
+    pub vars: Vec<DsgVarInfo>,
+}
+
+impl DsgMemLayout {
+    /// Get the range of bytes in the buffer occupied by the variable at `index`. Since the info
+    /// table doesn't record sizes, each variable is taken to extend up to the next one (or to the
+    /// end of the buffer).
+    pub fn var_range(&self, index: usize) -> Option<std::ops::Range<usize>> {
+        let start = self.vars.get(index)?.offset;
+        let end = self.vars.iter()
+            .map(|info| info.offset)
+            .filter(|&offset| offset > start)
+            .min()
+            .unwrap_or(self.size);
+        Some(start.min(self.size)..end.min(self.size))
+    }
+
+    /// Decode every variable from `buffer`, which should be a copy of the DSG memory buffer (or
+    /// at least its start).
+    pub fn decode_all(&self, buffer: &[u8]) -> Vec<DsgVarEntry> {
+        (0..self.vars.len())
+            .filter_map(|index| {
+                let range = self.var_range(index)?;
+                let bytes = buffer.get(range.start..range.end.min(buffer.len())).unwrap_or(&[]);
+                let info = self.vars[index];
+                Some(DsgVarEntry{index, var_type: info.var_type, offset: info.offset, value: DsgValue::decode(info.var_type, bytes)})
+            })
+            .collect()
+    }
+}
+
+/// One DSG variable of an object, with its current value - see
+/// [`utils::dump_dsg_mem()`](../utils/fn.dump_dsg_mem.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DsgVarEntry {
+    /// The index of the variable, as in the names Raymap gives them (e.g. `30` for `Int_30`).
+    pub index: usize,
+    /// The type of the variable.
+    pub var_type: DsgVarType,
+    /// The offset of the variable in the DSG memory buffer.
+    pub offset: usize,
+    /// The value of the variable.
+    pub value: DsgValue,
+}
+
+impl fmt::Display for DsgVarEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}_{} (+{:#x}): {}", self.var_type, self.index, self.offset, self.value)
+    }
+}
+
+/// Read the layout of the DSG memory of the given `super_object` in the Rayman 2 process given by
+/// `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object, which has a mind with DSG memory.
+///
+/// ## Returns:
+/// * On success, returns a [`DsgMemLayout`](struct.DsgMemLayout.html) describing the object's DSG
+///   memory.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn read_dsg_mem_layout(r2pid: Pid, super_object: usize) -> Result<DsgMemLayout> {
+    let dsg_mem = SuperObject::new(r2pid, super_object).dsg_mem()?;
+    let off_dsg_var = dsg_mem.dsg_var(r2pid)?;
+    let buffer = dsg_mem.buffer(r2pid)?;
+    let (size, vars) = read_dsg_var(r2pid, off_dsg_var)?;
+
+    Ok(DsgMemLayout{buffer, size, vars})
+}
+
+/// Read the buffer size and the variable info table from the DsgVar structure at `off_dsg_var`
+/// (which belongs to an AI Model, and is shared by all the objects using it).
+pub(crate) fn read_dsg_var(r2pid: Pid, off_dsg_var: usize) -> Result<(usize, Vec<DsgVarInfo>)> {
+    let (off_infos, size) = match read_prims::<u32>(r2pid, off_dsg_var + 4, 2) {
+        Ok(vec) => (vec[0] as usize, vec[1] as usize),
+        Err(err) => {return Err(err.context("Unable to read DsgVar header"));},
+    };
+    let num_infos = match read_prims::<u8>(r2pid, off_dsg_var + 0xC, 1) {
+        Ok(vec) => vec[0] as usize,
+        Err(err) => {return Err(err.context("Unable to read number of DSG variables"));},
+    };
+
+    // Each info entry takes up 12 bytes: offset in buffer, type number and save type.
+    let vars = match read_prims::<u32>(r2pid, off_infos, 3 * num_infos) {
+        Ok(vec) => vec.chunks(3)
+            .map(|entry| DsgVarInfo{offset: entry[0] as usize, var_type: entry[1].into()})
+            .collect(),
+        Err(err) => {return Err(err.context("Unable to read DSG variable info table"));},
+    };
+
+    Ok((size, vars))
+}
+
+/// A change to a DSG variable, as seen by a [`DsgFlightRecorder`](struct.DsgFlightRecorder.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DsgVarChange {
+    /// The name of the super-object the variable belongs to.
+    pub object: String,
+    /// The index of the variable in the object's DSG memory.
+    pub index: usize,
+    /// The type of the variable.
+    pub var_type: DsgVarType,
+    pub old: DsgValue,
+    pub new: DsgValue,
+}
+
+impl fmt::Display for DsgVarChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}_{}: {} -> {}", self.object, self.var_type, self.index, self.old, self.new)
+    }
+}
+
+struct RecordedObject {
+    name: String,
+    layout: DsgMemLayout,
+    last: Vec<u8>,
+}
+
+/// A "flight recorder" for level scripting: it keeps a copy of the DSG memory of every active
+/// object, and reports every variable that changes between calls to
+/// [`tick()`](#method.tick).
+pub struct DsgFlightRecorder {
+    r2pid: Pid,
+    objects: Vec<RecordedObject>,
+}
+
+impl DsgFlightRecorder {
+    /// Start recording the DSG variables of all the active super-objects in the Rayman 2 process
+    /// given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `DsgFlightRecorder`, with the current values of all variables
+    ///   recorded.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn new(r2pid: Pid) -> Result<DsgFlightRecorder> {
+        let mut ret = DsgFlightRecorder{r2pid, objects: Vec::new()};
+        ret.rescan()?;
+        Ok(ret)
+    }
+
+    /// Find all the active super-objects again, e.g. after the level has changed.
+    /// This forgets all recorded values.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn rescan(&mut self) -> Result<()> {
+        let object_types = read_object_types(self.r2pid)?;
+        let active_super_objects: HashMap<String,usize> = get_active_super_object_names(self.r2pid, &object_types[2], World::ActiveDynamic, 0)?;
+
+        self.objects.clear();
+        for (name, super_object) in active_super_objects {
+            // Plenty of objects have no mind or no DSG memory - just skip them.
+            if let Ok(layout) = read_dsg_mem_layout(self.r2pid, super_object) {
+                if let Ok(last) = read_prims::<u8>(self.r2pid, layout.buffer, layout.size) {
+                    self.objects.push(RecordedObject{name, layout, last});
+                }
+            }
+        }
+        self.objects.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(())
+    }
+
+    /// Get the number of objects whose DSG memory is being recorded.
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Read the DSG memory of all the recorded objects again, and compare it to what was there
+    /// last time.
+    ///
+    /// ## Returns:
+    /// * On success, returns a `Vec` of all the variables that have changed, sorted by object name
+    ///   and variable index.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn tick(&mut self) -> Result<Vec<DsgVarChange>> {
+        let mut ret = Vec::new();
+
+        for object in self.objects.iter_mut() {
+            let current = match read_prims::<u8>(self.r2pid, object.layout.buffer, object.layout.size) {
+                Ok(vec) => vec,
+                Err(err) => {return Err(err.context(format!("Unable to read DSG memory of {}", object.name)));},
+            };
+            if current == object.last {
+                continue;
+            }
+
+            for (index, info) in object.layout.vars.iter().enumerate() {
+                let range = object.layout.var_range(index).unwrap();
+                if current[range.clone()] != object.last[range.clone()] {
+                    ret.push(DsgVarChange{
+                        object: object.name.clone(),
+                        index,
+                        var_type: info.var_type,
+                        old: DsgValue::decode(info.var_type, &object.last[range.clone()]),
+                        new: DsgValue::decode(info.var_type, &current[range]),
+                    });
+                }
+            }
+            object.last = current;
+        }
+
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod dsg_tests {
+    use super::*;
+
+    #[test]
+    fn can_decode_values() {
+        let bytes = [0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x40, 0x40];
+        assert_eq!(DsgValue::decode(DsgVarType::Float, &bytes[..4]), DsgValue::Float(1.0));
+        assert_eq!(DsgValue::decode(DsgVarType::Int, &bytes[..4]), DsgValue::Int(0x3f800000));
+        assert_eq!(DsgValue::decode(DsgVarType::Vector, &bytes), DsgValue::Vector([1.0, 2.0, 3.0]));
+        assert_eq!(DsgValue::decode(DsgVarType::Vector, &bytes[..4]), DsgValue::Raw(bytes[..4].to_vec()));
+        assert_eq!(DsgVarType::from(30), DsgVarType::Unknown(30));
+    }
+
+    #[test]
+    fn can_encode_values() {
+        assert_eq!(DsgValue::Float(1.0).encode(), vec![0x00, 0x00, 0x80, 0x3f]);
+        assert_eq!(DsgValue::decode(DsgVarType::Vector, &DsgValue::Vector([1.0, 2.0, 3.0]).encode()), DsgValue::Vector([1.0, 2.0, 3.0]));
+        assert!(DsgValue::Int(30).is_type(DsgVarType::Int));
+        assert!(!DsgValue::Int(30).is_type(DsgVarType::Float));
+        assert!(DsgValue::Pointer(0x1234).is_type(DsgVarType::Perso));
+    }
+
+    #[test]
+    fn var_ranges_end_at_next_var() {
+        let layout = DsgMemLayout{
+            buffer: 0,
+            size: 16,
+            vars: vec![
+                DsgVarInfo{offset: 4, var_type: DsgVarType::Int},
+                DsgVarInfo{offset: 0, var_type: DsgVarType::Boolean},
+                DsgVarInfo{offset: 8, var_type: DsgVarType::Float},
+            ],
+        };
+        assert_eq!(layout.var_range(0), Some(4..8));
+        assert_eq!(layout.var_range(1), Some(0..4));
+        assert_eq!(layout.var_range(2), Some(8..16));
+        assert_eq!(layout.var_range(3), None);
+
+        let entries = layout.decode_all(&[1, 0, 0, 0, 30, 0, 0, 0, 0, 0, 0x80, 0x3f]);
+        assert_eq!(entries[0].value, DsgValue::Int(30));
+        assert_eq!(entries[1].value, DsgValue::Boolean(true));
+        assert_eq!(entries[2].value, DsgValue::Float(1.0));
+        assert_eq!(entries[0].to_string(), "Int_0 (+0x4): 30");
+    }
+}
@@ -0,0 +1,135 @@
+/*!
+  An easing engine for writes to Rayman 2's memory, so that effects like forced camera positions
+  or brightness changes can move smoothly instead of snapping from one value to another.
+  */
+
+extern crate nix;
+
+use std::{thread,time::{Duration,Instant},sync::{Arc,atomic::{AtomicBool,Ordering}}};
+use nix::unistd::Pid;
+use crate::{memory::write_prims,error::Result};
+
+/// How often a [`Tween`](struct.Tween.html) writes by default: roughly once per frame at 60 FPS.
+pub const DEFAULT_STEP: Duration = Duration::from_millis(16);
+
+/// The shape of the curve used to get from one value to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Start slowly, speed up, then slow down again (`3t² - 2t³`).
+    SmoothStep,
+}
+
+impl Easing {
+    /// Map the fraction `t` of the time elapsed (clamped to `0..=1`) to the fraction of the way
+    /// from the start value to the end value.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::SmoothStep => t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    /// Interpolate element-wise between `from` and `to`, at fraction `t` of the time elapsed.
+    pub fn interpolate(self, from: &[f32], to: &[f32], t: f32) -> Vec<f32> {
+        let s = self.apply(t);
+        from.iter().zip(to.iter()).map(|(a, b)| a + (b - a) * s).collect()
+    }
+}
+
+/// A background thread which moves some `f32`s in Rayman 2's memory from one set of values to
+/// another over time.
+///
+/// The tween is cancelled if it is dropped before it finishes - use [`wait()`](#method.wait) to
+/// let it run to completion.
+pub struct Tween {
+    cancel: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Tween {
+    /// Start moving the `f32`s at `address` in the Rayman 2 process given by `r2pid` from `from` to
+    /// `to` over `duration`, writing new values every `step`. The final write is always exactly
+    /// `to`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    /// * `from` and `to` should be the same length.
+    pub fn start(r2pid: Pid, address: usize, from: Vec<f32>, to: Vec<f32>, duration: Duration, easing: Easing, step: Duration) -> Tween {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_cancel = cancel.clone();
+
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            loop {
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                let elapsed = start.elapsed();
+                if elapsed >= duration {
+                    break;
+                }
+                let values = easing.interpolate(&from, &to, elapsed.as_secs_f32() / duration.as_secs_f32());
+                if let Err(err) = write_prims(r2pid, address, &values) {
+                    return Err(err.context("Couldn't write eased values"));
+                }
+                thread::sleep(step);
+            }
+            match write_prims(r2pid, address, &to) {
+                Ok(_) => Ok(()),
+                Err(err) => Err(err.context("Couldn't write eased values")),
+            }
+        });
+
+        Tween{cancel, handle: Some(handle)}
+    }
+
+    /// Check if the tween has finished (successfully or not).
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map(|handle| handle.is_finished()).unwrap_or(true)
+    }
+
+    /// Stop the tween where it is, leaving the last values written in place.
+    pub fn cancel(mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Wait for the tween to finish.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong if a write failed.
+    pub fn wait(mut self) -> Result<()> {
+        match self.handle.take().unwrap().join() {
+            Ok(result) => result,
+            Err(_) => Err("Tween thread panicked".into()),
+        }
+    }
+}
+
+impl Drop for Tween {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod easing_tests {
+    use super::*;
+
+    #[test]
+    fn easing_curves_hit_endpoints() {
+        for easing in [Easing::Linear, Easing::SmoothStep].iter() {
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(0.5), 0.5);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert!(Easing::SmoothStep.apply(0.25) < Easing::Linear.apply(0.25));
+        assert_eq!(Easing::Linear.interpolate(&[0.0, 10.0], &[1.0, 20.0], 0.5), vec![0.5, 15.0]);
+    }
+}
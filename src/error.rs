@@ -0,0 +1,168 @@
+/*!
+  The error type used throughout this crate, so that callers can tell apart things like "the game
+  has quit", "we aren't allowed to debug it" and "a pointer path led nowhere".
+  */
+
+extern crate nix;
+
+use std::{fmt,io,string::FromUtf8Error};
+use nix::{errno::Errno,unistd::Pid};
+
+/// Something that went wrong while dealing with Rayman 2.
+#[derive(Debug)]
+pub enum Error {
+    /// No Rayman 2 process could be found. The string describes how the search failed.
+    ProcessNotFound(String),
+    /// The process exists, but we don't have permission to debug it (e.g. we need
+    /// `CAP_SYS_PTRACE`).
+    PermissionDenied(Pid),
+    /// The process has gone away (e.g. because the game quit or crashed).
+    ProcessGone(Pid),
+    /// Reading `len` bytes at `addr` failed (e.g. because the address isn't mapped).
+    ReadFailed { addr: usize, len: usize, errno: Errno },
+    /// Writing `len` bytes at `addr` failed.
+    WriteFailed { addr: usize, len: usize, errno: Errno },
+    /// The given address isn't mapped readable in the process (only reported when
+    /// [read validation](../memory/fn.set_read_validation.html) is on).
+    Unmapped(usize),
+    /// Following a pointer path from `base` failed at step `step` (counting only the pointers
+    /// read, with the first one, e.g. at `base` itself, as step 0), when trying to read a pointer
+    /// at `addr`.
+    BadPointerPath { base: usize, step: usize, addr: usize },
+    /// Some text wasn't valid UTF-8.
+    Utf8(FromUtf8Error),
+    /// An I/O error, e.g. while reading a file in `/proc`.
+    Io(io::Error),
+    /// A system call (other than reading or writing memory) failed.
+    Sys(Errno),
+    /// Anything else, described in text.
+    Other(String),
+    /// Another error, along with a description of what was being done when it happened.
+    Context { context: String, source: Box<Error> },
+}
+
+/// The result type used throughout this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap this error with a description of what was being done when it happened.
+    pub fn context<S: Into<String>>(self, context: S) -> Error {
+        Error::Context{context: context.into(), source: Box::new(self)}
+    }
+
+    /// Get the underlying error, without any context that has been added to it.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context{source, ..} => source.root(),
+            other => other,
+        }
+    }
+
+    /// Check if this error means the process has gone away (so it's worth trying to find it again).
+    pub fn is_process_gone(&self) -> bool {
+        matches!(self.root(), Error::ProcessGone(_))
+    }
+
+    /// Turn the error from a failed read of `len` bytes at `addr` in the process given by `pid`
+    /// into the appropriate variant.
+    pub(crate) fn from_read(err: nix::Error, pid: Pid, addr: usize, len: usize) -> Error {
+        match err {
+            nix::Error::Sys(Errno::ESRCH) => Error::ProcessGone(pid),
+            nix::Error::Sys(Errno::EPERM) => Error::PermissionDenied(pid),
+            nix::Error::Sys(errno) => Error::ReadFailed{addr, len, errno},
+            other => Error::Other(other.to_string()),
+        }
+    }
+
+    /// Turn the error from a failed write of `len` bytes at `addr` in the process given by `pid`
+    /// into the appropriate variant.
+    pub(crate) fn from_write(err: nix::Error, pid: Pid, addr: usize, len: usize) -> Error {
+        match err {
+            nix::Error::Sys(Errno::ESRCH) => Error::ProcessGone(pid),
+            nix::Error::Sys(Errno::EPERM) => Error::PermissionDenied(pid),
+            nix::Error::Sys(errno) => Error::WriteFailed{addr, len, errno},
+            other => Error::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ProcessNotFound(how) => write!(f, "Couldn't find Rayman 2 - {}", how),
+            Error::PermissionDenied(pid) => write!(f, "Not allowed to debug process {} (do we have CAP_SYS_PTRACE?)", pid),
+            Error::ProcessGone(pid) => write!(f, "Process {} has gone away", pid),
+            Error::ReadFailed{addr, len, errno} => write!(f, "Failed to read {} bytes at {:#x}: {}", len, addr, errno.desc()),
+            Error::WriteFailed{addr, len, errno} => write!(f, "Failed to write {} bytes at {:#x}: {}", len, addr, errno.desc()),
+            Error::Unmapped(addr) => write!(f, "Address {:#x} isn't mapped readable", addr),
+            Error::BadPointerPath{base, step, addr} => write!(f, "Pointer path from {:#x} broken at step {} (reading {:#x})", base, step, addr),
+            Error::Utf8(err) => write!(f, "Invalid UTF-8: {}", err),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Sys(errno) => write!(f, "System call failed: {}", errno.desc()),
+            Error::Other(desc) => write!(f, "{}", desc),
+            Error::Context{context, source} => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Utf8(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::Context{source, ..} => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Error {
+        Error::Utf8(err)
+    }
+}
+
+impl From<nix::Error> for Error {
+    fn from(err: nix::Error) -> Error {
+        match err {
+            nix::Error::Sys(errno) => Error::Sys(errno),
+            other => Error::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(desc: String) -> Error {
+        Error::Other(desc)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(desc: &str) -> Error {
+        Error::Other(desc.into())
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    #[test]
+    fn context_keeps_root() {
+        let err = Error::ProcessGone(Pid::from_raw(1234)).context("Couldn't read level name");
+        assert!(err.is_process_gone());
+        assert_eq!(err.to_string(), "Couldn't read level name: Process 1234 has gone away");
+        assert!(!Error::from("Something else").context("Oops").is_process_gone());
+
+        // Nothing can have this PID, so reading it fails as if the game had quit.
+        let err = crate::utils::read_object_types(Pid::from_raw(i32::MAX - 1)).unwrap_err();
+        assert!(err.is_process_gone());
+        assert!(err.to_string().starts_with("Unable to read family names: "));
+    }
+}
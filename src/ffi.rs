@@ -0,0 +1,363 @@
+/*!
+  C bindings for the core of the library (attaching to the game, reading and writing its memory,
+  the level name, the hierarchy and DSG variables), so that overlays in other languages (e.g.
+  Python with `ctypes`, or Node with `ffi-napi`) can use the pointer logic here instead of
+  rewriting it.
+
+  The header is `include/walkoflife.h`. It's generated by `cbindgen` (into the build's `OUT_DIR`)
+  whenever the crate is built with the `capi` feature, and a test checks that the committed copy
+  still matches. To update the committed copy after changing this module, build with
+  `WALKOFLIFE_UPDATE_HEADER=1` set. To build a shared library, run
+  `cargo rustc --release --lib --crate-type cdylib --features capi`.
+
+  ## Conventions:
+  * The game is represented by an opaque `WolProcess` handle, from `wol_attach()`, which must be
+    given back to `wol_detach()`. A handle shouldn't be used from two threads at once.
+  * Functions which return a pointer return `NULL` on failure, and functions which return a
+    number return a negative number on failure. Either way, `wol_last_error()` then says what
+    went wrong.
+  * Strings returned (as `char *`) are UTF-8 and belong to the caller, who must free them with
+    `wol_free_string()`. The more complex results are JSON strings.
+  */
+
+extern crate nix;
+
+use std::{cell::RefCell,ffi::CString,fmt::Write,os::raw::c_char,panic::{self,AssertUnwindSafe},ptr,slice};
+use nix::{libc::pid_t,unistd::Pid};
+use crate::{process::Rayman2Process,utils::{HierarchyNode,World},dsg::read_dsg_mem_layout,memory::{read_into,write_prims},
+            telemetry::{json_string,dsg_json},error::Result};
+
+/// A handle on a running game, for the C bindings.
+pub struct WolProcess(Rayman2Process);
+
+thread_local! {
+    // What went wrong in the last call which failed on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Run `f`, turning an error (or a panic, which mustn't unwind into C) into `fail`, and keeping
+// the message for `wol_last_error()`.
+fn ffi_try<T, F>(fail: T, f: F) -> T
+    where F: FnOnce() -> Result<T>
+{
+    let message = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => {return value;},
+        Ok(Err(err)) => err.to_string(),
+        Err(_) => "walkoflife panicked".to_string(),
+    };
+    // Error messages never contain NULs, but don't panic if one somehow does.
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+    fail
+}
+
+// Get the process behind `handle`.
+unsafe fn process<'a>(handle: *const WolProcess) -> Result<&'a Rayman2Process> {
+    match handle.as_ref() {
+        Some(handle) => Ok(&handle.0),
+        None => Err("The WolProcess handle is NULL".into()),
+    }
+}
+
+// Hand a string over to C.
+fn to_c_string(string: String) -> Result<*mut c_char> {
+    match CString::new(string) {
+        Ok(string) => Ok(string.into_raw()),
+        Err(err) => Err(format!("Couldn't pass a string containing a NUL at {}", err.nul_position()).into()),
+    }
+}
+
+/// Get a description of what went wrong in the last call which failed on this thread, or `NULL`
+/// if nothing has failed yet. The string belongs to the library, and stays valid until the next
+/// call which fails on this thread.
+#[no_mangle]
+pub extern "C" fn wol_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Free a string returned by one of the other functions. Passing `NULL` does nothing.
+///
+/// ## Safety
+/// * `string` must have come from this library, and mustn't be used (or freed) afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wol_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Attach to the game running as the process `pid`, or find `Rayman2.exe` if `pid` is 0.
+///
+/// ## Returns:
+/// * On success, returns a handle on the game, to be freed with `wol_detach()`.
+/// * Returns `NULL` if the game isn't running.
+#[no_mangle]
+pub extern "C" fn wol_attach(pid: pid_t) -> *mut WolProcess {
+    ffi_try(ptr::null_mut(), || {
+        let r2 = match pid {
+            0 => Rayman2Process::attach()?,
+            pid => Rayman2Process::attach_pid(Pid::from_raw(pid))?,
+        };
+        Ok(Box::into_raw(Box::new(WolProcess(r2))))
+    })
+}
+
+/// Free a handle from `wol_attach()`. Passing `NULL` does nothing.
+///
+/// ## Safety
+/// * `handle` must have come from `wol_attach()`, and mustn't be used (or freed) afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wol_detach(handle: *mut WolProcess) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Get the process ID of the game, or -1 if `handle` is `NULL`.
+///
+/// ## Safety
+/// * `handle` must be `NULL` or a live handle from `wol_attach()`.
+#[no_mangle]
+pub unsafe extern "C" fn wol_pid(handle: *const WolProcess) -> pid_t {
+    ffi_try(-1, || Ok(process(handle)?.pid().as_raw()))
+}
+
+/// Read up to `len` bytes from `addr` in the game into `buf` - see `memory::read_into()`.
+///
+/// ## Returns:
+/// * On success, returns the number of bytes read (which may be less than `len` at the end of
+///   the readable memory).
+/// * Returns -1 if nothing could be read.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+/// * `buf` must point to at least `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wol_read(handle: *const WolProcess, addr: usize, buf: *mut u8, len: usize) -> isize {
+    ffi_try(-1, || {
+        let r2 = process(handle)?;
+        if buf.is_null() {
+            return Err("The buffer is NULL".into());
+        }
+        Ok(r2.read_into(addr, slice::from_raw_parts_mut(buf, len))? as isize)
+    })
+}
+
+/// Write the `len` bytes at `buf` to `addr` in the game - see `memory::write_prims()`.
+///
+/// ## Returns:
+/// * On success, returns 0.
+/// * Returns -1 on failure.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+/// * `buf` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wol_write(handle: *const WolProcess, addr: usize, buf: *const u8, len: usize) -> i32 {
+    ffi_try(-1, || {
+        let r2 = process(handle)?;
+        if buf.is_null() {
+            return Err("The buffer is NULL".into());
+        }
+        r2.write_prims(addr, slice::from_raw_parts(buf, len))?;
+        Ok(0)
+    })
+}
+
+/// Get the name of the level currently open.
+///
+/// ## Returns:
+/// * On success, returns the name, to be freed with `wol_free_string()`.
+/// * Returns `NULL` on failure.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+#[no_mangle]
+pub unsafe extern "C" fn wol_level_name(handle: *const WolProcess) -> *mut c_char {
+    ffi_try(ptr::null_mut(), || to_c_string(process(handle)?.level_name()?))
+}
+
+/// Get the active super-objects, as a JSON object mapping each name to its address.
+///
+/// ## Returns:
+/// * On success, returns the JSON, to be freed with `wol_free_string()`.
+/// * Returns `NULL` on failure.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+#[no_mangle]
+pub unsafe extern "C" fn wol_active_super_objects_json(handle: *const WolProcess) -> *mut c_char {
+    ffi_try(ptr::null_mut(), || {
+        let mut objects: Vec<(String, usize)> = process(handle)?.active_super_objects()?.into_iter().collect();
+        objects.sort();
+        let entries: Vec<String> = objects.iter().map(|(name, ptr)| format!("{}:{}", json_string(name), ptr)).collect();
+        to_c_string(format!("{{{}}}", entries.join(",")))
+    })
+}
+
+/// Walk the active part of the engine hierarchy (the dynamic world) - see
+/// `Rayman2Process::walk_hierarchy()`.
+///
+/// ## Returns:
+/// * On success, returns the root as JSON, to be freed with `wol_free_string()`. Each node is an
+///   object with `name`, `ptr`, `ai_model` and `family` (`null` if it isn't an actor), and
+///   `children` (an array of nodes).
+/// * Returns `NULL` on failure.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+#[no_mangle]
+pub unsafe extern "C" fn wol_hierarchy_json(handle: *const WolProcess) -> *mut c_char {
+    ffi_try(ptr::null_mut(), || {
+        let mut json = String::new();
+        hierarchy_json(&process(handle)?.walk_hierarchy(World::ActiveDynamic, 0)?, &mut json);
+        to_c_string(json)
+    })
+}
+
+fn hierarchy_json(node: &HierarchyNode, json: &mut String) {
+    let optional = |name: &Option<String>| name.as_deref().map_or("null".to_string(), json_string);
+    let _ = write!(json, "{{\"name\":{},\"ptr\":{},\"ai_model\":{},\"family\":{},\"children\":[",
+                   json_string(&node.name), node.ptr, optional(&node.ai_model), optional(&node.family));
+    for (i, child) in node.children.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        hierarchy_json(child, json);
+    }
+    json.push_str("]}");
+}
+
+/// Read every DSG variable on the super-object at `super_object` - see `utils::dump_dsg_mem()`.
+///
+/// ## Returns:
+/// * On success, returns a JSON array, to be freed with `wol_free_string()`, with an object for
+///   each variable: its `index`, `type` (as in `DsgVarType`), `offset` and `value`.
+/// * Returns `NULL` on failure.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+#[no_mangle]
+pub unsafe extern "C" fn wol_dsg_json(handle: *const WolProcess, super_object: usize) -> *mut c_char {
+    ffi_try(ptr::null_mut(), || {
+        let entries: Vec<String> = process(handle)?.dump_dsg_mem(super_object)?.iter().map(|entry| {
+            format!("{{\"index\":{},\"type\":{},\"offset\":{},\"value\":{}}}",
+                    entry.index, json_string(&format!("{:?}", entry.var_type)), entry.offset, dsg_json(&entry.value))
+        }).collect();
+        to_c_string(format!("[{}]", entries.join(",")))
+    })
+}
+
+/// Read the raw bytes of the DSG variable at `index` on the super-object at `super_object` into
+/// `buf` (little-endian, as the game stores it).
+///
+/// ## Returns:
+/// * On success, returns the size of the variable, in bytes.
+/// * Returns -1 on failure, including if `len` is less than the size of the variable.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+/// * `buf` must point to at least `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wol_dsg_read(handle: *const WolProcess, super_object: usize, index: usize, buf: *mut u8, len: usize) -> isize {
+    ffi_try(-1, || {
+        let r2 = process(handle)?;
+        if buf.is_null() {
+            return Err("The buffer is NULL".into());
+        }
+        let layout = read_dsg_mem_layout(r2.pid(), super_object)?;
+        let range = match layout.var_range(index) {
+            Some(range) => range,
+            None => {return Err(format!("There is no DSG variable {}", index).into());},
+        };
+        if range.len() > len {
+            return Err(format!("DSG variable {} takes up {} bytes, which doesn't fit in {}", index, range.len(), len).into());
+        }
+        match read_into(r2.pid(), layout.buffer + range.start, slice::from_raw_parts_mut(buf, range.len())) {
+            Ok(_) => Ok(range.len() as isize),
+            Err(err) => Err(err.context(format!("Couldn't read DSG variable {}", index))),
+        }
+    })
+}
+
+/// Write the DSG variable at `index` on the super-object at `super_object`, as the `len` bytes at
+/// `buf` (little-endian, as the game stores it).
+///
+/// ## Returns:
+/// * On success, returns 0.
+/// * Returns -1 on failure, including if `len` isn't the size of the variable.
+///
+/// ## Safety
+/// * `handle` must be a live handle from `wol_attach()`.
+/// * `buf` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wol_dsg_write(handle: *const WolProcess, super_object: usize, index: usize, buf: *const u8, len: usize) -> i32 {
+    ffi_try(-1, || {
+        let r2 = process(handle)?;
+        if buf.is_null() {
+            return Err("The buffer is NULL".into());
+        }
+        let layout = read_dsg_mem_layout(r2.pid(), super_object)?;
+        let range = match layout.var_range(index) {
+            Some(range) => range,
+            None => {return Err(format!("There is no DSG variable {}", index).into());},
+        };
+        if range.len() != len {
+            return Err(format!("DSG variable {} takes up {} bytes, not {}", index, range.len(), len).into());
+        }
+        match write_prims(r2.pid(), layout.buffer + range.start, slice::from_raw_parts(buf, len)) {
+            Ok(_) => Ok(0),
+            Err(err) => Err(err.context(format!("Couldn't write DSG variable {}", index))),
+        }
+    })
+}
+
+/// Get the version of this library, e.g. `0.1.0`. The string belongs to the library, and never
+/// needs to be freed.
+#[no_mangle]
+pub extern "C" fn wol_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod ffi_tests {
+    use super::*;
+    use std::ffi::CStr;
+    use nix::unistd::getpid;
+
+    #[test]
+    fn can_call_from_c() {
+        let handle = wol_attach(getpid().as_raw());
+        assert!(!handle.is_null());
+        unsafe {
+            assert_eq!(wol_pid(handle), getpid().as_raw());
+            let values = [1u8, 2, 3, 4];
+            let mut buf = [0u8; 4];
+            assert_eq!(wol_read(handle, values.as_ptr() as usize, buf.as_mut_ptr(), 4), 4);
+            assert_eq!(buf, values);
+
+            // Reading from NULL fails, and says why.
+            assert_eq!(wol_read(handle, 0, buf.as_mut_ptr(), 4), -1);
+            assert!(!wol_last_error().is_null());
+            assert_eq!(wol_pid(ptr::null()), -1);
+            assert_eq!(CStr::from_ptr(wol_last_error()).to_str().unwrap(), "The WolProcess handle is NULL");
+            wol_detach(handle);
+        }
+        assert_eq!(unsafe{CStr::from_ptr(wol_version())}.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+
+        let mut json = String::new();
+        hierarchy_json(&HierarchyNode{
+            name: "world".to_string(), ptr: 16, ai_model: None, family: None,
+            children: vec![HierarchyNode{name: "YLT_RaymanModel".to_string(), ptr: 32, ai_model: Some("Rayman".to_string()), family: Some("Rayman".to_string()), children: vec![]}],
+        }, &mut json);
+        assert_eq!(json, "{\"name\":\"world\",\"ptr\":16,\"ai_model\":null,\"family\":null,\"children\":[\
+                          {\"name\":\"YLT_RaymanModel\",\"ptr\":32,\"ai_model\":\"Rayman\",\"family\":\"Rayman\",\"children\":[]}]}");
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/walkoflife.h"));
+        let committed = include_str!("../include/walkoflife.h");
+        assert!(generated == committed, "include/walkoflife.h is out of date - build with WALKOFLIFE_UPDATE_HEADER=1 to update it");
+    }
+}
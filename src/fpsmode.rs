@@ -0,0 +1,274 @@
+/*!
+  A first-person mode, like the one in Robin's FunBox: the camera is locked to Rayman's eyes,
+  looking where he's facing, and pulling the stick back makes him strafe backwards (by holding the
+  game's strafe button for the player) rather than turn round, which would spin the camera with
+  him. The mouse can be used to look around, too.
+
+  The [`FpsController`](struct.FpsController.html) does all this in a background thread between
+  [`start()`](struct.FpsController.html#method.start) and
+  [`stop()`](struct.FpsController.html#method.stop).
+  */
+
+extern crate nix;
+
+use std::{thread,sync::{Arc,atomic::{AtomicBool,Ordering}},time::Duration};
+use nix::unistd::Pid;
+#[cfg(feature = "xte")]
+use crate::utils::send_input;
+use crate::{input::{VirtualPad,MouseMotion,get_stick},camera::{force_camera,release_camera},utils::get_main_character,error::Result};
+
+/// How often the camera is moved and the stick is checked by default.
+pub const DEFAULT_FPS_INTERVAL: Duration = Duration::from_millis(5);
+
+// How far up or down the mouse can look (about 80°), so the camera never looks straight up or down.
+const MAX_PITCH: f32 = 1.4;
+
+/// How the first-person mode behaves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FpsConfig {
+    /// How far above Rayman's position the camera goes.
+    pub eye_height: f32,
+    /// How far in front of the camera it looks.
+    pub look_distance: f32,
+    /// The direction Rayman faces, in his own coordinates (i.e. before his rotation is applied).
+    pub forward_axis: [f32; 3],
+    /// How far the stick's y axis (as the game stores it) has to go for it to count as pulled
+    /// back: at or below this if it's negative, or at or above it if it's positive.
+    pub back_threshold: f32,
+    /// How far the camera turns (in radians) for each count the mouse moves, with
+    /// [`with_mouse_look()`](struct.FpsController.html#method.with_mouse_look).
+    pub mouse_sensitivity: f32,
+    /// How often to move the camera and check the stick.
+    pub interval: Duration,
+}
+
+impl Default for FpsConfig {
+    /// The camera 1.5 units up, looking along Rayman's local -y, with the stick pulled back
+    /// halfway or more, and about 400 counts of the mouse for a quarter turn.
+    ///
+    /// ## Details:
+    /// * The forward axis and the sign of the stick's y axis haven't been checked against every
+    ///   version of the game - if the camera looks the wrong way, or strafing starts when pushing
+    ///   forward, flip them.
+    fn default() -> FpsConfig {
+        FpsConfig{
+            eye_height: 1.5,
+            look_distance: 10.0,
+            forward_axis: [0.0, -1.0, 0.0],
+            back_threshold: -0.5,
+            mouse_sensitivity: 0.0025,
+            interval: DEFAULT_FPS_INTERVAL,
+        }
+    }
+}
+
+/// How to hold the game's strafe button.
+pub enum StrafeInput {
+    /// Press `key` on a [`VirtualPad`](../input/struct.VirtualPad.html) (which must have been
+    /// made with it).
+    Pad{pad: VirtualPad, key: u16},
+    /// Press `key` (a key name `xte` understands, e.g. `Control_L`) on the X display `display`,
+    /// with `xte` - see [`utils::send_input()`](../utils/fn.send_input.html).
+    #[cfg(feature = "xte")]
+    Xte{display: String, key: String},
+    /// Don't strafe, and only lock the camera.
+    None,
+}
+
+impl StrafeInput {
+    // Press or let go of the strafe button.
+    fn set(&mut self, held: bool) -> Result<()> {
+        match self {
+            StrafeInput::Pad{pad, key} => match held {
+                true => pad.press(*key),
+                false => pad.release(*key),
+            },
+            #[cfg(feature = "xte")]
+            StrafeInput::Xte{display, key} => send_input(display, &format!("{} {}", if held {"keydown"} else {"keyup"}, key)),
+            StrafeInput::None => Ok(()),
+        }
+    }
+}
+
+/// Locks the camera to Rayman's eyes and strafes for the player, while it's running.
+///
+/// ## Details:
+/// * Reads and writes which fail (e.g. while a level is loading) are ignored, and tried again next
+///   time round.
+/// * The camera is released, and the strafe button let go, when it's stopped or dropped.
+pub struct FpsController {
+    r2pid: Pid,
+    config: FpsConfig,
+    // The inputs, while it isn't running (the thread has them otherwise).
+    inputs: Option<Inputs>,
+    stop: Arc<AtomicBool>,
+    strafing: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<Inputs>>,
+}
+
+struct Inputs {
+    strafe: StrafeInput,
+    mouse: Option<MouseMotion>,
+}
+
+impl FpsController {
+    /// Make a controller for the Rayman 2 process given by `r2pid`, which strafes with `strafe`.
+    /// It doesn't do anything until it's started.
+    pub fn new(r2pid: Pid, config: FpsConfig, strafe: StrafeInput) -> FpsController {
+        FpsController{
+            r2pid,
+            config,
+            inputs: Some(Inputs{strafe, mouse: None}),
+            stop: Arc::new(AtomicBool::new(false)),
+            strafing: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    /// Look around with `mouse` as well, from the next time the controller is started. The look
+    /// goes back to straight ahead each time it's started.
+    pub fn with_mouse_look(mut self, mouse: MouseMotion) -> FpsController {
+        if let Some(inputs) = &mut self.inputs {
+            inputs.mouse = Some(mouse);
+        }
+        self
+    }
+
+    /// Check if the controller is running.
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Check if the strafe button is being held for the player right now.
+    pub fn is_strafing(&self) -> bool {
+        self.strafing.load(Ordering::Relaxed)
+    }
+
+    /// Start locking the camera and strafing, in a background thread. This does nothing if it's
+    /// already running.
+    pub fn start(&mut self) {
+        let mut inputs = match self.inputs.take() {
+            Some(inputs) => inputs,
+            None => {return;},
+        };
+        let (r2pid, config) = (self.r2pid, self.config);
+        let (stop, strafing) = (self.stop.clone(), self.strafing.clone());
+        stop.store(false, Ordering::Relaxed);
+
+        self.handle = Some(thread::spawn(move || {
+            // How far the mouse has turned the camera from straight ahead, and up or down.
+            let mut look = (0.0, 0.0);
+            while !stop.load(Ordering::Relaxed) {
+                if let Some(Ok((x, y))) = inputs.mouse.as_mut().map(|mouse| mouse.take_motion()) {
+                    look = turn(look, x, y, config.mouse_sensitivity);
+                }
+                let view = get_main_character(r2pid)
+                    .and_then(|main_character| Ok((main_character.position()?, main_character.rotation()?)));
+                if let Ok(Some((eye, target))) = view.map(|(position, rotation)| camera_for(position, rotation, look, &config)) {
+                    let _ = force_camera(r2pid, eye, target);
+                }
+
+                if let Ok((_, y)) = get_stick(r2pid) {
+                    let back = pulled_back(y, config.back_threshold);
+                    if back != strafing.load(Ordering::Relaxed) && inputs.strafe.set(back).is_ok() {
+                        strafing.store(back, Ordering::Relaxed);
+                    }
+                }
+                thread::sleep(config.interval);
+            }
+
+            if strafing.swap(false, Ordering::Relaxed) {
+                let _ = inputs.strafe.set(false);
+            }
+            inputs
+        }));
+    }
+
+    /// Stop locking the camera and strafing, and release the camera. This does nothing if it
+    /// isn't running.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the camera can't be released.
+    pub fn stop(&mut self) -> Result<()> {
+        let handle = match self.handle.take() {
+            Some(handle) => handle,
+            None => {return Ok(());},
+        };
+        self.stop.store(true, Ordering::Relaxed);
+        // If the thread panicked, the inputs are lost, and it can't be started again.
+        self.inputs = handle.join().ok();
+        release_camera(self.r2pid)
+    }
+}
+
+impl Drop for FpsController {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+// Work out where the camera goes (Rayman's eyes) and what it looks at (straight ahead, level,
+// then turned by `look`), given his `position` and `rotation`. Gives `None` if he's facing
+// straight up or down.
+fn camera_for(position: [f32; 3], rotation: [f32; 9], look: (f32, f32), config: &FpsConfig) -> Option<([f32; 3], [f32; 3])> {
+    let axis = config.forward_axis;
+    let forward: [f32; 3] = std::array::from_fn(|row| (0..3).map(|col| rotation[3*row + col] * axis[col]).sum());
+    let length = forward[0].hypot(forward[1]);
+    if length < 1e-6 {
+        return None;
+    }
+    let (x, y) = (forward[0] / length, forward[1] / length);
+    let ((yaw_sin, yaw_cos), (pitch_sin, pitch_cos)) = (look.0.sin_cos(), look.1.sin_cos());
+    let direction = [(x * yaw_cos - y * yaw_sin) * pitch_cos, (x * yaw_sin + y * yaw_cos) * pitch_cos, pitch_sin];
+
+    let eye = [position[0], position[1], position[2] + config.eye_height];
+    let target = std::array::from_fn(|i| eye[i] + direction[i] * config.look_distance);
+    Some((eye, target))
+}
+
+// Turn the `look` (anticlockwise from straight ahead, and up from level) by the mouse moving `x`
+// to the right and `y` down.
+fn turn(look: (f32, f32), x: i32, y: i32, sensitivity: f32) -> (f32, f32) {
+    let yaw = (look.0 - x as f32 * sensitivity) % std::f32::consts::TAU;
+    let pitch = (look.1 - y as f32 * sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    (yaw, pitch)
+}
+
+// Check if the stick's y axis is pulled back past `threshold`.
+fn pulled_back(y: f32, threshold: f32) -> bool {
+    match threshold < 0.0 {
+        true => y <= threshold,
+        false => y >= threshold,
+    }
+}
+
+#[cfg(test)]
+mod fpsmode_tests {
+    use super::*;
+
+    #[test]
+    fn can_place_camera() {
+        let config = FpsConfig{eye_height: 2.0, look_distance: 10.0, ..FpsConfig::default()};
+        // Turned 90° anticlockwise about z, so local -y is world +x.
+        let rotation = [0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let (eye, target) = camera_for([1.0, 2.0, 3.0], rotation, (0.0, 0.0), &config).unwrap();
+        assert_eq!(eye, [1.0, 2.0, 5.0]);
+        assert_eq!(target, [11.0, 2.0, 5.0]);
+
+        // Moving the mouse left by a quarter turn looks along +y instead, and it can't look
+        // straight up.
+        let look = turn((0.0, 0.0), -(std::f32::consts::FRAC_PI_2 / config.mouse_sensitivity) as i32, -10000, config.mouse_sensitivity);
+        assert!((look.0 - std::f32::consts::FRAC_PI_2).abs() < 0.01 && look.1 == MAX_PITCH);
+        let (_, target) = camera_for([1.0, 2.0, 3.0], rotation, (look.0, 0.0), &config).unwrap();
+        assert!((target[0] - 1.0).abs() < 0.1 && (target[1] - 12.0).abs() < 0.1 && target[2] == 5.0);
+
+        // Facing straight down, there's nowhere level to look.
+        let config = FpsConfig{forward_axis: [0.0, 0.0, -1.0], ..config};
+        assert_eq!(camera_for([0.0; 3], [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], (0.0, 0.0), &config), None);
+
+        assert!(pulled_back(-0.8, -0.5) && !pulled_back(-0.2, -0.5) && !pulled_back(0.8, -0.5));
+        assert!(pulled_back(0.8, 0.5));
+    }
+}
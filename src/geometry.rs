@@ -0,0 +1,182 @@
+/*!
+  Measuring things in the game's world: distances between objects, the closest vertex of a mesh
+  to a point, and casting rays against triangles (e.g. "how far is Rayman from the finish gate?",
+  or "what's underneath him?").
+
+  Points are `[f32; 3]`s (x, y, z) in the game's coordinates, where z points up.
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{utils::get_so_translation,error::Result};
+#[cfg(feature = "derive")]
+use crate::mesh::{Mesh,MeshInstance};
+
+// Rays which are this close to parallel with a triangle are taken to miss it.
+const PARALLEL_EPSILON: f32 = 1e-7;
+
+/// Get the straight-line distance between points `a` and `b`.
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    length(sub(b, a))
+}
+
+/// Get the straight-line distance between the positions of the super-objects `so_a` and `so_b`,
+/// in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give pointers to valid super-objects.
+///
+/// ## Returns:
+/// * On success, returns the distance.
+/// * Returns an `Err` variant describing what went wrong,
+///   if either super-object has no global matrix, or if the memory read fails.
+pub fn distance_between(r2pid: Pid, so_a: usize, so_b: usize) -> Result<f32> {
+    Ok(distance(get_so_translation(r2pid, so_a)?, get_so_translation(r2pid, so_b)?))
+}
+
+/// A ray: a starting point, and a direction to go in from there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// Where the ray starts.
+    pub origin: [f32; 3],
+    /// The direction of the ray. This doesn't need to be normalised, but the distances in a
+    /// [`RayHit`](struct.RayHit.html) are in units of its length.
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    /// Make a ray from `origin` through `target`, so that distances along it are in the game's
+    /// units.
+    pub fn towards(origin: [f32; 3], target: [f32; 3]) -> Ray {
+        let direction = sub(target, origin);
+        let length = length(direction);
+        Ray{origin, direction: match length > 0.0 {
+            true => direction.map(|c| c / length),
+            false => direction,
+        }}
+    }
+
+    /// Make a ray pointing straight down from `origin`.
+    pub fn down(origin: [f32; 3]) -> Ray {
+        Ray{origin, direction: [0.0, 0.0, -1.0]}
+    }
+
+    /// Get the point `distance` along the ray.
+    pub fn at(&self, distance: f32) -> [f32; 3] {
+        std::array::from_fn(|i| self.origin[i] + self.direction[i] * distance)
+    }
+
+    /// Find where the ray hits `triangle` (from either side), if it does.
+    ///
+    /// ## Returns:
+    /// * The distance along the ray to the hit, or `None` if it misses (or if the triangle is
+    ///   behind the ray's origin).
+    pub fn intersect(&self, triangle: &[[f32; 3]; 3]) -> Option<f32> {
+        // Möller and Trumbore's algorithm.
+        let edge1 = sub(triangle[1], triangle[0]);
+        let edge2 = sub(triangle[2], triangle[0]);
+        let p = cross(self.direction, edge2);
+        let det = dot(edge1, p);
+        if det.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let t_vec = sub(self.origin, triangle[0]);
+        let u = dot(t_vec, p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = cross(t_vec, edge1);
+        let v = dot(self.direction, q) / det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = dot(edge2, q) / det;
+        match distance >= 0.0 {
+            true => Some(distance),
+            false => None,
+        }
+    }
+}
+
+/// Where a ray hit a triangle, as found by [`raycast()`](fn.raycast.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// The distance along the ray.
+    pub distance: f32,
+    /// The point where the ray hit.
+    pub point: [f32; 3],
+    /// The triangle that was hit.
+    pub triangle: [[f32; 3]; 3],
+}
+
+/// Find the nearest of `triangles` hit by `ray`, if any.
+pub fn raycast<I: IntoIterator<Item = [[f32; 3]; 3]>>(ray: &Ray, triangles: I) -> Option<RayHit> {
+    triangles.into_iter()
+        .filter_map(|triangle| Some(RayHit{distance: ray.intersect(&triangle)?, point: [0.0; 3], triangle}))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+        .map(|hit| RayHit{point: ray.at(hit.distance), ..hit})
+}
+
+/// Find the nearest triangle of any of `meshes` (in the world, as placed by their transforms) hit
+/// by `ray`, if any.
+///
+/// ## Returns:
+/// * The index in `meshes` of the mesh which was hit, and the hit, or `None` if nothing was hit.
+#[cfg(feature = "derive")]
+pub fn raycast_meshes(ray: &Ray, meshes: &[MeshInstance]) -> Option<(usize, RayHit)> {
+    meshes.iter().enumerate()
+        .filter_map(|(i, instance)| Some((i, raycast(ray, instance.triangles())?)))
+        .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+}
+
+/// Find the vertex of `mesh` closest to `point` (both in the mesh's own coordinates).
+///
+/// ## Returns:
+/// * The index of the vertex and its distance from `point`, or `None` if the mesh has no
+///   vertices.
+#[cfg(feature = "derive")]
+pub fn closest_vertex(mesh: &Mesh, point: [f32; 3]) -> Option<(usize, f32)> {
+    mesh.vertices.iter()
+        .map(|&vertex| distance(vertex, point))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod geometry_tests {
+    use super::*;
+
+    #[test]
+    fn can_cast_rays() {
+        let floor = [[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [0.0, 10.0, 0.0]];
+        let lower = [[0.0, 0.0, -5.0], [10.0, 0.0, -5.0], [0.0, 10.0, -5.0]];
+        let ray = Ray::down([1.0, 1.0, 3.0]);
+        assert_eq!(ray.intersect(&floor), Some(3.0));
+        assert_eq!(Ray::down([9.0, 9.0, 3.0]).intersect(&floor), None);
+        assert_eq!(Ray{origin: [1.0, 1.0, 3.0], direction: [0.0, 0.0, 1.0]}.intersect(&floor), None);
+
+        let hit = raycast(&ray, vec![lower, floor]).unwrap();
+        assert_eq!(hit.point, [1.0, 1.0, 0.0]);
+        assert_eq!(hit.triangle, floor);
+        assert_eq!(Ray::towards([0.0; 3], [0.0, 3.0, 4.0]).at(5.0), [0.0, 3.0, 4.0]);
+        assert_eq!(distance([1.0, 1.0, 1.0], [1.0, 4.0, 5.0]), 5.0);
+    }
+}
@@ -0,0 +1,316 @@
+/*!
+  Ghosts for the Ly races: recording where Rayman was (and which way he was facing) on every
+  frame of a run, and playing it back on later runs by moving a marker object around, so that
+  there's something to race against.
+
+  A [`GhostRecorder`](struct.GhostRecorder.html) records a [`Ghost`](struct.Ghost.html), which
+  can be saved to a file (see [`Ghost::save()`](struct.Ghost.html#method.save)), and a
+  [`GhostPlayer`](struct.GhostPlayer.html) plays one back. Both are kept in step with the race
+  timer rather than the clock, so the ghost is where Rayman was at the same point in the race,
+  however the frames happen to fall.
+
+  Playing a ghost back writes to the game's memory, so this is only built with the `ghost`
+  feature (which is off by default).
+
+  The file format is plain text: a `race <LEVEL>` line, followed by one line per frame with the
+  race timer (in milliseconds), the position (three numbers) and the rotation (nine numbers, row
+  by row), separated by spaces. Lines starting with `#` are ignored.
+  */
+
+extern crate nix;
+
+use std::{fs,path::Path,time::Duration};
+use nix::unistd::Pid;
+use crate::{races::{Race,RaceTimer,RaceEvent},superobject::SuperObject,utils::{read_object_types,get_active_super_object_names,get_main_character,World},error::{Error,Result}};
+
+/// How often a recorder or player needs to be polled to catch every frame (at up to 250 frames
+/// per second). Polling once per frame with a [`FrameTicker`](../timing/struct.FrameTicker.html)
+/// is better still.
+pub const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+/// Where Rayman was on one frame of a run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GhostSample {
+    /// The race timer (in milliseconds).
+    pub time: f32,
+    /// The position (x, y, z).
+    pub position: [f32; 3],
+    /// The rotation (a 3x3 matrix, row by row).
+    pub rotation: [f32; 9],
+}
+
+/// A recorded run of one of the races.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ghost {
+    /// The race that was run.
+    pub race: Race,
+    /// The frames of the run, in order.
+    pub samples: Vec<GhostSample>,
+}
+
+impl Ghost {
+    /// Get the final time of the run (in milliseconds), if it has any frames.
+    pub fn time(&self) -> Option<f32> {
+        self.samples.last().map(|sample| sample.time)
+    }
+
+    /// Check if this run was faster than `other` (a run with no frames is never faster).
+    pub fn is_faster_than(&self, other: &Ghost) -> bool {
+        match (self.time(), other.time()) {
+            (Some(time), Some(other_time)) => time < other_time,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Work out where the ghost was when the race timer read `time`.
+    ///
+    /// ## Details:
+    /// * The position is interpolated between the frames either side of `time`, and the rotation
+    ///   is taken from the frame before it.
+    /// * Before the first frame, this gives the first frame, and after the last frame, the last
+    ///   frame.
+    pub fn sample_at(&self, time: f32) -> Option<GhostSample> {
+        let next = self.samples.iter().position(|sample| sample.time > time);
+        match next {
+            Some(0) => self.samples.first().copied(),
+            Some(i) => {
+                let (before, after) = (self.samples[i - 1], self.samples[i]);
+                let t = (time - before.time) / (after.time - before.time);
+                let mut position = before.position;
+                for (coord, target) in position.iter_mut().zip(after.position.iter()) {
+                    *coord += (target - *coord) * t;
+                }
+                Some(GhostSample{time, position, rotation: before.rotation})
+            },
+            None => self.samples.last().copied(),
+        }
+    }
+
+    /// Read a ghost from `text` - see the [module documentation](index.html) for the format.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `Ghost`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if there's a line which doesn't make sense, or no `race` line.
+    pub fn parse(text: &str) -> Result<Ghost> {
+        let mut race = None;
+        let mut samples = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(level) = line.strip_prefix("race ") {
+                race = match Race::from_level(level.trim()) {
+                    Some(race) => Some(race),
+                    None => {return Err(format!("Line {} of ghost has an unknown race: {}", i + 1, level).into());},
+                };
+                continue;
+            }
+            let numbers = match line.split_whitespace().map(|number| number.parse().ok()).collect::<Option<Vec<f32>>>() {
+                Some(numbers) if numbers.len() == 13 => numbers,
+                _ => {return Err(format!("Line {} of ghost isn't a frame", i + 1).into());},
+            };
+            let mut sample = GhostSample{time: numbers[0], position: [0.0; 3], rotation: [0.0; 9]};
+            sample.position.copy_from_slice(&numbers[1..4]);
+            sample.rotation.copy_from_slice(&numbers[4..13]);
+            samples.push(sample);
+        }
+        match race {
+            Some(race) => Ok(Ghost{race, samples}),
+            None => Err("Ghost doesn't say which race it's for".into()),
+        }
+    }
+
+    /// Read a ghost from the file at `path` - see [`parse()`](#method.parse).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ghost> {
+        match fs::read_to_string(&path) {
+            Ok(text) => Ghost::parse(&text),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't read ghost {}", path.as_ref().display()))),
+        }
+    }
+
+    /// Write the ghost as text - see the [module documentation](index.html) for the format.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("# walkoflife ghost\nrace {}\n", self.race.level_name());
+        for sample in &self.samples {
+            let numbers: Vec<String> = std::iter::once(sample.time)
+                .chain(sample.position.iter().copied())
+                .chain(sample.rotation.iter().copied())
+                .map(|number| number.to_string())
+                .collect();
+            text.push_str(&numbers.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Write the ghost to the file at `path` (replacing anything already there).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        match fs::write(&path, self.to_text()) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't write ghost {}", path.as_ref().display()))),
+        }
+    }
+}
+
+/// Records a ghost of each run of one of the races, in a Rayman 2 process.
+///
+/// ## Details:
+/// * A run starts when the race's level starts running, and finishes when the end of the race is
+///   reached (see [`RaceEvent::Finished`](../races/enum.RaceEvent.html#variant.Finished)). If the
+///   level stops or the race restarts first, the run is thrown away, as for a
+///   [`SplitRecorder`](../splits/struct.SplitRecorder.html).
+/// * A frame is recorded each time the race timer changes, so this should be polled at least as
+///   often as [`FRAME_POLL_INTERVAL`](constant.FRAME_POLL_INTERVAL.html).
+pub struct GhostRecorder {
+    r2pid: Pid,
+    timer: RaceTimer,
+    samples: Option<Vec<GhostSample>>,
+}
+
+impl GhostRecorder {
+    /// Start recording runs of `race`, in the Rayman 2 process given by `r2pid`.
+    pub fn attach(r2pid: Pid, race: Race) -> GhostRecorder {
+        GhostRecorder{r2pid, timer: RaceTimer::attach(r2pid, race), samples: None}
+    }
+
+    /// Read the race timer, and record Rayman's position and rotation if it's a new frame.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Some` [`Ghost`](struct.Ghost.html) if a run has just finished, or
+    ///   `None` otherwise.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn poll(&mut self) -> Result<Option<Ghost>> {
+        for event in self.timer.poll()? {
+            let time = match event {
+                RaceEvent::Started(state) => {
+                    self.samples = Some(Vec::new());
+                    state.time
+                },
+                RaceEvent::TimeChanged{new, ..} => new,
+                RaceEvent::CountdownChanged{..} => {continue;},
+                RaceEvent::Finished(_) => {
+                    return Ok(self.samples.take().map(|samples| Ghost{race: self.timer.race(), samples}));
+                },
+                RaceEvent::Stopped => {
+                    self.samples = None;
+                    continue;
+                },
+            };
+
+            if let Some(samples) = self.samples.as_mut() {
+                let rayman = get_main_character(self.r2pid)?;
+                samples.push(GhostSample{time, position: rayman.position()?, rotation: rayman.rotation()?});
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Plays a ghost back, by moving a marker object to wherever the ghost was at the same point in
+/// the race.
+///
+/// ## Details:
+/// * The marker is an object in the level with the given name, which should be one that isn't
+///   otherwise used in the race (or anything moving it will fight with the ghost).
+/// * The marker is moved each time this is polled, so this should be polled at least as often as
+///   [`FRAME_POLL_INTERVAL`](constant.FRAME_POLL_INTERVAL.html) for the ghost to move smoothly.
+pub struct GhostPlayer {
+    r2pid: Pid,
+    ghost: Ghost,
+    marker_name: String,
+    timer: RaceTimer,
+    marker: Option<SuperObject>,
+}
+
+impl GhostPlayer {
+    /// Start playing `ghost` back with the object called `marker_name`, in the Rayman 2 process
+    /// given by `r2pid`. The ghost's race doesn't need to be running yet.
+    pub fn attach(r2pid: Pid, ghost: Ghost, marker_name: &str) -> GhostPlayer {
+        let timer = RaceTimer::attach(r2pid, ghost.race);
+        GhostPlayer{r2pid, ghost, marker_name: marker_name.to_string(), timer, marker: None}
+    }
+
+    /// Get the ghost being played back.
+    pub fn ghost(&self) -> &Ghost {
+        &self.ghost
+    }
+
+    /// Move the marker to wherever the ghost was at the current race time, if the race is
+    /// running.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the marker can't be found, or if the memory read or write fails.
+    pub fn poll(&mut self) -> Result<()> {
+        let time = match self.timer.time()? {
+            Some(time) => time,
+            None => {
+                // The marker will have moved by the time the level is running again.
+                self.marker = None;
+                return Ok(());
+            },
+        };
+        let marker = match self.marker {
+            Some(marker) => marker,
+            None => {
+                let marker = self.find_marker()?;
+                self.marker = Some(marker);
+                marker
+            },
+        };
+
+        if let Some(sample) = self.ghost.sample_at(time) {
+            let moved = marker.set_position(sample.position).and_then(|_| marker.set_rotation(sample.rotation));
+            if let Err(err) = moved {
+                self.marker = None;
+                return Err(err.context("Couldn't move ghost marker"));
+            }
+        }
+        Ok(())
+    }
+
+    fn find_marker(&self) -> Result<SuperObject> {
+        let object_types = read_object_types(self.r2pid)?;
+        match get_active_super_object_names(self.r2pid, &object_types[2], World::ActiveDynamic, 0)?.get(&self.marker_name) {
+            Some(&ptr) => Ok(SuperObject::new(self.r2pid, ptr)),
+            None => Err(format!("No {} object in the level for the ghost", self.marker_name).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod ghost_tests {
+    use super::*;
+
+    #[test]
+    fn can_save_and_replay_ghosts() {
+        let rotation = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let ghost = Ghost{race: Race::WalkOfPower, samples: vec![
+            GhostSample{time: 100.0, position: [0.0, 0.0, 0.0], rotation},
+            GhostSample{time: 200.0, position: [10.0, -4.0, 2.5], rotation},
+        ]};
+
+        let parsed = Ghost::parse(&ghost.to_text()).unwrap();
+        assert_eq!(parsed, ghost);
+        assert_eq!(parsed.time(), Some(200.0));
+        assert!(parsed.is_faster_than(&Ghost{race: Race::WalkOfPower, samples: Vec::new()}));
+
+        assert_eq!(ghost.sample_at(150.0).unwrap().position, [5.0, -2.0, 1.25]);
+        assert_eq!(ghost.sample_at(0.0).unwrap().time, 100.0);
+        assert_eq!(ghost.sample_at(500.0).unwrap().time, 200.0);
+
+        assert!(Ghost::parse("100 0 0 0").is_err());
+        assert!(Ghost::parse("race ly_99").is_err());
+    }
+}
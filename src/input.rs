@@ -0,0 +1,575 @@
+/*!
+  Functions for injecting input into Rayman 2 without fighting the player for control of the
+  character, and for giving feedback to the player through their input devices.
+
+  Analog movement can be injected by writing the stick axes directly into the game's memory (see
+  [`set_stick()`](fn.set_stick.html)). Anything else (jumping, shooting, menus) goes through a
+  [`VirtualPad`](struct.VirtualPad.html), which the game sees as a real input device, and which
+  works on Wayland as well as X11. (With the `xte` feature, input can still be sent with `xte`
+  instead.)
+  */
+
+extern crate nix;
+
+use std::{fs::{self,File,OpenOptions},io::{self,Read,Write},mem::size_of,os::unix::{fs::OpenOptionsExt,io::AsRawFd},path::Path,thread,time::{Duration,Instant}};
+use nix::unistd::Pid;
+#[cfg(feature = "xte")]
+use crate::utils::send_input;
+use crate::{remote::RemotePtr,relocation::address,constants::*,error::{Error,Result}};
+
+/// The shortest time [`hold_direction_for()`](fn.hold_direction_for.html) waits between writes:
+/// roughly once per frame at 60 FPS.
+pub const STICK_WRITE_INTERVAL: Duration = Duration::from_millis(16);
+
+// The stick axes the game reads, in the process given by `r2pid`.
+fn input_axes(r2pid: Pid) -> (RemotePtr<f32>, RemotePtr<f32>) {
+    (RemotePtr::new(address(r2pid, OFF_INPUT_X)), RemotePtr::new(address(r2pid, OFF_INPUT_Y)))
+}
+
+/// The highest key code known to the Linux input subsystem (`KEY_MAX` in
+/// `linux/input-event-codes.h`).
+const KEY_MAX: usize = 0x2FF;
+
+// Event types and codes from `linux/input-event-codes.h`.
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const EV_FF: u16 = 0x15;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BUS_USB: u16 = 0x03;
+/// Force-feedback effect type for rumble (`FF_RUMBLE` in `linux/input.h`).
+const FF_RUMBLE: u16 = 0x50;
+
+// EVIOCGKEY(len): get the global key state of an evdev device as a bitmask.
+ioctl_read_buf!(eviocgkey, b'E', 0x18, u8);
+// EVIOCSFF: upload a force-feedback effect (the kernel writes back its ID).
+ioctl_write_ptr!(eviocsff, b'E', 0x80, FfEffect);
+// EVIOCRMFF: erase a force-feedback effect.
+ioctl_write_int!(eviocrmff, b'E', 0x81);
+// UI_SET_EVBIT, UI_SET_KEYBIT and UI_SET_ABSBIT: enable an event type or code on a uinput device.
+ioctl_write_int!(ui_set_evbit, b'U', 100);
+ioctl_write_int!(ui_set_keybit, b'U', 101);
+ioctl_write_int!(ui_set_absbit, b'U', 103);
+// UI_DEV_SETUP and UI_ABS_SETUP: describe a uinput device and its axes.
+ioctl_write_ptr!(ui_dev_setup, b'U', 3, UinputSetup);
+ioctl_write_ptr!(ui_abs_setup, b'U', 4, UinputAbsSetup);
+// UI_DEV_CREATE and UI_DEV_DESTROY: make the uinput device appear and disappear.
+ioctl_none!(ui_dev_create, b'U', 1);
+ioctl_none!(ui_dev_destroy, b'U', 2);
+
+/// `struct ff_effect` from `linux/input.h`, with the union only ever used as a
+/// `struct ff_rumble_effect`.
+#[repr(C)]
+struct FfEffect {
+    effect_type: u16,
+    id: i16,
+    direction: u16,
+    trigger_button: u16,
+    trigger_interval: u16,
+    replay_length: u16,
+    replay_delay: u16,
+    // The union is as big and aligned as struct ff_periodic_effect, which contains a pointer.
+    u: [u64; 4],
+}
+
+/// `struct input_event` from `linux/input.h`.
+#[repr(C)]
+struct InputEvent {
+    time: nix::libc::timeval,
+    event_type: u16,
+    code: u16,
+    value: i32,
+}
+
+/// `struct uinput_setup` from `linux/uinput.h`.
+#[repr(C)]
+struct UinputSetup {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    name: [u8; 80],
+    ff_effects_max: u32,
+}
+
+/// `struct uinput_abs_setup` from `linux/uinput.h`.
+#[repr(C)]
+struct UinputAbsSetup {
+    code: u16,
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// Write a single event to an evdev or uinput `device`.
+fn emit(device: &mut File, event_type: u16, code: u16, value: i32) -> io::Result<()> {
+    let event = InputEvent{
+        time: nix::libc::timeval{tv_sec: 0, tv_usec: 0},
+        event_type,
+        code,
+        value,
+    };
+    let bytes = unsafe{std::slice::from_raw_parts((&event as *const InputEvent).cast::<u8>(), size_of::<InputEvent>())};
+    device.write_all(bytes)
+}
+
+/// The state of every key on an evdev device, one bit per key code.
+pub(crate) type KeyBits = [u8; KEY_MAX / 8 + 1];
+
+/// Read which keys are held down on the evdev `device`.
+pub(crate) fn read_key_bits(device: &File) -> Result<KeyBits> {
+    let mut key_bits = [0u8; KEY_MAX / 8 + 1];
+    match unsafe{eviocgkey(device.as_raw_fd(), &mut key_bits)} {
+        Ok(_) => Ok(key_bits),
+        Err(err) => Err(Error::from(err).context("Couldn't query key state of input device")),
+    }
+}
+
+/// Check whether the key with code `key` is held down in `key_bits`.
+pub(crate) fn is_key_held(key_bits: &KeyBits, key: u16) -> bool {
+    key_bits.get(key as usize / 8).is_some_and(|bits| bits & (1 << (key % 8)) != 0)
+}
+
+/// A guard which refuses to inject input while a user-configured "manual control" key is held
+/// down on a given evdev device (e.g. `/dev/input/by-id/...-event-kbd`).
+///
+/// This makes it possible to take over from any automation at any moment during practice, just by
+/// holding the key.
+pub struct ManualControlGuard {
+    device: File,
+    key: u16,
+}
+
+impl ManualControlGuard {
+    /// Open the evdev device at `device_path`, and watch for the key with code `key` (as given in
+    /// `linux/input-event-codes.h`, e.g. `42` for `KEY_LEFTSHIFT`).
+    ///
+    /// ## Requirements:
+    /// * We need read permission on `device_path` (e.g. by being in the `input` group).
+    /// * `key` must be no greater than `KEY_MAX` (`0x2FF`).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `ManualControlGuard`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn open<P: AsRef<Path>>(device_path: P, key: u16) -> Result<ManualControlGuard> {
+        if key as usize > KEY_MAX {
+            return Err(format!("Key code {} is out of range", key).into());
+        }
+        match File::open(device_path.as_ref()) {
+            Ok(device) => Ok(ManualControlGuard{device, key}),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't open input device {}", device_path.as_ref().display()))),
+        }
+    }
+
+    /// Check whether the manual control key is currently held down.
+    ///
+    /// ## Returns:
+    /// * On success, returns `true` if the key is held, and `false` otherwise.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the device can't be queried.
+    pub fn is_held(&self) -> Result<bool> {
+        Ok(is_key_held(&read_key_bits(&self.device)?, self.key))
+    }
+
+    /// Like [`send_input()`](../utils/fn.send_input.html) (with the `xte` feature), but refuse to do anything if the manual
+    /// control key is held.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(true)` if the input was sent, or `Ok(false)` if it was withheld
+    ///   because the player has taken manual control.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    #[cfg(feature = "xte")]
+    pub fn send_input(&self, disp: &str, command: &str) -> Result<bool> {
+        if self.is_held()? {
+            return Ok(false);
+        }
+        send_input(disp, command)?;
+        Ok(true)
+    }
+}
+
+/// Set the analog stick axes in the Rayman 2 process given by `r2pid` to `x` and `y`, by writing
+/// them at `OFF_INPUT_X` and `OFF_INPUT_Y`.
+///
+/// ## Details:
+/// * The game reads the real controller every frame, so this only lasts for a frame or so. Use
+///   [`hold_direction_for()`](fn.hold_direction_for.html) to keep a direction held.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory write fails.
+pub fn set_stick(r2pid: Pid, x: f32, y: f32) -> Result<()> {
+    let (input_x, input_y) = input_axes(r2pid);
+    let write_axes = || -> Result<()> {
+        input_x.write(r2pid, x)?;
+        input_y.write(r2pid, y)
+    };
+    match write_axes() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't set stick axes")),
+    }
+}
+
+/// Read the analog stick axes (x, y) in the Rayman 2 process given by `r2pid`, from
+/// `OFF_INPUT_X` and `OFF_INPUT_Y`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the axes.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_stick(r2pid: Pid) -> Result<(f32, f32)> {
+    let (input_x, input_y) = input_axes(r2pid);
+    let read_axes = || -> Result<(f32, f32)> {
+        Ok((input_x.read(r2pid)?, input_y.read(r2pid)?))
+    };
+    match read_axes() {
+        Ok(axes) => Ok(axes),
+        Err(err) => Err(err.context("Couldn't read stick axes")),
+    }
+}
+
+/// Put the analog stick back in the middle, in the Rayman 2 process given by `r2pid` - see
+/// [`set_stick()`](fn.set_stick.html).
+pub fn neutral(r2pid: Pid) -> Result<()> {
+    set_stick(r2pid, 0.0, 0.0)
+}
+
+/// Hold the analog stick at `x` and `y` for `duration`, in the Rayman 2 process given by
+/// `r2pid`, and then put it back in the middle. This blocks until it's done.
+///
+/// ## Details:
+/// * The axes are written again every [`STICK_WRITE_INTERVAL`](constant.STICK_WRITE_INTERVAL.html),
+///   so that the game doesn't get a chance to overwrite them for long.
+/// * If a `guard` is given, this stops as soon as the player holds the manual control key.
+///
+/// ## Returns:
+/// * On success, returns `Ok(true)` if the direction was held for the whole `duration`, or
+///   `Ok(false)` if the player took manual control.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn hold_direction_for(r2pid: Pid, x: f32, y: f32, duration: Duration, guard: Option<&ManualControlGuard>) -> Result<bool> {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        if let Some(guard) = guard {
+            if guard.is_held()? {
+                return Ok(false);
+            }
+        }
+        set_stick(r2pid, x, y)?;
+        thread::sleep(STICK_WRITE_INTERVAL.min(duration.saturating_sub(start.elapsed())));
+    }
+    neutral(r2pid)?;
+    Ok(true)
+}
+
+/// Rumble feedback on the user's gamepad (or anything else with force-feedback support), via an
+/// evdev device like `/dev/input/by-id/...-event-joystick`.
+///
+/// This makes it possible to signal things during practice (e.g. being on PB pace) without having
+/// to look away from the game.
+pub struct Rumble {
+    device: File,
+    effect_id: i16,
+}
+
+impl Rumble {
+    /// Open the evdev device at `device_path` for force-feedback.
+    ///
+    /// ## Requirements:
+    /// * We need read and write permission on `device_path` (e.g. by being in the `input` group).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `Rumble`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn open<P: AsRef<Path>>(device_path: P) -> Result<Rumble> {
+        match OpenOptions::new().read(true).write(true).open(device_path.as_ref()) {
+            Ok(device) => Ok(Rumble{device, effect_id: -1}),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't open force-feedback device {}", device_path.as_ref().display()))),
+        }
+    }
+
+    /// Rumble for `duration`, with the given magnitudes for the strong (low-frequency) and weak
+    /// (high-frequency) motors. This returns immediately; the device stops by itself.
+    ///
+    /// ## Requirements:
+    /// * The device needs to support `FF_RUMBLE` effects.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn rumble(&mut self, strong: u16, weak: u16, duration: Duration) -> Result<()> {
+        let mut effect = FfEffect{
+            effect_type: FF_RUMBLE,
+            id: self.effect_id, // -1 gets us a new effect, otherwise we update the old one.
+            direction: 0,
+            trigger_button: 0,
+            trigger_interval: 0,
+            replay_length: duration.as_millis().min(u16::MAX as u128) as u16,
+            replay_delay: 0,
+            u: [strong as u64 | (weak as u64) << 16, 0, 0, 0],
+        };
+        if let Err(err) = unsafe{eviocsff(self.device.as_raw_fd(), &mut effect as *mut FfEffect)} {
+            return Err(Error::from(err).context("Couldn't upload rumble effect"));
+        }
+        self.effect_id = effect.id;
+
+        // Play once
+        match emit(&mut self.device, EV_FF, self.effect_id as u16, 1) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::from(err).context("Couldn't start rumble effect")),
+        }
+    }
+}
+
+impl Drop for Rumble {
+    fn drop(&mut self) {
+        if self.effect_id >= 0 {
+            let _ = unsafe{eviocrmff(self.device.as_raw_fd(), self.effect_id as _)};
+        }
+    }
+}
+
+/// Relative motion from the user's mouse (or anything else with `REL_X` and `REL_Y` axes), via an
+/// evdev device like `/dev/input/by-id/...-event-mouse`, e.g. for mouse-look in first-person mode.
+///
+/// ## Details:
+/// * The device isn't grabbed, so the motion still goes to the game (and the desktop) as well.
+pub struct MouseMotion {
+    device: File,
+}
+
+impl MouseMotion {
+    /// Open the evdev device at `device_path` to read its motion.
+    ///
+    /// ## Requirements:
+    /// * We need read permission on `device_path` (e.g. by being in the `input` group).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `MouseMotion`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn open<P: AsRef<Path>>(device_path: P) -> Result<MouseMotion> {
+        // Don't block, so that the motion can be checked once per frame.
+        match OpenOptions::new().read(true).custom_flags(nix::libc::O_NONBLOCK).open(device_path.as_ref()) {
+            Ok(device) => Ok(MouseMotion{device}),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't open input device {}", device_path.as_ref().display()))),
+        }
+    }
+
+    /// Open the first mouse in `/dev/input/by-id` (by name), with [`open()`](#method.open).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `MouseMotion`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if there's no mouse there, or it can't be opened.
+    pub fn find() -> Result<MouseMotion> {
+        let mut mice: Vec<_> = match fs::read_dir("/dev/input/by-id") {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.file_name().is_some_and(|name| name.to_string_lossy().ends_with("-event-mouse")))
+                .collect(),
+            Err(err) => {return Err(Error::from(err).context("Couldn't look for a mouse"));},
+        };
+        mice.sort();
+        match mice.first() {
+            Some(path) => MouseMotion::open(path),
+            None => Err("Couldn't find a mouse in /dev/input/by-id".into()),
+        }
+    }
+
+    /// Get how far the mouse has moved (in its own counts, right and down) since the last call
+    /// (or since it was opened).
+    ///
+    /// ## Returns:
+    /// * On success, returns the motion along x and y.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the device can't be read (e.g. it's been unplugged).
+    pub fn take_motion(&mut self) -> Result<(i32, i32)> {
+        let mut motion = (0, 0);
+        let mut buf = [0u8; 64 * size_of::<InputEvent>()];
+        loop {
+            let len = match self.device.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {return Err(Error::from(err).context("Couldn't read mouse motion"));},
+            };
+            // The kernel only ever gives us whole events.
+            for bytes in buf[..len].chunks_exact(size_of::<InputEvent>()) {
+                let event = unsafe{bytes.as_ptr().cast::<InputEvent>().read_unaligned()};
+                match (event.event_type, event.code) {
+                    (EV_REL, REL_X) => motion.0 += event.value,
+                    (EV_REL, REL_Y) => motion.1 += event.value,
+                    _ => {},
+                }
+            }
+        }
+        Ok(motion)
+    }
+}
+
+/// The range of the stick axes on a [`VirtualPad`](struct.VirtualPad.html) made with
+/// [`gamepad()`](struct.VirtualPad.html#method.gamepad).
+const STICK_RANGE: i32 = 32767;
+
+/// A virtual keyboard or gamepad, made with the kernel's `uinput` module, for pressing buttons
+/// that can't be injected by writing to the game's memory (e.g. jumping, shooting, or navigating
+/// menus).
+///
+/// ## Details:
+/// * The game sees this as just another input device, so it works under X11 and Wayland alike,
+///   and doesn't need to know which display the game is on.
+/// * The device disappears when the `VirtualPad` is dropped.
+pub struct VirtualPad {
+    device: File,
+}
+
+impl VirtualPad {
+    /// Make a virtual keyboard called `name`, which can press the keys with codes in `keys` (as
+    /// given in `linux/input-event-codes.h`, e.g. `57` for `KEY_SPACE`).
+    ///
+    /// ## Requirements:
+    /// * We need write permission on `/dev/uinput` (e.g. through a udev rule), and the `uinput`
+    ///   module needs to be loaded.
+    /// * Every key must be no greater than `KEY_MAX` (`0x2FF`).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `VirtualPad`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn keyboard(name: &str, keys: &[u16]) -> Result<VirtualPad> {
+        VirtualPad::create(name, keys, false)
+    }
+
+    /// Make a virtual gamepad called `name`, with the buttons with codes in `buttons` (e.g.
+    /// `0x130` for `BTN_SOUTH`), and an analog stick (`ABS_X` and `ABS_Y`).
+    ///
+    /// ## Requirements:
+    /// * As for [`keyboard()`](#method.keyboard).
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `VirtualPad`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn gamepad(name: &str, buttons: &[u16]) -> Result<VirtualPad> {
+        VirtualPad::create(name, buttons, true)
+    }
+
+    fn create(name: &str, keys: &[u16], stick: bool) -> Result<VirtualPad> {
+        if let Some(key) = keys.iter().find(|&&key| key as usize > KEY_MAX) {
+            return Err(format!("Key code {} is out of range", key).into());
+        }
+        let device = match OpenOptions::new().write(true).open("/dev/uinput") {
+            Ok(device) => device,
+            Err(err) => {return Err(Error::from(err).context("Couldn't open /dev/uinput"));},
+        };
+        let fd = device.as_raw_fd();
+
+        let mut setup = UinputSetup{
+            bustype: BUS_USB,
+            vendor: 0,
+            product: 0,
+            version: 1,
+            name: [0; 80],
+            ff_effects_max: 0,
+        };
+        // Leave room for the terminating NUL.
+        let name_len = name.len().min(setup.name.len() - 1);
+        setup.name[..name_len].copy_from_slice(&name.as_bytes()[..name_len]);
+
+        let set_up_device = || -> nix::Result<()> {
+            unsafe {
+                ui_set_evbit(fd, EV_KEY as _)?;
+                for &key in keys {
+                    ui_set_keybit(fd, key as _)?;
+                }
+                if stick {
+                    ui_set_evbit(fd, EV_ABS as _)?;
+                    for &axis in &[ABS_X, ABS_Y] {
+                        ui_set_absbit(fd, axis as _)?;
+                        let abs_setup = UinputAbsSetup{
+                            code: axis,
+                            value: 0,
+                            minimum: -STICK_RANGE,
+                            maximum: STICK_RANGE,
+                            fuzz: 0,
+                            flat: 0,
+                            resolution: 0,
+                        };
+                        ui_abs_setup(fd, &abs_setup)?;
+                    }
+                }
+                ui_dev_setup(fd, &setup)?;
+                ui_dev_create(fd)?;
+            }
+            Ok(())
+        };
+        match set_up_device() {
+            Ok(_) => Ok(VirtualPad{device}),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't create virtual input device {}", name))),
+        }
+    }
+
+    /// Send a batch of events, followed by a `SYN_REPORT` so that they take effect together.
+    fn send(&mut self, events: &[(u16, u16, i32)]) -> Result<()> {
+        let mut send_events = || -> io::Result<()> {
+            for &(event_type, code, value) in events {
+                emit(&mut self.device, event_type, code, value)?;
+            }
+            emit(&mut self.device, EV_SYN, SYN_REPORT, 0)
+        };
+        match send_events() {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::from(err).context("Couldn't send virtual input events")),
+        }
+    }
+
+    /// Press the key or button with code `key` (which must have been given when the device was
+    /// made), and keep it held until [`release()`](#method.release) is called.
+    pub fn press(&mut self, key: u16) -> Result<()> {
+        self.send(&[(EV_KEY, key, 1)])
+    }
+
+    /// Let go of the key or button with code `key`.
+    pub fn release(&mut self, key: u16) -> Result<()> {
+        self.send(&[(EV_KEY, key, 0)])
+    }
+
+    /// Press the key or button with code `key`, hold it for `duration`, and let go. This blocks
+    /// until it's done.
+    ///
+    /// ## Details:
+    /// * The game only checks its input once per frame, so a `duration` shorter than a frame or
+    ///   two may be missed.
+    pub fn tap(&mut self, key: u16, duration: Duration) -> Result<()> {
+        self.press(key)?;
+        thread::sleep(duration);
+        self.release(key)
+    }
+
+    /// Move the analog stick to `x` and `y` (each from `-1.0` to `1.0`).
+    ///
+    /// ## Requirements:
+    /// * The device must have been made with [`gamepad()`](#method.gamepad).
+    pub fn move_stick(&mut self, x: f32, y: f32) -> Result<()> {
+        let scale = |value: f32| (value.clamp(-1.0, 1.0) * STICK_RANGE as f32) as i32;
+        self.send(&[(EV_ABS, ABS_X, scale(x)), (EV_ABS, ABS_Y, scale(y))])
+    }
+}
+
+impl Drop for VirtualPad {
+    fn drop(&mut self) {
+        let _ = unsafe{ui_dev_destroy(self.device.as_raw_fd())};
+    }
+}
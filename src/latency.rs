@@ -0,0 +1,264 @@
+/*!
+  Measuring the input latency of the game: how long it takes from an input being injected to the
+  game reacting to it. This is handy for tuning a Wine/compositor setup, since every layer between
+  the controller and the game can add a frame or two.
+
+  Each trial injects an input (see [`Stimulus`](enum.Stimulus.html)), and then reads the game's
+  memory as fast as possible until the chosen [`Response`](enum.Response.html) is seen.
+  */
+
+extern crate nix;
+
+use std::{fmt,thread,time::{Duration,Instant}};
+use nix::unistd::Pid;
+use crate::{memory::read_prims,input::{set_stick,neutral,VirtualPad,STICK_WRITE_INTERVAL},utils::get_engine_mode,relocation::{address,resolve_path},constants::{paths,OFF_INPUT_X},error::Result};
+
+/// The input injected at the start of each trial.
+pub enum Stimulus<'a> {
+    /// Push the analog stick to `x` and `y` by writing to the game's memory (see
+    /// [`set_stick()`](../input/fn.set_stick.html)). This skips the input stack altogether, so it
+    /// only measures the game itself.
+    MemoryStick { x: f32, y: f32 },
+    /// Push the stick of a virtual gamepad (see
+    /// [`VirtualPad::gamepad()`](../input/struct.VirtualPad.html#method.gamepad)) to `x` and `y`.
+    /// This goes all the way through the kernel, Wine and the game.
+    PadStick { pad: &'a mut VirtualPad, x: f32, y: f32 },
+    /// Press the button with code `key` on a virtual keyboard or gamepad.
+    PadKey { pad: &'a mut VirtualPad, key: u16 },
+}
+
+impl Stimulus<'_> {
+    fn apply(&mut self, r2pid: Pid) -> Result<()> {
+        match self {
+            Stimulus::MemoryStick{x, y} => set_stick(r2pid, *x, *y),
+            Stimulus::PadStick{pad, x, y} => pad.move_stick(*x, *y),
+            Stimulus::PadKey{pad, key} => pad.press(*key),
+        }
+    }
+
+    fn undo(&mut self, r2pid: Pid) -> Result<()> {
+        match self {
+            Stimulus::MemoryStick{..} => neutral(r2pid),
+            Stimulus::PadStick{pad, ..} => pad.move_stick(0.0, 0.0),
+            Stimulus::PadKey{pad, key} => pad.release(*key),
+        }
+    }
+}
+
+/// The change in the game's state which shows that it has reacted to the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Response {
+    /// Any of the `len` bytes at `address` change.
+    Memory { address: usize, len: usize },
+    /// The game's own stick axes (at `OFF_INPUT_X`) change, which happens as soon as the game has
+    /// read the controller.
+    StickAxes,
+    /// The main character moves by more than `min_distance`.
+    MainCharacterMoves { min_distance: f32 },
+}
+
+impl Response {
+    // The bytes which are watched, for the responses which watch memory.
+    fn memory(self, r2pid: Pid) -> Option<(usize, usize)> {
+        match self {
+            Response::Memory{address, len} => Some((address, len)),
+            Response::StickAxes => Some((address(r2pid, OFF_INPUT_X), 8)),
+            Response::MainCharacterMoves{..} => None,
+        }
+    }
+}
+
+// What a Response was compared against at the start of a trial.
+enum Baseline {
+    Memory(Vec<u8>),
+    Position(usize, [f32; 3]),
+}
+
+impl Baseline {
+    fn read(r2pid: Pid, response: Response) -> Result<Baseline> {
+        match response.memory(r2pid) {
+            Some((address, len)) => Ok(Baseline::Memory(read_prims::<u8>(r2pid, address, len)?)),
+            None => {
+                let off_position = resolve_path(r2pid, &paths::MAIN_CHAR_POSITION)?;
+                Ok(Baseline::Position(off_position, read_position(r2pid, off_position)?))
+            },
+        }
+    }
+
+    fn has_changed(&self, r2pid: Pid, response: Response) -> Result<bool> {
+        match (self, response) {
+            (Baseline::Memory(before), Response::Memory{..} | Response::StickAxes) => match response.memory(r2pid) {
+                Some((address, len)) => Ok(read_prims::<u8>(r2pid, address, len)? != *before),
+                None => Err("Response changed during a trial".into()),
+            },
+            (Baseline::Position(off_position, before), Response::MainCharacterMoves{min_distance}) => {
+                let now = read_position(r2pid, *off_position)?;
+                let distance = before.iter().zip(now.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt();
+                Ok(distance > min_distance)
+            },
+            _ => Err("Response changed during a trial".into()),
+        }
+    }
+}
+
+fn read_position(r2pid: Pid, off_position: usize) -> Result<[f32; 3]> {
+    match read_prims::<f32>(r2pid, off_position, 3) {
+        Ok(vec) if vec.len() == 3 => Ok([vec[0], vec[1], vec[2]]),
+        Ok(_) => Err("Couldn't read the whole position".into()),
+        Err(err) => Err(err.context("Couldn't read position")),
+    }
+}
+
+/// Settings for [`measure_latency()`](fn.measure_latency.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyConfig {
+    /// How many trials to run.
+    pub trials: usize,
+    /// How long to wait between reads of the game's memory. Zero reads as fast as possible.
+    pub sample_interval: Duration,
+    /// How long to wait for the game to react before giving up on a trial.
+    pub timeout: Duration,
+    /// How long to wait after undoing the input, before the next trial, so that the game has
+    /// settled down again.
+    pub settle: Duration,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> LatencyConfig {
+        LatencyConfig{
+            trials: 20,
+            sample_interval: Duration::from_micros(250),
+            timeout: Duration::from_millis(500),
+            settle: Duration::from_millis(300),
+        }
+    }
+}
+
+/// The results of [`measure_latency()`](fn.measure_latency.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// The latency of each trial where the game reacted, in the order they were run.
+    pub latencies: Vec<Duration>,
+    /// The number of trials where the game didn't react before the timeout.
+    pub missed: usize,
+}
+
+impl LatencyStats {
+    /// Get the shortest latency, if the game reacted at all.
+    pub fn min(&self) -> Option<Duration> {
+        self.latencies.iter().min().copied()
+    }
+
+    /// Get the longest latency, if the game reacted at all.
+    pub fn max(&self) -> Option<Duration> {
+        self.latencies.iter().max().copied()
+    }
+
+    /// Get the mean latency, if the game reacted at all.
+    pub fn mean(&self) -> Option<Duration> {
+        match self.latencies.len() {
+            0 => None,
+            n => Some(self.latencies.iter().sum::<Duration>() / n as u32),
+        }
+    }
+
+    /// Get the latency which `percent` percent of the trials were no slower than (e.g. `50.0` for
+    /// the median), if the game reacted at all.
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let last = sorted.len().checked_sub(1)?;
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * last as f64).round() as usize;
+        sorted.get(rank).copied()
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |latency: Option<Duration>| latency.map_or(0.0, |latency| latency.as_secs_f64() * 1000.0);
+        write!(f, "{} trials: min {:.1} ms, median {:.1} ms, mean {:.1} ms, 95th percentile {:.1} ms, max {:.1} ms",
+               self.latencies.len(), ms(self.min()), ms(self.percentile(50.0)), ms(self.mean()), ms(self.percentile(95.0)), ms(self.max()))?;
+        if self.missed > 0 {
+            write!(f, " ({} missed)", self.missed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Measure how long the Rayman 2 process given by `r2pid` takes to react to `stimulus`, by
+/// injecting it over and over and waiting for the `response` each time. This blocks until it's
+/// done.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * A level needs to be running, with nothing else moving the character around.
+///
+/// ## Details:
+/// * A `Stimulus::MemoryStick` is written again every
+///   [`STICK_WRITE_INTERVAL`](../input/constant.STICK_WRITE_INTERVAL.html) while waiting, since
+///   the game overwrites it with the real controller every frame.
+/// * The latency is measured from just before the input is injected, so it includes the time
+///   taken to inject it. It can't be any more precise than `config.sample_interval`.
+///
+/// ## Returns:
+/// * On success, returns the [`LatencyStats`](struct.LatencyStats.html).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the level isn't running, or if injecting the input or reading the memory fails.
+pub fn measure_latency(r2pid: Pid, mut stimulus: Stimulus, response: Response, config: &LatencyConfig) -> Result<LatencyStats> {
+    if !get_engine_mode(r2pid)?.is_playing() {
+        return Err("The level isn't running".into());
+    }
+
+    let mut stats = LatencyStats::default();
+    for _ in 0..config.trials {
+        let baseline = Baseline::read(r2pid, response)?;
+        let start = Instant::now();
+        stimulus.apply(r2pid)?;
+        let mut last_apply = start;
+
+        let reacted = loop {
+            if baseline.has_changed(r2pid, response)? {
+                break Some(start.elapsed());
+            }
+            if start.elapsed() >= config.timeout {
+                break None;
+            }
+            if let Stimulus::MemoryStick{..} = stimulus {
+                if last_apply.elapsed() >= STICK_WRITE_INTERVAL {
+                    stimulus.apply(r2pid)?;
+                    last_apply = Instant::now();
+                }
+            }
+            thread::sleep(config.sample_interval);
+        };
+
+        stimulus.undo(r2pid)?;
+        match reacted {
+            Some(latency) => stats.latencies.push(latency),
+            None => {stats.missed += 1;},
+        }
+        thread::sleep(config.settle);
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod latency_tests {
+    use super::*;
+
+    #[test]
+    fn can_summarise_latencies() {
+        let stats = LatencyStats{
+            latencies: [40, 10, 30, 20, 50].iter().map(|&ms| Duration::from_millis(ms)).collect(),
+            missed: 1,
+        };
+        assert_eq!(stats.min(), Some(Duration::from_millis(10)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(50)));
+        assert_eq!(stats.mean(), Some(Duration::from_millis(30)));
+        assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(30)));
+        assert_eq!(stats.percentile(100.0), Some(Duration::from_millis(50)));
+        assert!(stats.to_string().ends_with("(1 missed)"));
+        assert_eq!(LatencyStats::default().percentile(50.0), None);
+    }
+}
@@ -0,0 +1,167 @@
+/*!
+  Traits for describing structures in Rayman 2's memory, so that they can be read and written as
+  a whole. Usually these are implemented with `#[derive(EngineStruct)]` (with the `derive`
+  feature), e.g.:
+
+  ```
+  use walkoflife::{EngineStruct,remote::RemotePtr};
+
+  #[derive(EngineStruct)]
+  struct Matrix {
+      #[offset(0x0)]
+      kind: u32,
+      #[offset(0x4)]
+      position: [f32; 3],
+  }
+  ```
+
+  This implements [`EngineField`](trait.EngineField.html) and
+  [`EngineStruct`](trait.EngineStruct.html) for `Matrix`, so a `RemotePtr<Matrix>` can be
+  [`load()`](../remote/struct.RemotePtr.html#method.load)ed, and it also makes a `MatrixFields`
+  trait, so that `ptr.position()` gives a `RemotePtr<[f32; 3]>`.
+  */
+
+extern crate nix;
+
+pub use nix::unistd::Pid;
+use crate::{memory::{read_struct,write_prims},remote::RemotePtr,error::Result};
+
+/// Something which can be a field of an engine structure: it has a fixed size in the game's
+/// memory, and can be converted from and to the bytes there.
+pub trait EngineField: Sized {
+    /// The number of bytes taken up in the game's memory.
+    const SIZE: usize;
+
+    /// Convert from the bytes in the game's memory (`bytes.len()` is always `SIZE`).
+    fn from_bytes(bytes: &[u8]) -> Self;
+
+    /// Convert to the bytes in the game's memory (`bytes.len()` is always `SIZE`).
+    fn to_bytes(&self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_engine_field {
+    ($($prim:ty),*) => {
+        $(
+            impl EngineField for $prim {
+                const SIZE: usize = std::mem::size_of::<$prim>();
+
+                fn from_bytes(bytes: &[u8]) -> $prim {
+                    let mut buf = [0u8; std::mem::size_of::<$prim>()];
+                    buf.copy_from_slice(bytes);
+                    <$prim>::from_le_bytes(buf)
+                }
+
+                fn to_bytes(&self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_engine_field!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl<T: EngineField, const N: usize> EngineField for [T; N] {
+    const SIZE: usize = N * T::SIZE;
+
+    fn from_bytes(bytes: &[u8]) -> [T; N] {
+        std::array::from_fn(|i| T::from_bytes(&bytes[i * T::SIZE..(i + 1) * T::SIZE]))
+    }
+
+    fn to_bytes(&self, bytes: &mut [u8]) {
+        for (i, elem) in self.iter().enumerate() {
+            elem.to_bytes(&mut bytes[i * T::SIZE..(i + 1) * T::SIZE]);
+        }
+    }
+}
+
+// Pointers are 32-bit in the game's memory.
+impl<T> EngineField for RemotePtr<T> {
+    const SIZE: usize = 4;
+
+    fn from_bytes(bytes: &[u8]) -> RemotePtr<T> {
+        RemotePtr::new(u32::from_bytes(bytes) as usize)
+    }
+
+    fn to_bytes(&self, bytes: &mut [u8]) {
+        (self.addr() as u32).to_bytes(bytes)
+    }
+}
+
+/// A structure in the game's memory, made up of fields at known offsets.
+pub trait EngineStruct: EngineField {
+    /// Read the whole structure at `ptr` in the process given by `pid`, in one go.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    fn read_from(pid: Pid, ptr: RemotePtr<Self>) -> Result<Self> {
+        read_struct(pid, ptr.addr())
+    }
+
+    /// Write each field of the structure to `ptr` in the process given by `pid`. Any bytes
+    /// between the fields are left alone.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    fn write_to(&self, pid: Pid, ptr: RemotePtr<Self>) -> Result<()>;
+}
+
+/// Write a single field `value` to `ptr` in the process given by `pid` - used by
+/// `#[derive(EngineStruct)]`.
+pub fn write_field<T: EngineField>(pid: Pid, ptr: RemotePtr<T>, value: &T) -> Result<()> {
+    let mut bytes = vec![0u8; T::SIZE];
+    value.to_bytes(&mut bytes);
+    write_prims(pid, ptr.addr(), &bytes)
+}
+
+impl<T: EngineStruct> RemotePtr<T> {
+    /// Read the structure pointed to - see
+    /// [`EngineStruct::read_from()`](../layout/trait.EngineStruct.html#method.read_from).
+    pub fn load(self, pid: Pid) -> Result<T> {
+        T::read_from(pid, self)
+    }
+
+    /// Write the structure pointed to - see
+    /// [`EngineStruct::write_to()`](../layout/trait.EngineStruct.html#method.write_to).
+    pub fn store(self, pid: Pid, value: &T) -> Result<()> {
+        value.write_to(pid, self)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod layout_tests {
+    use super::*;
+    use crate::EngineStruct;
+    use nix::unistd::getpid;
+
+    #[derive(EngineStruct, Debug, PartialEq)]
+    struct Example {
+        #[offset(0x0)]
+        kind: u8,
+        #[offset(0x4)]
+        position: [f32; 3],
+        #[offset(0x10)]
+        next: RemotePtr<Example>,
+    }
+
+    #[test]
+    fn can_load_and_store_structs() {
+        let mut raw = [0u32; 5];
+        raw[0] = 7;
+        raw[2] = 2.5f32.to_bits();
+        raw[4] = 0x1234;
+        let ptr = RemotePtr::<Example>::new(raw.as_ptr() as usize);
+
+        assert_eq!(<Example as EngineField>::SIZE, 0x14);
+        let example = ptr.load(getpid()).unwrap();
+        assert_eq!(example, Example{kind: 7, position: [0.0, 2.5, 0.0], next: RemotePtr::new(0x1234)});
+        assert_eq!(ptr.position().offset(0).addr(), ptr.addr() + 4);
+
+        ptr.store(getpid(), &Example{kind: 9, position: [1.0; 3], next: RemotePtr::new(0)}).unwrap();
+        // The compiler doesn't know that the write changed `raw`.
+        std::hint::black_box(&mut raw);
+        assert_eq!(raw[0], 9);
+        assert_eq!(raw[1], 1.0f32.to_bits());
+        assert_eq!(raw[4], 0);
+    }
+}
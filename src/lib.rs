@@ -1,3 +1,76 @@
+// The ioctl macros are only needed for input devices.
+#[cfg_attr(feature = "input", macro_use)]
+extern crate nix;
+// So that `#[derive(EngineStruct)]` works inside this crate too.
+extern crate self as walkoflife;
+
+pub mod error;
+pub mod backend;
 pub mod memory;
+pub mod remote;
+pub mod layout;
+#[cfg(feature = "derive")]
+pub mod structs;
+#[cfg(feature = "derive")]
+pub mod mesh;
 pub mod utils;
+pub mod discovery;
+pub mod suspend;
 pub mod constants;
+pub mod relocation;
+pub mod offsets;
+pub mod config;
+pub mod registry;
+#[cfg(feature = "input")]
+pub mod input;
+#[cfg(feature = "input")]
+pub mod latency;
+#[cfg(feature = "input")]
+pub mod replay;
+pub mod symbols;
+pub mod timing;
+pub mod dsg;
+pub mod profile;
+pub mod openspace;
+pub mod races;
+pub mod telemetry;
+pub mod process;
+pub mod superobject;
+pub mod aimodel;
+pub mod search;
+pub mod prompt;
+pub mod geometry;
+#[cfg(feature = "saves")]
+pub mod saves;
+#[cfg(feature = "splits")]
+pub mod splits;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "effects")]
+pub mod easing;
+#[cfg(feature = "mirror")]
+pub mod mirror;
+pub mod camera;
+pub mod movement;
+#[cfg(feature = "input")]
+pub mod fpsmode;
+#[cfg(feature = "practice")]
+pub mod practice;
+#[cfg(feature = "ghost")]
+pub mod ghost;
+#[cfg(feature = "savestate")]
+pub mod savestate;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+#[cfg(feature = "capi")]
+pub mod ffi;
+
+pub use error::{Error,Result};
+#[cfg(feature = "derive")]
+pub use walkoflife_derive::EngineStruct;
@@ -1,39 +1,575 @@
-use std::{time,thread::sleep};
-use walkoflife::{memory::read_prims,utils};
+use std::{env,path::Path,time,thread::sleep,sync::mpsc::TryRecvError,fmt::Display,str::FromStr};
+use nix::{libc::pid_t,unistd::Pid};
+use walkoflife::{Result,process::Rayman2Process,mirror::Mirror,races::{Race,RaceTimer},splits::{SplitRecorder,RunLog,Run},telemetry::TelemetryFrame,input::VirtualPad,latency::{measure_latency,Stimulus,Response,LatencyConfig},constants::*,saves,watch::{LevelWatcher,LevelEvent,FrameSpikeWatcher,FrameSpikeConfig},utils::{World,HierarchyNode},memory::{PointerPath,MAX_PATH_STEPS},search::NamePattern,prompt::LineEditor,config::{Config,OutputFormat,parse_number},offsets::{OffsetProfile,Build},registry::Registry,dsg::DsgValue,mesh::{Family,MeshInstance,export_obj,export_gltf}};
 
-fn main() -> Result<(), String> {
-    let r2pid = match utils::find_attach_rayman2() {
-        Ok(ans) => ans,
-        Err(errstr) => {
-            return Err(format!("{} - is Rayman2.exe running?", errstr));
-        }
+const USAGE: &str = "\
+Usage: walkoflife [<command>] [--pid <PID>] [--offsets <FILE>] [<arguments>]
+
+Commands:
+  watch [<options>]               Watch the Walk of Life, or the levels in the config (the
+                                  default, if no command is given)
+  dump-hierarchy [<world>]        Print the hierarchy of super-objects (world: dynamic,
+                                  inactive or static; dynamic by default)
+  dump-dsg <object>               Print the DSG variables of a super-object (by name or address)
+  read <address> <type>           Read a value (type: u8, i8, u16, i16, u32, i32, f32, f64,
+                                  ptr or str)
+  write <address> <type> <value>  Write a value
+  export-mesh <family> <file>     Export the meshes of the family at the given address to an
+                                  .obj or .gltf file
+  dashboard                       Show a live dashboard in the terminal (with the tui feature)
+  repl                            Start an interactive prompt with the commands below
+  help                            Print this message
+
+Options for watch:
+  --backup-saves <DIR> --mirror <NAME> --log-spikes <MS> --measure-latency <TRIALS>
+  --splits <FILE> --format text|json --config <FILE>
+  --metrics <ADDRESS> (with the metrics feature) --serve <ADDRESS> (with the server feature)
+
+Options for every command:
+  --offsets <FILE>                Read the game at the addresses in an offset profile, for a
+                                  build other than the one the built-in addresses are for
+                                  (without it, a warning is printed for an unknown build)
+
+Options for every command (with the tracing feature):
+  --log-level <LEVEL>             Log pointer paths, hierarchy walks and watcher ticks to
+                                  stderr (level: error, warn, info, debug or trace)
+  --log-json                      Log as one JSON object per line (at debug, unless
+                                  --log-level is given)";
+
+const REPL_HELP: &str = "\
+  list [<pattern>]                List the active super-objects (matching a glob or /regex/)
+  hierarchy [<world>]             Print the hierarchy of super-objects
+  dsg <object>                    Print the DSG variables of a super-object
+  path <start> <step>...          Follow a pointer path, where each step is * (read a pointer
+                                  and go where it points) or an offset to add, e.g.
+                                  path YLT_RaymanModel 4 * 4 * 8
+  read <address> <type>           Read a value
+  write <address> <type> <value>  Write a value
+  help                            Print this message
+  quit                            Leave the prompt (so does Ctrl-D)
+
+Addresses can be numbers (in hex with 0x) or the names of active super-objects, which Tab
+completes.";
+
+const REPL_COMMANDS: &[&str] = &["list", "hierarchy", "dsg", "path", "read", "write", "help", "quit"];
+const TYPES: &[&str] = &["u8", "i8", "u16", "i16", "u32", "i32", "f32", "f64", "ptr", "str"];
+const WORLDS: &[&str] = &["dynamic", "inactive", "static"];
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--pid` can go anywhere, and applies to every command.
+    let pid = match args.iter().position(|arg| arg == "--pid") {
+        Some(idx) => match args.get(idx + 1).and_then(|pid| pid.parse::<pid_t>().ok()) {
+            Some(pid) => {
+                args.drain(idx..idx + 2);
+                Some(Pid::from_raw(pid))
+            },
+            None => {return Err("--pid needs a process ID".into());},
+        },
+        None => None,
+    };
+    let offsets = match args.iter().position(|arg| arg == "--offsets") {
+        Some(idx) => match args.get(idx + 1).map(OffsetProfile::load) {
+            Some(profile) => {
+                args.drain(idx..idx + 2);
+                Some(profile?)
+            },
+            None => {return Err("--offsets needs a file".into());},
+        },
+        None => None,
+    };
+    #[cfg(feature = "tracing")]
+    init_logging(&mut args)?;
+    // Without a command, the options are for `watch`, as they always were.
+    let (command, args) = match args.split_first() {
+        Some((command, rest)) if !command.starts_with("--") => (command.as_str(), rest),
+        _ => ("watch", &args[..]),
     };
 
-    let interval = time::Duration::from_millis(1000);
+    match command {
+        "help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        "watch" => watch(attach(pid, &offsets)?, args),
+        "dump-hierarchy" => dump_hierarchy(&attach(pid, &offsets)?, args),
+        "dump-dsg" => dump_dsg(&attach(pid, &offsets)?, args),
+        "read" => read(&attach(pid, &offsets)?, args),
+        "write" => write(&attach(pid, &offsets)?, args),
+        "export-mesh" => export_mesh(&attach(pid, &offsets)?, args),
+        "repl" => repl(&attach(pid, &offsets)?),
+        #[cfg(feature = "tui")]
+        "dashboard" => walkoflife::dashboard::run(&attach(pid, &offsets)?),
+        other => Err(format!("Unknown command {} (try walkoflife help)", other).into()),
+    }
+}
+
+// Send the library's spans and events to stderr, as text or (with `--log-json`) one JSON object
+// per line, if `--log-level` is given. Like `--pid`, these can go anywhere.
+#[cfg(feature = "tracing")]
+fn init_logging(args: &mut Vec<String>) -> Result<()> {
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let json = match args.iter().position(|arg| arg == "--log-json") {
+        Some(idx) => {
+            args.remove(idx);
+            true
+        },
+        None => false,
+    };
+    let level = match args.iter().position(|arg| arg == "--log-level") {
+        Some(idx) => match args.get(idx + 1).and_then(|level| level.parse::<tracing::Level>().ok()) {
+            Some(level) => {
+                args.drain(idx..idx + 2);
+                level
+            },
+            None => {return Err("--log-level needs to be error, warn, info, debug or trace".into());},
+        },
+        // JSON on its own means everything down to debug, which is enough to see why a read broke.
+        None if json => tracing::Level::DEBUG,
+        None => {return Ok(());},
+    };
+
+    let builder = tracing_subscriber::fmt().with_writer(std::io::stderr).with_max_level(level).with_span_events(FmtSpan::CLOSE);
+    let result = match json {
+        true => builder.json().with_current_span(true).with_span_list(true).try_init(),
+        false => builder.try_init(),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("Couldn't set up logging: {}", err).into()),
+    }
+}
+
+fn attach(pid: Option<Pid>, offsets: &Option<OffsetProfile>) -> Result<Rayman2Process> {
+    let attached = match pid {
+        Some(pid) => Rayman2Process::attach_pid(pid),
+        None => Rayman2Process::attach(),
+    };
+    match attached {
+        Ok(ans) => {
+            match (offsets, ans.build()) {
+                (Some(profile), _) => ans.set_offsets(profile.clone()),
+                (None, Build::Known(_)) => {},
+                (None, Build::Unknown(checksum)) => {
+                    let checksum = checksum.map(|checksum| format!(" (checksum {:#010x})", checksum)).unwrap_or_default();
+                    eprintln!("Warning: unknown build of the game{} - assuming the GOG addresses, which may be wrong for it. Pass --offsets <FILE> to give its addresses.", checksum);
+                },
+            }
+            Ok(ans)
+        },
+        Err(err) => Err(format!("{} - is Rayman2.exe running?", err).into()),
+    }
+}
+
+fn watch(r2: Rayman2Process, args: &[String]) -> Result<()> {
+    let mut config = match args.iter().position(|arg| arg == "--config") {
+        Some(idx) => match args.get(idx + 1) {
+            Some(path) => Config::load(path)?,
+            None => {return Err("--config needs a file".into());},
+        },
+        None => Config::default(),
+    };
+    if let Some(idx) = args.iter().position(|arg| arg == "--format") {
+        config.format = match args.get(idx + 1).and_then(|format| OutputFormat::from_name(format)) {
+            Some(format) => format,
+            None => {return Err("--format needs to be text or json".into());},
+        };
+    }
+    // In JSON mode, stdout only gets one telemetry frame per line, and everything else goes to
+    // stderr.
+    let json = config.format == OutputFormat::Json;
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--backup-saves") {
+        let backup_root = match args.get(idx + 1) {
+            Some(dir) => Path::new(dir),
+            None => {return Err("--backup-saves needs a directory".into());},
+        };
+        let backup = saves::backup_saves(r2.pid(), backup_root)?;
+        report(json, &format!("Backed up saves to {}", backup.display()));
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--measure-latency") {
+        let trials = match args.get(idx + 1).and_then(|trials| trials.parse::<usize>().ok()) {
+            Some(trials) => trials,
+            None => {return Err("--measure-latency needs a number of trials".into());},
+        };
+        // BTN_SOUTH, so that it's recognised as a gamepad.
+        let mut pad = VirtualPad::gamepad("walkoflife latency probe", &[0x130])?;
+        // Give the game a moment to notice the new device.
+        sleep(time::Duration::from_secs(1));
+        let config = LatencyConfig{trials, ..LatencyConfig::default()};
+        let stats = measure_latency(r2.pid(), Stimulus::PadStick{pad: &mut pad, x: 1.0, y: 0.0}, Response::StickAxes, &config)?;
+        println!("Input latency: {}", stats);
+        return Ok(());
+    }
+
+    let mut mirror = match args.iter().position(|arg| arg == "--mirror") {
+        Some(idx) => match args.get(idx + 1) {
+            Some(name) => Some(Mirror::create(name, &["countdown", "timer"])?),
+            None => {return Err("--mirror needs a shared memory name".into());},
+        },
+        None => None,
+    };
+
+    let spike_watcher = match args.iter().position(|arg| arg == "--log-spikes") {
+        Some(idx) => match args.get(idx + 1).and_then(|ms| ms.parse::<u32>().ok()) {
+            Some(threshold_ms) => {
+                let config = FrameSpikeConfig{threshold_ms, ..FrameSpikeConfig::default()};
+                Some(FrameSpikeWatcher::with_callback(r2.pid(), config, |spike| eprintln!("Frame spike: {}", spike)))
+            },
+            None => {return Err("--log-spikes needs a threshold in milliseconds".into());},
+        },
+        None => None,
+    };
+
+    #[cfg(feature = "metrics")]
+    let _metrics = match args.iter().position(|arg| arg == "--metrics") {
+        Some(idx) => match args.get(idx + 1) {
+            Some(address) => Some(walkoflife::metrics::MetricsExporter::spawn(r2.pid(), address.as_str(), time::Duration::from_millis(500))?),
+            None => {return Err("--metrics needs an address to listen on".into());},
+        },
+        None => None,
+    };
+
+    #[cfg(feature = "server")]
+    let _server = match args.iter().position(|arg| arg == "--serve") {
+        Some(idx) => match args.get(idx + 1) {
+            Some(address) => Some(walkoflife::server::TelemetryServer::spawn(r2.pid(), address.as_str(), time::Duration::from_millis(100))?),
+            None => {return Err("--serve needs an address to listen on".into());},
+        },
+        None => None,
+    };
+
+    let interval = config.poll_interval;
+    let level_watcher = LevelWatcher::spawn(r2.pid(), interval);
+    let level_events = level_watcher.events().unwrap();
+
+    // We only care about the levels in the config (the Walk of Life by default), and the race timers
+    // are only there in the races.
+    let race = match level_events.recv() {
+        Ok(LevelEvent::LevelEntered(name)) if config.watches_level(&name) => Race::from_level(&name),
+        _ => {return Ok(());},
+    };
+    let mut race_timer = race.map(|race| RaceTimer::attach(r2.pid(), race));
+
+    let mut splits = match (args.iter().position(|arg| arg == "--splits"), race) {
+        (Some(idx), Some(race)) => match args.get(idx + 1) {
+            Some(path) => Some((SplitRecorder::attach(r2.pid(), race), RunLog::open(path)?)),
+            None => {return Err("--splits needs a file to keep the runs in".into());},
+        },
+        _ => None,
+    };
+    let mut registry = Registry::from_config(r2.pid(), &config);
+
     loop {
         sleep(interval);
-        // We only care about the Walk of Life
-        if utils::get_current_level_name(r2pid)?.to_lowercase() != "ly_10" {
-            break;
+        match level_events.try_recv() {
+            Ok(LevelEvent::LevelExited(_)) | Err(TryRecvError::Disconnected) => {break;},
+            _ => {},
         }
-        let object_types = utils::read_object_types(r2pid)?;
-        let active_super_objects = utils::get_active_super_object_names(r2pid, &object_types[2], 0)?;
-        let global_ptr = active_super_objects["global"];
-        let timerobj_ptr = active_super_objects["GRP_TimerCourse_I3"];
-        let timer_ptr = utils::get_dsg_var_ptr(r2pid, timerobj_ptr, 84)?; // Float_16
-        let countdown_ptr = utils::get_dsg_var_ptr(r2pid, global_ptr, 84)?; // Int_30
-
-        let timer: f32 = read_prims(r2pid, timer_ptr, 1).unwrap()[0];
-        let countdown: i32 = read_prims(r2pid, countdown_ptr, 1).unwrap()[0];
+        if let Some((recorder, log)) = splits.as_mut() {
+            if let Some(run) = recorder.poll()? {
+                finish_run(json, log, run)?;
+            }
+        }
+        let state = match race_timer.as_mut() {
+            Some(race_timer) => race_timer.read()?,
+            None => None,
+        };
+        let variables = sample_variables(&mut registry);
+        if json {
+            let mut frame = TelemetryFrame::read(r2.pid(), state)?;
+            frame.variables = variables;
+            println!("{}", frame.to_json());
+        } else if !variables.is_empty() {
+            let values: Vec<String> = variables.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+            println!("{}", values.join("; "));
+        }
+        // Nothing to read while the level is (re)loading.
+        let (countdown, timer) = match state {
+            Some(state) => (state.countdown, state.time),
+            None => {continue;},
+        };
 
+        if let Some(mirror) = mirror.as_mut() {
+            mirror.update(&[countdown as f64, timer as f64])?;
+        }
+        if json {
+            continue;
+        }
         println!("{} -> {}", countdown, timer);
 
         // Try to figure out some other stuff…
-        let framerate: f32 = read_prims(r2pid, 0x5036A8, 1).unwrap()[0];
-        let inverse_framerate: f32 = read_prims(r2pid, 0x50043C, 1).unwrap()[0];
-        let delta_t: i32 = read_prims(r2pid, 0x500434, 1).unwrap()[0];
+        let framerate: f32 = r2.read_prims(r2.relocate(OFF_FRAMERATE), 1)?[0];
+        let inverse_framerate: f32 = r2.read_prims(r2.relocate(OFF_FRAME_LENGTH), 1)?[0];
+        let delta_t: i32 = r2.read_prims(r2.relocate(OFF_DELTA_T), 1)?[0];
         println!("Frame rate: {}; Inverse frame rate: {}; Delta t: {}", framerate, inverse_framerate, delta_t);
     };
 
+    // The end of the race is only noticed once the level has been left.
+    if let Some((mut recorder, mut log)) = splits {
+        if let Some(run) = recorder.poll()? {
+            finish_run(json, &mut log, run)?;
+        }
+    }
+
+    if let Some(spike_watcher) = spike_watcher {
+        let summary = spike_watcher.summary();
+        report(json, &format!("Frame spikes: {} in about {} frames (worst: {} ms)", summary.spikes, summary.frames, summary.worst_ms));
+    }
+
+    Ok(())
+}
+
+// Print the hierarchy of the given world (the dynamic world by default), one super-object per
+// line, indented under its parent.
+fn dump_hierarchy(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let world = match args.first().map(String::as_str) {
+        None | Some("dynamic") => World::ActiveDynamic,
+        Some("inactive") => World::InactiveDynamic,
+        Some("static") => World::Static,
+        Some(other) => {return Err(format!("Unknown world {} (try dynamic, inactive or static)", other).into());},
+    };
+    print_node(&r2.walk_hierarchy(world, 0)?, 0);
+    Ok(())
+}
+
+fn print_node(node: &HierarchyNode, depth: usize) {
+    match (&node.ai_model, &node.family) {
+        (Some(ai_model), Some(family)) => println!("{:indent$}{} at {:#x} (AI Model {}, family {})", "", node.name, node.ptr, ai_model, family, indent = 2 * depth),
+        _ => println!("{:indent$}{} at {:#x}", "", node.name, node.ptr, indent = 2 * depth),
+    }
+    for child in &node.children {
+        print_node(child, depth + 1);
+    }
+}
+
+// Print the DSG variables of a super-object, given by name or by address.
+fn dump_dsg(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let object = match args.first() {
+        Some(object) => object,
+        None => {return Err("dump-dsg needs a super-object".into());},
+    };
+    for entry in r2.dump_dsg_mem(resolve_address(r2, object)?)? {
+        println!("{}", entry);
+    }
+    Ok(())
+}
+
+fn read(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let (addr, kind) = match args {
+        [addr, kind, ..] => (resolve_address(r2, addr)?, kind.as_str()),
+        _ => {return Err("read needs an address and a type".into());},
+    };
+    let value = match kind {
+        "u8" => read_value::<u8>(r2, addr)?,
+        "i8" => read_value::<i8>(r2, addr)?,
+        "u16" => read_value::<u16>(r2, addr)?,
+        "i16" => read_value::<i16>(r2, addr)?,
+        "u32" => read_value::<u32>(r2, addr)?,
+        "i32" => read_value::<i32>(r2, addr)?,
+        "f32" => read_value::<f32>(r2, addr)?,
+        "f64" => read_value::<f64>(r2, addr)?,
+        "ptr" => format!("{:#x}", r2.remote_ptr::<u32>(addr).read(r2.pid())?),
+        "str" => r2.read_string(addr, 256)?,
+        other => {return Err(format!("Unknown type {}", other).into());},
+    };
+    println!("{}", value);
+    Ok(())
+}
+
+fn read_value<T: Copy + Display>(r2: &Rayman2Process, addr: usize) -> Result<String> {
+    Ok(r2.remote_ptr::<T>(addr).read(r2.pid())?.to_string())
+}
+
+fn write(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let (addr, kind, value) = match args {
+        [addr, kind, value, ..] => (resolve_address(r2, addr)?, kind.as_str(), value.as_str()),
+        _ => {return Err("write needs an address, a type and a value".into());},
+    };
+    match kind {
+        "u8" => write_value::<u8>(r2, addr, value),
+        "i8" => write_value::<i8>(r2, addr, value),
+        "u16" => write_value::<u16>(r2, addr, value),
+        "i16" => write_value::<i16>(r2, addr, value),
+        "u32" => write_value::<u32>(r2, addr, value),
+        "i32" => write_value::<i32>(r2, addr, value),
+        "f32" => write_value::<f32>(r2, addr, value),
+        "f64" => write_value::<f64>(r2, addr, value),
+        "ptr" => r2.remote_ptr::<u32>(addr).write(r2.pid(), parse_address(value)? as u32),
+        // With the terminating null, as the game expects.
+        "str" => r2.write_prims(addr, &[value.as_bytes(), &[0]].concat()),
+        other => Err(format!("Unknown type {}", other).into()),
+    }
+}
+
+fn write_value<T: Copy + FromStr>(r2: &Rayman2Process, addr: usize, value: &str) -> Result<()> {
+    match value.parse::<T>() {
+        Ok(value) => r2.remote_ptr::<T>(addr).write(r2.pid(), value),
+        Err(_) => Err(format!("Can't write {} as that type", value).into()),
+    }
+}
+
+// Export the meshes of the family at the given address, to OBJ or glTF by the file's extension.
+fn export_mesh(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let (family_ptr, path) = match args {
+        [family, path, ..] => (parse_address(family)?, Path::new(path)),
+        _ => {return Err("export-mesh needs the address of a family and a file to write".into());},
+    };
+    let family = Family::read(r2.pid(), family_ptr)?;
+    let family_name = r2.families().ok()
+        .and_then(|mut names| names.nth(family.index))
+        .unwrap_or_else(|| format!("family{}", family.index));
+    let named_meshes = family.named_meshes(&family_name);
+    let instances: Vec<MeshInstance> = named_meshes.iter()
+        .map(|(name, mesh)| MeshInstance{name, mesh, transform: None})
+        .collect();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gltf") => export_gltf(path, &instances)?,
+        Some("obj") => export_obj(path, &instances)?,
+        _ => {return Err("export-mesh can only write .obj or .gltf files".into());},
+    }
+    println!("Exported {} meshes of {} to {}", instances.len(), family_name, path.display());
+    Ok(())
+}
+
+fn parse_address(value: &str) -> Result<usize> {
+    match parse_number(value) {
+        Some(addr) => Ok(addr),
+        None => Err(format!("{} isn't an address", value).into()),
+    }
+}
+
+// Run commands from an interactive prompt until the user quits, reporting errors rather than
+// stopping at them.
+fn repl(r2: &Rayman2Process) -> Result<()> {
+    let mut editor = LineEditor::new();
+    let candidates = |before: &str| -> Vec<String> {
+        let words: Vec<&str> = before.split_whitespace().collect();
+        let fixed = match words.as_slice() {
+            [] => Some(REPL_COMMANDS),
+            ["read", _] | ["write", _] => Some(TYPES),
+            ["hierarchy"] => Some(WORLDS),
+            _ => None,
+        };
+        match fixed {
+            Some(words) => words.iter().map(|word| word.to_string()).collect(),
+            // The names are read again each time, since the hierarchy changes as the game runs.
+            None => r2.active_super_objects().map(|names| names.into_keys().collect()).unwrap_or_default(),
+        }
+    };
+
+    println!("Type help for a list of commands.");
+    while let Some(line) = editor.read_line("walkoflife> ", candidates)? {
+        let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (command.as_str(), args),
+            None => continue,
+        };
+        let result = match command {
+            "list" => list(r2, args),
+            "hierarchy" => dump_hierarchy(r2, args),
+            "dsg" => dump_dsg(r2, args),
+            "path" => follow_path(r2, args),
+            "read" => read(r2, args),
+            "write" => write(r2, args),
+            "help" => {
+                println!("{}", REPL_HELP);
+                Ok(())
+            },
+            "quit" | "exit" => {break;},
+            other => Err(format!("Unknown command {} (try help)", other).into()),
+        };
+        if let Err(err) = result {
+            eprintln!("Error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+// List the active super-objects whose names match the pattern (all of them by default), by name.
+fn list(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let pattern = NamePattern::parse(args.first().map(String::as_str).unwrap_or("*"))?;
+    let mut super_objects: Vec<(String, usize)> = r2.active_super_objects()?.into_iter()
+        .filter(|(name, _)| pattern.matches(name))
+        .collect();
+    super_objects.sort();
+    for (name, ptr) in super_objects {
+        println!("{} at {:#x}", name, ptr);
+    }
+    Ok(())
+}
+
+// Follow a pointer path typed as a start address and a series of steps: `*` to read a pointer and
+// go where it points, or a number of bytes to add.
+fn follow_path(r2: &Rayman2Process, args: &[String]) -> Result<()> {
+    let (start, steps) = match args.split_first() {
+        Some((start, steps)) => (resolve_address(r2, start)?, steps),
+        None => {return Err("path needs a start address".into());},
+    };
+    if steps.len() > MAX_PATH_STEPS {
+        return Err(format!("A pointer path can't have more than {} steps", MAX_PATH_STEPS).into());
+    }
+    let mut path = PointerPath::new(start);
+    for step in steps {
+        path = match step.as_str() {
+            "*" => path.deref(),
+            offset => match parse_number(offset.trim_start_matches('+')) {
+                Some(offset) => path.off(offset),
+                None => {return Err(format!("{} isn't a step (try * or an offset)", step).into());},
+            },
+        };
+    }
+    println!("{:#x}", r2.resolve_path(&path, start)?);
+    Ok(())
+}
+
+// Get an address from what the user typed: a number, or the name of an active super-object (for
+// its address).
+fn resolve_address(r2: &Rayman2Process, value: &str) -> Result<usize> {
+    match parse_number(value) {
+        Some(addr) => Ok(addr),
+        None => match r2.active_super_objects()?.get(value) {
+            Some(&ptr) => Ok(ptr),
+            None => Err(format!("{} isn't an address or the name of an active super-object", value).into()),
+        },
+    }
+}
+
+// Read the variables in the registry, leaving out any which can't be read right now (e.g. because
+// their object isn't active).
+fn sample_variables(registry: &mut Registry) -> Vec<(String, DsgValue)> {
+    let names: Vec<String> = registry.names().map(String::from).collect();
+    names.into_iter()
+        .filter_map(|name| match registry.get(&name).and_then(|variable| variable.read()) {
+            Ok(value) => Some((name, value)),
+            Err(_) => None,
+        })
+        .collect()
+}
+
+// Print a message for the user, keeping it out of the way of the telemetry in JSON mode.
+fn report(json: bool, message: &str) {
+    match json {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
+
+fn finish_run(json: bool, log: &mut RunLog, run: Run) -> Result<()> {
+    let race = run.race;
+    report(json, &format!("Finished in {} ms (splits: {:?})", run.time().unwrap_or(0.0), run.splits));
+    log.record(run)?;
+    if let (Some(best), Some(average)) = (log.personal_best(race), log.average(race)) {
+        report(json, &format!("Personal best: {} ms; average: {} ms", best.time().unwrap_or(0.0), average));
+    }
     Ok(())
 }
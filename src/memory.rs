@@ -1,12 +1,19 @@
 /*!
-  Functions for manipulating the memory of another program on Linux
-  (with a particular view to Rayman 2).
+  Functions for manipulating the memory of another program (with a particular view to Rayman 2).
+  The actual reading and writing is done by the platform's
+  [`MemoryBackend`](../backend/trait.MemoryBackend.html).
   */
 
 extern crate nix;
 
-use nix::{unistd::Pid,sys::uio::{process_vm_readv,process_vm_writev,IoVec,RemoteIoVec},Result};
-use std::mem::size_of;
+use nix::{unistd::Pid,errno::Errno};
+use std::{mem::{size_of,size_of_val},thread,time::Duration,collections::BTreeMap,sync::{Arc,Mutex,atomic::{AtomicBool,AtomicUsize,Ordering}}};
+use crate::{backend::{MemoryBackend,NativeBackend},remote::RemotePtr,discovery::read_maps,error::{Error,Result}};
+
+/// Anything which can be read from another process's memory by
+/// [`read_struct()`](fn.read_struct.html): primitives, arrays, pointers, and structures with
+/// `#[derive(EngineStruct)]` - see [`layout`](../layout/index.html).
+pub use crate::layout::EngineField as FromRemoteBytes;
 
 /// Read `n` primitives (i.e. objects implementing `Copy`) from the memory of a process given by
 /// `pid`, starting from a location given by `offset`.
@@ -15,24 +22,345 @@ use std::mem::size_of;
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 ///
 /// ## Returns:
-/// * Return type is a [`nix::Result`](../../nix/type.Result.html), reflecting the success or
-/// failure of the underlying operation(s).
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
 /// * On success, returns a `Vec<T>` containing the data read, with `len()` equal to `n`.
+/// * If [read validation](fn.set_read_validation.html) is on, returns
+///   [`Error::Unmapped`](../error/enum.Error.html#variant.Unmapped) if any of the memory isn't
+///   mapped readable.
 pub fn read_prims<T:Copy>(pid: Pid, offset: usize, n: usize) -> Result<Vec<T>> {
     let bytes_per_prim = size_of::<T>();
     let mut ret: Vec<T> = Vec::with_capacity(n);
 
     let byteslice = unsafe{std::slice::from_raw_parts_mut(ret.as_mut_ptr().cast::<u8>(), n * bytes_per_prim)};
-    let iovec = IoVec::from_mut_slice(byteslice);
-    let iovec_rem = RemoteIoVec{base: offset, len: n * bytes_per_prim};
-
-    let bytes_copied = process_vm_readv(pid, &[iovec], &[iovec_rem])?;
+    let bytes_copied = validate_read(pid, offset, n * bytes_per_prim, NativeBackend::new(pid).read_bytes(offset, byteslice))?;
     unsafe {
         ret.set_len(bytes_copied / bytes_per_prim);
     }
     Ok(ret)
 }
 
+/// Read primitives (i.e. objects implementing `Copy`) from the memory of a process given by
+/// `pid`, starting from a location given by `offset`, into `buf`. Unlike
+/// [`read_prims()`](fn.read_prims.html), this doesn't allocate, so it's better for things which
+/// are read very often (e.g. every frame).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
+/// * On success, returns the number of primitives read, which may be less than `buf.len()` if
+///   the end of the readable memory was reached. The rest of `buf` is left as it was. If
+///   [read validation](fn.set_read_validation.html) is on, an `Err` is returned instead.
+pub fn read_into<T:Copy>(pid: Pid, offset: usize, buf: &mut [T]) -> Result<usize> {
+    let n_bytes = size_of_val(buf);
+
+    let byteslice = unsafe{std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<u8>(), n_bytes)};
+    Ok(validate_read(pid, offset, n_bytes, NativeBackend::new(pid).read_bytes(offset, byteslice))? / size_of::<T>())
+}
+
+/// Read several regions of memory, each given as `(address, length)` in `regions`, from the
+/// process given by `pid`, using as few system calls as possible. This is much faster than
+/// reading lots of small things one at a time, e.g. when walking the hierarchy.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Details:
+/// * A region which can't be read (e.g. because of a bad pointer) doesn't stop the others from
+///   being read.
+///
+/// ## Returns:
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
+/// * On success, returns a `Vec<Vec<u8>>` with one entry per region, in the same order. Each
+///   entry is as long as the region, unless not all of it could be read, in which case it's
+///   shorter (or empty).
+pub fn read_batch(pid: Pid, regions: &[(usize, usize)]) -> Result<Vec<Vec<u8>>> {
+    let mut ret: Vec<Vec<u8>> = regions.iter().map(|&(_, len)| vec![0u8; len]).collect();
+    let lens_read = NativeBackend::new(pid).read_regions(regions, &mut ret)?;
+
+    for (buf, &len) in ret.iter_mut().zip(lens_read.iter()) {
+        buf.truncate(len);
+    }
+    Ok(ret)
+}
+
+static VALIDATE_READS: AtomicBool = AtomicBool::new(false);
+
+/// Turn read validation on or off (it's off to begin with). When it's on, a read from memory
+/// which isn't all mapped readable in the other process gives
+/// [`Error::Unmapped`](../error/enum.Error.html#variant.Unmapped), with the first address that
+/// isn't, rather than a bare `EFAULT` (or a short read). This is handy for debugging pointer
+/// paths.
+///
+/// ## Details:
+/// * The process's [`MemoryMap`](struct.MemoryMap.html) is only read when a read comes up short,
+///   so this doesn't slow down reads that work.
+/// * This affects every thread, since reads are often done on background threads.
+pub fn set_read_validation(on: bool) {
+    VALIDATE_READS.store(on, Ordering::Relaxed);
+}
+
+/// Check if read validation is on - see [`set_read_validation()`](fn.set_read_validation.html).
+pub fn read_validation() -> bool {
+    VALIDATE_READS.load(Ordering::Relaxed)
+}
+
+// If read validation is on, check the result of reading `len` bytes at `addr` - see
+// `check_read()`.
+fn validate_read(pid: Pid, addr: usize, len: usize, result: Result<usize>) -> Result<usize> {
+    check_read(pid, addr, len, result, read_validation())
+}
+
+// If `validate` is set and the read of `len` bytes at `addr` (which gave `result`, the number of
+// bytes read) didn't read them all, check the memory map, and say which address wasn't mapped.
+fn check_read(pid: Pid, addr: usize, len: usize, result: Result<usize>, validate: bool) -> Result<usize> {
+    match result {
+        Ok(read) if read >= len => Ok(read),
+        result if !validate => result,
+        Ok(_) | Err(Error::ReadFailed{..}) => match MemoryMap::read(pid).ok().and_then(|map| map.first_unreadable(addr, len)) {
+            Some(unmapped) => Err(Error::Unmapped(unmapped)),
+            None => result,
+        },
+        result => result,
+    }
+}
+
+/// Whether a [`Mapping`](struct.Mapping.html) can be read, written or executed, and whether it's
+/// shared with other processes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Permissions {
+    /// The region can be read.
+    pub read: bool,
+    /// The region can be written.
+    pub write: bool,
+    /// The region can be executed.
+    pub execute: bool,
+    /// The region is shared with other processes (rather than copied on write).
+    pub shared: bool,
+}
+
+impl Permissions {
+    // Parse permissions as given in `/proc/<pid>/maps`, e.g. `rw-p`.
+    fn parse(perms: &str) -> Option<Permissions> {
+        match perms.as_bytes() {
+            &[read, write, execute, shared] => Some(Permissions{
+                read: read == b'r',
+                write: write == b'w',
+                execute: execute == b'x',
+                shared: shared == b's',
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A region of another process's memory, as listed in `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    /// The address of the start of the region.
+    pub start: usize,
+    /// The address just after the end of the region.
+    pub end: usize,
+    /// What can be done with the region.
+    pub perms: Permissions,
+    /// The offset in the file that's mapped (if any).
+    pub offset: usize,
+    /// The path of the file that's mapped, or a name in brackets like `[stack]`, or empty for an
+    /// anonymous mapping.
+    pub path: String,
+}
+
+impl Mapping {
+    /// Get the length of the region, in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check if the region is empty.
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    /// Check if `addr` is in the region.
+    pub fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end
+    }
+
+    // Parse a line of `/proc/<pid>/maps`.
+    fn parse(line: &str) -> Option<Mapping> {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let (start, end) = (usize::from_str_radix(start, 16).ok()?, usize::from_str_radix(end, 16).ok()?);
+        let perms = Permissions::parse(fields.next()?)?;
+        let offset = usize::from_str_radix(fields.next()?, 16).ok()?;
+        // The path comes after the device and inode, and may contain spaces.
+        let path = match fields.nth(2) {
+            Some(first) => line[line.rfind(first).unwrap_or(0)..].to_string(),
+            None => String::new(),
+        };
+        Some(Mapping{start, end, perms, offset, path})
+    }
+}
+
+/// The memory map of another process, as listed in `/proc/<pid>/maps`, for checking whether
+/// addresses can be read before reading them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryMap {
+    mappings: Vec<Mapping>,
+}
+
+impl MemoryMap {
+    /// Read the memory map of the process given by `pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permission to read `/proc/<pid>/maps`.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `MemoryMap`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the maps can't be read.
+    pub fn read(pid: Pid) -> Result<MemoryMap> {
+        Ok(MemoryMap::parse(&read_maps(pid)?))
+    }
+
+    /// Parse a memory map in the format of `/proc/<pid>/maps`. Lines which can't be parsed are
+    /// left out.
+    pub fn parse(maps: &str) -> MemoryMap {
+        let mut mappings: Vec<Mapping> = maps.lines().filter_map(Mapping::parse).collect();
+        mappings.sort_by_key(|mapping| mapping.start);
+        MemoryMap{mappings}
+    }
+
+    /// Get the mappings, in order of address.
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// Find the mapping containing `addr`, if there is one.
+    pub fn find(&self, addr: usize) -> Option<&Mapping> {
+        let i = self.mappings.partition_point(|mapping| mapping.end <= addr);
+        self.mappings.get(i).filter(|mapping| mapping.contains(addr))
+    }
+
+    /// Check if all `len` bytes at `addr` are mapped readable (possibly across several mappings).
+    pub fn is_readable(&self, addr: usize, len: usize) -> bool {
+        self.first_unreadable(addr, len).is_none()
+    }
+
+    /// Check if all `len` bytes at `addr` are mapped writable (possibly across several mappings).
+    pub fn is_writable(&self, addr: usize, len: usize) -> bool {
+        self.first_without(addr, len, |perms| perms.write).is_none()
+    }
+
+    /// Get the first of the `len` bytes at `addr` which isn't mapped readable, if any.
+    pub fn first_unreadable(&self, addr: usize, len: usize) -> Option<usize> {
+        self.first_without(addr, len, |perms| perms.read)
+    }
+
+    fn first_without<F: Fn(Permissions) -> bool>(&self, addr: usize, len: usize, allowed: F) -> Option<usize> {
+        let end = addr.checked_add(len)?;
+        let mut cur = addr;
+        while cur < end {
+            match self.find(cur) {
+                Some(mapping) if allowed(mapping.perms) => {cur = mapping.end;},
+                _ => {return Some(cur);},
+            }
+        }
+        None
+    }
+
+    /// Get the start and length of each readable mapping, apart from the kernel's (which can't
+    /// always be read even when they say they can).
+    pub fn readable_regions(&self) -> Vec<(usize, usize)> {
+        self.mappings.iter()
+            .filter(|mapping| mapping.perms.read && !mapping.is_empty() && !KERNEL_MAPPINGS.contains(&mapping.path.as_str()))
+            .map(|mapping| (mapping.start, mapping.len()))
+            .collect()
+    }
+}
+
+/// A copy of several regions of another process's memory, all read at (as near as possible) the
+/// same instant, so that values which are read together (e.g. a timer and a countdown) are
+/// consistent with each other.
+///
+/// ## Details:
+/// * Add the regions you're interested in with [`with_region()`](#method.with_region), then call
+///   [`refresh()`](#method.refresh) to read them all with a single
+///   [`read_batch()`](fn.read_batch.html), and [`read()`](#method.read) values out of the copy.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    regions: Vec<(usize, usize)>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Make an empty snapshot, with no regions.
+    pub fn new() -> Snapshot {
+        Snapshot::default()
+    }
+
+    /// Add the `len` bytes at `addr` to the regions read by [`refresh()`](#method.refresh).
+    pub fn with_region(mut self, addr: usize, len: usize) -> Snapshot {
+        self.add_region(addr, len);
+        self
+    }
+
+    /// Add the `len` bytes at `addr` to the regions read by [`refresh()`](#method.refresh).
+    pub fn add_region(&mut self, addr: usize, len: usize) {
+        self.regions.push((addr, len));
+        self.data.push(Vec::new());
+    }
+
+    /// Read all the regions again from the process given by `pid`, in one go.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`. If part of a region couldn't be read, values there will be
+    ///   missing from the snapshot.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn refresh(&mut self, pid: Pid) -> Result<()> {
+        self.data = read_batch(pid, &self.regions)?;
+        Ok(())
+    }
+
+    /// Get a `T` at `addr` (in the other process) from the snapshot, as it was when it was last
+    /// [`refresh()`](#method.refresh)ed.
+    ///
+    /// ## Returns:
+    /// * `Some` value if `addr` is in one of the regions and was read successfully, otherwise
+    ///   `None`.
+    pub fn read<T:Copy>(&self, addr: usize) -> Option<T> {
+        self.regions.iter().zip(self.data.iter())
+            .find(|((base, _), bytes)| addr >= *base && addr + size_of::<T>() <= base + bytes.len())
+            .map(|((base, _), bytes)| unsafe{bytes[addr - base..].as_ptr().cast::<T>().read_unaligned()})
+    }
+}
+
+/// Read a `T` (e.g. an engine structure declared with `#[derive(EngineStruct)]`) from the memory
+/// of a process given by `pid`, at the location given by `offset`, in one go.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
+/// * On success, returns the `T`. If it couldn't all be read, an `Err` is returned rather than a
+///   partial `T`.
+pub fn read_struct<T: FromRemoteBytes>(pid: Pid, offset: usize) -> Result<T> {
+    let bytes = read_prims::<u8>(pid, offset, T::SIZE)?;
+    if bytes.len() < T::SIZE {
+        return Err(format!("Short read of structure at {:#x}", offset).into());
+    }
+    Ok(T::from_bytes(&bytes))
+}
+
 /// Read a UTF-8 string from the memory of a process given by `pid`, starting from the location
 /// given by `offset`.
 ///
@@ -40,12 +368,17 @@ pub fn read_prims<T:Copy>(pid: Pid, offset: usize, n: usize) -> Result<Vec<T>> {
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 ///
 /// ## Returns:
-/// * Return type is a [`nix::Result`](../../nix/type.Result.html), reflecting the success or
-/// failure of the underlying operation(s).
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
 /// * On success, returns a `String` at most `n` bytes long. It can be shorter if a null terminator
-/// or invalid character is found.
+///   or invalid character is found.
 pub fn read_string(pid: Pid, offset: usize, n: usize) -> Result<String> {
-    let bytes = read_prims::<u8>(pid, offset, n)?;
+    decode_string(read_prims::<u8>(pid, offset, n)?)
+}
+
+/// Make a `String` from `bytes` read from another process, stopping at the null terminator or
+/// the first invalid character, as in [`read_string()`](fn.read_string.html).
+pub(crate) fn decode_string(bytes: Vec<u8>) -> Result<String> {
     // Truncate at null terminator
     let trunc = match bytes.iter().position(|&x| x==0) {
         Some(idx) => bytes[0..idx].to_vec(),
@@ -53,7 +386,53 @@ pub fn read_string(pid: Pid, offset: usize, n: usize) -> Result<String> {
     };
     match String::from_utf8(trunc) {
         Ok(string) => Ok(string),
-        Err(err) => Ok(String::from_utf8(read_prims::<u8>(pid, offset, err.utf8_error().valid_up_to()).unwrap()).unwrap()),
+        Err(err) => {
+            // Keep whatever was valid before the first bad byte.
+            let valid_up_to = err.utf8_error().valid_up_to();
+            let mut bytes = err.into_bytes();
+            bytes.truncate(valid_up_to);
+            Ok(String::from_utf8(bytes)?)
+        },
+    }
+}
+
+/// How big the pointers in a game are, as given by its
+/// [`GameProfile`](../profile/struct.GameProfile.html).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PointerWidth {
+    /// 32-bit pointers, as in Rayman 2 (and the other original OpenSpace games).
+    #[default]
+    Bits32,
+    /// 64-bit pointers.
+    Bits64,
+}
+
+impl PointerWidth {
+    /// Get the size of a pointer, in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            PointerWidth::Bits32 => 4,
+            PointerWidth::Bits64 => 8,
+        }
+    }
+}
+
+/// Read a pointer of the given `width` from the memory of the process given by `pid`, at `addr`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the pointer as a `usize`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails (or only part of the pointer could be read).
+pub fn read_pointer(pid: Pid, addr: usize, width: PointerWidth) -> Result<usize> {
+    // The game's pointers are little-endian, whatever we are.
+    let bytes = read_prims::<u8>(pid, addr, width.size())?;
+    match (width, bytes.len() == width.size()) {
+        (PointerWidth::Bits32, true) => Ok(u32::from_bytes(&bytes) as usize),
+        (PointerWidth::Bits64, true) => Ok(u64::from_bytes(&bytes) as usize),
+        (_, false) => Err(Error::ReadFailed{addr, len: width.size(), errno: Errno::EFAULT}),
     }
 }
 
@@ -61,36 +440,194 @@ pub fn read_string(pid: Pid, offset: usize, n: usize) -> Result<String> {
 ///
 /// ## Details:
 /// * `base` is a pointer which gives the beginning of the "path", i.e. this function will
-/// start by reading a 32-bit integer from the memory location given by `base`.
+///   start by reading a 32-bit integer from the memory location given by `base`.
 /// * If any `offsets` are specified, each one indicates another node on the "path". So after
-/// reading the integer at `base`, it will add `offsets.unwrap()[0]` to that integer, and then use
-/// it as another pointer to read the next integer.
+///   reading the integer at `base`, it will add `offsets.unwrap()[0]` to that integer, and then use
+///   it as another pointer to read the next integer.
 ///     * Then it will add `offsets.unwrap()[1]` to _that_ integer, and use it as the next pointer,
-/// and so on.
+///       and so on.
 /// * Note that the `offsets` can all be zero, to follow a simple path (in which each pointer
-/// simply points to the next one).
+///   simply points to the next one).
 /// * The return value is the final integer read.
 ///
 /// ## Requirements:
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 ///
 /// ## Returns:
-/// * Return type is a [`nix::Result`](../../nix/type.Result.html), reflecting the success or
-/// failure of the underlying operation(s).
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
 /// * On success, returns a `usize` corresponding to the desired pointer.
 pub fn get_pointer_path(pid: Pid, base: usize, offsets: Option<&Vec<usize>>) -> Result<usize> {
-    let mut cur_address = base;
+    // Rayman 2 is 100% 32-bit.
+    get_pointer_path_with(pid, PointerWidth::Bits32, base, offsets)
+}
 
-    // Rayman 2 is 100% 32-bit, so we need to cast a u32 to a usize.
-    cur_address = read_prims::<u32>(pid, cur_address, 1)?[0] as usize;
+/// Look up a pointer in the memory of the process given by `pid`, by following a "path" of
+/// pointers which are `width` wide - see [`get_pointer_path()`](fn.get_pointer_path.html).
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(Display, level = "debug"),
+                                                   fields(pid = %pid, base = format_args!("{:#x}", base), offsets = ?offsets)))]
+pub fn get_pointer_path_with(pid: Pid, width: PointerWidth, base: usize, offsets: Option<&Vec<usize>>) -> Result<usize> {
+    // We don't know what the path leads to, only that it's pointers all the way.
+    let mut cur = match RemotePtr::<RemotePtr<()>>::with_width(base, width).follow(pid) {
+        Ok(ptr) => ptr,
+        Err(Error::ReadFailed{..}) => {return Err(Error::BadPointerPath{base, step: 0, addr: base});},
+        Err(err) => {return Err(err);},
+    };
 
     if let Some(offs) = offsets {
-        for offset in offs.iter() {
-            cur_address = read_prims::<u32>(pid, cur_address + offset, 1)?[0] as usize;
+        for (i, offset) in offs.iter().enumerate() {
+            let next = RemotePtr::<RemotePtr<()>>::with_width(add_offset(base, cur.addr(), *offset)?, width);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(step = i + 1, ptr = format_args!("{:#x}", cur.addr()), addr = format_args!("{:#x}", next.addr()), "Following pointer");
+            cur = match next.follow(pid) {
+                Ok(ptr) => ptr,
+                // A bad pointer somewhere along the path - say where, rather than just where it
+                // pointed to.
+                Err(Error::ReadFailed{..}) => {return Err(Error::BadPointerPath{base, step: i + 1, addr: next.addr()});},
+                Err(err @ Error::Unmapped(_)) => {return Err(err.context(format!("Pointer path from {:#x} broken at step {}", base, i + 1)));},
+                Err(err) => {return Err(err);},
+            };
         }
     }
 
-    Ok(cur_address)
+    Ok(cur.addr())
+}
+
+// Add `offset` to `address` on a pointer path from `base`, which may be garbage (or typed in by
+// hand), so it mustn't overflow.
+fn add_offset(base: usize, address: usize, offset: usize) -> Result<usize> {
+    match address.checked_add(offset) {
+        Some(address) => Ok(address),
+        None => Err(format!("Pointer path from {:#x} overflows adding {:#x} to {:#x}", base, offset, address).into()),
+    }
+}
+
+/// The most steps a [`PointerPath`](struct.PointerPath.html) can have.
+pub const MAX_PATH_STEPS: usize = 16;
+
+/// A step in a [`PointerPath`](struct.PointerPath.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathStep {
+    /// Read a pointer at the current address, and go where it points.
+    Deref,
+    /// Add a number of bytes to the current address.
+    Offset(usize),
+}
+
+/// A self-describing path through pointers in another process's memory, which can be built at
+/// compile time, e.g.:
+///
+/// ```
+/// use walkoflife::memory::PointerPath;
+///
+/// // Super-object -> perso -> standard game info -> super-object name index
+/// const NAME_INDEX: PointerPath = PointerPath::relative().off(0x4).deref().off(0x4).deref().off(0x8);
+/// ```
+///
+/// ## Details:
+/// * Unlike [`get_pointer_path()`](fn.get_pointer_path.html), nothing is read unless the path says
+///   so, and the result is the address reached at the end. Add a final
+///   [`deref()`](#method.deref) to get the pointer stored there instead.
+/// * Some well-known paths are in [`constants::paths`](../constants/paths/index.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PointerPath {
+    base: usize,
+    steps: [PathStep; MAX_PATH_STEPS],
+    len: usize,
+}
+
+impl PointerPath {
+    /// Start a path at the fixed address `base`.
+    pub const fn new(base: usize) -> PointerPath {
+        PointerPath{base, steps: [PathStep::Offset(0); MAX_PATH_STEPS], len: 0}
+    }
+
+    /// Start a path which doesn't have a fixed base (e.g. one which starts at a super-object), to
+    /// be followed with [`resolve_from()`](#method.resolve_from).
+    pub const fn relative() -> PointerPath {
+        PointerPath::new(0)
+    }
+
+    /// Add a step which reads a pointer and goes where it points.
+    ///
+    /// ## Requirements:
+    /// * The path can't have more than `MAX_PATH_STEPS` steps.
+    pub const fn deref(self) -> PointerPath {
+        self.then(PathStep::Deref)
+    }
+
+    /// Add a step which adds `offset` bytes to the address.
+    ///
+    /// ## Requirements:
+    /// * The path can't have more than `MAX_PATH_STEPS` steps.
+    pub const fn off(self, offset: usize) -> PointerPath {
+        self.then(PathStep::Offset(offset))
+    }
+
+    const fn then(mut self, step: PathStep) -> PointerPath {
+        self.steps[self.len] = step;
+        self.len += 1;
+        self
+    }
+
+    /// Get the fixed address the path starts at (`0` for a relative path).
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Get the steps in the path.
+    pub fn steps(&self) -> &[PathStep] {
+        &self.steps[..self.len]
+    }
+
+    /// Follow the path from its base, in the process given by `pid`, reading 32-bit pointers as
+    /// in Rayman 2.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the address at the end of the path.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if any of the pointers along the way can't be read.
+    pub fn resolve(&self, pid: Pid) -> Result<usize> {
+        self.resolve_from(pid, self.base)
+    }
+
+    /// Follow the path from its base, reading pointers which are `width` wide - see
+    /// [`resolve()`](#method.resolve).
+    pub fn resolve_with(&self, pid: Pid, width: PointerWidth) -> Result<usize> {
+        self.resolve_from_with(pid, width, self.base)
+    }
+
+    /// Follow the path starting at `start` instead of its base (e.g. a super-object, for a
+    /// [`relative()`](#method.relative) path) - see [`resolve()`](#method.resolve).
+    pub fn resolve_from(&self, pid: Pid, start: usize) -> Result<usize> {
+        self.resolve_from_with(pid, PointerWidth::Bits32, start)
+    }
+
+    /// Follow the path starting at `start`, reading pointers which are `width` wide - see
+    /// [`resolve_from()`](#method.resolve_from).
+    pub fn resolve_from_with(&self, pid: Pid, width: PointerWidth, start: usize) -> Result<usize> {
+        let mut cur_address = start;
+        // Steps are counted as for `get_pointer_path()`: only the pointers read, from 0.
+        let mut step = 0;
+        for path_step in self.steps() {
+            cur_address = match path_step {
+                PathStep::Offset(offset) => add_offset(start, cur_address, *offset)?,
+                PathStep::Deref => match read_pointer(pid, cur_address, width) {
+                    Ok(ptr) => {
+                        step += 1;
+                        ptr
+                    },
+                    Err(Error::ReadFailed{..}) => {return Err(Error::BadPointerPath{base: start, step, addr: cur_address});},
+                    Err(err @ Error::Unmapped(_)) => {return Err(err.context(format!("Pointer path from {:#x} broken at step {}", start, step)));},
+                    Err(err) => {return Err(err);},
+                },
+            };
+        }
+        Ok(cur_address)
+    }
 }
 
 /// Write an array (technically a vector) of primitives (i.e. objects implementing `Copy`) to 
@@ -100,25 +637,614 @@ pub fn get_pointer_path(pid: Pid, base: usize, offsets: Option<&Vec<usize>>) ->
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 ///
 /// ## Returns:
-/// * Return type is a [`nix::Result`](../../nix/type.Result.html), reflecting the success or
-/// failure of the underlying operation(s).
+/// * Return type is a [`walkoflife::Result`](../error/type.Result.html), reflecting the success or
+///   failure of the underlying operation(s).
 /// * On success, returns `Ok(())`.
-pub fn write_prims<T:Copy>(pid: Pid, offset: usize, data: &Vec<T>) -> Result<()> {
-    let bytes_per_prim = size_of::<T>();
-    let n = data.len();
+pub fn write_prims<T:Copy>(pid: Pid, offset: usize, data: &[T]) -> Result<()> {
+    let n_bytes = size_of_val(data);
+
+    let byteslice = unsafe{std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), n_bytes)};
+    NativeBackend::new(pid).write_bytes(offset, byteslice)
+}
 
-    let byteslice = unsafe{std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), n * bytes_per_prim)};
-    let iovec = IoVec::from_slice(byteslice);
-    let iovec_rem = RemoteIoVec{base: offset, len: n * bytes_per_prim};
+// Memory is scanned this many bytes at a time.
+const SCAN_CHUNK: usize = 1 << 20;
+// When re-reading the matches of a scan, matches this close together are read in one go.
+const SCAN_GAP: usize = 0x1000;
+// Mappings which belong to the kernel, and can't always be read.
+const KERNEL_MAPPINGS: &[&str] = &["[vvar]", "[vsyscall]"];
 
-    let _ = process_vm_writev(pid, &[iovec], &[iovec_rem])?;
-    Ok(())
+/// What a value has to be to match a [`scan()`](fn.scan.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanPredicate<T> {
+    /// Anything (an "unknown initial value", to narrow down later by how it changes).
+    Any,
+    /// Exactly the given value.
+    Exact(T),
+    /// Between the given values (inclusive).
+    Range(T, T),
+    /// Different from the last scan.
+    Changed,
+    /// The same as the last scan.
+    Unchanged,
+    /// Greater than the last scan.
+    Increased,
+    /// Less than the last scan.
+    Decreased,
+}
+
+impl<T:Copy + PartialOrd> ScanPredicate<T> {
+    /// Check if `value` matches, given what it was in the last scan (if there was one - the
+    /// predicates which compare with the last scan never match without one).
+    pub fn matches(&self, value: T, previous: Option<T>) -> bool {
+        match (self, previous) {
+            (ScanPredicate::Any, _) => true,
+            (ScanPredicate::Exact(expected), _) => value == *expected,
+            (ScanPredicate::Range(min, max), _) => *min <= value && value <= *max,
+            (ScanPredicate::Changed, Some(previous)) => value != previous,
+            (ScanPredicate::Unchanged, Some(previous)) => value == previous,
+            (ScanPredicate::Increased, Some(previous)) => value > previous,
+            (ScanPredicate::Decreased, Some(previous)) => value < previous,
+            (_, None) => false,
+        }
+    }
+}
+
+/// The addresses found by a [`scan()`](fn.scan.html), with their values at the time, which can
+/// be narrowed down with further scans (as in Cheat Engine).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scan<T> {
+    matches: Vec<(usize, T)>,
+}
+
+impl<T:Copy + PartialOrd> Scan<T> {
+    /// Get the addresses and values that matched, in order of address.
+    pub fn matches(&self) -> &[(usize, T)] {
+        &self.matches
+    }
+
+    /// Get the addresses that matched, in order.
+    pub fn addresses(&self) -> Vec<usize> {
+        self.matches.iter().map(|&(addr, _)| addr).collect()
+    }
+
+    /// Get the number of addresses that matched.
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Check if nothing matched.
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Read the addresses that matched again from the process given by `pid`, and keep only
+    /// those whose value now matches `predicate` (which is compared with their values in the last
+    /// scan).
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the number of addresses left. Addresses which can't be read any more
+    ///   are dropped.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn narrow(&mut self, pid: Pid, predicate: ScanPredicate<T>) -> Result<usize> {
+        // Read the matches in spans, rather than one at a time.
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for &(addr, _) in &self.matches {
+            match spans.last_mut() {
+                Some((start, len)) if addr <= *start + *len + SCAN_GAP && addr + size_of::<T>() - *start <= SCAN_CHUNK => {
+                    *len = addr + size_of::<T>() - *start;
+                },
+                _ => spans.push((addr, size_of::<T>())),
+            }
+        }
+        let data = read_batch(pid, &spans)?;
+
+        let mut span = 0;
+        self.matches.retain_mut(|(addr, value)| {
+            while spans[span].0 + spans[span].1 <= *addr {
+                span += 1;
+            }
+            match value_at::<T>(&data[span], *addr - spans[span].0) {
+                Some(new_value) if predicate.matches(new_value, Some(*value)) => {
+                    *value = new_value;
+                    true
+                },
+                _ => false,
+            }
+        });
+        Ok(self.matches.len())
+    }
+}
+
+// Get a `T` from `bytes` at `offset`, if there's enough there.
+fn value_at<T:Copy>(bytes: &[u8], offset: usize) -> Option<T> {
+    bytes.get(offset..offset + size_of::<T>()).map(|bytes| unsafe{bytes.as_ptr().cast::<T>().read_unaligned()})
+}
+
+/// Scan all the readable memory of the process given by `pid` for values of type `T` (e.g. `u8`
+/// for the health, or `f32` for a coordinate) matching `predicate`, to find where something is
+/// kept in a new build of the game. The result can then be narrowed down with
+/// [`Scan::narrow()`](struct.Scan.html#method.narrow).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`), and to read
+///   `/proc/<pid>/maps`.
+///
+/// ## Returns:
+/// * On success, returns the [`Scan`](struct.Scan.html).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the maps can't be read.
+///
+/// ## Details:
+/// * Only addresses which are a multiple of `T`'s size are checked (as the game's values are
+///   aligned).
+/// * Scanning for [`ScanPredicate::Any`](enum.ScanPredicate.html#variant.Any) matches every
+///   address, which takes a lot of memory - it's better to scan just the game's globals with
+///   [`scan_regions()`](fn.scan_regions.html) for that.
+pub fn scan<T:Copy + PartialOrd>(pid: Pid, predicate: ScanPredicate<T>) -> Result<Scan<T>> {
+    scan_regions(pid, &MemoryMap::read(pid)?.readable_regions(), predicate)
+}
+
+/// Scan the given `regions` (start address and length) of the memory of the process given by
+/// `pid` for values matching `predicate` - see [`scan()`](fn.scan.html).
+pub fn scan_regions<T:Copy + PartialOrd>(pid: Pid, regions: &[(usize, usize)], predicate: ScanPredicate<T>) -> Result<Scan<T>> {
+    let size = size_of::<T>();
+    let mut matches = Vec::new();
+    for &(start, len) in regions {
+        // Start at the first aligned address in the region.
+        let first = start.next_multiple_of(size);
+        let end = start + len;
+        let mut chunk_start = first;
+        while chunk_start + size <= end {
+            let chunk_len = SCAN_CHUNK.min(end - chunk_start) / size * size;
+            // Parts which can't be read (e.g. guard pages) are skipped.
+            let bytes = read_prims::<u8>(pid, chunk_start, chunk_len).unwrap_or_default();
+            matches.extend((0..bytes.len() / size)
+                .map(|i| (chunk_start + i * size, value_at::<T>(&bytes, i * size)))
+                .filter_map(|(addr, value)| value.filter(|&value| predicate.matches(value, None)).map(|value| (addr, value))));
+            chunk_start += chunk_len;
+        }
+    }
+    Ok(Scan{matches})
+}
+
+/// How often a [`Freezer`](struct.Freezer.html) writes its values back by default.
+pub const DEFAULT_FREEZE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Identifies a value frozen by a [`Freezer`](struct.Freezer.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FreezeId(usize);
+
+#[derive(Debug)]
+struct FrozenValue {
+    addr: usize,
+    bytes: Vec<u8>,
+    enabled: bool,
+}
+
+/// A background thread which keeps writing values into another process's memory, so that the
+/// game can't change them, as trainers do (e.g. to keep the health at its maximum, or the race
+/// countdown at 1).
+///
+/// ## Details:
+/// * Values are added with [`freeze()`](#method.freeze) (or [`hold()`](#method.hold), which
+///   unfreezes the value when the guard it returns is dropped), and can be turned off and on
+///   again without forgetting them with [`set_enabled()`](#method.set_enabled).
+/// * Writes which fail (e.g. because the level changed and the address isn't mapped any more)
+///   are ignored, and tried again next time round.
+/// * Everything is unfrozen when the `Freezer` is dropped.
+pub struct Freezer {
+    values: Arc<Mutex<BTreeMap<FreezeId,FrozenValue>>>,
+    next_id: AtomicUsize,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Freezer {
+    /// Start freezing values in the process given by `pid`, writing them every
+    /// [`DEFAULT_FREEZE_INTERVAL`](constant.DEFAULT_FREEZE_INTERVAL.html).
+    pub fn new(pid: Pid) -> Freezer {
+        Freezer::with_interval(pid, DEFAULT_FREEZE_INTERVAL)
+    }
+
+    /// Start freezing values in the process given by `pid`, writing them every `interval`.
+    pub fn with_interval(pid: Pid, interval: Duration) -> Freezer {
+        let values: Arc<Mutex<BTreeMap<FreezeId,FrozenValue>>> = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_values, thread_stop) = (values.clone(), stop.clone());
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Ok(values) = thread_values.lock() {
+                    for value in values.values().filter(|value| value.enabled) {
+                        let _ = write_prims(pid, value.addr, &value.bytes);
+                    }
+                }
+                thread::sleep(interval);
+            }
+        });
+
+        Freezer{values, next_id: AtomicUsize::new(0), stop, handle: Some(handle)}
+    }
+
+    /// Keep writing `bytes` at `addr`, until [`unfreeze()`](#method.unfreeze)d.
+    pub fn freeze(&self, addr: usize, bytes: &[u8]) -> FreezeId {
+        let id = FreezeId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.lock().insert(id, FrozenValue{addr, bytes: bytes.to_vec(), enabled: true});
+        id
+    }
+
+    /// Keep writing `value` (a primitive, e.g. a `u8` for the health) at `addr` - see
+    /// [`freeze()`](#method.freeze).
+    pub fn freeze_value<T:Copy>(&self, addr: usize, value: T) -> FreezeId {
+        let bytes = unsafe{std::slice::from_raw_parts((&value as *const T).cast::<u8>(), size_of::<T>())};
+        self.freeze(addr, bytes)
+    }
+
+    /// Keep writing `bytes` at `addr` until the guard returned is dropped - see
+    /// [`freeze()`](#method.freeze).
+    pub fn hold(&self, addr: usize, bytes: &[u8]) -> FreezeGuard<'_> {
+        let id = self.freeze(addr, bytes);
+        FreezeGuard{freezer: self, id}
+    }
+
+    /// Stop writing the value given by `id`, and forget it.
+    ///
+    /// ## Returns:
+    /// * `true` if the value was frozen, otherwise `false`.
+    pub fn unfreeze(&self, id: FreezeId) -> bool {
+        self.lock().remove(&id).is_some()
+    }
+
+    /// Stop writing all the values, and forget them.
+    pub fn unfreeze_all(&self) {
+        self.lock().clear();
+    }
+
+    /// Turn writing the value given by `id` off (or back on), without forgetting it.
+    ///
+    /// ## Returns:
+    /// * `true` if the value is frozen (whether or not it's enabled), otherwise `false`.
+    pub fn set_enabled(&self, id: FreezeId, enabled: bool) -> bool {
+        match self.lock().get_mut(&id) {
+            Some(value) => {
+                value.enabled = enabled;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Check if the value given by `id` is frozen and enabled.
+    pub fn is_enabled(&self, id: FreezeId) -> bool {
+        self.lock().get(&id).is_some_and(|value| value.enabled)
+    }
+
+    /// Get the number of values frozen (whether or not they're enabled).
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if there are no values frozen.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The writing thread never panics while holding the lock, so poisoning can be ignored.
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<FreezeId,FrozenValue>> {
+        self.values.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Freezer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Keeps a value frozen by a [`Freezer`](struct.Freezer.html) until dropped - see
+/// [`Freezer::hold()`](struct.Freezer.html#method.hold).
+pub struct FreezeGuard<'a> {
+    freezer: &'a Freezer,
+    id: FreezeId,
+}
+
+impl FreezeGuard<'_> {
+    /// Get the `FreezeId` of the value.
+    pub fn id(&self) -> FreezeId {
+        self.id
+    }
+
+    /// Turn writing the value off (or back on), without unfreezing it - see
+    /// [`Freezer::set_enabled()`](struct.Freezer.html#method.set_enabled).
+    pub fn set_enabled(&self, enabled: bool) {
+        self.freezer.set_enabled(self.id, enabled);
+    }
+}
+
+impl Drop for FreezeGuard<'_> {
+    fn drop(&mut self) {
+        self.freezer.unfreeze(self.id);
+    }
+}
+
+/// A set of writes into another process's memory which can be undone: the original bytes are
+/// read before each write, and written back by [`rollback()`](#method.rollback), or when the
+/// transaction is dropped without being [`commit()`](#method.commit)ted. This makes it safe to
+/// try things out on the game, since a mistake (or a panic) doesn't leave it half-changed.
+///
+/// ## Details:
+/// * The original bytes are written back in the reverse order, so writes which overlap are
+///   undone correctly.
+/// * Nothing can be undone if this program is killed outright (e.g. with `SIGKILL`), since the
+///   original bytes are only kept here.
+/// * If the game changes the memory itself in the meantime, rolling back overwrites that too.
+#[derive(Debug)]
+pub struct Transaction {
+    pid: Pid,
+    // The original bytes at each address written, in the order they were written.
+    originals: Vec<(usize, Vec<u8>)>,
+    committed: bool,
+}
+
+impl Transaction {
+    /// Start a transaction in the process given by `pid`.
+    pub fn new(pid: Pid) -> Transaction {
+        Transaction{pid, originals: Vec::new(), committed: false}
+    }
+
+    /// Write `data` starting at `offset`, as with [`write_prims()`](fn.write_prims.html), keeping
+    /// the bytes which were there.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the original bytes can't be read (in which case nothing is written), or the write
+    ///   fails.
+    pub fn write_prims<T:Copy>(&mut self, offset: usize, data: &[T]) -> Result<()> {
+        let original = match read_prims::<u8>(self.pid, offset, size_of_val(data)) {
+            Ok(original) => original,
+            Err(err) => {return Err(err.context(format!("Couldn't read original bytes at {:#x}", offset)));},
+        };
+        // Kept even if the write fails, since some of it might have been written.
+        self.originals.push((offset, original));
+        write_prims(self.pid, offset, data)
+    }
+
+    /// Write `value` at `offset` - see [`write_prims()`](#method.write_prims).
+    pub fn write_value<T:Copy>(&mut self, offset: usize, value: T) -> Result<()> {
+        self.write_prims(offset, &[value])
+    }
+
+    /// Get the number of writes made so far.
+    pub fn len(&self) -> usize {
+        self.originals.len()
+    }
+
+    /// Check if nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+
+    /// Keep the writes, and forget the original bytes.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Write all the original bytes back, newest first.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if any of the original bytes can't be written back (the others still are).
+    pub fn rollback(mut self) -> Result<()> {
+        self.committed = true;
+        self.restore()
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        while let Some((offset, original)) = self.originals.pop() {
+            if let Err(err) = write_prims(self.pid, offset, &original) {
+                result = Err(err.context(format!("Couldn't restore original bytes at {:#x}", offset)));
+            }
+        }
+        result
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = self.restore();
+        }
+    }
 }
 
 #[cfg(test)]
 mod byte_tests {
     use super::*;
-    use nix::{sys::{ptrace,wait::{waitpid,WaitStatus},signal::{raise,Signal::SIGTRAP}},unistd::{fork,ForkResult},libc::SYS_write};
+    use nix::{sys::{ptrace,wait::{waitpid,WaitStatus},signal::{raise,Signal::SIGTRAP}},unistd::{fork,ForkResult,getpid},libc::SYS_write};
+
+    #[test]
+    fn can_read_into_buffers() {
+        let values: [u16; 4] = [1, 2, 3, 4];
+        let mut buf = [0u16; 3];
+        assert_eq!(read_into(getpid(), values[1..].as_ptr() as usize, &mut buf).unwrap(), 3);
+        assert_eq!(buf, [2, 3, 4]);
+    }
+
+    #[test]
+    fn can_read_batches() {
+        let values: [u8; 6] = [1, 2, 3, 4, 5, 6];
+        let base = values.as_ptr() as usize;
+        // The null page is never mapped, so the middle region can't be read.
+        let batch = read_batch(getpid(), &[(base, 2), (0, 4), (base + 3, 3), (base, 0)]).unwrap();
+        assert_eq!(batch, vec![vec![1, 2], vec![], vec![4, 5, 6], vec![]]);
+    }
+
+    #[test]
+    fn can_follow_paths() {
+        const PATH: PointerPath = PointerPath::new(0x1000).off(0x4).off(0x8);
+        assert_eq!(PATH.resolve(getpid()).unwrap(), 0x100C);
+        assert_eq!(PATH.steps(), &[PathStep::Offset(0x4), PathStep::Offset(0x8)]);
+
+        let value = 0u32;
+        match PointerPath::relative().deref().resolve_from(getpid(), 0) {
+            Err(Error::BadPointerPath{step: 0, addr: 0, ..}) => {},
+            other => panic!("Expected a broken path, got {:?}", other),
+        }
+        // The offsets aren't counted as steps, the same as for get_pointer_path().
+        let pointer = 0u64;
+        match PointerPath::relative().off(4).deref().off(8).deref().resolve_from_with(getpid(), PointerWidth::Bits64, &pointer as *const u64 as usize - 4) {
+            Err(Error::BadPointerPath{step: 1, ..}) => {},
+            other => panic!("Expected a path broken at step 1, got {:?}", other),
+        }
+        match get_pointer_path(getpid(), 0, None) {
+            Err(Error::BadPointerPath{step: 0, addr: 0, ..}) => {},
+            other => panic!("Expected a broken path, got {:?}", other),
+        }
+        assert!(PointerPath::new(0x10).off(usize::MAX).resolve(getpid()).is_err());
+        // Only the bottom 32 bits of a pointer are stored, so only follow one if it fits.
+        let stored = &value as *const u32 as usize as u32;
+        if stored as usize == &value as *const u32 as usize {
+            assert_eq!(PointerPath::relative().deref().resolve_from(getpid(), &stored as *const u32 as usize).unwrap(), stored as usize);
+        }
+        let wide = &value as *const u32 as u64;
+        assert_eq!(PointerPath::relative().deref().resolve_from_with(getpid(), PointerWidth::Bits64, &wide as *const u64 as usize).unwrap(), wide as usize);
+        assert_eq!(get_pointer_path_with(getpid(), PointerWidth::Bits64, &wide as *const u64 as usize, None).unwrap(), wide as usize);
+    }
+
+    #[test]
+    fn can_read_structs() {
+        let values: [u16; 3] = [7, 8, 9];
+        assert_eq!(read_struct::<[u16; 2]>(getpid(), values[1..].as_ptr() as usize).unwrap(), [8, 9]);
+        assert!(read_struct::<u32>(getpid(), 0).is_err());
+    }
+
+    #[test]
+    fn snapshots_read_consistent_values() {
+        let mut values: [u32; 4] = [1, 2, 3, 4];
+        let base = values.as_ptr() as usize;
+        let mut snapshot = Snapshot::new().with_region(base, 8).with_region(base + 12, 4);
+        snapshot.refresh(getpid()).unwrap();
+        values[1] = 20;
+        std::hint::black_box(&mut values);
+        assert_eq!(snapshot.read::<u32>(base + 4), Some(2));
+        assert_eq!(snapshot.read::<u32>(base + 12), Some(4));
+        assert_eq!(snapshot.read::<u32>(base + 8), None);
+        assert_eq!(snapshot.read::<u16>(base + 6), Some(0));
+    }
+
+    #[test]
+    fn can_scan_for_values() {
+        let mut values: [u32; 8] = [3, 10, 3, 7, 3, 0, 12, 3];
+        let base = values.as_ptr() as usize;
+        let regions = [(base, size_of_val(&values))];
+
+        let mut scan = scan_regions(getpid(), &regions, ScanPredicate::Exact(3u32)).unwrap();
+        assert_eq!(scan.addresses(), vec![base, base + 8, base + 16, base + 28]);
+        values[2] = 4;
+        values[4] = 2;
+        std::hint::black_box(&mut values);
+        assert_eq!(scan.narrow(getpid(), ScanPredicate::Changed).unwrap(), 2);
+        assert_eq!(scan.matches(), &[(base + 8, 4), (base + 16, 2)]);
+        assert_eq!(scan.narrow(getpid(), ScanPredicate::Unchanged).unwrap(), 2);
+        assert_eq!(scan.narrow(getpid(), ScanPredicate::Increased).unwrap(), 0);
+
+        let scan = scan_regions(getpid(), &regions, ScanPredicate::Range(7u32, 12)).unwrap();
+        assert_eq!(scan.addresses(), vec![base + 4, base + 12, base + 24]);
+        assert!(!ScanPredicate::Changed.matches(1u8, None));
+    }
+
+    #[test]
+    fn can_check_memory_maps() {
+        let map = MemoryMap::parse("00400000-00401000 r--p 00000000 08:01 123 /games/Rayman 2/Rayman2.exe\n\
+                                    00401000-00402000 rw-p 00001000 08:01 123 /games/Rayman 2/Rayman2.exe\n\
+                                    00500000-00510000 ---p 00000000 00:00 0 \n\
+                                    7fff0000-7fff1000 r--p 00000000 00:00 0 [vvar]\n");
+        assert_eq!(map.mappings()[0].path, "/games/Rayman 2/Rayman2.exe");
+        assert_eq!(map.mappings()[2].path, "");
+        assert_eq!(map.find(0x401800).map(|mapping| mapping.offset), Some(0x1000));
+        assert!(map.find(0x402000).is_none());
+        assert!(map.is_readable(0x400800, 0x1000));
+        assert!(!map.is_writable(0x400800, 0x1000));
+        assert_eq!(map.first_unreadable(0x401800, 0x1000), Some(0x402000));
+        assert_eq!(map.first_unreadable(0x500000, 4), Some(0x500000));
+        assert_eq!(map.readable_regions(), vec![(0x400000, 0x1000), (0x401000, 0x1000)]);
+
+        // Turning validation on for every thread would upset the other tests, so check directly.
+        let mut buf = [0u8; 4];
+        let result = NativeBackend::new(getpid()).read_bytes(0, &mut buf);
+        match check_read(getpid(), 0, 4, result, true) {
+            Err(Error::Unmapped(0)) => {},
+            other => panic!("Expected an unmapped address, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn can_freeze_values() {
+        let value = Box::new(0u32);
+        let addr = &*value as *const u32 as usize;
+        let read = || unsafe{std::ptr::read_volatile(addr as *const u32)};
+        let wait_for = |expected: u32| (0..1000).any(|_| {
+            thread::sleep(Duration::from_millis(1));
+            read() == expected
+        });
+
+        let freezer = Freezer::new(getpid());
+        let id = freezer.freeze_value(addr, 5u32);
+        assert!(wait_for(5));
+        assert!(freezer.set_enabled(id, false));
+        assert!(!freezer.is_enabled(id));
+        write_prims(getpid(), addr, &[7u32]).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(read(), 7);
+        assert!(freezer.unfreeze(id));
+        assert!(!freezer.unfreeze(id));
+        {
+            let guard = freezer.hold(addr, &9u32.to_ne_bytes());
+            assert!(guard.freezer.is_enabled(guard.id()));
+            assert!(wait_for(9));
+        }
+        assert!(freezer.is_empty());
+    }
+
+    #[test]
+    fn can_roll_back_writes() {
+        let values = Box::new([1u32, 2, 3]);
+        let addr = values.as_ptr() as usize;
+        let read = || unsafe{std::ptr::read_volatile(addr as *const [u32; 3])};
+
+        let mut transaction = Transaction::new(getpid());
+        transaction.write_prims(addr, &[10u32, 20]).unwrap();
+        transaction.write_value(addr + 4, 30u32).unwrap();
+        assert_eq!(read(), [10, 30, 3]);
+        assert_eq!(transaction.len(), 2);
+        transaction.rollback().unwrap();
+        assert_eq!(read(), [1, 2, 3]);
+
+        {
+            let mut transaction = Transaction::new(getpid());
+            transaction.write_value(addr + 8, 9u32).unwrap();
+            assert!(transaction.write_value(0, 1u32).is_err());
+        }
+        assert_eq!(read(), [1, 2, 3]);
+
+        let mut transaction = Transaction::new(getpid());
+        transaction.write_value(addr, 5u32).unwrap();
+        transaction.commit();
+        assert_eq!(read(), [5, 2, 3]);
+    }
 
     #[test]
     fn can_read_strings() {
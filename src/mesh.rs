@@ -0,0 +1,678 @@
+/*!
+  The geometry of families in Rayman 2: each family has a table of physical objects, each physical
+  object has a visual set with one mesh per level of detail, and each mesh has its vertices and a
+  number of sub-blocks (e.g. indexed triangles with texture coordinates).
+
+  This reads the same structures as
+  [`utils::get_family_po_vert_offsets()`](../utils/fn.get_family_po_vert_offsets.html), but keeps
+  their shape, using the declarations in [`structs`](../structs/index.html).
+
+  Meshes can be exported to Wavefront OBJ (with [`export_obj()`](fn.export_obj.html)) or glTF
+  (with [`export_gltf()`](fn.export_gltf.html)), e.g. to look at a level's geometry in Blender,
+  and deformed live (with [`write_vertices()`](fn.write_vertices.html) or a
+  [`MeshDeformer`](struct.MeshDeformer.html)), for FunBox-style visual effects.
+  */
+
+extern crate nix;
+
+use std::{fs,fmt::Write,path::Path};
+use nix::unistd::Pid;
+use crate::{memory::write_prims,remote::RemotePtr,structs::{FamilyHeader,ObjectTableEntry,VisualSet,MeshHeader,TriangleElement},superobject::{SuperObject,matrix_translation},telemetry::{json_number,json_string},error::{Error,Result}};
+
+// Each entry in a family's default objects table takes up 0x14 bytes.
+const OBJECT_TABLE_ENTRY_SIZE: usize = 0x14;
+// The most levels of detail or sub-blocks we'll believe a visual set or mesh has, in case it's
+// garbage (e.g. while a level is loading).
+const MAX_COUNT: usize = 256;
+
+/// A family, with its physical objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Family {
+    /// Pointer to the family in Rayman 2's memory.
+    pub ptr: usize,
+    /// The index of the family in the families table.
+    pub index: usize,
+    /// The physical objects in the family's default objects table, in order. Entries which have
+    /// no visual set (or couldn't be read) are left out.
+    pub physical_objects: Vec<PhysicalObject>,
+}
+
+/// A physical object of a family.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalObject {
+    /// The index of the physical object in the family's default objects table (as used by the
+    /// `indices` of [`utils::get_family_po_vert_offsets()`](../utils/fn.get_family_po_vert_offsets.html)).
+    pub index: usize,
+    /// The type of visual (`0` for meshes).
+    pub visual_type: i16,
+    /// The meshes, one for each level of detail (most detailed first). This is empty if the
+    /// visual isn't made of meshes.
+    pub lods: Vec<Mesh>,
+}
+
+/// A mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    /// Pointer to the mesh in Rayman 2's memory.
+    pub ptr: usize,
+    /// Pointer to the vertices in Rayman 2's memory (the key used by
+    /// [`utils::get_family_po_vert_offsets()`](../utils/fn.get_family_po_vert_offsets.html)).
+    pub vertices_ptr: usize,
+    /// The vertices (x, y, z), as of when they were read.
+    pub vertices: Vec<[f32; 3]>,
+    /// The sub-blocks, in order.
+    pub sub_blocks: Vec<SubBlock>,
+}
+
+/// The kinds of mesh sub-block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubBlockType {
+    /// Indexed triangles, with texture coordinates.
+    Triangles,
+    /// Sprites.
+    Sprites,
+    /// Any other type number.
+    Unknown(u16),
+}
+
+impl From<u16> for SubBlockType {
+    fn from(type_number: u16) -> SubBlockType {
+        match type_number {
+            1 => SubBlockType::Triangles,
+            3 => SubBlockType::Sprites,
+            other => SubBlockType::Unknown(other),
+        }
+    }
+}
+
+/// A sub-block of a mesh. Only the triangles sub-blocks have their contents read - the others just
+/// have their type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubBlock {
+    /// The type of the sub-block.
+    pub block_type: SubBlockType,
+    /// Pointer to the sub-block in Rayman 2's memory.
+    pub ptr: usize,
+    /// The triangles, as indices into the mesh's vertices.
+    pub triangles: Vec<[u16; 3]>,
+    /// For each triangle, the indices into `uvs` of its corners.
+    pub uv_indices: Vec<[u16; 3]>,
+    /// The texture coordinates (u, v).
+    pub uvs: Vec<[f32; 2]>,
+}
+
+impl Family {
+    /// Read the family at `ptr`, with all its physical objects and meshes, from the Rayman 2
+    /// process given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    /// * You need to give a pointer to a valid family.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `Family`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the family or its objects table can't be read.
+    pub fn read(r2pid: Pid, ptr: usize) -> Result<Family> {
+        let header = match RemotePtr::<FamilyHeader>::new(ptr).load(r2pid) {
+            Ok(header) => header,
+            Err(err) => {return Err(err.context("Couldn't read family"));},
+        };
+        let table = match header.default_objects.load(r2pid) {
+            Ok(table) => table,
+            Err(err) => {return Err(err.context("Couldn't read default objects table"));},
+        };
+
+        let physical_objects = (0..table.num_entries as usize)
+            .filter_map(|index| {
+                let entry = RemotePtr::<ObjectTableEntry>::new(table.entries.addr() + index * OBJECT_TABLE_ENTRY_SIZE);
+                // Some entries have no visual set, so failures here are skipped rather than
+                // returned (as in `get_family_po_vert_offsets()`).
+                PhysicalObject::read(r2pid, index, entry).ok()
+            })
+            .collect();
+
+        Ok(Family{ptr, index: header.index as usize, physical_objects})
+    }
+
+    /// Get a name for each physical object (`<family_name>_po<index>`, e.g. for exporting), along
+    /// with its mesh at its most detailed level.
+    pub fn named_meshes(&self, family_name: &str) -> Vec<(String, &Mesh)> {
+        self.physical_objects.iter()
+            .filter_map(|po| Some((format!("{}_po{}", family_name, po.index), po.lods.first()?)))
+            .collect()
+    }
+
+    /// Go through all the meshes of all the physical objects, at their most detailed level.
+    pub fn meshes(&self) -> impl Iterator<Item = &Mesh> {
+        self.physical_objects.iter().filter_map(|po| po.lods.first())
+    }
+}
+
+impl PhysicalObject {
+    /// Read the physical object in the default objects table `entry` (at `index` in the table),
+    /// from the Rayman 2 process given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `PhysicalObject`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the entry has no visual set, or if the memory read fails.
+    pub fn read(r2pid: Pid, index: usize, entry: RemotePtr<ObjectTableEntry>) -> Result<PhysicalObject> {
+        let visual_set = match entry.load(r2pid).and_then(|entry| entry.visual_set.follow(r2pid)) {
+            Ok(ptr) if ptr.is_null() => {return Err(format!("Physical object {} has no visual set", index).into());},
+            Ok(ptr) => ptr,
+            Err(err) => {return Err(err.context(format!("Couldn't get visual set of physical object {}", index)));},
+        };
+        let VisualSet{num_of_lod, visual_type, lod_visuals} = match visual_set.load(r2pid) {
+            Ok(visual_set) => visual_set,
+            Err(err) => {return Err(err.context(format!("Couldn't read visual set of physical object {}", index)));},
+        };
+
+        let lods = match (visual_type, num_of_lod) {
+            (0, 1..=i16::MAX) => {
+                // Pointers in Rayman 2's memory are 32-bit.
+                let ptrs = match lod_visuals.cast::<u32>().read_n(r2pid, (num_of_lod as usize).min(MAX_COUNT)) {
+                    Ok(ptrs) => ptrs,
+                    Err(err) => {return Err(err.context("Couldn't read levels of detail"));},
+                };
+                ptrs.into_iter()
+                    .map(|ptr| Mesh::read(r2pid, RemotePtr::new(ptr as usize)))
+                    .collect::<Result<Vec<Mesh>>>()?
+            },
+            _ => Vec::new(),
+        };
+
+        Ok(PhysicalObject{index, visual_type, lods})
+    }
+}
+
+impl Mesh {
+    /// Read the mesh at `ptr` from the Rayman 2 process given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    /// * You need to give a pointer to a valid mesh.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `Mesh`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn read(r2pid: Pid, ptr: RemotePtr<MeshHeader>) -> Result<Mesh> {
+        let header = match ptr.load(r2pid) {
+            Ok(header) => header,
+            Err(err) => {return Err(err.context("Couldn't read mesh header"));},
+        };
+        let vertices = match header.vertices.read_n(r2pid, header.num_vertices.max(0) as usize) {
+            Ok(vertices) => vertices,
+            Err(err) => {return Err(err.context("Couldn't get vertex positions"));},
+        };
+
+        let num_sub_blocks = (header.num_sub_blocks.max(0) as usize).min(MAX_COUNT);
+        let (types, block_ptrs) = match (header.sub_block_types.read_n(r2pid, num_sub_blocks), header.sub_blocks.cast::<u32>().read_n(r2pid, num_sub_blocks)) {
+            (Ok(types), Ok(block_ptrs)) => (types, block_ptrs),
+            (Err(err), _) | (_, Err(err)) => {return Err(err.context("Couldn't read sub-blocks"));},
+        };
+        let sub_blocks = types.into_iter().zip(block_ptrs)
+            .map(|(block_type, block_ptr)| SubBlock::read(r2pid, block_type.into(), block_ptr as usize))
+            .collect::<Result<Vec<SubBlock>>>()?;
+
+        Ok(Mesh{ptr: ptr.addr(), vertices_ptr: header.vertices.addr(), vertices, sub_blocks})
+    }
+
+    /// Go through all the triangles in all the sub-blocks, as vertex positions.
+    pub fn triangles(&self) -> impl Iterator<Item = [[f32; 3]; 3]> + '_ {
+        self.sub_blocks.iter()
+            .flat_map(|block| block.triangles.iter())
+            .filter_map(move |triangle| {
+                let [a, b, c] = *triangle;
+                Some([*self.vertices.get(a as usize)?, *self.vertices.get(b as usize)?, *self.vertices.get(c as usize)?])
+            })
+    }
+
+    /// Get the smallest and largest x, y and z of the vertices, or `None` if there are none.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.vertices.first()?;
+        Some(self.vertices.iter().fold((first, first), |(min, max), vertex| (
+            std::array::from_fn(|i| min[i].min(vertex[i])),
+            std::array::from_fn(|i| max[i].max(vertex[i])),
+        )))
+    }
+}
+
+impl SubBlock {
+    /// Read the sub-block of type `block_type` at `ptr` from the Rayman 2 process given by
+    /// `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `SubBlock`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn read(r2pid: Pid, block_type: SubBlockType, ptr: usize) -> Result<SubBlock> {
+        let mut block = SubBlock{block_type, ptr, triangles: Vec::new(), uv_indices: Vec::new(), uvs: Vec::new()};
+        if block_type != SubBlockType::Triangles || ptr == 0 {
+            return Ok(block);
+        }
+
+        let element = match RemotePtr::<TriangleElement>::new(ptr).load(r2pid) {
+            Ok(element) => element,
+            Err(err) => {return Err(err.context("Couldn't read triangles sub-block"));},
+        };
+        let num_triangles = element.num_triangles as usize;
+        block.triangles = match element.triangles.read_n(r2pid, num_triangles) {
+            Ok(triangles) => triangles,
+            Err(err) => {return Err(err.context("Couldn't read triangles"));},
+        };
+        // Not every sub-block is textured.
+        if !element.uvs.is_null() && !element.uv_indices.is_null() {
+            block.uv_indices = match element.uv_indices.read_n(r2pid, num_triangles) {
+                Ok(uv_indices) => uv_indices,
+                Err(err) => {return Err(err.context("Couldn't read texture coordinate indices"));},
+            };
+            block.uvs = match element.uvs.read_n(r2pid, element.num_uvs as usize) {
+                Ok(uvs) => uvs,
+                Err(err) => {return Err(err.context("Couldn't read texture coordinates"));},
+            };
+        }
+        Ok(block)
+    }
+}
+
+/// Where a mesh is in the world: the position, and the rotation and scale, from a super-object's
+/// global matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// The position (x, y, z).
+    pub position: [f32; 3],
+    /// The rotation (with the scale applied), as a 3x3 matrix, row by row.
+    pub rotation: [f32; 9],
+}
+
+impl Transform {
+    /// The transform which leaves vertices where they are.
+    pub const IDENTITY: Transform = Transform{position: [0.0; 3], rotation: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]};
+
+    /// Read the transform of the super-object at `super_object` in the Rayman 2 process given by
+    /// `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    /// * You need to give a pointer to a valid super-object.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `Transform`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the super-object has no global matrix, or if the memory read fails.
+    pub fn read(r2pid: Pid, super_object: usize) -> Result<Transform> {
+        let matrix = SuperObject::new(r2pid, super_object).matrix()?;
+        Ok(Transform{position: matrix_translation(&matrix), rotation: std::array::from_fn(|i| matrix[4*(i / 3) + i % 3])})
+    }
+
+    /// Move `vertex` from the mesh's own coordinates into the world.
+    pub fn apply(&self, vertex: [f32; 3]) -> [f32; 3] {
+        let r = &self.rotation;
+        std::array::from_fn(|i| r[3*i] * vertex[0] + r[3*i + 1] * vertex[1] + r[3*i + 2] * vertex[2] + self.position[i])
+    }
+}
+
+/// A mesh to export, with its name and (optionally) where it is in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshInstance<'a> {
+    /// The name to give the object in the exported file.
+    pub name: &'a str,
+    /// The mesh.
+    pub mesh: &'a Mesh,
+    /// Where to put the mesh, or `None` to leave it in its own coordinates.
+    pub transform: Option<Transform>,
+}
+
+impl MeshInstance<'_> {
+    /// Go through the vertices, moved into the world by the transform (if any).
+    pub fn vertices(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        let transform = self.transform.unwrap_or(Transform::IDENTITY);
+        self.mesh.vertices.iter().map(move |&vertex| transform.apply(vertex))
+    }
+
+    /// Go through the triangles, as vertex positions moved into the world by the transform (if
+    /// any) - see [`Mesh::triangles()`](struct.Mesh.html#method.triangles).
+    pub fn triangles(&self) -> impl Iterator<Item = [[f32; 3]; 3]> + '_ {
+        let transform = self.transform.unwrap_or(Transform::IDENTITY);
+        self.mesh.triangles().map(move |triangle| triangle.map(|vertex| transform.apply(vertex)))
+    }
+}
+
+/// Write `meshes` to a Wavefront OBJ file at `path`, as one object each, with their texture
+/// coordinates.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the file can't be written.
+///
+/// ## Details:
+/// * The coordinates are left as the game has them, with z pointing up (so tell Blender's
+///   importer that "up" is z).
+pub fn export_obj<P: AsRef<Path>>(path: P, meshes: &[MeshInstance]) -> Result<()> {
+    match fs::write(&path, to_obj(meshes)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't write OBJ file {}", path.as_ref().display()))),
+    }
+}
+
+fn to_obj(meshes: &[MeshInstance]) -> String {
+    let mut obj = String::from("# Exported by walkoflife\n");
+    // OBJ indices are 1-based, and count up through the whole file.
+    let (mut first_vertex, mut first_uv) = (1, 1);
+    for instance in meshes {
+        let _ = writeln!(obj, "o {}", instance.name.replace(char::is_whitespace, "_"));
+        for [x, y, z] in instance.vertices() {
+            let _ = writeln!(obj, "v {} {} {}", x, y, z);
+        }
+        for block in &instance.mesh.sub_blocks {
+            for [u, v] in &block.uvs {
+                let _ = writeln!(obj, "vt {} {}", u, v);
+            }
+            for (i, triangle) in block.triangles.iter().enumerate() {
+                obj.push('f');
+                for corner in 0..3 {
+                    let _ = write!(obj, " {}", first_vertex + triangle[corner] as usize);
+                    if let Some(uv) = block.uv_indices.get(i).map(|uvs| uvs[corner] as usize).filter(|&uv| uv < block.uvs.len()) {
+                        let _ = write!(obj, "/{}", first_uv + uv);
+                    }
+                }
+                obj.push('\n');
+            }
+            first_uv += block.uvs.len();
+        }
+        first_vertex += instance.mesh.vertices.len();
+    }
+    obj
+}
+
+/// Write `meshes` to a glTF file at `path`, as one node each, with the vertex data in a `.bin`
+/// file next to it (e.g. `track.gltf` and `track.bin`).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if either file can't be written.
+///
+/// ## Details:
+/// * glTF has y pointing up, so the game's coordinates are turned to match.
+/// * Each triangle gets its own three vertices, since the game indexes positions and texture
+///   coordinates separately.
+pub fn export_gltf<P: AsRef<Path>>(path: P, meshes: &[MeshInstance]) -> Result<()> {
+    let path = path.as_ref();
+    let bin_path = path.with_extension("bin");
+    let bin_name = bin_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let (json, bin) = to_gltf(meshes, &bin_name);
+    match fs::write(&bin_path, bin).and_then(|_| fs::write(path, json)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't write glTF file {}", path.display()))),
+    }
+}
+
+fn to_gltf(meshes: &[MeshInstance], bin_name: &str) -> (String, Vec<u8>) {
+    const FLOAT: u32 = 5126;
+    const TRIANGLES: u32 = 4;
+    let mut bin = Vec::new();
+    let (mut views, mut accessors, mut gltf_meshes, mut nodes) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+    // Add a buffer view and an accessor for `values`, returning the accessor's index.
+    let mut add_accessor = |bin: &mut Vec<u8>, values: &[f32], kind: &str, components: usize, bounds: bool| {
+        let offset = bin.len();
+        for value in values {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}}}", offset, bin.len() - offset));
+        let mut accessor = format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"{}\"",
+                                   views.len() - 1, FLOAT, values.len() / components, kind);
+        if bounds {
+            let (min, max): (Vec<String>, Vec<String>) = (0..components)
+                .map(|c| {
+                    let column = values.iter().skip(c).step_by(components);
+                    (json_number(column.clone().copied().fold(f32::INFINITY, f32::min)), json_number(column.copied().fold(f32::NEG_INFINITY, f32::max)))
+                })
+                .unzip();
+            let _ = write!(accessor, ",\"min\":[{}],\"max\":[{}]", min.join(","), max.join(","));
+        }
+        accessor.push('}');
+        accessors.push(accessor);
+        accessors.len() - 1
+    };
+
+    for instance in meshes {
+        let vertices: Vec<[f32; 3]> = instance.vertices().collect();
+        let mut primitives = Vec::new();
+        for block in &instance.mesh.sub_blocks {
+            let corners: Vec<(usize, Option<[f32; 2]>)> = block.triangles.iter().enumerate()
+                .filter(|(_, triangle)| triangle.iter().all(|&v| (v as usize) < vertices.len()))
+                .flat_map(|(i, triangle)| (0..3).map(move |corner| {
+                    let uv = block.uv_indices.get(i).and_then(|uvs| block.uvs.get(uvs[corner] as usize)).copied();
+                    (triangle[corner] as usize, uv)
+                }))
+                .collect();
+            if corners.is_empty() {
+                continue;
+            }
+            // Turn z-up into y-up (subtracting, so that 0 doesn't become -0).
+            let positions: Vec<f32> = corners.iter().flat_map(|&(v, _)| {
+                let [x, y, z] = vertices[v];
+                [x, z, 0.0 - y]
+            }).collect();
+            let position = add_accessor(&mut bin, &positions, "VEC3", 3, true);
+            let mut attributes = format!("\"POSITION\":{}", position);
+            if corners.iter().all(|(_, uv)| uv.is_some()) {
+                let uvs: Vec<f32> = corners.iter().flat_map(|(_, uv)| uv.unwrap_or_default()).collect();
+                let _ = write!(attributes, ",\"TEXCOORD_0\":{}", add_accessor(&mut bin, &uvs, "VEC2", 2, false));
+            }
+            primitives.push(format!("{{\"attributes\":{{{}}},\"mode\":{}}}", attributes, TRIANGLES));
+        }
+        if primitives.is_empty() {
+            continue;
+        }
+        gltf_meshes.push(format!("{{\"name\":{},\"primitives\":[{}]}}", json_string(instance.name), primitives.join(",")));
+        nodes.push(format!("{{\"name\":{},\"mesh\":{}}}", json_string(instance.name), gltf_meshes.len() - 1));
+    }
+
+    let json = format!(concat!("{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"walkoflife\"}},",
+                               "\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}],\"nodes\":[{}],\"meshes\":[{}],",
+                               "\"accessors\":[{}],\"bufferViews\":[{}],\"buffers\":[{{\"uri\":{},\"byteLength\":{}}}]}}\n"),
+                       (0..nodes.len()).map(|i| i.to_string()).collect::<Vec<String>>().join(","), nodes.join(","),
+                       gltf_meshes.join(","), accessors.join(","), views.join(","), json_string(bin_name), bin.len());
+    (json, bin)
+}
+
+/// Write new vertex positions for `mesh` into the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * `vertices` needs to have three floats (x, y, z) for each of the mesh's vertices.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if `vertices` is the wrong length, or if the memory write fails.
+///
+/// ## Details:
+/// * The game doesn't usually rewrite the vertices of a mesh, so they stay put until the level is
+///   reloaded (or until they're written again).
+pub fn write_vertices(r2pid: Pid, mesh: &Mesh, vertices: &[f32]) -> Result<()> {
+    if vertices.len() != 3 * mesh.vertices.len() {
+        return Err(format!("Mesh has {} vertices, but got {} floats", mesh.vertices.len(), vertices.len()).into());
+    }
+    match write_prims(r2pid, mesh.vertices_ptr, vertices) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't write vertex positions")),
+    }
+}
+
+/// Deforms a mesh in a Rayman 2 process, always starting from its original vertices (as they were
+/// when it was read), so effects can be animated without drifting.
+///
+/// ## Details:
+/// * The original vertices are put back when the `MeshDeformer` is dropped (ignoring errors, e.g.
+///   if the game has quit).
+pub struct MeshDeformer {
+    r2pid: Pid,
+    mesh: Mesh,
+}
+
+impl MeshDeformer {
+    /// Start deforming `mesh` (as read with [`Mesh::read()`](struct.Mesh.html#method.read)) in the
+    /// Rayman 2 process given by `r2pid`.
+    pub fn new(r2pid: Pid, mesh: Mesh) -> MeshDeformer {
+        MeshDeformer{r2pid, mesh}
+    }
+
+    /// Get the mesh, with its original vertices.
+    pub fn original(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// Move each of the original vertices with `deform`, which is given the vertex's index and
+    /// position, and write the results to the game.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory write fails.
+    pub fn apply<F: FnMut(usize, [f32; 3]) -> [f32; 3]>(&self, mut deform: F) -> Result<()> {
+        let vertices: Vec<f32> = self.mesh.vertices.iter().enumerate()
+            .flat_map(|(i, &vertex)| deform(i, vertex))
+            .collect();
+        write_vertices(self.r2pid, &self.mesh, &vertices)
+    }
+
+    /// Scale the mesh by `factor` about the middle of its bounds - see [`apply()`](#method.apply).
+    pub fn scale(&self, factor: f32) -> Result<()> {
+        let centre = self.centre();
+        self.apply(|_, vertex| scaled(vertex, centre, factor))
+    }
+
+    /// Move each vertex by up to `amount` in each direction, pseudo-randomly - see
+    /// [`apply()`](#method.apply). The same `seed` always gives the same offsets, so change it
+    /// (e.g. every frame) to make the mesh shake.
+    pub fn jitter(&self, amount: f32, seed: u32) -> Result<()> {
+        self.apply(|i, vertex| jittered(vertex, i, amount, seed))
+    }
+
+    /// Send a ripple out from the middle of the mesh, raising and lowering the vertices (along z)
+    /// by up to `amplitude`, with waves `wavelength` apart, at time `time` (in waves, so it goes
+    /// through one full wave as `time` goes up by 1) - see [`apply()`](#method.apply).
+    pub fn ripple(&self, amplitude: f32, wavelength: f32, time: f32) -> Result<()> {
+        let centre = self.centre();
+        self.apply(|_, vertex| rippled(vertex, centre, amplitude, wavelength, time))
+    }
+
+    /// Put the original vertices back.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory write fails.
+    pub fn restore(&self) -> Result<()> {
+        self.apply(|_, vertex| vertex)
+    }
+
+    fn centre(&self) -> [f32; 3] {
+        match self.mesh.bounds() {
+            Some((min, max)) => std::array::from_fn(|i| (min[i] + max[i]) / 2.0),
+            None => [0.0; 3],
+        }
+    }
+}
+
+impl Drop for MeshDeformer {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+fn scaled(vertex: [f32; 3], centre: [f32; 3], factor: f32) -> [f32; 3] {
+    std::array::from_fn(|i| centre[i] + (vertex[i] - centre[i]) * factor)
+}
+
+fn jittered(vertex: [f32; 3], index: usize, amount: f32, seed: u32) -> [f32; 3] {
+    std::array::from_fn(|axis| {
+        // A cheap hash (from SplitMix) of the seed, vertex and axis, scaled to -1..1.
+        let mut x = seed ^ (index as u32).wrapping_mul(3).wrapping_add(axis as u32).wrapping_mul(0x9E3779B9);
+        x = (x ^ (x >> 16)).wrapping_mul(0x85EBCA6B);
+        x = (x ^ (x >> 13)).wrapping_mul(0xC2B2AE35);
+        x ^= x >> 16;
+        vertex[axis] + amount * (x as f32 / u32::MAX as f32 * 2.0 - 1.0)
+    })
+}
+
+fn rippled(vertex: [f32; 3], centre: [f32; 3], amplitude: f32, wavelength: f32, time: f32) -> [f32; 3] {
+    let distance = (vertex[0] - centre[0]).hypot(vertex[1] - centre[1]);
+    let phase = 2.0 * std::f32::consts::PI * (distance / wavelength - time);
+    [vertex[0], vertex[1], vertex[2] + amplitude * phase.sin()]
+}
+
+#[cfg(test)]
+mod mesh_tests {
+    use super::*;
+
+    #[test]
+    fn can_index_triangles() {
+        let mesh = Mesh{
+            ptr: 0,
+            vertices_ptr: 0,
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, -2.0, 0.0], [0.0, 1.0, 3.0]],
+            sub_blocks: vec![
+                SubBlock{block_type: SubBlockType::Triangles, ptr: 0, triangles: vec![[0, 1, 2], [0, 1, 7]], uv_indices: Vec::new(), uvs: Vec::new()},
+                SubBlock{block_type: 3.into(), ptr: 0, triangles: Vec::new(), uv_indices: Vec::new(), uvs: Vec::new()},
+            ],
+        };
+        // The second triangle is out of range, so it's skipped.
+        assert_eq!(mesh.triangles().collect::<Vec<_>>(), vec![[[0.0, 0.0, 0.0], [1.0, -2.0, 0.0], [0.0, 1.0, 3.0]]]);
+        assert_eq!(mesh.bounds(), Some(([0.0, -2.0, 0.0], [1.0, 1.0, 3.0])));
+        assert_eq!(mesh.sub_blocks[1].block_type, SubBlockType::Sprites);
+    }
+
+    #[test]
+    fn can_export_meshes() {
+        let mesh = Mesh{
+            ptr: 0,
+            vertices_ptr: 0,
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            sub_blocks: vec![SubBlock{block_type: SubBlockType::Triangles, ptr: 0, triangles: vec![[0, 1, 2]], uv_indices: vec![[1, 0, 1]], uvs: vec![[0.0, 0.0], [1.0, 0.5]]}],
+        };
+        let moved = Transform{position: [0.0, 0.0, 10.0], ..Transform::IDENTITY};
+        let meshes = [
+            MeshInstance{name: "first", mesh: &mesh, transform: None},
+            MeshInstance{name: "second one", mesh: &mesh, transform: Some(moved)},
+        ];
+
+        let obj = to_obj(&meshes);
+        assert!(obj.contains("o first\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0.5\nf 1/2 2/1 3/2\n"));
+        assert!(obj.contains("o second_one\nv 0 0 10\n"));
+        assert!(obj.contains("f 4/4 5/3 6/4\n"));
+
+        let (json, bin) = to_gltf(&meshes, "test.bin");
+        // Two primitives, each with 3 positions and 3 UVs.
+        assert_eq!(bin.len(), 2 * (3 * 12 + 3 * 8));
+        assert!(json.contains("\"nodes\":[{\"name\":\"first\",\"mesh\":0},{\"name\":\"second one\",\"mesh\":1}]"));
+        assert!(json.contains("\"min\":[0,10,-1],\"max\":[1,10,0]"));
+        assert!(json.contains("\"buffers\":[{\"uri\":\"test.bin\",\"byteLength\":120}]"));
+    }
+
+    #[test]
+    fn can_deform_vertices() {
+        assert_eq!(scaled([2.0, 0.0, -1.0], [1.0, 0.0, 0.0], 2.0), [3.0, 0.0, -2.0]);
+        let shaken = jittered([1.0, 1.0, 1.0], 5, 0.5, 42);
+        assert!(shaken.iter().all(|&value| (0.5..=1.5).contains(&value)));
+        assert_eq!(shaken, jittered([1.0, 1.0, 1.0], 5, 0.5, 42));
+        assert_ne!(shaken, jittered([1.0, 1.0, 1.0], 5, 0.5, 43));
+        // A quarter of a wave out from the centre is at the top of the wave at time 0.
+        let [x, y, z] = rippled([0.0, 1.0, 5.0], [0.0; 3], 2.0, 4.0, 0.0);
+        assert_eq!((x, y), (0.0, 1.0));
+        assert!((z - 7.0).abs() < 1e-5);
+    }
+}
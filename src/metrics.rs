@@ -0,0 +1,220 @@
+/*!
+  Publishing the game's frame rate, frame time and race timers over HTTP, in the Prometheus text
+  format, so that long practice sessions can be graphed (e.g. to spot timer drift).
+
+  A [`MetricsExporter`](struct.MetricsExporter.html) polls the game in the background (like the
+  watchers in [`watch`](../watch/index.html)), and serves the latest values at `/metrics`.
+  */
+
+extern crate nix;
+
+use std::{io::{Read,Write},net::{TcpStream,ToSocketAddrs},sync::{Arc,Mutex},time::Duration};
+use nix::unistd::Pid;
+use crate::{watch::{self,Poller},races::{Race,RaceTimer,DEFAULT_RACES},utils::get_delta_t,memory::read_prims,relocation,constants::{OFF_FRAMERATE,OFF_FRAME_LENGTH},error::Result};
+
+// How long a client gets to send its request, or to take the response, before it's dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+// The longest request which is read.
+const MAX_REQUEST: usize = 16 * 1024;
+
+/// The values published by a [`MetricsExporter`](struct.MetricsExporter.html).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// The game's frame rate, if it could be read.
+    pub framerate: Option<f32>,
+    /// The length of a frame (the inverse of the frame rate), if it could be read.
+    pub frame_length: Option<f32>,
+    /// The last frame time (in milliseconds), if it could be read.
+    pub delta_t: Option<u32>,
+    /// The race countdown (in seconds), if a race is running.
+    pub race_countdown: Option<i32>,
+    /// The race timer (in milliseconds), if a race is running.
+    pub race_timer: Option<f32>,
+    /// The race being run, if any.
+    pub race: Option<Race>,
+    /// The number of times the game has been polled.
+    pub polls: u64,
+    /// The number of reads which have failed.
+    pub read_errors: u64,
+}
+
+impl MetricsSnapshot {
+    /// Write the values in the Prometheus text format. Values which aren't known are left out.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, labels: &str, value: Option<f64>| {
+            if let Some(value) = value {
+                text.push_str(&format!("# HELP walkoflife_{} {}\n# TYPE walkoflife_{} {}\nwalkoflife_{}{} {}\n",
+                                       name, help, name, kind, name, labels, value));
+            }
+        };
+        let race_labels = match self.race {
+            Some(race) => format!("{{level=\"{}\"}}", race.level_name()),
+            None => String::new(),
+        };
+
+        metric("framerate", "gauge", "The game's frame rate.", "", self.framerate.map(f64::from));
+        metric("frame_length", "gauge", "The length of a frame, as used by the game.", "", self.frame_length.map(f64::from));
+        metric("delta_t_milliseconds", "gauge", "The time taken by the last frame.", "", self.delta_t.map(f64::from));
+        metric("race_countdown_seconds", "gauge", "The race countdown, as displayed on the screen.", &race_labels, self.race_countdown.map(f64::from));
+        metric("race_timer_milliseconds", "gauge", "The game's internal race timer.", &race_labels, self.race_timer.map(f64::from));
+        metric("polls_total", "counter", "The number of times the game has been polled.", "", Some(self.polls as f64));
+        metric("read_errors_total", "counter", "The number of reads of the game's memory which have failed.", "", Some(self.read_errors as f64));
+        text
+    }
+}
+
+/// Polls the game in the background, and serves the values over HTTP at `/metrics`.
+///
+/// ## Details:
+/// * Failed reads (e.g. while a level is loading, or after the game has quit) don't stop the
+///   exporter - they're counted in `walkoflife_read_errors_total` instead, and the value is left
+///   out until it can be read again.
+pub struct MetricsExporter {
+    snapshot: Arc<Mutex<MetricsSnapshot>>,
+    poller: Poller,
+    server: Poller,
+}
+
+impl MetricsExporter {
+    /// Start polling the Rayman 2 process given by `r2pid` every `interval`, and serving the
+    /// values on `address` (e.g. `"127.0.0.1:9898"`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `MetricsExporter`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the address can't be listened on.
+    pub fn spawn<A: ToSocketAddrs>(r2pid: Pid, address: A, interval: Duration) -> Result<MetricsExporter> {
+        let snapshot = Arc::new(Mutex::new(MetricsSnapshot::default()));
+        let poll_snapshot = snapshot.clone();
+        let mut timers = DEFAULT_RACES.map(|race| RaceTimer::attach(r2pid, race));
+        let poller = Poller::spawn(interval, move || {
+            let mut errors = 0;
+            let framerate = counted(&mut errors, read_prims::<f32>(r2pid, relocation::address(r2pid, OFF_FRAMERATE), 1).map(|vec| vec[0]));
+            let frame_length = counted(&mut errors, read_prims::<f32>(r2pid, relocation::address(r2pid, OFF_FRAME_LENGTH), 1).map(|vec| vec[0]));
+            let delta_t = counted(&mut errors, get_delta_t(r2pid));
+            let mut race = None;
+            for timer in timers.iter_mut() {
+                if let Some(state) = counted(&mut errors, timer.read()).flatten() {
+                    race = Some((timer.race(), state));
+                }
+            }
+
+            let mut snapshot = poll_snapshot.lock().unwrap();
+            snapshot.framerate = framerate;
+            snapshot.frame_length = frame_length;
+            snapshot.delta_t = delta_t;
+            snapshot.race = race.map(|(race, _)| race);
+            snapshot.race_countdown = race.map(|(_, state)| state.countdown);
+            snapshot.race_timer = race.map(|(_, state)| state.time);
+            snapshot.polls += 1;
+            snapshot.read_errors += errors;
+            Ok(())
+        });
+
+        let serve_snapshot = snapshot.clone();
+        let server = watch::serve(address, "metrics requests", move |stream| {
+            // A client going wrong shouldn't stop the server.
+            let _ = respond(stream, &serve_snapshot);
+        })?;
+
+        Ok(MetricsExporter{snapshot, poller, server})
+    }
+
+    /// Get the values as they were last polled.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Stop polling and serving, and wait for the background threads to finish.
+    ///
+    /// ## Returns:
+    /// * If the threads stopped because they were asked to, returns `Ok(())`.
+    /// * If one had already stopped because of an error, returns an `Err` variant with a text
+    ///   description of what went wrong.
+    pub fn stop(mut self) -> Result<()> {
+        let poller = self.poller.stop();
+        self.server.stop()?;
+        poller
+    }
+}
+
+// Get the value of `result`, counting it in `errors` if it failed.
+fn counted<T>(errors: &mut u64, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(_) => {
+            *errors += 1;
+            None
+        },
+    }
+}
+
+// Answer one HTTP request on `stream`, once all of its headers have arrived.
+fn respond(mut stream: TcpStream, snapshot: &Mutex<MetricsSnapshot>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") {
+        match stream.read(&mut buf)? {
+            0 => {return Err(std::io::ErrorKind::UnexpectedEof.into());},
+            len => request.extend_from_slice(&buf[..len]),
+        }
+        if request.len() > MAX_REQUEST {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", snapshot.lock().unwrap().render()),
+        _ => ("404 Not Found", "Not found - try /metrics\n".to_string()),
+    };
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, body.len(), body)
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    #[test]
+    fn can_render_metrics() {
+        let snapshot = MetricsSnapshot{
+            framerate: Some(60.0),
+            delta_t: Some(17),
+            race_countdown: Some(87),
+            race: Some(Race::WalkOfLife),
+            polls: 3,
+            read_errors: 1,
+            ..MetricsSnapshot::default()
+        };
+        let text = snapshot.render();
+        assert!(text.contains("# TYPE walkoflife_framerate gauge\nwalkoflife_framerate 60\n"));
+        assert!(text.contains("walkoflife_race_countdown_seconds{level=\"ly_10\"} 87\n"));
+        assert!(text.contains("# TYPE walkoflife_read_errors_total counter\nwalkoflife_read_errors_total 1\n"));
+        assert!(!text.contains("frame_length"));
+        assert!(!text.contains("race_timer"));
+    }
+
+    #[test]
+    fn can_answer_requests() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let snapshot = Mutex::new(MetricsSnapshot{polls: 2, ..MetricsSnapshot::default()});
+
+        // The request arrives in two pieces, as it can from a real client.
+        client.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+        let server = std::thread::spawn(move || respond(listener.accept().unwrap().0, &snapshot));
+        std::thread::sleep(Duration::from_millis(20));
+        client.write_all(b"Host: localhost\r\n\r\n").unwrap();
+        server.join().unwrap().unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\nwalkoflife_polls_total 2\n"));
+    }
+}
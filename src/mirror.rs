@@ -0,0 +1,173 @@
+/*!
+  A read-only mirror of selected live values in a POSIX shared-memory segment, so that other
+  programs (audio cue generators, overlays written in other languages, etc.) can follow them with
+  no sockets and no system calls into the game.
+
+  ## Layout:
+  All numbers are little-endian. The segment starts with a 16-byte header:
+
+  | Offset | Type  | Contents                                                       |
+  |--------|-------|----------------------------------------------------------------|
+  | 0x0    | `u32` | Magic number: `0x4C4F5757` (the bytes `WWOL`)                  |
+  | 0x4    | `u32` | Layout version (currently `1`)                                 |
+  | 0x8    | `u32` | Sequence number (see below)                                    |
+  | 0xC    | `u32` | Number of values                                               |
+
+  This is followed by one 32-byte entry per value:
+
+  | Offset | Type       | Contents                                                  |
+  |--------|------------|-----------------------------------------------------------|
+  | 0x0    | `[u8; 24]` | Name of the value, UTF-8, padded with NULs                |
+  | 0x18   | `f64`      | The value                                                 |
+
+  The sequence number is odd while the values are being updated. To get a consistent set of
+  values, read the sequence number, wait until it's even, read the values, and then check that
+  the sequence number hasn't changed (if it has, try again).
+  */
+
+extern crate nix;
+
+use std::{ptr,sync::atomic::{AtomicU32,Ordering,fence}};
+use nix::{fcntl::OFlag,sys::{mman::{shm_open,shm_unlink,mmap,munmap,ProtFlags,MapFlags},stat::Mode},unistd::{ftruncate,close}};
+use crate::error::{Error,Result};
+
+/// The magic number at the start of the segment.
+pub const MIRROR_MAGIC: u32 = 0x4C4F5757;
+/// The version of the layout described in the [module documentation](index.html).
+pub const MIRROR_VERSION: u32 = 1;
+/// The size of the header at the start of the segment.
+pub const MIRROR_HEADER_SIZE: usize = 0x10;
+/// The size of each entry after the header.
+pub const MIRROR_ENTRY_SIZE: usize = 0x20;
+/// The longest name a value can have, in bytes.
+pub const MIRROR_NAME_LEN: usize = 24;
+
+/// A shared-memory segment mirroring some values, laid out as described in the
+/// [module documentation](index.html).
+///
+/// ## Details:
+/// * The segment is removed when the `Mirror` is dropped.
+pub struct Mirror {
+    name: String,
+    base: *mut u8,
+    len: usize,
+    num_values: usize,
+}
+
+// The segment is only written through `&mut self`.
+unsafe impl Send for Mirror {}
+
+impl Mirror {
+    /// Make a shared-memory segment called `name` (e.g. `/walkoflife`), mirroring values with
+    /// the given `names`, which all start at `0.0`.
+    ///
+    /// ## Requirements:
+    /// * `name` should start with a `/`, and contain no other `/`s. On Linux, the segment appears
+    ///   in `/dev/shm`.
+    /// * No name in `names` can be longer than `MIRROR_NAME_LEN` bytes.
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `Mirror`. Other users can only read the segment.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn create(name: &str, names: &[&str]) -> Result<Mirror> {
+        if let Some(long_name) = names.iter().find(|value_name| value_name.len() > MIRROR_NAME_LEN) {
+            return Err(format!("Value name {} is too long to mirror", long_name).into());
+        }
+        let len = MIRROR_HEADER_SIZE + names.len() * MIRROR_ENTRY_SIZE;
+
+        let fd = match shm_open(name, OFlag::O_CREAT | OFlag::O_RDWR | OFlag::O_TRUNC, Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IROTH) {
+            Ok(fd) => fd,
+            Err(err) => {return Err(Error::from(err).context(format!("Couldn't create shared memory {}", name)));},
+        };
+        let mapped = ftruncate(fd, len as _)
+            .and_then(|_| unsafe{mmap(ptr::null_mut(), len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, fd, 0)});
+        // The mapping stays valid without the file descriptor.
+        let _ = close(fd);
+        let base = match mapped {
+            Ok(base) => base.cast::<u8>(),
+            Err(err) => {
+                let _ = shm_unlink(name);
+                return Err(Error::from(err).context(format!("Couldn't map shared memory {}", name)));
+            },
+        };
+
+        let mirror = Mirror{name: name.to_string(), base, len, num_values: names.len()};
+        unsafe {
+            // ftruncate() fills the segment with zeroes, so the names are already padded.
+            mirror.write_u32(0x0, MIRROR_MAGIC);
+            mirror.write_u32(0x4, MIRROR_VERSION);
+            mirror.write_u32(0xC, names.len() as u32);
+            for (i, value_name) in names.iter().enumerate() {
+                ptr::copy_nonoverlapping(value_name.as_ptr(), mirror.base.add(MIRROR_HEADER_SIZE + i * MIRROR_ENTRY_SIZE), value_name.len());
+            }
+        }
+        Ok(mirror)
+    }
+
+    unsafe fn write_u32(&self, offset: usize, value: u32) {
+        ptr::write_volatile(self.base.add(offset).cast::<[u8; 4]>(), value.to_le_bytes());
+    }
+
+    fn sequence(&self) -> &AtomicU32 {
+        unsafe{&*self.base.add(0x8).cast::<AtomicU32>()}
+    }
+
+    /// Get the name of the shared-memory segment.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Update all the values, in the same order as the names given to
+    /// [`create()`](#method.create).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the wrong number of values is given.
+    pub fn update(&mut self, values: &[f64]) -> Result<()> {
+        if values.len() != self.num_values {
+            return Err(format!("Mirror has {} values, but {} were given", self.num_values, values.len()).into());
+        }
+
+        let sequence = self.sequence().load(Ordering::Relaxed);
+        self.sequence().store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (i, value) in values.iter().enumerate() {
+            let offset = MIRROR_HEADER_SIZE + i * MIRROR_ENTRY_SIZE + MIRROR_NAME_LEN;
+            unsafe{ptr::write_volatile(self.base.add(offset).cast::<[u8; 8]>(), value.to_le_bytes())};
+        }
+        self.sequence().store(sequence.wrapping_add(2), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl Drop for Mirror {
+    fn drop(&mut self) {
+        let _ = unsafe{munmap(self.base.cast(), self.len)};
+        let _ = shm_unlink(self.name.as_str());
+    }
+}
+
+#[cfg(test)]
+mod mirror_tests {
+    use super::*;
+    use std::{convert::TryInto,fs};
+
+    #[test]
+    fn can_mirror_values() {
+        let name = format!("/walkoflife-test-{}", std::process::id());
+        let mut mirror = Mirror::create(&name, &["countdown", "timer"]).unwrap();
+        mirror.update(&[30.0, 1.5]).unwrap();
+        assert!(mirror.update(&[1.0]).is_err());
+
+        let bytes = fs::read(format!("/dev/shm{}", name)).unwrap();
+        assert_eq!(bytes.len(), MIRROR_HEADER_SIZE + 2 * MIRROR_ENTRY_SIZE);
+        assert_eq!(&bytes[0..4], b"WWOL");
+        assert_eq!(u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]), 2);
+        assert_eq!(&bytes[0x30..0x35], b"timer");
+        assert_eq!(f64::from_le_bytes(bytes[0x48..0x50].try_into().unwrap()), 1.5);
+
+        drop(mirror);
+        assert!(fs::metadata(format!("/dev/shm{}", name)).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+/*!
+  Modifiers for how Rayman moves, for challenge modes like "ice physics" (turning very slowly) or
+  "tank controls", or just running faster or slower than usual.
+
+  Each modifier has a guard which puts things back as they were when it's dropped:
+  [`override_turn_factor()`](fn.override_turn_factor.html) gives a
+  [`RestoreGuard`](struct.RestoreGuard.html), and a
+  [`SpeedMultiplier`](struct.SpeedMultiplier.html) stops scaling when it's dropped.
+  */
+
+extern crate nix;
+
+use std::{thread,sync::{Arc,atomic::{AtomicBool,AtomicU32,Ordering}}};
+use nix::unistd::Pid;
+use crate::{remote::RemotePtr,relocation::address,timing::FrameTicker,utils::get_main_character,constants::*,error::Result};
+
+// The turn factor, in the process given by `r2pid`.
+fn turn_factor(r2pid: Pid) -> RemotePtr<f32> {
+    RemotePtr::new(address(r2pid, OFF_TURN_FACTOR))
+}
+
+// Any step longer than this in one frame is taken to be a teleport (e.g. respawning at a
+// checkpoint, or a new level), and isn't scaled.
+const MAX_STEP: f32 = 5.0;
+
+/// Writes a value back into Rayman 2's memory as it was, when it's dropped.
+pub struct RestoreGuard<T: Copy> {
+    r2pid: Pid,
+    ptr: RemotePtr<T>,
+    original: T,
+    restored: bool,
+}
+
+impl<T: Copy> RestoreGuard<T> {
+    /// Get the value which will be written back.
+    pub fn original(&self) -> T {
+        self.original
+    }
+
+    /// Write the value back now, rather than when the guard is dropped.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory write fails.
+    pub fn restore(mut self) -> Result<()> {
+        self.restored = true;
+        match self.ptr.write(self.r2pid, self.original) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.context("Couldn't restore original value")),
+        }
+    }
+}
+
+impl<T: Copy> Drop for RestoreGuard<T> {
+    fn drop(&mut self) {
+        if !self.restored {
+            let _ = self.ptr.write(self.r2pid, self.original);
+        }
+    }
+}
+
+/// Read how quickly Rayman turns, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the turn factor.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_turn_factor(r2pid: Pid) -> Result<f32> {
+    match turn_factor(r2pid).read(r2pid) {
+        Ok(factor) => Ok(factor),
+        Err(err) => Err(err.context("Couldn't read turn factor")),
+    }
+}
+
+/// Set how quickly Rayman turns to `factor`, in the Rayman 2 process given by `r2pid`. Lower is
+/// slower, which makes him feel like he's on ice.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory write fails.
+pub fn set_turn_factor(r2pid: Pid, factor: f32) -> Result<()> {
+    match turn_factor(r2pid).write(r2pid, factor) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't set turn factor")),
+    }
+}
+
+/// Set how quickly Rayman turns to `factor` until the guard returned is dropped - see
+/// [`set_turn_factor()`](fn.set_turn_factor.html).
+///
+/// ## Returns:
+/// * On success, returns a guard which puts the turn factor back as it was.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read or write fails.
+pub fn override_turn_factor(r2pid: Pid, factor: f32) -> Result<RestoreGuard<f32>> {
+    let original = get_turn_factor(r2pid)?;
+    set_turn_factor(r2pid, factor)?;
+    Ok(RestoreGuard{r2pid, ptr: turn_factor(r2pid), original, restored: false})
+}
+
+/// A background thread which scales how far Rayman moves across the ground each frame, to make
+/// him faster or slower than usual.
+///
+/// ## Details:
+/// * Each frame, the step he's taken along x and y since the last frame is scaled by the factor,
+///   and his position is moved to match. Falling and jumping (along z) aren't changed.
+/// * Speeding him up can push him through thin walls, since the game's collisions only see where
+///   he ends up.
+/// * Reads and writes which fail (e.g. while a level is loading) are ignored, and tried again next
+///   frame.
+/// * Scaling stops when the `SpeedMultiplier` is dropped.
+pub struct SpeedMultiplier {
+    // The factor, as the bits of an f32, so it can be changed while running.
+    factor: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl SpeedMultiplier {
+    /// Start scaling Rayman's movement by `factor`, in the Rayman 2 process given by `r2pid`.
+    pub fn start(r2pid: Pid, factor: f32) -> SpeedMultiplier {
+        let factor = Arc::new(AtomicU32::new(factor.to_bits()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_factor, thread_stop) = (factor.clone(), stop.clone());
+
+        let handle = thread::spawn(move || {
+            let mut frames = FrameTicker::new(r2pid);
+            // Where Rayman was left last frame, and which super-object he was.
+            let mut last: Option<(usize, [f32; 3])> = None;
+            while !thread_stop.load(Ordering::Relaxed) {
+                // Running out of time only means that the game isn't drawing frames right now.
+                if frames.tick().is_err() {
+                    continue;
+                }
+                let position_ptr = match get_main_character(r2pid).and_then(|main_character| main_character.ptr.position_ptr(r2pid)) {
+                    Ok(ptr) => ptr,
+                    Err(_) => {
+                        last = None;
+                        continue;
+                    },
+                };
+                let position = match position_ptr.read(r2pid) {
+                    Ok(position) => position,
+                    Err(_) => continue,
+                };
+                let scaled = match last {
+                    Some((addr, last)) if addr == position_ptr.addr() => {
+                        scale_step(last, position, f32::from_bits(thread_factor.load(Ordering::Relaxed)))
+                    },
+                    _ => position,
+                };
+                if scaled != position && position_ptr.write(r2pid, scaled).is_err() {
+                    last = None;
+                    continue;
+                }
+                last = Some((position_ptr.addr(), scaled));
+            }
+        });
+
+        SpeedMultiplier{factor, stop, handle: Some(handle)}
+    }
+
+    /// Get the factor Rayman's movement is being scaled by.
+    pub fn factor(&self) -> f32 {
+        f32::from_bits(self.factor.load(Ordering::Relaxed))
+    }
+
+    /// Change the factor Rayman's movement is scaled by, from the next frame.
+    pub fn set_factor(&self, factor: f32) {
+        self.factor.store(factor.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Stop scaling, and wait for the thread to finish.
+    pub fn stop(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for SpeedMultiplier {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+// Scale the step across the ground from `last` to `now` by `factor`, leaving teleports alone.
+fn scale_step(last: [f32; 3], now: [f32; 3], factor: f32) -> [f32; 3] {
+    let (dx, dy) = (now[0] - last[0], now[1] - last[1]);
+    if dx.hypot(dy) > MAX_STEP {
+        return now;
+    }
+    [last[0] + dx * factor, last[1] + dy * factor, now[2]]
+}
+
+#[cfg(test)]
+mod movement_tests {
+    use super::*;
+
+    #[test]
+    fn can_scale_steps() {
+        assert_eq!(scale_step([0.0, 0.0, 0.0], [1.0, -0.5, 2.0], 2.0), [2.0, -1.0, 2.0]);
+        assert_eq!(scale_step([10.0, 10.0, 0.0], [11.0, 10.0, -1.0], 0.5), [10.5, 10.0, -1.0]);
+        // Respawning isn't a step.
+        assert_eq!(scale_step([0.0, 0.0, 0.0], [100.0, 0.0, 0.0], 2.0), [100.0, 0.0, 0.0]);
+    }
+}
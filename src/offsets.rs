@@ -0,0 +1,345 @@
+/*!
+  The addresses in [`constants`](../constants/index.html) are only right for one build of the PC
+  version of Rayman 2. An [`OffsetProfile`](struct.OffsetProfile.html) collects them together, so
+  that other builds can be described (e.g. in a file - see
+  [`OffsetProfile::parse()`](struct.OffsetProfile.html#method.parse)), and recognised by the
+  checksum of their executable, rather than having the library read garbage from them.
+
+  A profile is used for a process with
+  [`Rayman2Process::set_offsets()`](../process/struct.Rayman2Process.html#method.set_offsets) (or
+  [`relocation::set_offsets()`](../relocation/fn.set_offsets.html)), after which everything this
+  crate reads from it is at the profile's addresses.
+  */
+
+extern crate nix;
+
+use std::{convert::TryFrom,fs,path::Path};
+use nix::unistd::Pid;
+use crate::{constants::*,config::{self,TomlLine,Value},discovery::module_path,error::{Error,Result}};
+
+/// The addresses of the things this crate reads, for a particular build of the game.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OffsetProfile {
+    /// The name of the build, for messages.
+    pub name: String,
+    /// The CRC-32 of the build's executable, if it's known - see
+    /// [`executable_checksum()`](fn.executable_checksum.html).
+    pub checksum: Option<u32>,
+    /// As `OFF_ENGINE_STRUCTURE`.
+    pub engine_structure: usize,
+    /// As `OFF_FRAME_COUNTER`.
+    pub frame_counter: usize,
+    /// As `OFF_DELTA_T`.
+    pub delta_t: usize,
+    /// As `OFF_FRAME_LENGTH`.
+    pub frame_length: usize,
+    /// As `OFF_FRAMERATE`.
+    pub framerate: usize,
+    /// As `OFF_HEALTH_PTR_1`.
+    pub health_ptr: usize,
+    /// As `OFF_MAIN_CHAR`.
+    pub main_char: usize,
+    /// As `OFF_INPUT_X`.
+    pub input_x: usize,
+    /// As `OFF_INPUT_Y`.
+    pub input_y: usize,
+    /// As `OFF_OBJECT_TYPES`.
+    pub object_types: usize,
+    /// As `OFF_DYNAM_WORLD`.
+    pub dynam_world: usize,
+    /// As `OFF_INACTIVE_DYNAM_WORLD`.
+    pub inactive_dynam_world: usize,
+    /// As `OFF_FATHER_SECTOR`.
+    pub father_sector: usize,
+    /// As `OFF_DNM_P_ST_DYNAMICS_CAMERA_MECHANICS`.
+    pub camera_mechanics: usize,
+    /// As `OFF_FORCE_CAMERA_POS`.
+    pub force_camera_pos: usize,
+    /// As `OFF_FORCE_CAMERA_TGT`.
+    pub force_camera_tgt: usize,
+    /// As `OFF_VOID_PTR`.
+    pub void_ptr: usize,
+    /// As `OFF_BRIGHTNESS_PTR`.
+    pub brightness_ptr: usize,
+    /// As `OFF_CAMERA_ARRAY_PTR`.
+    pub camera_array_ptr: usize,
+    /// As `OFF_TURN_FACTOR`.
+    pub turn_factor: usize,
+}
+
+impl OffsetProfile {
+    /// The build the `OFF_*` constants were found in (the GOG version, as used by Robin's FunBox).
+    ///
+    /// ## Details:
+    /// * Its checksum isn't recorded yet, so it can't be recognised by
+    ///   [`identify()`](fn.identify.html) - it's used by default instead, and the build counts as
+    ///   unknown.
+    pub fn classic() -> OffsetProfile {
+        OffsetProfile{
+            name: "GOG".to_string(),
+            checksum: None,
+            engine_structure: OFF_ENGINE_STRUCTURE,
+            frame_counter: OFF_FRAME_COUNTER,
+            delta_t: OFF_DELTA_T,
+            frame_length: OFF_FRAME_LENGTH,
+            framerate: OFF_FRAMERATE,
+            health_ptr: OFF_HEALTH_PTR_1,
+            main_char: OFF_MAIN_CHAR,
+            input_x: OFF_INPUT_X,
+            input_y: OFF_INPUT_Y,
+            object_types: OFF_OBJECT_TYPES,
+            dynam_world: OFF_DYNAM_WORLD,
+            inactive_dynam_world: OFF_INACTIVE_DYNAM_WORLD,
+            father_sector: OFF_FATHER_SECTOR,
+            camera_mechanics: OFF_DNM_P_ST_DYNAMICS_CAMERA_MECHANICS,
+            force_camera_pos: OFF_FORCE_CAMERA_POS,
+            force_camera_tgt: OFF_FORCE_CAMERA_TGT,
+            void_ptr: OFF_VOID_PTR,
+            brightness_ptr: OFF_BRIGHTNESS_PTR,
+            camera_array_ptr: OFF_CAMERA_ARRAY_PTR,
+            turn_factor: OFF_TURN_FACTOR,
+        }
+    }
+
+    // The addresses, along with the keys they have in a profile file.
+    fn addresses_mut(&mut self) -> [(&'static str, &mut usize); 20] {
+        [
+            ("engine_structure", &mut self.engine_structure),
+            ("frame_counter", &mut self.frame_counter),
+            ("delta_t", &mut self.delta_t),
+            ("frame_length", &mut self.frame_length),
+            ("framerate", &mut self.framerate),
+            ("health_ptr", &mut self.health_ptr),
+            ("main_char", &mut self.main_char),
+            ("input_x", &mut self.input_x),
+            ("input_y", &mut self.input_y),
+            ("object_types", &mut self.object_types),
+            ("dynam_world", &mut self.dynam_world),
+            ("inactive_dynam_world", &mut self.inactive_dynam_world),
+            ("father_sector", &mut self.father_sector),
+            ("camera_mechanics", &mut self.camera_mechanics),
+            ("force_camera_pos", &mut self.force_camera_pos),
+            ("force_camera_tgt", &mut self.force_camera_tgt),
+            ("void_ptr", &mut self.void_ptr),
+            ("brightness_ptr", &mut self.brightness_ptr),
+            ("camera_array_ptr", &mut self.camera_array_ptr),
+            ("turn_factor", &mut self.turn_factor),
+        ]
+    }
+
+    /// Get the address in this build of `offset` (one of the `OFF_*` constants, which are the
+    /// addresses in the [`classic()`](#method.classic) build).
+    ///
+    /// ## Details:
+    /// * Constants which aren't in the profile are left as they are, except for `OFF_LEVEL_NAME`,
+    ///   which moves with the engine structure.
+    pub fn translate(&self, offset: usize) -> usize {
+        match offset {
+            OFF_ENGINE_STRUCTURE => self.engine_structure,
+            OFF_LEVEL_NAME => self.engine_structure + (OFF_LEVEL_NAME - OFF_ENGINE_STRUCTURE),
+            OFF_FRAME_COUNTER => self.frame_counter,
+            OFF_DELTA_T => self.delta_t,
+            OFF_FRAME_LENGTH => self.frame_length,
+            OFF_FRAMERATE => self.framerate,
+            OFF_HEALTH_PTR_1 => self.health_ptr,
+            OFF_MAIN_CHAR => self.main_char,
+            OFF_INPUT_X => self.input_x,
+            OFF_INPUT_Y => self.input_y,
+            OFF_OBJECT_TYPES => self.object_types,
+            OFF_DYNAM_WORLD => self.dynam_world,
+            OFF_INACTIVE_DYNAM_WORLD => self.inactive_dynam_world,
+            OFF_FATHER_SECTOR => self.father_sector,
+            OFF_DNM_P_ST_DYNAMICS_CAMERA_MECHANICS => self.camera_mechanics,
+            OFF_FORCE_CAMERA_POS => self.force_camera_pos,
+            OFF_FORCE_CAMERA_TGT => self.force_camera_tgt,
+            OFF_VOID_PTR => self.void_ptr,
+            OFF_BRIGHTNESS_PTR => self.brightness_ptr,
+            OFF_CAMERA_ARRAY_PTR => self.camera_array_ptr,
+            OFF_TURN_FACTOR => self.turn_factor,
+            _ => offset,
+        }
+    }
+
+    /// Read a profile from `text`, which has one `key = value` per line, as in TOML, e.g.:
+    ///
+    /// ```text
+    /// # My build
+    /// name = "Rayman 2 (my build)"
+    /// checksum = 0x12345678
+    /// delta_t = 0x500434
+    /// ```
+    ///
+    /// ## Details:
+    /// * The keys are `name`, `checksum`, and the names of the fields of `OffsetProfile`.
+    /// * Values are read as in a [config file](../config/struct.Config.html#method.parse), so the
+    ///   name needs to be in double quotes, and numbers can be given in decimal or in hex
+    ///   (starting with `0x`).
+    /// * Any address which isn't given is taken from [`classic()`](#method.classic).
+    ///
+    /// ## Returns:
+    /// * On success, returns the profile.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if there's a line which doesn't make sense.
+    pub fn parse(text: &str) -> Result<OffsetProfile> {
+        let mut profile = OffsetProfile::classic();
+        profile.name = "custom".to_string();
+
+        for line in config::parse_lines(text, "offset profile") {
+            let (line_number, line) = line?;
+            let error = |message: &str| config::line_error("offset profile", line_number, message);
+            let (key, value) = match line {
+                TomlLine::KeyValue(key, value) => (key, value),
+                TomlLine::Table(table) => {return Err(error(&format!("unknown table {}", table)));},
+            };
+            match (key.as_str(), value) {
+                ("name", Value::String(name)) => {profile.name = name;},
+                ("checksum", Value::Integer(checksum)) => match u32::try_from(checksum) {
+                    Ok(checksum) => {profile.checksum = Some(checksum);},
+                    Err(_) => {return Err(error("the checksum needs to fit in 32 bits"));},
+                },
+                (key, Value::Integer(number)) => match profile.addresses_mut().iter_mut().find(|(name, _)| *name == key) {
+                    Some((_, address)) => {**address = number;},
+                    None => {return Err(error(&format!("unknown key {}", key)));},
+                },
+                (key, _) => {return Err(error(&format!("unknown key {}, or the wrong type of value for it", key)));},
+            }
+        }
+
+        Ok(profile)
+    }
+
+    /// Read a profile from the file at `path` - see [`parse()`](#method.parse).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OffsetProfile> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {return Err(Error::from(err).context(format!("Couldn't read offset profile {}", path.as_ref().display())));},
+        };
+        OffsetProfile::parse(&text)
+    }
+}
+
+impl Default for OffsetProfile {
+    fn default() -> OffsetProfile {
+        OffsetProfile::classic()
+    }
+}
+
+/// Calculate the CRC-32 (as used by zip, PNG, etc.) of `bytes`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/// Calculate the CRC-32 of the executable with one of the given `names`, as mapped into the
+/// process given by `pid` (see [`module_path()`](../discovery/fn.module_path.html)).
+///
+/// ## Returns:
+/// * On success, returns the checksum.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the executable can't be found or read.
+pub fn executable_checksum(pid: Pid, names: &[&str]) -> Result<u32> {
+    let path = module_path(pid, names)?;
+    match fs::read(&path) {
+        Ok(bytes) => Ok(crc32(&bytes)),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't read {}", path.display()))),
+    }
+}
+
+/// Get the profiles of the builds this crate knows about, which
+/// [`identify()`](fn.identify.html) recognises by their checksums.
+///
+/// ## Details:
+/// * So far this is only [`OffsetProfile::classic()`](struct.OffsetProfile.html#method.classic),
+///   whose checksum hasn't been recorded, so no build is recognised yet. Other builds (e.g. the
+///   Ubisoft Connect version, or the demo) need a profile file with their addresses and checksum.
+pub fn builtin_profiles() -> Vec<OffsetProfile> {
+    vec![OffsetProfile::classic()]
+}
+
+/// Which build of the game a process is running, as worked out by [`identify()`](fn.identify.html).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Build {
+    /// The executable's checksum matches this built-in profile.
+    Known(OffsetProfile),
+    /// The executable's checksum (given, if it could be read) doesn't match any built-in profile,
+    /// so the `OFF_*` constants may well be wrong for it.
+    Unknown(Option<u32>),
+}
+
+impl Build {
+    /// Check if the build was recognised.
+    pub fn is_known(&self) -> bool {
+        matches!(self, Build::Known(_))
+    }
+}
+
+/// Work out which build of the game is running in the process given by `pid`, by comparing the
+/// checksum of its executable (which is called one of `names`) with the
+/// [`builtin_profiles()`](fn.builtin_profiles.html).
+pub fn identify(pid: Pid, names: &[&str]) -> Build {
+    let profiles = builtin_profiles();
+    match detect_profile(pid, names, &profiles) {
+        Ok(profile) => Build::Known(profile.clone()),
+        Err(_) => Build::Unknown(executable_checksum(pid, names).ok()),
+    }
+}
+
+/// Work out which of the `profiles` (e.g. loaded with
+/// [`OffsetProfile::load()`](struct.OffsetProfile.html#method.load)) suits the build of the game
+/// running in the process given by `pid`, by the checksum of its executable (which is called one
+/// of `names`).
+///
+/// ## Returns:
+/// * On success, returns the first profile with a matching checksum.
+/// * Returns an `Err` variant describing what went wrong,
+///   if none of the profiles match (giving the checksum, so that a profile can be written for the
+///   build), or if the executable can't be read.
+pub fn detect_profile<'a>(pid: Pid, names: &[&str], profiles: &'a [OffsetProfile]) -> Result<&'a OffsetProfile> {
+    let checksum = executable_checksum(pid, names)?;
+    match profiles.iter().find(|profile| profile.checksum == Some(checksum)) {
+        Some(profile) => Ok(profile),
+        None => Err(format!("Unknown build of the game (checksum {:#010x}) - its addresses need an offset profile", checksum).into()),
+    }
+}
+
+#[cfg(test)]
+mod offsets_tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_profiles() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+
+        let profile = OffsetProfile::parse("# A test build\nname = \"Test\"\nchecksum = 0xCBF43926\n\ndelta_t = 0x600434 # moved\nmain_char = 1234\n").unwrap();
+        assert_eq!(profile.name, "Test");
+        assert_eq!(profile.checksum, Some(0xCBF43926));
+        assert_eq!(profile.delta_t, 0x600434);
+        assert_eq!(profile.main_char, 1234);
+        assert_eq!(profile.object_types, OFF_OBJECT_TYPES);
+        assert_eq!(profile.translate(OFF_DELTA_T), 0x600434);
+        assert_eq!(profile.translate(OFF_LEVEL_NAME), OFF_LEVEL_NAME);
+        assert_eq!(profile.translate(OFF_TURN_FACTOR), OFF_TURN_FACTOR);
+
+        let profile = OffsetProfile::parse("void_ptr = 0x1000\nbrightness_ptr = 0x2000\ncamera_array_ptr = 0x3000\nturn_factor = 0x4000").unwrap();
+        assert_eq!(profile.translate(OFF_VOID_PTR), 0x1000);
+        assert_eq!(profile.translate(OFF_BRIGHTNESS_PTR), 0x2000);
+        assert_eq!(profile.translate(OFF_CAMERA_ARRAY_PTR), 0x3000);
+        assert_eq!(profile.translate(OFF_TURN_FACTOR), 0x4000);
+        assert!(!identify(Pid::from_raw(i32::MAX), &["Rayman2.exe"]).is_known());
+
+        assert!(OffsetProfile::parse("nonsense = 1").is_err());
+        assert!(OffsetProfile::parse("delta_t = 0xZZ").is_err());
+        assert!(OffsetProfile::parse("delta_t").is_err());
+        assert!(OffsetProfile::parse("checksum = 0x123456789").is_err());
+        assert!(OffsetProfile::parse("name = Test").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+  The parts of the engine hierarchy which are common to all the games built on Ubisoft's
+  OpenSpace engine (Rayman 2, Rayman 3, Tonic Trouble, ...), described by an
+  [`EngineProfile`](struct.EngineProfile.html) rather than hard-coded for Rayman 2.
+
+  A profile gives the layout of the structures the hierarchy walkers go through: the super-object
+  header, the perso, its standard game info (with the family, AI Model and name indices), and
+  the mind and DSG memory. [`RAYMAN2`](constant.RAYMAN2.html), the layout the rest of this crate
+  was written against, is the only one provided, since it's the only one which has been checked
+  against a running game. Profiles for the other games can be built by hand (e.g. from
+  [Raymap](https://github.com/byvar/raymap)'s reverse engineering), along with a
+  [`GameProfile`](../profile/struct.GameProfile.html) and an
+  [`OffsetProfile`](../offsets/struct.OffsetProfile.html) for their absolute addresses.
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{memory::{read_batch,read_pointer,PointerWidth},error::Result};
+
+/// The layout of the engine's structures in one OpenSpace game, as offsets in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EngineProfile {
+    /// The name of the game, for messages.
+    pub name: &'static str,
+    /// How big the game's pointers are.
+    pub pointer_width: PointerWidth,
+    /// Where the type of a super-object is (a `u32`).
+    pub super_object_type: usize,
+    /// Where a super-object's data (e.g. its perso) is.
+    pub super_object_data: usize,
+    /// Where a super-object's first child is.
+    pub super_object_first_child: usize,
+    /// Where a super-object's next brother is.
+    pub super_object_next_brother: usize,
+    /// Where a super-object's parent is.
+    pub super_object_parent: usize,
+    /// Where a super-object's global matrix is.
+    pub super_object_matrix: usize,
+    /// The type of super-object which has a perso (i.e. an actor).
+    pub super_object_type_perso: u32,
+    /// Where a perso's standard game info is.
+    pub perso_std_game: usize,
+    /// Where a perso's dynamics are.
+    pub perso_dynamics: usize,
+    /// Where a perso's brain is (the brain points straight to the mind).
+    pub perso_brain: usize,
+    /// Where the family, AI Model and super-object name indices are in the standard game info
+    /// (one after another, a `u32` each).
+    pub std_game_indices: usize,
+    /// Where the custom bits are in the standard game info.
+    pub std_game_custom_bits: usize,
+    /// Where a mind's AI Model is.
+    pub mind_ai_model: usize,
+    /// Where a mind's DSG memory is.
+    pub mind_dsg_mem: usize,
+    /// Where the current buffer of a DSG memory is.
+    pub dsg_mem_buffer: usize,
+}
+
+/// The original PC version of Rayman 2.
+pub const RAYMAN2: EngineProfile = EngineProfile{
+    name: "Rayman 2",
+    pointer_width: PointerWidth::Bits32,
+    super_object_type: 0x0,
+    super_object_data: 0x4,
+    super_object_first_child: 0x8,
+    super_object_next_brother: 0x14,
+    super_object_parent: 0x1C,
+    super_object_matrix: 0x20,
+    super_object_type_perso: 0x2,
+    perso_std_game: 0x4,
+    perso_dynamics: 0x8,
+    perso_brain: 0xC,
+    std_game_indices: 0x0,
+    std_game_custom_bits: 0x24,
+    mind_ai_model: 0x0,
+    mind_dsg_mem: 0xC,
+    dsg_mem_buffer: 0x8,
+};
+
+/// The parts of a super-object's header needed when walking the hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SuperObjectHeader {
+    /// Pointer to the super-object itself.
+    pub ptr: usize,
+    /// The type of super-object (e.g. `super_object_type_perso` for an actor).
+    pub kind: u32,
+    /// Pointer to its data (e.g. its perso).
+    pub data: usize,
+    /// Pointer to its first child (or 0).
+    pub first_child: usize,
+    /// Pointer to its next brother (or 0).
+    pub next_brother: usize,
+    /// Pointer to its parent (or 0).
+    pub parent: usize,
+    /// Pointer to its global matrix (or 0).
+    pub matrix: usize,
+}
+
+impl EngineProfile {
+    // How many bytes of a super-object's header need to be read to get all of a
+    // `SuperObjectHeader`.
+    fn header_len(&self) -> usize {
+        [self.super_object_type + 4, self.super_object_data, self.super_object_first_child,
+         self.super_object_next_brother, self.super_object_parent, self.super_object_matrix]
+            .iter().map(|&offset| offset + self.pointer_width.size()).max().unwrap_or(0)
+    }
+
+    // Decode a pointer at `offset` in `bytes` (which must be long enough).
+    fn pointer_at(&self, bytes: &[u8], offset: usize) -> usize {
+        let mut buf = [0u8; 8];
+        buf[..self.pointer_width.size()].copy_from_slice(&bytes[offset..offset + self.pointer_width.size()]);
+        u64::from_le_bytes(buf) as usize
+    }
+
+    // Decode a super-object's header from `bytes` (which must be at least `header_len()` long).
+    fn decode_header(&self, ptr: usize, bytes: &[u8]) -> SuperObjectHeader {
+        let kind_at = self.super_object_type;
+        SuperObjectHeader{
+            ptr,
+            kind: u32::from_le_bytes([bytes[kind_at], bytes[kind_at + 1], bytes[kind_at + 2], bytes[kind_at + 3]]),
+            data: self.pointer_at(bytes, self.super_object_data),
+            first_child: self.pointer_at(bytes, self.super_object_first_child),
+            next_brother: self.pointer_at(bytes, self.super_object_next_brother),
+            parent: self.pointer_at(bytes, self.super_object_parent),
+            matrix: self.pointer_at(bytes, self.super_object_matrix),
+        }
+    }
+}
+
+/// Read the header of the super-object at `ptr`, in the process given by `pid`, which is running
+/// a game laid out as `engine` says.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the header.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn read_super_object(pid: Pid, engine: &EngineProfile, ptr: usize) -> Result<SuperObjectHeader> {
+    let len = engine.header_len();
+    match read_batch(pid, &[(ptr, len)])?.pop() {
+        Some(bytes) if bytes.len() == len => Ok(engine.decode_header(ptr, &bytes)),
+        _ => Err(format!("Couldn't read the super-object at {:#x}", ptr).into()),
+    }
+}
+
+/// Walk the brothers starting from the super-object at `first` (following each one's next
+/// brother), reading each one's header - see [`read_super_object()`](fn.read_super_object.html).
+///
+/// ## Details:
+/// * The walk stops at a null pointer, at a super-object which can't be read, or if the list loops
+///   back on itself (e.g. while a level is loading), rather than going round forever.
+pub fn read_brothers(pid: Pid, engine: &EngineProfile, first: usize) -> Result<Vec<SuperObjectHeader>> {
+    let mut ret: Vec<SuperObjectHeader> = Vec::new();
+    let mut next_brother = first;
+    while next_brother != 0 && !ret.iter().any(|brother| brother.ptr == next_brother) {
+        let header = match read_super_object(pid, engine, next_brother) {
+            Ok(header) => header,
+            Err(_) => {break;},
+        };
+        next_brother = header.next_brother;
+        ret.push(header);
+    }
+    Ok(ret)
+}
+
+/// Follow perso -> standard game info for all the `super_objects` at once, and read the family,
+/// AI Model and super-object name indices from there.
+///
+/// ## Returns:
+/// * On success, returns the indices of each super-object (family, AI Model, name), in the same
+///   order, or `None` where they can't be read (e.g. because it isn't an actor).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the process can't be read at all.
+pub fn read_object_indices(pid: Pid, engine: &EngineProfile, super_objects: &[SuperObjectHeader]) -> Result<Vec<Option<[usize; 3]>>> {
+    let width = engine.pointer_width.size();
+    let std_game_ptrs: Vec<(usize, usize)> = super_objects.iter()
+        .map(|super_object| (super_object.data + engine.perso_std_game, width))
+        .collect();
+    let regions: Vec<(usize, usize)> = read_batch(pid, &std_game_ptrs)?.iter()
+        .map(|bytes| match bytes.len() == width {
+            true => (engine.pointer_at(bytes, 0) + engine.std_game_indices, 12),
+            false => (0, 0),
+        })
+        .collect();
+
+    Ok(read_batch(pid, &regions)?.into_iter()
+        .map(|bytes| match bytes.len() {
+            12 => Some(std::array::from_fn(|i| u32::from_le_bytes([bytes[4*i], bytes[4*i + 1], bytes[4*i + 2], bytes[4*i + 3]]) as usize)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Get a pointer to the mind of the super-object at `super_object` (perso -> brain -> mind).
+///
+/// ## Returns:
+/// * On success, returns the pointer to the mind.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn read_mind(pid: Pid, engine: &EngineProfile, super_object: usize) -> Result<usize> {
+    let perso = read_pointer(pid, super_object + engine.super_object_data, engine.pointer_width)?;
+    let brain = read_pointer(pid, perso + engine.perso_brain, engine.pointer_width)?;
+    read_pointer(pid, brain, engine.pointer_width)
+}
+
+/// Get a pointer to the current DSG memory buffer of the super-object at `super_object`.
+///
+/// ## Returns:
+/// * On success, returns the pointer to the buffer.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn read_dsg_buffer(pid: Pid, engine: &EngineProfile, super_object: usize) -> Result<usize> {
+    let mind = read_mind(pid, engine, super_object)?;
+    let dsg_mem = read_pointer(pid, mind + engine.mind_dsg_mem, engine.pointer_width)?;
+    read_pointer(pid, dsg_mem + engine.dsg_mem_buffer, engine.pointer_width)
+}
+
+#[cfg(test)]
+mod openspace_tests {
+    use super::*;
+    use nix::unistd::getpid;
+
+    #[test]
+    fn can_read_headers() {
+        // Our own pointers are 64-bit, so lay the super-objects out as a 64-bit game would: the
+        // type, data, first child, next brother, parent and global matrix, 8 bytes each.
+        const ENGINE: EngineProfile = EngineProfile{
+            pointer_width: PointerWidth::Bits64,
+            super_object_data: 0x8,
+            super_object_first_child: 0x10,
+            super_object_next_brother: 0x18,
+            super_object_parent: 0x20,
+            super_object_matrix: 0x28,
+            ..RAYMAN2
+        };
+        let mut brothers = [[0u64; 6]; 2];
+        let base = brothers.as_ptr() as usize;
+        brothers[0] = [2, 0x1000, 0, (base + 48) as u64, 0, 0x2000];
+        brothers[1] = [4, 0, 0x3000, base as u64, 0, 0];
+        std::hint::black_box(&mut brothers);
+
+        assert_eq!(RAYMAN2.header_len(), 0x24);
+        assert_eq!(ENGINE.header_len(), 48);
+        let headers = read_brothers(getpid(), &ENGINE, base).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!((headers[0].kind, headers[0].data, headers[0].matrix), (2, 0x1000, 0x2000));
+        assert_eq!((headers[1].ptr, headers[1].first_child), (base + 48, 0x3000));
+    }
+}
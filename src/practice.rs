@@ -0,0 +1,76 @@
+/*!
+  Helpers for practising the end of the Walk of Life over and over, by putting the countdown and
+  the race timer back where they should be.
+
+  These change the race itself, so they're only built with the `practice` feature (which is off
+  by default), to keep them out of anything used for timing real runs.
+  */
+
+use crate::{process::Rayman2Process,races::{timer_var_ptr,COUNTDOWN_INDEX,COUNTDOWN_OFFSET,TIMER_OBJECT,TIMER_INDEX,TIMER_OFFSET},error::Result};
+
+/// The name of the Walk of Life level.
+pub const WALK_OF_LIFE: &str = "ly_10";
+
+/// Make sure that we really are racing in the Walk of Life, so that nothing gets written anywhere
+/// else.
+fn check_racing(r2: &Rayman2Process) -> Result<()> {
+    let level_name = r2.level_name()?;
+    if level_name.to_lowercase() != WALK_OF_LIFE {
+        return Err(format!("Not in the Walk of Life (in {})", level_name).into());
+    }
+    if !r2.engine_mode()?.is_playing() {
+        return Err("The level isn't running".into());
+    }
+    Ok(())
+}
+
+/// Set the countdown (as displayed on the screen) to `seconds`, in the Rayman 2 process `r2`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+/// * The Walk of Life needs to be running.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the game isn't in the Walk of Life, or if the memory write fails.
+pub fn set_countdown(r2: &Rayman2Process, seconds: i32) -> Result<()> {
+    check_racing(r2)?;
+    let global = r2.global_object()?;
+    let countdown = timer_var_ptr(r2.pid(), global, COUNTDOWN_INDEX, COUNTDOWN_OFFSET)?;
+    match r2.write_prims(countdown, &[seconds]) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't set countdown")),
+    }
+}
+
+/// Reset the game's internal race timer to zero, in the Rayman 2 process `r2`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+/// * The Walk of Life needs to be running.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the game isn't in the Walk of Life, or if the memory write fails.
+pub fn reset_timer(r2: &Rayman2Process) -> Result<()> {
+    check_racing(r2)?;
+    let timer = match r2.active_super_objects()?.get(TIMER_OBJECT) {
+        Some(&super_object) => super_object,
+        None => {return Err(format!("No {} object in the level", TIMER_OBJECT).into());},
+    };
+    let timer = timer_var_ptr(r2.pid(), timer, TIMER_INDEX, TIMER_OFFSET)?;
+    match r2.write_prims(timer, &[0.0f32]) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't reset race timer")),
+    }
+}
+
+/// Set up another attempt at the end of the race: reset the timer, and set the countdown to
+/// `seconds` - see [`reset_timer()`](fn.reset_timer.html) and
+/// [`set_countdown()`](fn.set_countdown.html).
+pub fn restart_segment(r2: &Rayman2Process, seconds: i32) -> Result<()> {
+    reset_timer(r2)?;
+    set_countdown(r2, seconds)
+}
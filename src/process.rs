@@ -0,0 +1,546 @@
+/*!
+  A handle on a running Rayman 2 process, wrapping the functions in [`memory`](../memory/index.html)
+  and [`utils`](../utils/index.html) so that the `Pid` doesn't need to be passed around, and so
+  that things which don't change often can be cached.
+  */
+
+extern crate nix;
+
+use std::{cell::RefCell,collections::HashMap,sync::Arc};
+use nix::unistd::Pid;
+use crate::{memory::{self,PointerPath},remote::RemotePtr,profile::GameProfile,openspace,relocation::{self,AddressResolver},offsets::{self,Build,OffsetProfile},utils::{self,World},discovery,timing::{self,FrameTicker},superobject::SuperObject,aimodel::{AiModel,AiModelCache,Behaviour,BehaviourId,BehaviourNames},dsg::{DsgValue,DsgVarEntry},search::{NamePattern,SuperObjectMatch},suspend,error::{Error,Result}};
+
+/// The family, AI Model and super-object name tables, as returned by
+/// [`utils::read_object_types()`](../utils/fn.read_object_types.html).
+pub type ObjectTypes = Arc<[Vec<String>; 3]>;
+
+/// A running Rayman 2 process.
+pub struct Rayman2Process {
+    pid: Pid,
+    profile: GameProfile,
+    // The object-type tables, along with the name of the level they were read in.
+    object_types: RefCell<Option<(String, ObjectTypes)>>,
+    // The AI Models read so far, for the same level as the object-type tables.
+    ai_models: RefCell<AiModelCache>,
+    // The name of the global object, for the same level as the object-type tables.
+    global_name: RefCell<Option<String>>,
+}
+
+impl Rayman2Process {
+    /// Find the currently-running `Rayman2.exe` process, as with
+    /// [`find_attach_rayman2()`](../utils/fn.find_attach_rayman2.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns a `Rayman2Process` for the running game.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn attach() -> Result<Rayman2Process> {
+        Ok(Rayman2Process::from_pid(utils::find_attach_rayman2()?).located())
+    }
+
+    /// Find a running process with one of the given `names`, as with
+    /// [`find_attach_by_name()`](../discovery/fn.find_attach_by_name.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns a `Rayman2Process` for the running game.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn attach_by_name(names: &[&str]) -> Result<Rayman2Process> {
+        let pid = discovery::find_attach_by_name(names)?;
+        relocation::detect(pid, names);
+        Ok(Rayman2Process::from_pid(pid))
+    }
+
+    /// Find a running process for the game described by `profile` (by its executable names, as
+    /// with [`find_attach_by_name()`](../discovery/fn.find_attach_by_name.html)), and read its
+    /// memory as the profile says.
+    ///
+    /// ## Returns:
+    /// * On success, returns a `Rayman2Process` for the running game.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn attach_profile(profile: GameProfile) -> Result<Rayman2Process> {
+        Ok(Rayman2Process::from_pid(discovery::find_attach_by_name(profile.executable_names)?).with_profile(profile).located())
+    }
+
+    /// Use the process given by `pid`, after checking that it exists, as with
+    /// [`attach_pid()`](../discovery/fn.attach_pid.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns a `Rayman2Process` for the process.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn attach_pid(pid: Pid) -> Result<Rayman2Process> {
+        Ok(Rayman2Process::from_pid(discovery::attach_pid(pid)?).located())
+    }
+
+    /// Use the process given by `pid`, which should be Rayman 2.
+    pub fn from_pid(pid: Pid) -> Rayman2Process {
+        Rayman2Process{pid, profile: GameProfile::default(), object_types: RefCell::new(None), ai_models: RefCell::new(AiModelCache::new()), global_name: RefCell::new(None)}
+    }
+
+    /// Read the process's memory as described by `profile`, instead of as Rayman 2.
+    pub fn with_profile(mut self, profile: GameProfile) -> Rayman2Process {
+        self.profile = profile;
+        self
+    }
+
+    // Look up where the executable is loaded, for everything which reads the process from now on.
+    fn located(self) -> Rayman2Process {
+        relocation::detect(self.pid, self.profile.executable_names);
+        self
+    }
+
+    /// Get the PID of the process.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Get the profile of the game the process is running.
+    pub fn profile(&self) -> GameProfile {
+        self.profile
+    }
+
+    /// Get what the `OFF_*` constants are moved by to match where the game's executable is loaded
+    /// - see [`relocation::resolver()`](../relocation/fn.resolver.html).
+    ///
+    /// ## Details:
+    /// * This is looked up when attaching, since it can't change while the process is running.
+    pub fn address_resolver(&self) -> AddressResolver {
+        relocation::resolver(self.pid)
+    }
+
+    /// Get the real address of `offset` (one of the `OFF_*` constants) in this process, for the
+    /// build it's running (see [`offsets()`](#method.offsets)) and where it's loaded (see
+    /// [`address_resolver()`](#method.address_resolver)).
+    pub fn relocate(&self, offset: usize) -> usize {
+        relocation::address(self.pid, offset)
+    }
+
+    /// Get the build of the game that's running, which is worked out when attaching - see
+    /// [`relocation::detect()`](../relocation/fn.detect.html). If it's
+    /// [`Build::Unknown`](../offsets/enum.Build.html#variant.Unknown), the addresses read may
+    /// well be wrong unless a profile is given with [`set_offsets()`](#method.set_offsets).
+    pub fn build(&self) -> Build {
+        relocation::build(self.pid).unwrap_or_else(|| offsets::identify(self.pid, self.profile.executable_names))
+    }
+
+    /// Work out which of the `profiles` suits the build of the game that's running - see
+    /// [`offsets::detect_profile()`](../offsets/fn.detect_profile.html). The result can be given
+    /// to [`set_offsets()`](#method.set_offsets).
+    pub fn detect_offsets<'a>(&self, profiles: &'a [OffsetProfile]) -> Result<&'a OffsetProfile> {
+        offsets::detect_profile(self.pid, self.profile.executable_names, profiles)
+    }
+
+    /// Read the game at the addresses in `profile` instead of the `OFF_*` constants from now on,
+    /// for a build of the game they don't suit - see
+    /// [`relocation::set_offsets()`](../relocation/fn.set_offsets.html).
+    pub fn set_offsets(&self, profile: OffsetProfile) {
+        relocation::set_offsets(self.pid, profile);
+    }
+
+    /// Get the addresses the game is read at: the profile given to
+    /// [`set_offsets()`](#method.set_offsets), or otherwise
+    /// [`OffsetProfile::classic()`](../offsets/struct.OffsetProfile.html#method.classic).
+    pub fn offsets(&self) -> OffsetProfile {
+        relocation::offsets(self.pid).unwrap_or_default()
+    }
+
+    /// Check if the process is still running (e.g. it hasn't quit or crashed) - see
+    /// [`discovery::is_alive()`](../discovery/fn.is_alive.html).
+    pub fn is_alive(&self) -> bool {
+        discovery::is_alive(self.pid)
+    }
+
+    /// Check that the process is still running, so that a tool can tell the game has gone away
+    /// before trying to read anything.
+    ///
+    /// ## Returns:
+    /// * If it's still running, returns `Ok(())`.
+    /// * Otherwise, returns [`Error::ProcessGone`](../error/enum.Error.html#variant.ProcessGone).
+    pub fn check_alive(&self) -> Result<()> {
+        match self.is_alive() {
+            true => Ok(()),
+            false => Err(Error::ProcessGone(self.pid)),
+        }
+    }
+
+    /// Stop the game altogether, e.g. to make several writes at once - see
+    /// [`suspend::suspend()`](../suspend/fn.suspend.html). This isn't the game's own pause menu.
+    pub fn pause(&self) -> Result<()> {
+        suspend::suspend(self.pid)
+    }
+
+    /// Start the game again after [`pause()`](#method.pause) - see
+    /// [`suspend::resume()`](../suspend/fn.resume.html).
+    pub fn resume(&self) -> Result<()> {
+        suspend::resume(self.pid)
+    }
+
+    /// Check if the game has been stopped altogether (e.g. with [`pause()`](#method.pause)) - see
+    /// [`suspend::is_suspended()`](../suspend/fn.is_suspended.html).
+    pub fn is_suspended(&self) -> bool {
+        suspend::is_suspended(self.pid)
+    }
+
+    /// Stop the game, call `f`, and start the game again, so that everything `f` writes happens
+    /// between the same two frames.
+    ///
+    /// ## Returns:
+    /// * On success, returns what `f` returned.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `f` fails, or if the game can't be stopped or started again.
+    ///
+    /// ## Details:
+    /// * The game is started again even if `f` fails or panics, unless it was already stopped
+    ///   beforehand - see [`SuspendGuard`](../suspend/struct.SuspendGuard.html).
+    pub fn with_paused<F, R>(&self, f: F) -> Result<R>
+        where F: FnOnce(&Rayman2Process) -> Result<R>
+    {
+        let guard = suspend::SuspendGuard::new(self.pid)?;
+        let result = f(self);
+        guard.release()?;
+        result
+    }
+
+    /// Find the game again (by the executable names in its profile), e.g. after it has been
+    /// restarted, and use the new process from now on. Everything cached about the old process is
+    /// forgotten, apart from its offset profile (see [`set_offsets()`](#method.set_offsets)).
+    ///
+    /// ## Returns:
+    /// * On success, returns the PID of the new process (which may be the same as the old one, if
+    ///   it's still running).
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the game isn't running.
+    pub fn reattach(&mut self) -> Result<Pid> {
+        let pid = discovery::find_attach_by_name(self.profile.executable_names)?;
+        if pid != self.pid {
+            let offsets = relocation::offsets(self.pid);
+            self.pid = pid;
+            relocation::detect(pid, self.profile.executable_names);
+            if let Some(profile) = offsets {
+                relocation::set_offsets(pid, profile);
+            }
+            self.invalidate_cache();
+        }
+        Ok(pid)
+    }
+
+    /// Forget everything cached about the process, so that it gets read again next time.
+    pub fn invalidate_cache(&self) {
+        self.object_types.replace(None);
+        self.ai_models.borrow_mut().clear();
+        self.global_name.replace(None);
+    }
+
+    /// Read `n` primitives starting from `offset` - see
+    /// [`memory::read_prims()`](../memory/fn.read_prims.html).
+    pub fn read_prims<T:Copy>(&self, offset: usize, n: usize) -> Result<Vec<T>> {
+        memory::read_prims(self.pid, offset, n)
+    }
+
+    /// Read primitives starting from `offset` into `buf` - see
+    /// [`memory::read_into()`](../memory/fn.read_into.html).
+    pub fn read_into<T:Copy>(&self, offset: usize, buf: &mut [T]) -> Result<usize> {
+        memory::read_into(self.pid, offset, buf)
+    }
+
+    /// Read several `(address, length)` regions at once - see
+    /// [`memory::read_batch()`](../memory/fn.read_batch.html).
+    pub fn read_batch(&self, regions: &[(usize, usize)]) -> Result<Vec<Vec<u8>>> {
+        memory::read_batch(self.pid, regions)
+    }
+
+    /// Read a string of at most `n` bytes starting from `offset` - see
+    /// [`memory::read_string()`](../memory/fn.read_string.html).
+    pub fn read_string(&self, offset: usize, n: usize) -> Result<String> {
+        memory::read_string(self.pid, offset, n)
+    }
+
+    /// Follow a pointer path, with pointers as wide as the profile says - see
+    /// [`memory::get_pointer_path_with()`](../memory/fn.get_pointer_path_with.html).
+    pub fn get_pointer_path(&self, base: usize, offsets: Option<&Vec<usize>>) -> Result<usize> {
+        memory::get_pointer_path_with(self.pid, self.profile.pointer_width, base, offsets)
+    }
+
+    /// Follow `path` from `start` (or from its base, if `start` is 0), with pointers as wide as
+    /// the profile says - see
+    /// [`PointerPath::resolve_from_with()`](../memory/struct.PointerPath.html#method.resolve_from_with).
+    pub fn resolve_path(&self, path: &PointerPath, start: usize) -> Result<usize> {
+        match start {
+            0 => path.resolve_with(self.pid, self.profile.pointer_width),
+            start => path.resolve_from_with(self.pid, self.profile.pointer_width, start),
+        }
+    }
+
+    /// Walk the brothers starting from the super-object at `first`, with the engine layout the
+    /// profile says - see [`openspace::read_brothers()`](../openspace/fn.read_brothers.html).
+    pub fn brothers(&self, first: usize) -> Result<Vec<openspace::SuperObjectHeader>> {
+        openspace::read_brothers(self.pid, self.profile.engine, first)
+    }
+
+    /// Make a pointer to `addr` in the game, as wide as the profile says - see
+    /// [`RemotePtr`](../remote/struct.RemotePtr.html).
+    pub fn remote_ptr<T>(&self, addr: usize) -> RemotePtr<T> {
+        RemotePtr::with_width(addr, self.profile.pointer_width)
+    }
+
+    /// Write `data` starting at `offset` - see
+    /// [`memory::write_prims()`](../memory/fn.write_prims.html).
+    pub fn write_prims<T:Copy>(&self, offset: usize, data: &[T]) -> Result<()> {
+        memory::write_prims(self.pid, offset, data)
+    }
+
+    /// Scan all the game's readable memory for values matching `predicate` - see
+    /// [`memory::scan()`](../memory/fn.scan.html).
+    pub fn scan<T:Copy + PartialOrd>(&self, predicate: memory::ScanPredicate<T>) -> Result<memory::Scan<T>> {
+        memory::scan(self.pid, predicate)
+    }
+
+    /// Start a background thread to keep values frozen in the game - see
+    /// [`memory::Freezer`](../memory/struct.Freezer.html).
+    pub fn freezer(&self) -> memory::Freezer {
+        memory::Freezer::new(self.pid)
+    }
+
+    /// Start a set of writes to the game which can be undone - see
+    /// [`memory::Transaction`](../memory/struct.Transaction.html).
+    pub fn transaction(&self) -> memory::Transaction {
+        memory::Transaction::new(self.pid)
+    }
+
+    /// Read the name of the level currently open - see
+    /// [`utils::get_current_level_name()`](../utils/fn.get_current_level_name.html).
+    pub fn level_name(&self) -> Result<String> {
+        utils::get_current_level_name(self.pid)
+    }
+
+    /// Read the mode the engine is in - see
+    /// [`utils::get_engine_mode()`](../utils/fn.get_engine_mode.html).
+    pub fn engine_mode(&self) -> Result<utils::EngineMode> {
+        utils::get_engine_mode(self.pid)
+    }
+
+    /// Check if the game is paused - see [`utils::is_paused()`](../utils/fn.is_paused.html).
+    pub fn is_paused(&self) -> Result<bool> {
+        utils::is_paused(self.pid)
+    }
+
+    /// Get the length of the last frame, in milliseconds - see
+    /// [`utils::get_delta_t()`](../utils/fn.get_delta_t.html).
+    pub fn delta_t(&self) -> Result<u32> {
+        utils::get_delta_t(self.pid)
+    }
+
+    /// Get the number of frames the engine has run - see
+    /// [`timing::get_frame_counter()`](../timing/fn.get_frame_counter.html).
+    pub fn frame_counter(&self) -> Result<u32> {
+        timing::get_frame_counter(self.pid)
+    }
+
+    /// Block until the engine starts a new frame - see
+    /// [`timing::wait_for_next_frame()`](../timing/fn.wait_for_next_frame.html).
+    pub fn wait_for_next_frame(&self) -> Result<u32> {
+        timing::wait_for_next_frame(self.pid)
+    }
+
+    /// Get an iterator which yields once per frame - see
+    /// [`timing::FrameTicker`](../timing/struct.FrameTicker.html).
+    pub fn frames(&self) -> FrameTicker {
+        FrameTicker::new(self.pid)
+    }
+
+    /// Get the main character (i.e. Rayman) - see
+    /// [`utils::get_main_character()`](../utils/fn.get_main_character.html).
+    pub fn main_character(&self) -> Result<SuperObject> {
+        utils::get_main_character(self.pid)
+    }
+
+    /// Read Rayman's current health - see [`utils::get_health()`](../utils/fn.get_health.html).
+    pub fn health(&self) -> Result<u8> {
+        utils::get_health(self.pid)
+    }
+
+    /// Read the most health Rayman can currently have - see
+    /// [`utils::get_max_health()`](../utils/fn.get_max_health.html).
+    pub fn max_health(&self) -> Result<u8> {
+        utils::get_max_health(self.pid)
+    }
+
+    /// Set Rayman's health - see [`utils::set_health()`](../utils/fn.set_health.html).
+    pub fn set_health(&self, value: u8) -> Result<()> {
+        utils::set_health(self.pid, value)
+    }
+
+    /// Get the object types in the engine hierarchy - see
+    /// [`utils::read_object_types()`](../utils/fn.read_object_types.html).
+    ///
+    /// ## Details:
+    /// * The tables are only read again if the level has changed since last time (or if
+    ///   [`invalidate_cache()`](#method.invalidate_cache) has been called), so this is cheap to
+    ///   call often.
+    pub fn object_types(&self) -> Result<ObjectTypes> {
+        let level_name = self.level_name()?;
+        if let Some((cached_level, types)) = &*self.object_types.borrow() {
+            if *cached_level == level_name {
+                return Ok(types.clone());
+            }
+        }
+
+        let types = Arc::new(utils::read_object_types(self.pid)?);
+        self.object_types.replace(Some((level_name, types.clone())));
+        self.ai_models.borrow_mut().clear();
+        self.global_name.replace(None);
+        Ok(types)
+    }
+
+    /// Go through the family names one at a time, without reading the whole table - see
+    /// [`utils::object_names()`](../utils/fn.object_names.html).
+    pub fn families(&self) -> Result<utils::ObjectNames> {
+        utils::object_names(self.pid, utils::ObjectTable::Families)
+    }
+
+    /// Go through the AI Model names one at a time, without reading the whole table - see
+    /// [`utils::object_names()`](../utils/fn.object_names.html).
+    pub fn ai_models(&self) -> Result<utils::ObjectNames> {
+        utils::object_names(self.pid, utils::ObjectTable::AiModels)
+    }
+
+    /// Go through the super-object names one at a time, without reading the whole table - see
+    /// [`utils::object_names()`](../utils/fn.object_names.html).
+    pub fn super_object_names(&self) -> Result<utils::ObjectNames> {
+        utils::object_names(self.pid, utils::ObjectTable::SuperObjectNames)
+    }
+
+    /// Get the names and pointers of the active super-objects in the dynamic world - see
+    /// [`utils::get_active_super_object_names()`](../utils/fn.get_active_super_object_names.html).
+    pub fn active_super_objects(&self) -> Result<HashMap<String,usize>> {
+        let object_types = self.object_types()?;
+        utils::get_active_super_object_names(self.pid, &object_types[2], World::ActiveDynamic, 0)
+    }
+
+    /// Get the names and pointers of the super-objects at the top of the given `world` (e.g. the
+    /// objects which aren't active right now, or the sectors) - see
+    /// [`utils::get_active_super_object_names()`](../utils/fn.get_active_super_object_names.html).
+    pub fn world_super_objects(&self, world: World) -> Result<HashMap<String,usize>> {
+        let object_types = self.object_types()?;
+        utils::get_active_super_object_names(self.pid, &object_types[2], world, 0)
+    }
+
+    /// Walk the engine hierarchy from `root` (or the root of `world` if that is 0) - see
+    /// [`utils::walk_hierarchy()`](../utils/fn.walk_hierarchy.html).
+    pub fn walk_hierarchy(&self, world: World, root: usize) -> Result<utils::HierarchyNode> {
+        let object_types = self.object_types()?;
+        utils::walk_hierarchy(self.pid, &object_types, world, root)
+    }
+
+    /// Find the actors whose names match `pattern` - see
+    /// [`utils::find_super_objects()`](../utils/fn.find_super_objects.html).
+    pub fn find_super_objects(&self, pattern: &NamePattern) -> Result<Vec<SuperObjectMatch>> {
+        let object_types = self.object_types()?;
+        utils::find_super_objects(self.pid, &object_types, pattern)
+    }
+
+    /// Get all the active instances of the family called `family` - see
+    /// [`utils::get_active_family_instances()`](../utils/fn.get_active_family_instances.html).
+    pub fn family_instances(&self, family: &str) -> Result<Vec<utils::FamilyInstance>> {
+        let object_types = self.object_types()?;
+        utils::get_active_family_instances(self.pid, &object_types, family, World::ActiveDynamic, 0)
+    }
+
+    /// Get a pointer to the global script object of the current level - see
+    /// [`utils::find_global_object()`](../utils/fn.find_global_object.html).
+    ///
+    /// ## Details:
+    /// * The object's name is remembered for each level, so the search is only done once.
+    pub fn global_object(&self) -> Result<usize> {
+        let object_types = self.object_types()?;
+        if let Some(name) = &*self.global_name.borrow() {
+            if let Some(&super_object) = self.active_super_objects()?.get(name) {
+                return Ok(super_object);
+            }
+        }
+
+        let (name, super_object) = utils::find_global_object(self.pid, &object_types)?;
+        self.global_name.replace(Some(name));
+        Ok(super_object)
+    }
+
+    /// Get the AI Model names and pointers of the active super-objects in the dynamic world - see
+    /// [`utils::get_active_super_object_ai_model_names()`](../utils/fn.get_active_super_object_ai_model_names.html).
+    pub fn active_super_object_ai_models(&self) -> Result<HashMap<String,Vec<usize>>> {
+        let object_types = self.object_types()?;
+        utils::get_active_super_object_ai_model_names(self.pid, &object_types[1], World::ActiveDynamic, 0)
+    }
+
+    /// Get the AI Model used by `super_object` - see
+    /// [`AiModel::read()`](../aimodel/struct.AiModel.html#method.read).
+    ///
+    /// ## Details:
+    /// * Each AI Model is only read once per level, so this is cheap to call often.
+    pub fn ai_model(&self, super_object: usize) -> Result<Arc<AiModel>> {
+        let object_types = self.object_types()?;
+        self.ai_models.borrow_mut().get(self.pid, super_object, &object_types[1])
+    }
+
+    /// Get the normal behaviours (comports) of the AI Model used by `super_object`, named from
+    /// `names` (if given) - see
+    /// [`AiModel::normal_behaviour_list()`](../aimodel/struct.AiModel.html#method.normal_behaviour_list).
+    pub fn normal_behaviours(&self, super_object: usize, names: Option<&BehaviourNames>) -> Result<Vec<Behaviour>> {
+        Ok(self.ai_model(super_object)?.normal_behaviour_list(names))
+    }
+
+    /// Get the name of the active behaviour (comport) on `super_object` - see
+    /// [`utils::get_active_normal_behaviour_name()`](../utils/fn.get_active_normal_behaviour_name.html).
+    ///
+    /// ## Details:
+    /// * This uses the cached AI Model (see [`ai_model()`](#method.ai_model)).
+    pub fn active_normal_behaviour_name(&self, super_object: usize, names: Option<&BehaviourNames>) -> Result<String> {
+        let model = self.ai_model(super_object)?;
+        let active = utils::get_active_normal_behaviour(self.pid, super_object)?;
+        match model.active_normal_behaviour(active, names) {
+            Some(behaviour) => Ok(behaviour.name),
+            None => Err(format!("Active behaviour {:#x} isn't one of {}'s", active, model.name).into()),
+        }
+    }
+
+    /// Switch the active behaviour (comport) on `super_object` - see
+    /// [`utils::set_active_normal_behaviour()`](../utils/fn.set_active_normal_behaviour.html).
+    ///
+    /// ## Details:
+    /// * This uses the cached AI Model (see [`ai_model()`](#method.ai_model)).
+    pub fn set_active_normal_behaviour(&self, super_object: usize, behaviour: BehaviourId, names: Option<&BehaviourNames>) -> Result<Behaviour> {
+        let model = self.ai_model(super_object)?;
+        let behaviour = match model.find_normal_behaviour(behaviour, names) {
+            Some(found) => found,
+            None => {return Err(format!("{} has no behaviour {:?}", model.name, behaviour).into());},
+        };
+        self.super_object(super_object).set_active_normal_behaviour(behaviour.ptr)?;
+        Ok(behaviour)
+    }
+
+    /// Get a typed handle on the super-object at `ptr`.
+    pub fn super_object(&self, ptr: usize) -> SuperObject {
+        SuperObject::new(self.pid, ptr)
+    }
+
+    /// Get a pointer to a DSG variable on `super_object` - see
+    /// [`utils::get_dsg_var_ptr()`](../utils/fn.get_dsg_var_ptr.html).
+    pub fn dsg_var_ptr(&self, super_object: usize, offset: usize) -> Result<usize> {
+        utils::get_dsg_var_ptr(self.pid, super_object, offset)
+    }
+
+    /// Read a DSG variable on `super_object` by its index - see
+    /// [`utils::get_dsg_var_by_index()`](../utils/fn.get_dsg_var_by_index.html).
+    pub fn dsg_var_by_index(&self, super_object: usize, index: usize) -> Result<DsgValue> {
+        utils::get_dsg_var_by_index(self.pid, super_object, index)
+    }
+
+    /// Write a DSG variable on `super_object` by its index - see
+    /// [`utils::set_dsg_var_by_index()`](../utils/fn.set_dsg_var_by_index.html).
+    pub fn set_dsg_var_by_index(&self, super_object: usize, index: usize, value: &DsgValue) -> Result<()> {
+        utils::set_dsg_var_by_index(self.pid, super_object, index, value)
+    }
+
+    /// Read every DSG variable on `super_object` - see
+    /// [`utils::dump_dsg_mem()`](../utils/fn.dump_dsg_mem.html).
+    pub fn dump_dsg_mem(&self, super_object: usize) -> Result<Vec<DsgVarEntry>> {
+        utils::dump_dsg_mem(self.pid, super_object)
+    }
+}
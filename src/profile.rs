@@ -0,0 +1,36 @@
+/*!
+  Profiles of the games this crate can attach to, describing what differs from one build of the
+  engine to the next (e.g. the size of pointers), so that the same traversal code can be reused.
+  The profile is chosen when attaching (see
+  [`Rayman2Process::attach_profile()`](../process/struct.Rayman2Process.html#method.attach_profile)).
+  */
+
+use crate::{memory::PointerWidth,discovery::RAYMAN2_NAMES,openspace::{self,EngineProfile}};
+
+/// What needs to be known about a game before attaching to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameProfile {
+    /// The name of the game, for messages.
+    pub name: &'static str,
+    /// The names the game's executable is usually run as.
+    pub executable_names: &'static [&'static str],
+    /// How big the game's pointers are.
+    pub pointer_width: PointerWidth,
+    /// How the game's engine structures are laid out - see
+    /// [`openspace`](../openspace/index.html).
+    pub engine: &'static EngineProfile,
+}
+
+/// The original PC version of Rayman 2.
+pub const RAYMAN2: GameProfile = GameProfile{
+    name: "Rayman 2",
+    executable_names: RAYMAN2_NAMES,
+    pointer_width: PointerWidth::Bits32,
+    engine: &openspace::RAYMAN2,
+};
+
+impl Default for GameProfile {
+    fn default() -> GameProfile {
+        RAYMAN2
+    }
+}
@@ -0,0 +1,243 @@
+/*!
+  A small line editor for interactive prompts (like `walkoflife repl`), with history and tab
+  completion, so that names from the live hierarchy don't have to be typed out in full.
+
+  [`LineEditor::read_line()`](struct.LineEditor.html#method.read_line) puts the terminal into raw
+  mode while the line is being typed, and handles the keys itself: backspace, tab, up and down (to
+  go through the history), Ctrl-C (to clear the line), Ctrl-D (to quit, on an empty line) and
+  Enter. Only ASCII text can be typed, which is all the game's names use. If standard input isn't
+  a terminal (e.g. a script is piped in), lines are read as they are, with no editing.
+  */
+
+extern crate nix;
+
+use std::io::{self,BufRead,Read,Write};
+use nix::{sys::termios::{tcgetattr,tcsetattr,SetArg,LocalFlags,InputFlags,Termios},unistd::isatty};
+use crate::error::{Error,Result};
+
+const STDIN: i32 = 0;
+// The most lines kept in the history.
+const MAX_HISTORY: usize = 1000;
+
+/// Reads lines from the terminal, keeping a history of them.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+}
+
+/// What completing a word gives, from [`complete()`](fn.complete.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// Nothing matches the word.
+    NoMatch,
+    /// The word can be replaced by this (a whole candidate if only one matched, or the longest
+    /// prefix all the matches have otherwise).
+    Replace{word: String, whole: bool},
+    /// Several candidates match, and the word is already as long as the prefix they all have.
+    Ambiguous(Vec<String>),
+}
+
+impl LineEditor {
+    /// Make a line editor with an empty history.
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    /// Get the lines read so far, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Read a line, showing `prompt` first. When tab is pressed, `candidates` is given the line up
+    /// to the word being typed, and gives the words which could go there - see
+    /// [`complete()`](fn.complete.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns the line (without the newline), or `None` at the end of the input
+    ///   (or if Ctrl-D is pressed on an empty line).
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the terminal can't be read or set up.
+    pub fn read_line<F>(&mut self, prompt: &str, mut candidates: F) -> Result<Option<String>>
+        where F: FnMut(&str) -> Vec<String>
+    {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let line = match isatty(STDIN) {
+            Ok(true) => self.edit_line(prompt, &mut candidates)?,
+            _ => {
+                let mut line = String::new();
+                match io::stdin().lock().read_line(&mut line)? {
+                    0 => None,
+                    _ => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+                }
+            },
+        };
+
+        if let Some(line) = &line {
+            if !line.trim().is_empty() && self.history.last() != Some(line) {
+                if self.history.len() == MAX_HISTORY {
+                    self.history.remove(0);
+                }
+                self.history.push(line.clone());
+            }
+        }
+        Ok(line)
+    }
+
+    // Read a line from the terminal in raw mode, handling the keys ourselves.
+    fn edit_line(&self, prompt: &str, candidates: &mut dyn FnMut(&str) -> Vec<String>) -> Result<Option<String>> {
+        let _raw = RawMode::new()?;
+        let mut stdin = io::stdin().lock();
+        let mut stdout = io::stdout();
+        let mut line = String::new();
+        // Where we are in the history (`history.len()` for the line being typed), and what was
+        // being typed before going back through it.
+        let mut history_idx = self.history.len();
+        let mut typed = String::new();
+
+        loop {
+            let mut byte = [0u8];
+            if stdin.read(&mut byte)? == 0 {
+                writeln!(stdout)?;
+                return Ok(match line.is_empty() {
+                    true => None,
+                    false => Some(line),
+                });
+            }
+            match byte[0] {
+                b'\r' | b'\n' => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Some(line));
+                },
+                // Ctrl-C
+                0x03 => {
+                    write!(stdout, "^C\r\n")?;
+                    line.clear();
+                    history_idx = self.history.len();
+                },
+                // Ctrl-D
+                0x04 if line.is_empty() => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                },
+                // Backspace (or Ctrl-H)
+                0x7F | 0x08 => {line.pop();},
+                b'\t' => {
+                    let word_start = line.rfind(' ').map(|idx| idx + 1).unwrap_or(0);
+                    match complete(&line[word_start..], &candidates(&line[..word_start])) {
+                        Completion::NoMatch => {},
+                        Completion::Replace{word, whole} => {
+                            line.truncate(word_start);
+                            line.push_str(&word);
+                            if whole {
+                                line.push(' ');
+                            }
+                        },
+                        Completion::Ambiguous(matches) => {
+                            write!(stdout, "\r\n{}\r\n", matches.join("  "))?;
+                        },
+                    }
+                },
+                // An escape sequence: only the up and down arrows (`ESC [ A` and `ESC [ B`) do
+                // anything.
+                0x1B => {
+                    let mut seq = [0u8; 2];
+                    stdin.read_exact(&mut seq)?;
+                    match seq {
+                        [b'[', b'A'] if history_idx > 0 => {
+                            if history_idx == self.history.len() {
+                                typed = line.clone();
+                            }
+                            history_idx -= 1;
+                            line = self.history[history_idx].clone();
+                        },
+                        [b'[', b'B'] if history_idx < self.history.len() => {
+                            history_idx += 1;
+                            line = match self.history.get(history_idx) {
+                                Some(older) => older.clone(),
+                                None => typed.clone(),
+                            };
+                        },
+                        _ => {},
+                    }
+                },
+                byte @ 0x20..=0x7E => {line.push(byte as char);},
+                _ => {},
+            }
+            // Redraw the whole line, which is simplest.
+            write!(stdout, "\r\x1B[K{}{}", prompt, line)?;
+            stdout.flush()?;
+        }
+    }
+}
+
+/// Complete `word` from `candidates` (which needn't be sorted or unique).
+///
+/// ## Details:
+/// * Only candidates which start with `word` are matched (with the same case).
+pub fn complete(word: &str, candidates: &[String]) -> Completion {
+    let mut matches: Vec<String> = candidates.iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .cloned()
+        .collect();
+    matches.sort();
+    matches.dedup();
+
+    match matches.len() {
+        0 => Completion::NoMatch,
+        1 => Completion::Replace{word: matches.remove(0), whole: true},
+        _ => {
+            let first = &matches[0];
+            let common = matches.iter().skip(1).fold(first.len(), |len, other| {
+                first.bytes().zip(other.bytes()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            match common > word.len() {
+                true => Completion::Replace{word: first[..common].to_string(), whole: false},
+                false => Completion::Ambiguous(matches),
+            }
+        },
+    }
+}
+
+// Keeps the terminal in raw mode (no echo, no line buffering, no signals from Ctrl-C) until
+// dropped, when it's put back as it was.
+struct RawMode(Termios);
+
+impl RawMode {
+    fn new() -> Result<RawMode> {
+        let original = match tcgetattr(STDIN) {
+            Ok(termios) => termios,
+            Err(err) => {return Err(Error::from(err).context("Couldn't get the terminal's settings"));},
+        };
+        let mut raw = original.clone();
+        raw.local_flags.remove(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG | LocalFlags::IEXTEN);
+        raw.input_flags.remove(InputFlags::ICRNL | InputFlags::IXON);
+        if let Err(err) = tcsetattr(STDIN, SetArg::TCSANOW, &raw) {
+            return Err(Error::from(err).context("Couldn't put the terminal into raw mode"));
+        }
+        Ok(RawMode(original))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = tcsetattr(STDIN, SetArg::TCSANOW, &self.0);
+    }
+}
+
+#[cfg(test)]
+mod prompt_tests {
+    use super::*;
+
+    #[test]
+    fn can_complete_words() {
+        let names: Vec<String> = ["YLT_RaymanModel", "YLT_RaymanModel", "GRP_TimerCourse_I3", "GRP_TimerCourse_I30", "GRP_Ly_I1"]
+            .iter().map(|name| name.to_string()).collect();
+        assert_eq!(complete("YLT", &names), Completion::Replace{word: "YLT_RaymanModel".to_string(), whole: true});
+        assert_eq!(complete("GRP_T", &names), Completion::Replace{word: "GRP_TimerCourse_I3".to_string(), whole: false});
+        assert_eq!(complete("GRP_TimerCourse_I3", &names),
+                   Completion::Ambiguous(vec!["GRP_TimerCourse_I3".to_string(), "GRP_TimerCourse_I30".to_string()]));
+        assert_eq!(complete("grp", &names), Completion::NoMatch);
+        assert_eq!(complete("", &[]), Completion::NoMatch);
+    }
+}
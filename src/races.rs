@@ -0,0 +1,331 @@
+/*!
+  The timers in the Ly races (the Walk of Life and the Walk of Power): the countdown shown on the
+  screen, and the game's internal tracker of how long you've been racing.
+
+  The timers are where they've always been read from in the Walk of Life: 84 bytes into the DSG
+  memory of the global object and of `GRP_TimerCourse_I3`. The Walk of Power is assumed to keep
+  them in the same places, but that hasn't been checked against the game, so it isn't watched by
+  default (e.g. by [`Config::default()`](../config/struct.Config.html#method.default)).
+
+  The game doesn't say anywhere we know of that the end of a race has been reached, but the timer
+  shows it: when Rayman crosses the finish, the timer stops while the level keeps running and the
+  countdown keeps whatever time was left (as in `walkoflife_test.txt`, where it stops at 107915
+  with 25 seconds to go). Leaving the level, reloading it, or running out of time doesn't do that,
+  so only a stopped timer counts as a finish - see
+  [`RaceEvent::Finished`](enum.RaceEvent.html#variant.Finished).
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{memory::Snapshot,dsg::read_dsg_mem_layout,timing::get_frame_counter,utils::{read_object_types,get_active_super_object_names,find_global_object,get_current_level_name,get_engine_mode,is_paused,World},error::Result};
+
+/// The name of the object which keeps the race timer.
+pub const TIMER_OBJECT: &str = "GRP_TimerCourse_I3";
+/// The index of the countdown (Int_30) in the global object's DSG variables.
+pub const COUNTDOWN_INDEX: usize = 30;
+/// The offset of the countdown in the global object's DSG memory, in bytes.
+pub const COUNTDOWN_OFFSET: usize = 84;
+/// The index of the race timer (Float_16) in the timer object's DSG variables.
+pub const TIMER_INDEX: usize = 16;
+/// The offset of the race timer in the timer object's DSG memory, in bytes.
+pub const TIMER_OFFSET: usize = 84;
+
+/// How many frames the race timer has to stay still for the race to count as finished (while the
+/// game isn't paused, and there's time left on the countdown).
+pub const FINISH_FRAMES: u32 = 30;
+
+/// The races watched unless told otherwise: only the ones whose timers have been checked against
+/// the game.
+pub const DEFAULT_RACES: [Race; 1] = [Race::WalkOfLife];
+
+/// One of the Ly races.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Race {
+    /// The Walk of Life (`ly_10`).
+    WalkOfLife,
+    /// The Walk of Power (`ly_20`).
+    WalkOfPower,
+}
+
+impl Race {
+    /// Get the name of the race's level.
+    pub fn level_name(self) -> &'static str {
+        match self {
+            Race::WalkOfLife => "ly_10",
+            Race::WalkOfPower => "ly_20",
+        }
+    }
+
+    /// Get the race run in the level called `level_name` (in any case), if it's one of them.
+    pub fn from_level(level_name: &str) -> Option<Race> {
+        [Race::WalkOfLife, Race::WalkOfPower].iter()
+            .find(|race| race.level_name().eq_ignore_ascii_case(level_name))
+            .copied()
+    }
+}
+
+/// The countdown and timer, read at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaceState {
+    /// The number of seconds before the level times out, as displayed on the screen.
+    pub countdown: i32,
+    /// How long the race has been going, as tracked by the game (in milliseconds).
+    pub time: f32,
+}
+
+/// Something that happened to the race, as found by [`RaceTimer::poll()`](struct.RaceTimer.html#method.poll).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaceEvent {
+    /// The timers were read for the first time since the race's level was (re)loaded.
+    Started(RaceState),
+    /// The countdown changed from `old` to `new`.
+    CountdownChanged { old: i32, new: i32 },
+    /// The timer changed from `old` to `new`.
+    TimeChanged { old: f32, new: f32 },
+    /// The end of the race has been reached, with the timers as given: the timer has stopped for
+    /// [`FINISH_FRAMES`](constant.FINISH_FRAMES.html) frames, with time still left on the
+    /// countdown. This happens at most once between `Started` and `Stopped`.
+    Finished(RaceState),
+    /// The race's level has stopped running (e.g. it's been left, or is reloading), or the timer
+    /// has gone back to the start (e.g. because the race has been restarted). If this comes
+    /// before `Finished`, the run was abandoned.
+    Stopped,
+}
+
+// Works out when the end of a race has been reached, from the timers on each poll - see the
+// module documentation.
+#[derive(Debug, Default)]
+struct FinishCheck {
+    // The frame on which the timer was first seen stopped, if it's stopped.
+    stopped_since: Option<u32>,
+    finished: bool,
+}
+
+impl FinishCheck {
+    // Say if the race has just been finished, given the timers on the last poll and on this one.
+    // `frame` gives the current frame number, or `None` if the game is paused, and is only called
+    // if the timer hasn't moved.
+    fn update<F: FnOnce() -> Result<Option<u32>>>(&mut self, old: RaceState, new: RaceState, frame: F) -> Result<bool> {
+        if self.finished || new.time != old.time || new.time <= 0.0 || new.countdown <= 0 {
+            self.stopped_since = None;
+            return Ok(false);
+        }
+        let frame = match frame()? {
+            Some(frame) => frame,
+            None => {
+                // The timer doesn't move while the game is paused either.
+                self.stopped_since = None;
+                return Ok(false);
+            },
+        };
+        match self.stopped_since {
+            Some(since) if frame.wrapping_sub(since) >= FINISH_FRAMES => {
+                self.finished = true;
+                Ok(true)
+            },
+            Some(_) => Ok(false),
+            None => {
+                self.stopped_since = Some(frame);
+                Ok(false)
+            },
+        }
+    }
+}
+
+/// The timers of one of the Ly races in a Rayman 2 process.
+///
+/// ## Details:
+/// * The timers are found again whenever the race's level is (re)loaded, so a `RaceTimer` can be
+///   kept for as long as the game is running.
+pub struct RaceTimer {
+    r2pid: Pid,
+    race: Race,
+    // Pointers to the countdown and the timer, while the level is running.
+    pointers: Option<(usize, usize)>,
+    last: Option<RaceState>,
+    finish: FinishCheck,
+}
+
+impl RaceTimer {
+    /// Start following the timers of `race` in the Rayman 2 process given by `r2pid`. The race
+    /// doesn't need to be running yet.
+    pub fn attach(r2pid: Pid, race: Race) -> RaceTimer {
+        RaceTimer{r2pid, race, pointers: None, last: None, finish: FinishCheck::default()}
+    }
+
+    /// Get the race whose timers these are.
+    pub fn race(&self) -> Race {
+        self.race
+    }
+
+    // Check that the race's level is running, and find the timers if they haven't been found
+    // since it was loaded. Gives `None` if it isn't running.
+    fn pointers(&mut self) -> Result<Option<(usize, usize)>> {
+        let running = get_engine_mode(self.r2pid)?.is_playing()
+            && Race::from_level(&get_current_level_name(self.r2pid)?) == Some(self.race);
+        if !running {
+            // Everything will have moved by the time it's running again.
+            self.pointers = None;
+            return Ok(None);
+        }
+        if self.pointers.is_none() {
+            self.pointers = Some(self.find_pointers()?);
+        }
+        Ok(self.pointers)
+    }
+
+    fn find_pointers(&self) -> Result<(usize, usize)> {
+        let find = || -> Result<(usize, usize)> {
+            let object_types = read_object_types(self.r2pid)?;
+            let timer_object = match get_active_super_object_names(self.r2pid, &object_types[2], World::ActiveDynamic, 0)?.get(TIMER_OBJECT) {
+                Some(&super_object) => super_object,
+                None => {return Err(format!("No {} object in the level", TIMER_OBJECT).into());},
+            };
+            let (_, global) = find_global_object(self.r2pid, &object_types)?;
+            Ok((timer_var_ptr(self.r2pid, global, COUNTDOWN_INDEX, COUNTDOWN_OFFSET)?,
+                timer_var_ptr(self.r2pid, timer_object, TIMER_INDEX, TIMER_OFFSET)?))
+        };
+        match find() {
+            Ok(pointers) => Ok(pointers),
+            Err(err) => Err(err.context(format!("Couldn't find the timers for {}", self.race.level_name()))),
+        }
+    }
+
+    /// Read the countdown and the timer together, so that they're from the same frame.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Some` [`RaceState`](struct.RaceState.html) if the race's level is
+    ///   running, or `None` if it isn't.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn read(&mut self) -> Result<Option<RaceState>> {
+        let (countdown_ptr, timer_ptr) = match self.pointers()? {
+            Some(pointers) => pointers,
+            None => {return Ok(None);},
+        };
+        let mut snapshot = Snapshot::new().with_region(countdown_ptr, 4).with_region(timer_ptr, 4);
+        if let Err(err) = snapshot.refresh(self.r2pid) {
+            self.pointers = None;
+            return Err(err.context("Couldn't read timers"));
+        }
+        match (snapshot.read::<i32>(countdown_ptr), snapshot.read::<f32>(timer_ptr)) {
+            (Some(countdown), Some(time)) => Ok(Some(RaceState{countdown, time})),
+            _ => {
+                // The level has probably just been reloaded - try finding them again next time.
+                self.pointers = None;
+                Err("Couldn't read timers".into())
+            },
+        }
+    }
+
+    /// Read the race timer (in milliseconds) - see [`read()`](#method.read).
+    pub fn time(&mut self) -> Result<Option<f32>> {
+        Ok(self.read()?.map(|state| state.time))
+    }
+
+    /// Read the countdown (in seconds) - see [`read()`](#method.read).
+    pub fn countdown(&mut self) -> Result<Option<i32>> {
+        Ok(self.read()?.map(|state| state.countdown))
+    }
+
+    /// Check if the race is under way, i.e. its level is running and the countdown hasn't run
+    /// out.
+    pub fn is_running(&mut self) -> Result<bool> {
+        Ok(self.read()?.is_some_and(|state| state.countdown > 0))
+    }
+
+    /// Read the timers, and say what has changed since the last time this was called.
+    ///
+    /// ## Returns:
+    /// * On success, returns the [`RaceEvent`](enum.RaceEvent.html)s, in the order they should be
+    ///   handled (which is empty if nothing has changed).
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn poll(&mut self) -> Result<Vec<RaceEvent>> {
+        let state = self.read()?;
+        let mut events = Vec::new();
+        match (self.last, state) {
+            (None, Some(new)) => events.push(RaceEvent::Started(new)),
+            (Some(_), None) => events.push(RaceEvent::Stopped),
+            (Some(old), Some(new)) if new.time < old.time => {
+                // The race has started again without the level stopping.
+                events.push(RaceEvent::Stopped);
+                events.push(RaceEvent::Started(new));
+            },
+            (Some(old), Some(new)) => {
+                if old.countdown != new.countdown {
+                    events.push(RaceEvent::CountdownChanged{old: old.countdown, new: new.countdown});
+                }
+                if old.time != new.time {
+                    events.push(RaceEvent::TimeChanged{old: old.time, new: new.time});
+                }
+                let r2pid = self.r2pid;
+                let frame = || match is_paused(r2pid)? {
+                    true => Ok(None),
+                    false => Ok(Some(get_frame_counter(r2pid)?)),
+                };
+                if self.finish.update(old, new, frame)? {
+                    events.push(RaceEvent::Finished(new));
+                }
+            },
+            (None, None) => {},
+        }
+        if let Some(RaceEvent::Started(_)) = events.last() {
+            self.finish = FinishCheck::default();
+        }
+        self.last = state;
+        Ok(events)
+    }
+}
+
+// Get a pointer to the timer at `offset` in the DSG memory of `super_object`, checking that it's
+// the variable at `index` (so that anything looking it up by index finds the same one).
+pub(crate) fn timer_var_ptr(r2pid: Pid, super_object: usize, index: usize, offset: usize) -> Result<usize> {
+    let layout = read_dsg_mem_layout(r2pid, super_object)?;
+    match layout.vars.get(index) {
+        Some(info) if info.offset == offset => Ok(layout.buffer + offset),
+        Some(info) => Err(format!("DSG variable {} is at offset {}, not {} - is this a different version of the level?", index, info.offset, offset).into()),
+        None => Err(format!("No DSG variable with index {} (object has {})", index, layout.vars.len()).into()),
+    }
+}
+
+#[cfg(test)]
+mod races_tests {
+    use super::*;
+
+    #[test]
+    fn can_recognise_race_levels() {
+        assert_eq!(Race::from_level("Ly_10"), Some(Race::WalkOfLife));
+        assert_eq!(Race::from_level("ly_20"), Some(Race::WalkOfPower));
+        assert_eq!(Race::from_level("ly_30"), None);
+        assert_eq!(Race::from_level(Race::WalkOfPower.level_name()), Some(Race::WalkOfPower));
+    }
+
+    #[test]
+    fn can_tell_when_races_finish() {
+        let state = |countdown, time| RaceState{countdown, time};
+        let mut check = FinishCheck::default();
+        // Waiting at the start, with the timer on 0.
+        assert!(!check.update(state(30, 0.0), state(30, 0.0), || Ok(Some(10))).unwrap());
+        assert!(!check.update(state(30, 0.0), state(30, 0.0), || Ok(Some(100))).unwrap());
+        // Racing.
+        assert!(!check.update(state(30, 0.0), state(29, 990.0), || panic!("Timer is moving")).unwrap());
+        // Paused, for longer than it takes to finish.
+        assert!(!check.update(state(29, 990.0), state(29, 990.0), || Ok(None)).unwrap());
+        assert!(!check.update(state(29, 990.0), state(29, 990.0), || Ok(Some(200))).unwrap());
+        assert!(!check.update(state(29, 990.0), state(29, 990.0), || Ok(None)).unwrap());
+        assert!(!check.update(state(29, 990.0), state(29, 990.0), || Ok(Some(300))).unwrap());
+        // Out of time.
+        assert!(!check.update(state(1, 990.0), state(0, 990.0), || Ok(Some(400))).unwrap());
+        assert!(!check.update(state(0, 990.0), state(0, 990.0), || Ok(Some(500))).unwrap());
+        // Over the finish line.
+        assert!(!check.update(state(25, 107915.0), state(25, 107915.0), || Ok(Some(600))).unwrap());
+        assert!(!check.update(state(25, 107915.0), state(25, 107915.0), || Ok(Some(600 + FINISH_FRAMES - 1))).unwrap());
+        assert!(check.update(state(25, 107915.0), state(25, 107915.0), || Ok(Some(600 + FINISH_FRAMES))).unwrap());
+        assert!(!check.update(state(25, 107915.0), state(25, 107915.0), || Ok(Some(900))).unwrap());
+    }
+}
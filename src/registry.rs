@@ -0,0 +1,287 @@
+/*!
+  Variables with names: instead of tools knowing that the race timer is DSG variable 16 of
+  `GRP_TimerCourse_I3`, they're declared once (in code, or in a [`Config`](../config/struct.Config.html)
+  file), and looked up by name, e.g.:
+
+  ```no_run
+  # use walkoflife::{registry::Registry,config::Config,process::Rayman2Process};
+  # fn main() -> walkoflife::Result<()> {
+  let r2 = Rayman2Process::attach()?;
+  let mut registry = Registry::from_config(r2.pid(), &Config::load("walkoflife.toml")?);
+  let timer = registry.get("race_timer")?.read_f32()?;
+  # Ok(())
+  # }
+  ```
+
+  The [`Registry`](struct.Registry.html) finds where each variable is whenever a level is
+  (re)loaded, since everything moves then, and remembers it until the next time.
+  */
+
+extern crate nix;
+
+use std::collections::HashMap;
+use nix::unistd::Pid;
+use crate::{config::{Config,WatchedVariable},dsg::{DsgValue,DsgVarType,DsgMemLayout,read_dsg_mem_layout},memory::{read_prims,write_prims},
+            utils::{read_object_types,get_active_super_object_names,find_global_object,get_current_level_name,get_engine_mode,World},error::Result};
+
+/// Where a variable is in the DSG memory of its object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DsgLocation {
+    /// The variable at this index (the number in the names Raymap gives them, e.g. `30` for
+    /// `Int_30`).
+    Index(usize),
+    /// The variable this many bytes into the DSG memory buffer.
+    Offset(usize),
+}
+
+/// A variable found by a [`Registry`](struct.Registry.html), which can be read and written until
+/// the level is next (re)loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Variable {
+    r2pid: Pid,
+    addr: usize,
+    var_type: DsgVarType,
+    len: usize,
+}
+
+impl Variable {
+    /// Get the address of the variable in the game's memory.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Get the type of the variable.
+    pub fn var_type(&self) -> DsgVarType {
+        self.var_type
+    }
+
+    /// Read the variable, decoding it according to its type.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the decoded [`DsgValue`](../dsg/enum.DsgValue.html).
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn read(&self) -> Result<DsgValue> {
+        match read_prims::<u8>(self.r2pid, self.addr, self.len) {
+            Ok(bytes) => Ok(DsgValue::decode(self.var_type, &bytes)),
+            Err(err) => Err(err.context(format!("Unable to read variable at {:#x}", self.addr))),
+        }
+    }
+
+    /// Read the variable, which needs to be a `Float` - see [`read()`](#method.read).
+    pub fn read_f32(&self) -> Result<f32> {
+        match self.read()? {
+            DsgValue::Float(val) => Ok(val),
+            other => Err(format!("Variable at {:#x} is {:?}, not a float", self.addr, other).into()),
+        }
+    }
+
+    /// Read the variable, which needs to be one of the integer types (which are all widened) -
+    /// see [`read()`](#method.read).
+    pub fn read_i64(&self) -> Result<i64> {
+        match self.read()? {
+            DsgValue::Byte(val) => Ok(val as i64),
+            DsgValue::UByte(val) => Ok(val as i64),
+            DsgValue::Short(val) => Ok(val as i64),
+            DsgValue::UShort(val) => Ok(val as i64),
+            DsgValue::Int(val) => Ok(val as i64),
+            DsgValue::UInt(val) => Ok(val as i64),
+            other => Err(format!("Variable at {:#x} is {:?}, not an integer", self.addr, other).into()),
+        }
+    }
+
+    /// Read the variable, which needs to be a `Boolean` - see [`read()`](#method.read).
+    pub fn read_bool(&self) -> Result<bool> {
+        match self.read()? {
+            DsgValue::Boolean(val) => Ok(val),
+            other => Err(format!("Variable at {:#x} is {:?}, not a boolean", self.addr, other).into()),
+        }
+    }
+
+    /// Read the variable, which needs to be a `Vector` - see [`read()`](#method.read).
+    pub fn read_vector(&self) -> Result<[f32; 3]> {
+        match self.read()? {
+            DsgValue::Vector(val) => Ok(val),
+            other => Err(format!("Variable at {:#x} is {:?}, not a vector", self.addr, other).into()),
+        }
+    }
+
+    /// Write `value` over the variable.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `value` isn't the variable's type, or if the memory write fails.
+    pub fn write(&self, value: &DsgValue) -> Result<()> {
+        let bytes = value.encode();
+        if !value.is_type(self.var_type) || bytes.len() > self.len {
+            return Err(format!("Can't write {:?} to variable at {:#x} of type {:?}", value, self.addr, self.var_type).into());
+        }
+        write_prims(self.r2pid, self.addr, &bytes)
+    }
+}
+
+// What was found for each variable, by name (or why it couldn't be).
+type Resolved = HashMap<String, std::result::Result<Variable, String>>;
+
+/// Variables declared by name, which are found in the game when they're needed.
+pub struct Registry {
+    r2pid: Pid,
+    declarations: Vec<WatchedVariable>,
+    // The level the variables were last found in, and what was found there.
+    resolved: Option<(String, Resolved)>,
+}
+
+impl Registry {
+    /// Make an empty registry for the Rayman 2 process given by `r2pid`.
+    pub fn new(r2pid: Pid) -> Registry {
+        Registry{r2pid, declarations: Vec::new(), resolved: None}
+    }
+
+    /// Make a registry of the variables declared in `config`.
+    pub fn from_config(r2pid: Pid, config: &Config) -> Registry {
+        let mut registry = Registry::new(r2pid);
+        for variable in &config.variables {
+            registry.declare(variable.clone());
+        }
+        registry
+    }
+
+    /// Declare a variable, replacing any other with the same name.
+    pub fn declare(&mut self, variable: WatchedVariable) {
+        self.declarations.retain(|declared| declared.name != variable.name);
+        self.declarations.push(variable);
+        self.resolved = None;
+    }
+
+    /// Get the names of the variables, in the order they were declared.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.declarations.iter().map(|variable| variable.name.as_str())
+    }
+
+    /// Forget where the variables are, so that they're found again next time (e.g. if the
+    /// objects they belong to have been respawned without the level being reloaded).
+    pub fn invalidate(&mut self) {
+        self.resolved = None;
+    }
+
+    /// Find the variable called `name` in the game.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the [`Variable`](struct.Variable.html), to read or write.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if no variable called `name` has been declared, or if it can't be found in the current
+    ///   level (e.g. because its object isn't there).
+    ///
+    /// ## Details:
+    /// * All the variables are found again the first time this is called after a level has been
+    ///   (re)loaded, so it's cheap to call every time they're needed.
+    pub fn get(&mut self, name: &str) -> Result<Variable> {
+        if !get_engine_mode(self.r2pid)?.is_playing() {
+            // Everything will have moved by the time it's running again (even if it's the same
+            // level).
+            self.resolved = None;
+            return Err(format!("Can't find variable {} while no level is running", name).into());
+        }
+        let level = get_current_level_name(self.r2pid)?;
+        if self.resolved.as_ref().map(|(resolved_level, _)| resolved_level) != Some(&level) {
+            self.resolved = Some((level, self.resolve_all()?));
+        }
+        match self.resolved.as_ref().and_then(|(_, variables)| variables.get(name)) {
+            Some(Ok(variable)) => Ok(*variable),
+            Some(Err(err)) => Err(format!("Couldn't find variable {}: {}", name, err).into()),
+            None => Err(format!("No variable called {} has been declared", name).into()),
+        }
+    }
+
+    fn resolve_all(&self) -> Result<Resolved> {
+        let object_types = read_object_types(self.r2pid)?;
+        let objects = get_active_super_object_names(self.r2pid, &object_types[2], World::ActiveDynamic, 0)?;
+        // Only look for the global object if it's needed, since that can take a while.
+        let global = match self.declarations.iter().any(|variable| variable.object == "global" && !objects.contains_key("global")) {
+            true => find_global_object(self.r2pid, &object_types).ok().map(|(_, global)| global),
+            false => None,
+        };
+
+        Ok(self.declarations.iter()
+            .map(|variable| {
+                let super_object = match (objects.get(&variable.object), variable.object.as_str()) {
+                    (Some(&super_object), _) => Some(super_object),
+                    (None, "global") => global,
+                    (None, _) => None,
+                };
+                let found = match super_object {
+                    Some(super_object) => self.resolve(variable, super_object).map_err(|err| err.to_string()),
+                    None => Err(format!("There's no {} object in the level", variable.object)),
+                };
+                (variable.name.clone(), found)
+            })
+            .collect())
+    }
+
+    // Find `variable` in the DSG memory of `super_object`.
+    fn resolve(&self, variable: &WatchedVariable, super_object: usize) -> Result<Variable> {
+        let layout = read_dsg_mem_layout(self.r2pid, super_object)?;
+        let (offset, len, var_type) = match variable.location {
+            DsgLocation::Index(index) => at_index(&layout, index, variable.var_type)?,
+            DsgLocation::Offset(offset) => match (layout.vars.iter().position(|info| info.offset == offset), variable.var_type) {
+                (Some(index), declared) => at_index(&layout, index, declared)?,
+                // An offset which isn't the start of a variable (e.g. part of a vector), which
+                // needs a type to make sense of.
+                (None, Some(var_type)) => match var_type.size() {
+                    Some(size) if offset + size <= layout.size => (offset, size, var_type),
+                    _ => {return Err(format!("A {:?} at offset {:#x} doesn't fit in the DSG memory ({} bytes)", var_type, offset, layout.size).into());},
+                },
+                (None, None) => {return Err(format!("No DSG variable starts at offset {:#x}, so it needs a type", offset).into());},
+            },
+        };
+        Ok(Variable{r2pid: self.r2pid, addr: layout.buffer + offset, var_type, len})
+    }
+}
+
+// Get the offset, length and type of the variable at `index` in `layout`, or of the `declared`
+// type there instead.
+fn at_index(layout: &DsgMemLayout, index: usize, declared: Option<DsgVarType>) -> Result<(usize, usize, DsgVarType)> {
+    let range = match layout.var_range(index) {
+        Some(range) => range,
+        None => {return Err(format!("No DSG variable with index {} (object has {})", index, layout.vars.len()).into());},
+    };
+    let var_type = declared.unwrap_or(layout.vars[index].var_type);
+    Ok((range.start, var_type.size().unwrap_or(range.len()).min(range.len()), var_type))
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+    use nix::unistd::getpid;
+
+    #[test]
+    fn can_use_variables() {
+        let mut buffer: [u8; 16] = [0; 16];
+        buffer[4..8].copy_from_slice(&1.5f32.to_le_bytes());
+        let variable = Variable{r2pid: getpid(), addr: buffer.as_ptr() as usize + 4, var_type: DsgVarType::Float, len: 4};
+        std::hint::black_box(&mut buffer);
+
+        assert_eq!(variable.read_f32().unwrap(), 1.5);
+        assert!(variable.read_i64().is_err());
+        variable.write(&DsgValue::Float(-2.0)).unwrap();
+        assert_eq!(variable.read().unwrap(), DsgValue::Float(-2.0));
+        assert!(variable.write(&DsgValue::Int(3)).is_err());
+
+        let mut registry = Registry::new(getpid());
+        registry.declare(WatchedVariable{name: "race_timer".to_string(), object: "GRP_TimerCourse_I3".to_string(),
+                                         location: DsgLocation::Offset(84), var_type: Some(DsgVarType::Float)});
+        registry.declare(WatchedVariable{name: "race_timer".to_string(), object: "GRP_TimerCourse_I3".to_string(),
+                                         location: DsgLocation::Index(16), var_type: None});
+        assert_eq!(registry.names().collect::<Vec<&str>>(), vec!["race_timer"]);
+    }
+}
@@ -0,0 +1,216 @@
+/*!
+  Coping with `Rayman2.exe` being loaded somewhere other than its usual base address (which can
+  happen under newer versions of Wine and Proton), by moving the `OFF_*` constants to match.
+
+  Everything in this crate which reads an `OFF_*` address goes through [`address()`](fn.address.html),
+  which looks up where the game is loaded the first time each process is read, so a relocated game
+  doesn't need to be handled specially. The same goes for builds with different addresses, once
+  their [`OffsetProfile`](../offsets/struct.OffsetProfile.html) has been given with
+  [`set_offsets()`](fn.set_offsets.html), or recognised by [`detect()`](fn.detect.html).
+  */
+
+extern crate nix;
+
+use std::sync::Mutex;
+use nix::unistd::Pid;
+use crate::{constants::{IMAGE_BASE,resolved},discovery::{module_base,RAYMAN2_NAMES},offsets::{self,Build,OffsetProfile},memory::PointerPath,error::Result};
+
+// Where things are in each of the processes read so far, by PID.
+static PROCESSES: Mutex<Vec<(Pid, Addresses)>> = Mutex::new(Vec::new());
+
+// Where things are in one process.
+struct Addresses {
+    resolver: AddressResolver,
+    // The addresses for the build it's running, if they aren't the `OFF_*` constants.
+    offsets: Option<OffsetProfile>,
+    // The build it's running, if it's been looked for.
+    build: Option<Build>,
+}
+
+// Change what's known about the process given by `pid`, starting from `resolver` if nothing is
+// known yet.
+fn update<F: FnOnce(&mut Addresses)>(pid: Pid, resolver: AddressResolver, f: F) {
+    let mut processes = PROCESSES.lock().unwrap_or_else(|err| err.into_inner());
+    match processes.iter_mut().find(|(other, _)| *other == pid) {
+        Some((_, addresses)) => f(addresses),
+        None => {
+            let mut addresses = Addresses{resolver, offsets: None, build: None};
+            f(&mut addresses);
+            processes.push((pid, addresses));
+        },
+    }
+}
+
+// Look at what's known about the process given by `pid`, if anything.
+fn lookup<R, F: FnOnce(&Addresses) -> R>(pid: Pid, f: F) -> Option<R> {
+    let processes = PROCESSES.lock().unwrap_or_else(|err| err.into_inner());
+    processes.iter().find(|(other, _)| *other == pid).map(|(_, addresses)| f(addresses))
+}
+
+/// Turns the `OFF_*` addresses in [`constants`](../constants/index.html), which assume that the
+/// game is loaded at `IMAGE_BASE`, into the addresses in a particular process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddressResolver {
+    base: usize,
+}
+
+impl AddressResolver {
+    /// Make a resolver for a game loaded at `base`.
+    pub const fn new(base: usize) -> AddressResolver {
+        AddressResolver{base}
+    }
+
+    /// Make a resolver for a game loaded at the usual base, which leaves addresses as they are.
+    pub const fn classic() -> AddressResolver {
+        AddressResolver::new(IMAGE_BASE)
+    }
+
+    /// Find where the executable with one of the given `names` is loaded in the process given by
+    /// `pid` - see [`module_base()`](../discovery/fn.module_base.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns a resolver for that process.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn find(pid: Pid, names: &[&str]) -> Result<AddressResolver> {
+        match module_base(pid, names) {
+            Ok(base) => Ok(AddressResolver::new(base)),
+            Err(err) => Err(err.context("Couldn't find where the game is loaded")),
+        }
+    }
+
+    /// Get the address the game is loaded at.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Check if the game isn't loaded at the usual base, i.e. if the `OFF_*` constants can't be
+    /// used as they are.
+    pub fn is_relocated(&self) -> bool {
+        self.base != IMAGE_BASE
+    }
+
+    /// Get the real address of `offset` (one of the `OFF_*` constants).
+    pub const fn resolve(&self, offset: usize) -> usize {
+        resolved(offset, self.base)
+    }
+}
+
+impl Default for AddressResolver {
+    fn default() -> AddressResolver {
+        AddressResolver::classic()
+    }
+}
+
+/// Use `resolver` for the `OFF_*` addresses in the process given by `pid` from now on.
+pub fn set_resolver(pid: Pid, resolver: AddressResolver) {
+    update(pid, resolver, |addresses| addresses.resolver = resolver);
+}
+
+/// Find where the executable with one of the given `names` is loaded in the process given by
+/// `pid` (see [`AddressResolver::find()`](struct.AddressResolver.html#method.find)), and which
+/// build of the game it is (see [`offsets::identify()`](../offsets/fn.identify.html)), and use
+/// them for its `OFF_*` addresses from now on.
+///
+/// ## Returns:
+/// * Returns the resolver now used for the process.
+///
+/// ## Details:
+/// * If the executable can't be found (e.g. because the maps can't be read), the usual base is
+///   assumed.
+/// * If the build is one of the [built-in profiles](../offsets/fn.builtin_profiles.html), its
+///   addresses are used. Otherwise, the `OFF_*` constants are used as they are, and
+///   [`build()`](fn.build.html) says that the build is unknown, so that the caller can warn
+///   about it (or give a profile with [`set_offsets()`](fn.set_offsets.html)).
+pub fn detect(pid: Pid, names: &[&str]) -> AddressResolver {
+    let resolver = AddressResolver::find(pid, names).unwrap_or_default();
+    let build = offsets::identify(pid, names);
+    #[cfg(feature = "tracing")]
+    if let Build::Unknown(checksum) = build {
+        tracing::warn!(pid = %pid, checksum = ?checksum, "Unknown build of the game - assuming the GOG addresses");
+    }
+    update(pid, resolver, |addresses| {
+        addresses.resolver = resolver;
+        if let Build::Known(ref profile) = build {
+            addresses.offsets = Some(profile.clone());
+        }
+        addresses.build = Some(build);
+    });
+    resolver
+}
+
+/// Get the build of the game running in the process given by `pid`, as found by
+/// [`detect()`](fn.detect.html) (if it's been called for the process).
+pub fn build(pid: Pid) -> Option<Build> {
+    lookup(pid, |addresses| addresses.build.clone()).flatten()
+}
+
+/// Get the resolver for the `OFF_*` addresses in the process given by `pid`: the one it was last
+/// given (see [`set_resolver()`](fn.set_resolver.html) and [`detect()`](fn.detect.html)), or
+/// otherwise one found for `Rayman2.exe`.
+pub fn resolver(pid: Pid) -> AddressResolver {
+    match lookup(pid, |addresses| addresses.resolver) {
+        Some(resolver) => resolver,
+        None => detect(pid, RAYMAN2_NAMES),
+    }
+}
+
+/// Read the process given by `pid` with the addresses in `profile` (for a build of the game other
+/// than the one the `OFF_*` constants come from - see [`offsets`](../offsets/index.html)) from
+/// now on.
+///
+/// ## Details:
+/// * The profile's addresses are still moved to where the game is loaded, as the `OFF_*`
+///   constants would be.
+pub fn set_offsets(pid: Pid, profile: OffsetProfile) {
+    let resolver = resolver(pid);
+    update(pid, resolver, |addresses| addresses.offsets = Some(profile));
+}
+
+/// Get the offset profile given for the process given by `pid` with
+/// [`set_offsets()`](fn.set_offsets.html), if there is one.
+pub fn offsets(pid: Pid) -> Option<OffsetProfile> {
+    lookup(pid, |addresses| addresses.offsets.clone()).flatten()
+}
+
+/// Get the real address of `offset` (one of the `OFF_*` constants) in the process given by `pid`,
+/// for the build it's running (see [`set_offsets()`](fn.set_offsets.html)) and where it's loaded
+/// (see [`resolver()`](fn.resolver.html)).
+pub fn address(pid: Pid, offset: usize) -> usize {
+    let resolver = resolver(pid);
+    let offset = lookup(pid, |addresses| addresses.offsets.as_ref().map(|profile| profile.translate(offset)))
+        .flatten()
+        .unwrap_or(offset);
+    resolver.resolve(offset)
+}
+
+/// Follow `path` (one of the paths in [`constants::paths`](../constants/paths/index.html) which
+/// starts at an `OFF_*` address) in the process given by `pid`, from where its base really is -
+/// see [`address()`](fn.address.html) and
+/// [`PointerPath::resolve_from()`](../memory/struct.PointerPath.html#method.resolve_from).
+pub fn resolve_path(pid: Pid, path: &PointerPath) -> Result<usize> {
+    path.resolve_from(pid, address(pid, path.base()))
+}
+
+#[cfg(test)]
+mod relocation_tests {
+    use super::*;
+    use crate::constants::{OFF_MAIN_CHAR,OFF_LEVEL_NAME,paths};
+
+    #[test]
+    fn can_relocate_addresses() {
+        // Nothing can have this PID, so nothing is read from it.
+        let pid = Pid::from_raw(i32::MAX);
+        set_resolver(pid, AddressResolver::new(0x10000));
+        assert_eq!(address(pid, OFF_MAIN_CHAR), OFF_MAIN_CHAR - IMAGE_BASE + 0x10000);
+        set_resolver(pid, AddressResolver::classic());
+        assert_eq!(address(pid, OFF_MAIN_CHAR), OFF_MAIN_CHAR);
+        assert!(resolve_path(pid, &paths::MAIN_CHAR).is_err());
+
+        let mut profile = OffsetProfile::classic();
+        profile.engine_structure += 0x100;
+        set_offsets(pid, profile);
+        set_resolver(pid, AddressResolver::new(0x10000));
+        assert_eq!(address(pid, OFF_LEVEL_NAME), OFF_LEVEL_NAME + 0x100 - IMAGE_BASE + 0x10000);
+        assert_eq!(address(pid, OFF_MAIN_CHAR), OFF_MAIN_CHAR - IMAGE_BASE + 0x10000);
+    }
+}
@@ -0,0 +1,161 @@
+/*!
+  A typed pointer into Rayman 2's memory, so that the type of what's being pointed to is checked
+  at compile time, and byte offsets can't silently be added to the wrong base.
+
+  Our own pointers are 64-bit, but Rayman 2's are 32-bit, so a `RemotePtr` keeps its address as a
+  `usize`, along with how wide the pointers stored in the game are, which is only needed when
+  following one (see [`follow()`](struct.RemotePtr.html#method.follow)). That way, the same code
+  can read a game with 64-bit pointers, given a pointer made with
+  [`with_width()`](struct.RemotePtr.html#method.with_width).
+  */
+
+extern crate nix;
+
+use std::{fmt,marker::PhantomData,mem::size_of};
+use nix::unistd::Pid;
+use crate::{memory::{read_prims,write_prims,read_pointer,PointerWidth},error::Result};
+
+/// A pointer to a `T` in the memory of another process.
+///
+/// ## Details:
+/// * Pointers stored in the game's memory may not be as wide as ours, so a
+///   `RemotePtr<RemotePtr<T>>` should be followed with [`follow()`](#method.follow), rather than
+///   read with [`read()`](#method.read).
+pub struct RemotePtr<T> {
+    addr: usize,
+    width: PointerWidth,
+    target: PhantomData<fn() -> T>,
+}
+
+// These are implemented by hand, since deriving them would require `T` to implement them too.
+impl<T> Clone for RemotePtr<T> {
+    fn clone(&self) -> RemotePtr<T> {
+        *self
+    }
+}
+
+impl<T> Copy for RemotePtr<T> {}
+
+impl<T> PartialEq for RemotePtr<T> {
+    fn eq(&self, other: &RemotePtr<T>) -> bool {
+        self.addr == other.addr && self.width == other.width
+    }
+}
+
+impl<T> Eq for RemotePtr<T> {}
+
+impl<T> fmt::Debug for RemotePtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RemotePtr({:#x})", self.addr)
+    }
+}
+
+impl<T> RemotePtr<T> {
+    /// Refer to the `T` at `addr`, in a game with 32-bit pointers like Rayman 2.
+    pub const fn new(addr: usize) -> RemotePtr<T> {
+        RemotePtr::with_width(addr, PointerWidth::Bits32)
+    }
+
+    /// Refer to the `T` at `addr`, in a game whose pointers are `width` wide.
+    pub const fn with_width(addr: usize, width: PointerWidth) -> RemotePtr<T> {
+        RemotePtr{addr, width, target: PhantomData}
+    }
+
+    /// Get the width of the pointers stored in the game.
+    pub fn width(self) -> PointerWidth {
+        self.width
+    }
+
+    /// Get the address pointed to.
+    pub fn addr(self) -> usize {
+        self.addr
+    }
+
+    /// Check if this is a null pointer.
+    pub fn is_null(self) -> bool {
+        self.addr == 0
+    }
+
+    /// Get a pointer to the `count`th `T` after this one (or before it, if `count` is negative),
+    /// as in an array.
+    pub fn offset(self, count: isize) -> RemotePtr<T> {
+        RemotePtr::with_width((self.addr as isize + count * size_of::<T>() as isize) as usize, self.width)
+    }
+
+    /// Get a pointer to a field of type `U`, `byte_offset` bytes into the `T`.
+    pub fn field<U>(self, byte_offset: usize) -> RemotePtr<U> {
+        RemotePtr::with_width(self.addr + byte_offset, self.width)
+    }
+
+    /// Treat the `T` as a `U` instead.
+    pub fn cast<U>(self) -> RemotePtr<U> {
+        RemotePtr::with_width(self.addr, self.width)
+    }
+}
+
+impl<T: Copy> RemotePtr<T> {
+    /// Read the `T` from the process given by `pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    pub fn read(self, pid: Pid) -> Result<T> {
+        match read_prims::<T>(pid, self.addr, 1)?.first() {
+            Some(&value) => Ok(value),
+            None => Err(format!("Short read at {:#x}", self.addr).into()),
+        }
+    }
+
+    /// Read `n` consecutive `T`s from the process given by `pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    pub fn read_n(self, pid: Pid, n: usize) -> Result<Vec<T>> {
+        read_prims::<T>(pid, self.addr, n)
+    }
+
+    /// Write `value` over the `T` in the process given by `pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    pub fn write(self, pid: Pid, value: T) -> Result<()> {
+        write_prims(pid, self.addr, &[value])
+    }
+}
+
+impl<T> RemotePtr<RemotePtr<T>> {
+    /// Read the pointer stored here (as wide as this pointer's `width()`), in the process given
+    /// by `pid` - see [`memory::read_pointer()`](../memory/fn.read_pointer.html).
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    pub fn follow(self, pid: Pid) -> Result<RemotePtr<T>> {
+        Ok(RemotePtr::with_width(read_pointer(pid, self.addr, self.width)?, self.width))
+    }
+}
+
+#[cfg(test)]
+mod remote_tests {
+    use super::*;
+    use nix::unistd::getpid;
+
+    #[test]
+    fn can_read_through_pointers() {
+        let values: [u16; 4] = [10, 20, 30, 40];
+        let ptr = RemotePtr::<u16>::new(values.as_ptr() as usize);
+        assert_eq!(ptr.offset(2).read(getpid()).unwrap(), 30);
+        assert_eq!(ptr.field::<u8>(2).read(getpid()).unwrap(), 20);
+
+        // Only the bottom 32 bits of a pointer are stored, so only follow one if it fits.
+        let stored = values.as_ptr() as usize as u32;
+        if stored as usize == values.as_ptr() as usize {
+            let ptr_ptr = RemotePtr::<RemotePtr<u16>>::new(&stored as *const u32 as usize);
+            assert_eq!(ptr_ptr.follow(getpid()).unwrap().offset(3).read(getpid()).unwrap(), 40);
+        }
+        let wide = values.as_ptr() as u64;
+        let wide_ptr = RemotePtr::<RemotePtr<u16>>::with_width(&wide as *const u64 as usize, PointerWidth::Bits64);
+        let target = wide_ptr.follow(getpid()).unwrap();
+        assert_eq!(target.width(), PointerWidth::Bits64);
+        assert_eq!(target.field::<u16>(2).read(getpid()).unwrap(), 20);
+        assert!(RemotePtr::<RemotePtr<u8>>::new(0).follow(getpid()).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+/*!
+  Recording the player's input frame by frame, and playing it back exactly, for TAS-style
+  experiments.
+
+  A [`Recorder`](struct.Recorder.html) captures the analog stick axes (at `OFF_INPUT_X` and
+  `OFF_INPUT_Y`) and the state of chosen buttons on an evdev device, once per frame, into a
+  [`Replay`](struct.Replay.html). A [`Player`](struct.Player.html) plays it back, writing the same
+  stick axes and pressing and releasing the same buttons on a
+  [`VirtualPad`](../input/struct.VirtualPad.html), one frame at a time.
+
+  Both are driven one frame at a time (with `capture_frame()` and `play_frame()`), or by `record()`
+  and `play()` with a function which waits for the game's next frame, e.g.
+  `|| wait_for_next_frame(r2pid).map(|_| ())` (see
+  [`timing::wait_for_next_frame()`](../timing/fn.wait_for_next_frame.html)).
+
+  The file format is binary, with all numbers little-endian:
+
+  * The magic number `WOLR`, and the format version (a byte, currently `1`).
+  * The number of buttons (a byte, at most 32), then the key code of each (a `u16` each).
+  * For each frame: the stick axes (an `f32` each for x and y), then one bit per button (set if
+    it's held), packed into as few bytes as will hold them, lowest bit first.
+  */
+
+extern crate nix;
+
+use std::{fs::{self,File},path::Path};
+use nix::unistd::Pid;
+use crate::{input::{get_stick,set_stick,read_key_bits,is_key_held,VirtualPad},error::{Error,Result}};
+
+const MAGIC: &[u8; 4] = b"WOLR";
+const VERSION: u8 = 1;
+/// The most buttons a replay can hold.
+pub const MAX_BUTTONS: usize = 32;
+
+/// The input on one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReplayFrame {
+    /// The stick's x axis.
+    pub x: f32,
+    /// The stick's y axis.
+    pub y: f32,
+    /// Which buttons are held, one bit each, in the order of the replay's `buttons`.
+    pub buttons: u32,
+}
+
+impl ReplayFrame {
+    /// Check if the button at `index` (in the replay's `buttons`) is held.
+    pub fn is_held(&self, index: usize) -> bool {
+        index < MAX_BUTTONS && self.buttons & (1 << index) != 0
+    }
+}
+
+/// A recording of the input on each frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Replay {
+    /// The key codes of the buttons recorded (as given in `linux/input-event-codes.h`).
+    pub buttons: Vec<u16>,
+    /// The input on each frame, in order.
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// Encode the replay in the binary format - see the [module documentation](index.html).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let button_bytes = self.buttons.len().div_ceil(8);
+        let mut bytes = Vec::with_capacity(6 + 2 * self.buttons.len() + (8 + button_bytes) * self.frames.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.buttons.len() as u8);
+        for button in &self.buttons {
+            bytes.extend_from_slice(&button.to_le_bytes());
+        }
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.x.to_le_bytes());
+            bytes.extend_from_slice(&frame.y.to_le_bytes());
+            bytes.extend_from_slice(&frame.buttons.to_le_bytes()[..button_bytes]);
+        }
+        bytes
+    }
+
+    /// Decode a replay from the binary format - see the [module documentation](index.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `Replay`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `bytes` isn't a replay, or is cut short.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay> {
+        match bytes {
+            [m0, m1, m2, m3, version, num_buttons, rest @ ..] if [*m0, *m1, *m2, *m3] == *MAGIC => {
+                if *version != VERSION {
+                    return Err(format!("Unknown replay version {}", version).into());
+                }
+                let num_buttons = *num_buttons as usize;
+                if num_buttons > MAX_BUTTONS || rest.len() < 2 * num_buttons {
+                    return Err("Replay has a bad button list".into());
+                }
+                let (button_list, frame_data) = rest.split_at(2 * num_buttons);
+                let buttons = button_list.chunks(2).map(|code| u16::from_le_bytes([code[0], code[1]])).collect();
+
+                let frame_len = 8 + num_buttons.div_ceil(8);
+                if frame_data.len() % frame_len != 0 {
+                    return Err("Replay is cut short".into());
+                }
+                let frames = frame_data.chunks(frame_len).map(|frame| {
+                    let mut buttons = [0u8; 4];
+                    buttons[..frame_len - 8].copy_from_slice(&frame[8..]);
+                    ReplayFrame{
+                        x: f32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]),
+                        y: f32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+                        buttons: u32::from_le_bytes(buttons),
+                    }
+                }).collect();
+                Ok(Replay{buttons, frames})
+            },
+            _ => Err("Not a replay".into()),
+        }
+    }
+
+    /// Read a replay from the file at `path` - see [`from_bytes()`](#method.from_bytes).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay> {
+        match fs::read(&path) {
+            Ok(bytes) => Replay::from_bytes(&bytes),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't read replay {}", path.as_ref().display()))),
+        }
+    }
+
+    /// Write the replay to the file at `path` (replacing anything already there).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        match fs::write(&path, self.to_bytes()) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't write replay {}", path.as_ref().display()))),
+        }
+    }
+}
+
+/// Records the input to a Rayman 2 process, one frame at a time.
+pub struct Recorder {
+    r2pid: Pid,
+    device: Option<File>,
+    replay: Replay,
+}
+
+impl Recorder {
+    /// Start recording the stick in the Rayman 2 process given by `r2pid`, without any buttons.
+    pub fn new(r2pid: Pid) -> Recorder {
+        Recorder{r2pid, device: None, replay: Replay::default()}
+    }
+
+    /// Start recording the stick in the Rayman 2 process given by `r2pid`, along with the
+    /// `buttons` (given as key codes, e.g. `0x130` for `BTN_SOUTH`) on the evdev device at
+    /// `device_path` (e.g. `/dev/input/by-id/...-event-joystick`).
+    ///
+    /// ## Requirements:
+    /// * We need read permission on `device_path` (e.g. by being in the `input` group).
+    /// * There can be at most [`MAX_BUTTONS`](constant.MAX_BUTTONS.html) buttons.
+    ///
+    /// ## Returns:
+    /// * On success, returns a new `Recorder`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn with_buttons<P: AsRef<Path>>(r2pid: Pid, device_path: P, buttons: &[u16]) -> Result<Recorder> {
+        if buttons.len() > MAX_BUTTONS {
+            return Err(format!("Can't record more than {} buttons", MAX_BUTTONS).into());
+        }
+        let device = match File::open(device_path.as_ref()) {
+            Ok(device) => device,
+            Err(err) => {return Err(Error::from(err).context(format!("Couldn't open input device {}", device_path.as_ref().display())));},
+        };
+        Ok(Recorder{r2pid, device: Some(device), replay: Replay{buttons: buttons.to_vec(), frames: Vec::new()}})
+    }
+
+    /// Get the number of frames recorded so far.
+    pub fn frames(&self) -> usize {
+        self.replay.frames.len()
+    }
+
+    /// Record the input as it is now, as the next frame. This should be called once per frame.
+    ///
+    /// ## Returns:
+    /// * On success, returns the frame recorded.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails, or the device can't be queried.
+    pub fn capture_frame(&mut self) -> Result<ReplayFrame> {
+        let (x, y) = get_stick(self.r2pid)?;
+        let mut buttons = 0;
+        if let Some(device) = &self.device {
+            let key_bits = read_key_bits(device)?;
+            for (i, &button) in self.replay.buttons.iter().enumerate() {
+                if is_key_held(&key_bits, button) {
+                    buttons |= 1 << i;
+                }
+            }
+        }
+        let frame = ReplayFrame{x, y, buttons};
+        self.replay.frames.push(frame);
+        Ok(frame)
+    }
+
+    /// Record `frames` frames, calling `wait_frame` before each one, which should block until the
+    /// game's next frame.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `wait_frame` or [`capture_frame()`](#method.capture_frame) fails.
+    pub fn record<F>(&mut self, frames: usize, mut wait_frame: F) -> Result<()>
+        where F: FnMut() -> Result<()> {
+        for _ in 0..frames {
+            wait_frame()?;
+            self.capture_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Stop recording, and get the replay.
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+/// Plays a [`Replay`](struct.Replay.html) back into a Rayman 2 process, one frame at a time.
+pub struct Player<'a> {
+    r2pid: Pid,
+    replay: &'a Replay,
+    pad: Option<&'a mut VirtualPad>,
+    next: usize,
+    held: u32,
+}
+
+impl<'a> Player<'a> {
+    /// Get ready to play `replay` back into the Rayman 2 process given by `r2pid`, pressing its
+    /// buttons on `pad` (if given - otherwise, only the stick is played back).
+    ///
+    /// ## Details:
+    /// * `pad` needs to have been made with all the replay's buttons (see
+    ///   [`VirtualPad::gamepad()`](../input/struct.VirtualPad.html#method.gamepad)).
+    pub fn new(r2pid: Pid, replay: &'a Replay, pad: Option<&'a mut VirtualPad>) -> Player<'a> {
+        Player{r2pid, replay, pad, next: 0, held: 0}
+    }
+
+    /// Check if every frame has been played.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.frames.len()
+    }
+
+    /// Play the next frame: write its stick axes, and press or release any buttons which have
+    /// changed since the last one. This should be called once per frame.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(true)` if a frame was played, or `Ok(false)` if there are none
+    ///   left (in which case the stick is put back in the middle, and the buttons are released).
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory write fails, or the buttons can't be pressed.
+    pub fn play_frame(&mut self) -> Result<bool> {
+        let frame = match self.replay.frames.get(self.next) {
+            Some(frame) => *frame,
+            None => {
+                self.apply(ReplayFrame::default())?;
+                return Ok(false);
+            },
+        };
+        self.apply(frame)?;
+        self.next += 1;
+        Ok(true)
+    }
+
+    fn apply(&mut self, frame: ReplayFrame) -> Result<()> {
+        set_stick(self.r2pid, frame.x, frame.y)?;
+        if let Some(pad) = self.pad.as_mut() {
+            for (i, &button) in self.replay.buttons.iter().enumerate() {
+                match (self.held & (1 << i) != 0, frame.is_held(i)) {
+                    (false, true) => pad.press(button)?,
+                    (true, false) => pad.release(button)?,
+                    _ => {},
+                }
+            }
+        }
+        self.held = frame.buttons;
+        Ok(())
+    }
+
+    /// Play every frame that's left, calling `wait_frame` before each one, which should block
+    /// until the game's next frame. This blocks until it's done.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `wait_frame` or [`play_frame()`](#method.play_frame) fails.
+    pub fn play<F>(&mut self, mut wait_frame: F) -> Result<()>
+        where F: FnMut() -> Result<()> {
+        loop {
+            wait_frame()?;
+            if !self.play_frame()? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_replays() {
+        let replay = Replay{
+            buttons: (0x130..0x13A).collect(),
+            frames: vec![
+                ReplayFrame{x: 0.5, y: -1.0, buttons: 0b10_0000_0001},
+                ReplayFrame{x: 0.0, y: 0.0, buttons: 0},
+            ],
+        };
+        let bytes = replay.to_bytes();
+        // Header, 10 buttons, then 2 frames of 8 + 2 bytes each.
+        assert_eq!(bytes.len(), 6 + 20 + 2 * 10);
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+        assert!(replay.frames[0].is_held(9));
+        assert!(!replay.frames[0].is_held(1));
+
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::from_bytes(b"nope").is_err());
+    }
+}
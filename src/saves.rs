@@ -0,0 +1,133 @@
+/*!
+  Functions for finding Rayman 2's save files (inside its Wine prefix) and backing them up, so
+  that practice tools can't ruin real progress.
+  */
+
+extern crate nix;
+
+use std::{fs,path::{Path,PathBuf},time::{SystemTime,UNIX_EPOCH}};
+use nix::unistd::Pid;
+use crate::{utils::get_environment,error::{Error,Result}};
+
+/// Find the directory Rayman 2 is installed in, for the process given by `r2pid`.
+///
+/// ## Details:
+/// * If the game was launched with a Windows path (e.g. `C:\GOG Games\Rayman 2\Rayman2.exe`),
+///   the drive is looked up in `dosdevices` in the process's `WINEPREFIX` (or `~/.wine` if that
+///   isn't set).
+/// * If it was launched with a Unix path, that is used directly.
+///
+/// ## Requirements:
+/// * This program needs to have permission to read `/proc/<r2pid>/cmdline` and
+///   `/proc/<r2pid>/environ`.
+///
+/// ## Returns:
+/// * On success, returns the path of the installation directory.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn find_game_dir(r2pid: Pid) -> Result<PathBuf> {
+    let cmdline = match fs::read(format!("/proc/{}/cmdline", r2pid)) {
+        Ok(buf) => buf,
+        Err(err) => {return Err(Error::from(err).context("Unable to read Rayman 2's command line"));},
+    };
+    // Under Wine, the first argument may be the preloader or wine itself - we want the .exe.
+    let exe = match cmdline
+        .split(|&x| x == 0)
+        .map(String::from_utf8_lossy)
+        .find(|arg| arg.to_lowercase().ends_with(".exe")) {
+        Some(arg) => arg.into_owned(),
+        None => {return Err("No .exe in Rayman 2's command line".into());},
+    };
+
+    let exe_path = match exe.as_bytes() {
+        [drive, b':', rest @ ..] => {
+            let env = get_environment(r2pid)?;
+            let prefix = match (env.get("WINEPREFIX"), env.get("HOME")) {
+                (Some(prefix), _) => PathBuf::from(prefix),
+                (None, Some(home)) => Path::new(home).join(".wine"),
+                (None, None) => {return Err("Can't find Rayman 2's Wine prefix".into());},
+            };
+            let rest = String::from_utf8_lossy(rest).replace('\\', "/");
+            prefix
+                .join("dosdevices")
+                .join(format!("{}:", (*drive as char).to_ascii_lowercase()))
+                .join(rest.trim_start_matches('/'))
+        },
+        _ => PathBuf::from(exe),
+    };
+
+    match exe_path.parent() {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => Err(format!("Can't find directory containing {}", exe_path.display()).into()),
+    }
+}
+
+/// Find the directory Rayman 2 keeps its save games in (`Data/SaveGame` in the installation
+/// directory), for the process given by `r2pid`.
+///
+/// ## Returns:
+/// * On success, returns the path of the save directory.
+/// * Returns an `Err` variant describing what went wrong on failure,
+///   including if the directory doesn't exist.
+pub fn find_save_dir(r2pid: Pid) -> Result<PathBuf> {
+    let save_dir = find_game_dir(r2pid)?.join("Data").join("SaveGame");
+    if save_dir.is_dir() {
+        Ok(save_dir)
+    } else {
+        Err(format!("Save directory {} doesn't exist", save_dir.display()).into())
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Back up the save games of the Rayman 2 process given by `r2pid` into a new subdirectory of
+/// `backup_root`, named after the current Unix time.
+///
+/// ## Returns:
+/// * On success, returns the path of the new backup, which can be given to
+///   [`restore_saves()`](fn.restore_saves.html).
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn backup_saves(r2pid: Pid, backup_root: &Path) -> Result<PathBuf> {
+    let save_dir = find_save_dir(r2pid)?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+    let backup = backup_root.join(format!("SaveGame-{}", timestamp));
+
+    match copy_dir(&save_dir, &backup) {
+        Ok(_) => Ok(backup),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't back up {} to {}", save_dir.display(), backup.display()))),
+    }
+}
+
+/// Restore the save games of the Rayman 2 process given by `r2pid` from `backup`, as made by
+/// [`backup_saves()`](fn.backup_saves.html). Files in the save directory which aren't in the
+/// backup are left alone.
+///
+/// ## Requirements:
+/// * The game shouldn't be writing its saves at the time - preferably it should be at the main
+///   menu, or not running at all (in which case use [`restore_saves_to()`](fn.restore_saves_to.html)).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn restore_saves(r2pid: Pid, backup: &Path) -> Result<()> {
+    restore_saves_to(&find_save_dir(r2pid)?, backup)
+}
+
+/// Restore save games from `backup` into `save_dir` - see
+/// [`restore_saves()`](fn.restore_saves.html).
+pub fn restore_saves_to(save_dir: &Path, backup: &Path) -> Result<()> {
+    match copy_dir(backup, save_dir) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't restore {} to {}", backup.display(), save_dir.display()))),
+    }
+}
@@ -0,0 +1,234 @@
+/*!
+  Save states: copying the game's writable memory, and writing it back later, to practise a
+  section over and over.
+
+  [`capture()`](fn.capture.html) copies every writable private region of the process (as listed in
+  `/proc/<pid>/maps`), and [`capture_globals()`](fn.capture_globals.html) copies just the game's
+  own global variables (the writable part of `Rayman2.exe`, which includes the engine structure),
+  which is much quicker, but only puts back what lives there. The game is
+  [suspended](../suspend/index.html) while its memory is copied or written back, so that it
+  doesn't change halfway through.
+
+  Restoring a state writes over almost everything the game has, so it can crash the game if
+  anything outside its memory has changed since the state was captured (e.g. files it has open,
+  or Wine's own state) - save your game first.
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{memory::{read_prims,write_prims},discovery::{RAYMAN2_NAMES,file_name,read_maps},suspend::SuspendGuard,error::Result};
+
+// Mappings which mustn't be written back: the stack is in use, and the rest belong to the kernel.
+const SKIPPED_MAPPINGS: &[&str] = &["[stack]", "[vvar]", "[vdso]", "[vsyscall]"];
+// Runs of zeroes at least this long are compressed.
+const MIN_ZERO_RUN: usize = 8;
+
+/// A region of memory in a [`SaveState`](struct.SaveState.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// The address of the start of the region.
+    pub start: usize,
+    /// The length of the region, in bytes.
+    pub len: usize,
+    // The contents, compressed.
+    data: Vec<u8>,
+}
+
+impl Region {
+    /// Get the contents of the region.
+    pub fn contents(&self) -> Vec<u8> {
+        decompress(&self.data, self.len)
+    }
+}
+
+/// A copy of some or all of the game's memory, as made by [`capture()`](fn.capture.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveState {
+    /// The regions of memory, in the order they were captured.
+    pub regions: Vec<Region>,
+}
+
+impl SaveState {
+    /// Get the total length of the regions, in bytes.
+    pub fn size(&self) -> usize {
+        self.regions.iter().map(|region| region.len).sum()
+    }
+
+    /// Get the number of bytes the regions actually take up, once compressed.
+    pub fn compressed_size(&self) -> usize {
+        self.regions.iter().map(|region| region.data.len()).sum()
+    }
+}
+
+/// Capture all the writable private memory of the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`), to send it signals,
+///   and to read `/proc/<pid>/maps`.
+///
+/// ## Returns:
+/// * On success, returns the [`SaveState`](struct.SaveState.html).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the process can't be stopped, or if its maps can't be read.
+///
+/// ## Details:
+/// * Regions which can't be read (e.g. guard pages) are left out.
+pub fn capture(r2pid: Pid) -> Result<SaveState> {
+    let regions = writable_regions(&read_maps(r2pid)?, None);
+    capture_regions(r2pid, &regions)
+}
+
+/// Capture just the game's global variables (the writable part of `Rayman2.exe`, including the
+/// engine structure) in the Rayman 2 process given by `r2pid` - see [`capture()`](fn.capture.html).
+pub fn capture_globals(r2pid: Pid) -> Result<SaveState> {
+    let regions = writable_regions(&read_maps(r2pid)?, Some(RAYMAN2_NAMES));
+    if regions.is_empty() {
+        return Err("Couldn't find the game's global variables".into());
+    }
+    capture_regions(r2pid, &regions)
+}
+
+/// Capture the given `regions` (start address and length) of the memory of the Rayman 2 process
+/// given by `r2pid` - see [`capture()`](fn.capture.html).
+pub fn capture_regions(r2pid: Pid, regions: &[(usize, usize)]) -> Result<SaveState> {
+    let _suspended = SuspendGuard::new(r2pid)?;
+    Ok(SaveState{regions: regions.iter()
+        .filter_map(|&(start, len)| {
+            let bytes = read_prims::<u8>(r2pid, start, len).ok().filter(|bytes| bytes.len() == len)?;
+            Some(Region{start, len, data: compress(&bytes)})
+        })
+        .collect()})
+}
+
+/// Write `state` back into the memory of the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`), and to send it
+///   signals.
+/// * `state` should have been captured from the same process (and the same level).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the process can't be stopped, or if a region can't be written (in which case the
+///   regions before it have already been written).
+pub fn restore(r2pid: Pid, state: &SaveState) -> Result<()> {
+    let _suspended = SuspendGuard::new(r2pid)?;
+    for region in &state.regions {
+        if let Err(err) = write_prims(r2pid, region.start, &region.contents()) {
+            return Err(err.context(format!("Couldn't restore memory at {:#x}", region.start)));
+        }
+    }
+    Ok(())
+}
+
+// Get the start and length of each writable private mapping in `maps`. If `module` is given, only
+// the mappings of a file called one of those names are included, along with any anonymous
+// mappings straight after them (i.e. their zero-initialised data).
+fn writable_regions(maps: &str, module: Option<&[&str]>) -> Vec<(usize, usize)> {
+    let mut regions = Vec::new();
+    let mut module_end = None;
+    for line in maps.lines() {
+        let mut fields = line.split_whitespace();
+        let (range, perms) = match (fields.next(), fields.next()) {
+            (Some(range), Some(perms)) => (range, perms),
+            _ => continue,
+        };
+        let (start, end) = match range.split_once('-').map(|(start, end)| (usize::from_str_radix(start, 16), usize::from_str_radix(end, 16))) {
+            Some((Ok(start), Ok(end))) if end > start => (start, end),
+            _ => continue,
+        };
+        // The path is the last field (after the offset, device and inode), but may contain spaces.
+        let path = fields.nth(3).map(|first| &line[line.find(first).unwrap_or(line.len())..]).unwrap_or("");
+
+        let in_module = match module {
+            Some(names) if !path.is_empty() => names.iter().any(|name| file_name(path).eq_ignore_ascii_case(name)),
+            Some(_) => module_end == Some(start),
+            None => !SKIPPED_MAPPINGS.contains(&path),
+        };
+        module_end = match in_module {
+            true => Some(end),
+            false => None,
+        };
+        if in_module && perms.starts_with("rw") && perms.ends_with('p') {
+            regions.push((start, end - start));
+        }
+    }
+    regions
+}
+
+// Compress `bytes` as a series of runs: a `u32` count of zeroes, then a `u32` count of literal
+// bytes and the bytes themselves. The game's memory is mostly zeroes, so this goes a long way.
+fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let zeroes = bytes[i..].iter().take_while(|&&byte| byte == 0).count();
+        i += zeroes;
+        // The literal bytes go up to the next run of zeroes long enough to be worth it (or the
+        // zeroes at the end, which the next round picks up).
+        let literal_start = i;
+        while i < bytes.len() {
+            match bytes[i..].iter().take_while(|&&byte| byte == 0).count() {
+                0 => {i += 1;},
+                run if run >= MIN_ZERO_RUN || i + run == bytes.len() => {break;},
+                run => {i += run;},
+            }
+        }
+        out.extend_from_slice(&(zeroes as u32).to_le_bytes());
+        out.extend_from_slice(&((i - literal_start) as u32).to_le_bytes());
+        out.extend_from_slice(&bytes[literal_start..i]);
+    }
+    out
+}
+
+// Undo `compress()`, giving `len` bytes.
+fn decompress(data: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    let count = |i: &mut usize| {
+        let count = match data.get(*i..*i + 4) {
+            Some(bytes) => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            None => 0,
+        };
+        *i += 4;
+        count
+    };
+    while i < data.len() {
+        let zeroes = count(&mut i);
+        out.resize(out.len() + zeroes, 0);
+        let literal = count(&mut i);
+        out.extend_from_slice(data.get(i..i + literal).unwrap_or(&[]));
+        i += literal;
+    }
+    out.resize(len, 0);
+    out
+}
+
+#[cfg(test)]
+mod savestate_tests {
+    use super::*;
+
+    #[test]
+    fn can_compress_memory() {
+        for bytes in [vec![], vec![0; 100], vec![1, 2, 3], [vec![5; 10], vec![0; 50], vec![1, 0, 0, 2], vec![0; 3]].concat()] {
+            let compressed = compress(&bytes);
+            assert_eq!(decompress(&compressed, bytes.len()), bytes);
+        }
+        assert_eq!(compress(&[0; 4096]).len(), 8);
+    }
+
+    #[test]
+    fn can_find_writable_regions() {
+        let maps = "00400000-00401000 r--p 00000000 08:01 123 /games/Rayman 2/Rayman2.exe\n\
+                    00401000-004f0000 r-xp 00001000 08:01 123 /games/Rayman 2/Rayman2.exe\n\
+                    004f0000-00500000 rw-p 000f0000 08:01 123 /games/Rayman 2/Rayman2.exe\n\
+                    00500000-00510000 rw-p 00000000 00:00 0 \n\
+                    00600000-00700000 rw-p 00000000 00:00 0 \n\
+                    7ffd0000-7ffe0000 rw-p 00000000 00:00 0 [stack]\n\
+                    7fff0000-7fff1000 rw-s 00000000 00:05 9 /dev/shm/wine\n";
+        assert_eq!(writable_regions(maps, Some(RAYMAN2_NAMES)), vec![(0x4f0000, 0x10000), (0x500000, 0x10000)]);
+        assert_eq!(writable_regions(maps, None), vec![(0x4f0000, 0x10000), (0x500000, 0x10000), (0x600000, 0x100000)]);
+    }
+}
@@ -0,0 +1,155 @@
+/*!
+  Finding super-objects by name, without knowing their exact names (e.g. `GRP_TimerCourse*` for the
+  race timer, whatever its instance number is in the current level).
+
+  Names are matched with a [`NamePattern`](enum.NamePattern.html): a glob, or (with the `regex`
+  feature) a regular expression. See [`utils::find_super_objects()`](../utils/fn.find_super_objects.html)
+  for searching the hierarchy with one.
+  */
+
+use std::fmt;
+use crate::{utils::World,error::Result};
+
+/// A pattern for matching the names of super-objects.
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// A glob, where `*` matches any number of characters and `?` matches any one character.
+    /// Globs ignore case, since the names in the game aren't always capitalised consistently.
+    Glob(String),
+    /// A regular expression, which can match anywhere in the name (use `^` and `$` to match the
+    /// whole name).
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl NamePattern {
+    /// Make a glob pattern.
+    pub fn glob(pattern: &str) -> NamePattern {
+        NamePattern::Glob(pattern.to_string())
+    }
+
+    /// Make a regular expression pattern.
+    ///
+    /// ## Returns:
+    /// * On success, returns the pattern.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `pattern` isn't a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<NamePattern> {
+        match regex::Regex::new(pattern) {
+            Ok(regex) => Ok(NamePattern::Regex(regex)),
+            Err(err) => Err(format!("Invalid regular expression {}: {}", pattern, err).into()),
+        }
+    }
+
+    /// Make a pattern from what the user typed: a regular expression if it's between slashes
+    /// (e.g. `/^GRP_.*_I[0-9]+$/`), or a glob otherwise.
+    ///
+    /// ## Returns:
+    /// * On success, returns the pattern.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `pattern` is an invalid regular expression, or if it's a regular expression and the
+    ///   `regex` feature isn't on.
+    pub fn parse(pattern: &str) -> Result<NamePattern> {
+        match pattern.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            #[cfg(feature = "regex")]
+            Some(regex) => NamePattern::regex(regex),
+            #[cfg(not(feature = "regex"))]
+            Some(_) => Err(format!("Can't search for {}: regular expressions need the regex feature", pattern).into()),
+            None => Ok(NamePattern::glob(pattern)),
+        }
+    }
+
+    /// Check if `name` matches this pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(glob) => glob_matches(glob, name),
+            #[cfg(feature = "regex")]
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NamePattern::Glob(glob) => f.write_str(glob),
+            #[cfg(feature = "regex")]
+            NamePattern::Regex(regex) => write!(f, "/{}/", regex),
+        }
+    }
+}
+
+// Match `name` against `glob`, ignoring case.
+fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().flat_map(char::to_lowercase).collect();
+    let name: Vec<char> = name.chars().flat_map(char::to_lowercase).collect();
+    let (mut g, mut n) = (0, 0);
+    // Where to go back to if what comes after the last `*` doesn't match: the position in the glob
+    // just after it, and the position in the name it's matched up to.
+    let mut star = None;
+
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g + 1, n));
+                g += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            },
+            _ => match star {
+                Some((star_g, star_n)) => {
+                    // Let the `*` take one more character, and try again.
+                    star = Some((star_g, star_n + 1));
+                    g = star_g;
+                    n = star_n + 1;
+                },
+                None => {return false;},
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+/// A super-object found by [`utils::find_super_objects()`](../utils/fn.find_super_objects.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuperObjectMatch {
+    /// The name of the super-object.
+    pub name: String,
+    /// Pointer to the super-object in Rayman 2's memory.
+    pub ptr: usize,
+    /// The name of its AI Model.
+    pub ai_model: Option<String>,
+    /// The name of its family.
+    pub family: Option<String>,
+    /// Which part of the hierarchy it was found in.
+    pub world: World,
+}
+
+impl fmt::Display for SuperObjectMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {:#x} (AI Model {}, family {}, in the {})", self.name, self.ptr,
+               self.ai_model.as_deref().unwrap_or("unknown"), self.family.as_deref().unwrap_or("unknown"), self.world)
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[test]
+    fn can_match_globs() {
+        assert!(NamePattern::glob("GRP_TimerCourse*").matches("GRP_TimerCourse_I3"));
+        assert!(NamePattern::glob("grp_timer*_i?").matches("GRP_TimerCourse_I3"));
+        assert!(NamePattern::glob("*Course*I3").matches("GRP_TimerCourse_I3"));
+        assert!(!NamePattern::glob("*Course*I3").matches("GRP_TimerCourse_I30"));
+        assert!(!NamePattern::glob("GRP_?").matches("GRP_"));
+        assert!(NamePattern::parse("*").unwrap().matches(""));
+        #[cfg(feature = "regex")]
+        assert!(NamePattern::parse("/^GRP_.*_I[0-9]+$/").unwrap().matches("GRP_TimerCourse_I3"));
+        #[cfg(not(feature = "regex"))]
+        assert!(NamePattern::parse("/^GRP_/").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/*!
+  A WebSocket server which broadcasts the game's state to any number of clients, so that
+  browser-based overlays (e.g. for streams) can show the race timers and positions without
+  reading the game's memory themselves.
+
+  Each message is a [`TelemetryFrame`](../telemetry/struct.TelemetryFrame.html) as JSON (see
+  [`TelemetryFrame::to_json()`](../telemetry/struct.TelemetryFrame.html#method.to_json)), sent as
+  a text message every time the game is polled. Pings from the clients are answered, and so are
+  close frames, but anything else they send is ignored.
+  */
+
+extern crate nix;
+
+use std::{io::ErrorKind,net::{TcpStream,ToSocketAddrs},sync::{Arc,Mutex,mpsc::{sync_channel,SyncSender,TryRecvError}},time::Duration};
+use nix::unistd::Pid;
+use tungstenite::Message;
+use crate::{watch::{self,Poller},races::{RaceTimer,DEFAULT_RACES},telemetry::TelemetryFrame,error::Result};
+
+// How long a client gets to finish its handshake, or to take a message, before it's dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+// How long a client's thread waits for it to send something before checking for messages to it.
+const CLIENT_READ_INTERVAL: Duration = Duration::from_millis(20);
+// How many messages can be waiting for a client before it's dropped for being too slow.
+const CLIENT_BACKLOG: usize = 16;
+
+// The senders for the messages to each connected client.
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<str>>>>>;
+
+/// Broadcasts the state of the game over WebSocket, from background threads.
+///
+/// ## Details:
+/// * Failing to read the game (e.g. while a level is loading) doesn't stop the server - nothing is
+///   sent until it can be read again.
+/// * Clients which go away, or are too slow to take a message, are dropped.
+pub struct TelemetryServer {
+    clients: Clients,
+    poller: Poller,
+    server: Poller,
+}
+
+impl TelemetryServer {
+    /// Start polling the Rayman 2 process given by `r2pid` every `interval`, and serving
+    /// WebSocket clients on `address` (e.g. `"127.0.0.1:9899"`).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `TelemetryServer`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the address can't be listened on.
+    pub fn spawn<A: ToSocketAddrs>(r2pid: Pid, address: A, interval: Duration) -> Result<TelemetryServer> {
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let poll_clients = clients.clone();
+        let mut timers = DEFAULT_RACES.map(|race| RaceTimer::attach(r2pid, race));
+        let poller = Poller::spawn(interval, move || {
+            let race = timers.iter_mut().find_map(|timer| timer.read().ok().flatten());
+            if let Ok(frame) = TelemetryFrame::read(r2pid, race) {
+                broadcast(&poll_clients, &frame.to_json());
+            }
+            Ok(())
+        });
+
+        let accept_clients = clients.clone();
+        let server = watch::serve(address, "WebSocket clients", move |stream| serve_client(stream, &accept_clients))?;
+
+        Ok(TelemetryServer{clients, poller, server})
+    }
+
+    /// Get the number of clients currently connected.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Stop polling and serving, and wait for the background threads to finish. The clients are
+    /// sent a close frame by their own threads.
+    ///
+    /// ## Returns:
+    /// * If the threads stopped because they were asked to, returns `Ok(())`.
+    /// * If one had already stopped because of an error, returns an `Err` variant with a text
+    ///   description of what went wrong.
+    pub fn stop(mut self) -> Result<()> {
+        let poller = self.poller.stop();
+        let server = self.server.stop();
+        self.clients.lock().unwrap().clear();
+        poller.and(server)
+    }
+}
+
+// Send `message` to all the `clients`, dropping any which are too far behind (or have gone). The
+// messages are written by each client's own thread, so nothing waits for the network here.
+fn broadcast(clients: &Mutex<Vec<SyncSender<Arc<str>>>>, message: &str) {
+    let message: Arc<str> = message.into();
+    clients.lock().unwrap().retain(|client| client.try_send(message.clone()).is_ok());
+}
+
+// Accept the WebSocket handshake on `stream`, join the `clients`, and then send the client every
+// message broadcast to it until it goes away, or until it's dropped from the `clients`.
+fn serve_client(stream: TcpStream, clients: &Mutex<Vec<SyncSender<Arc<str>>>>) {
+    let timeouts = stream.set_read_timeout(Some(CLIENT_TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)));
+    if timeouts.is_err() {
+        return;
+    }
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(_) => {return;},
+    };
+    if socket.get_ref().set_read_timeout(Some(CLIENT_READ_INTERVAL)).is_err() {
+        return;
+    }
+    let (sender, messages) = sync_channel(CLIENT_BACKLOG);
+    clients.lock().unwrap().push(sender);
+
+    loop {
+        // Pings are answered and close frames acknowledged as they're read.
+        match socket.read() {
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(_) => {return;},
+        }
+        loop {
+            match messages.try_recv() {
+                Ok(message) => if socket.send(Message::text(&*message)).is_err() {
+                    return;
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = socket.close(None);
+                    let _ = socket.flush();
+                    return;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use std::{net::TcpListener,thread};
+
+    #[test]
+    fn can_serve_websockets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let server_clients = clients.clone();
+        let server = thread::spawn(move || serve_client(listener.accept().unwrap().0, &server_clients));
+
+        let (mut client, _) = tungstenite::client(format!("ws://{}/", address), TcpStream::connect(address).unwrap()).unwrap();
+        while clients.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        broadcast(&clients, "{\"level\":\"ly_10\"}");
+        assert_eq!(client.read().unwrap(), Message::text("{\"level\":\"ly_10\"}"));
+
+        client.send(Message::Ping(b"hi".to_vec())).unwrap();
+        assert_eq!(client.read().unwrap(), Message::Pong(b"hi".to_vec()));
+
+        client.close(None).unwrap();
+        while client.read().is_ok() {}
+        server.join().unwrap();
+        broadcast(&clients, "gone");
+        assert!(clients.lock().unwrap().is_empty());
+    }
+}
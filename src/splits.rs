@@ -0,0 +1,263 @@
+/*!
+  Recording split times in the Ly races, and keeping a log of runs on disk, so that personal bests
+  and averages can be looked up.
+
+  A [`SplitRecorder`](struct.SplitRecorder.html) follows a [`RaceTimer`](../races/struct.RaceTimer.html),
+  and records a split whenever a checkpoint adds time to the countdown (or whenever
+  [`split()`](struct.SplitRecorder.html#method.split) is called). The finished
+  [`Run`](struct.Run.html)s can be kept in a [`RunLog`](struct.RunLog.html), which is a plain text
+  file with one run per line:
+
+  ```text
+  <UNIX TIMESTAMP> <LEVEL> <SPLIT> <SPLIT> ... <FINISH>
+  ```
+
+  where each split is the race timer (in milliseconds) at that point.
+  */
+
+extern crate nix;
+
+use std::{fs::{self,OpenOptions},io::{ErrorKind,Write},path::{Path,PathBuf},time::{SystemTime,UNIX_EPOCH}};
+use nix::unistd::Pid;
+use crate::{races::{Race,RaceTimer,RaceEvent},error::{Error,Result}};
+
+/// One run of a race.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// When the run finished, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The race that was run.
+    pub race: Race,
+    /// The race timer (in milliseconds) at each split, in order. The last one is the finish.
+    pub splits: Vec<f32>,
+}
+
+impl Run {
+    /// Get the final time of the run (in milliseconds), if it has any splits.
+    pub fn time(&self) -> Option<f32> {
+        self.splits.last().copied()
+    }
+
+    /// Get the time (in milliseconds) taken for each segment, i.e. between each split and the one
+    /// before it.
+    pub fn segments(&self) -> Vec<f32> {
+        let mut previous = 0.0;
+        self.splits.iter().map(|&split| {
+            let segment = split - previous;
+            previous = split;
+            segment
+        }).collect()
+    }
+
+    fn to_line(&self) -> String {
+        let splits: Vec<String> = self.splits.iter().map(|split| split.to_string()).collect();
+        format!("{} {} {}", self.timestamp, self.race.level_name(), splits.join(" "))
+    }
+
+    fn from_line(line: &str) -> Option<Run> {
+        let mut fields = line.split_whitespace();
+        let timestamp = fields.next()?.parse().ok()?;
+        let race = Race::from_level(fields.next()?)?;
+        let splits = fields.map(|split| split.parse().ok()).collect::<Option<Vec<f32>>>()?;
+        Some(Run{timestamp, race, splits})
+    }
+}
+
+/// Records splits in one of the Ly races, in a Rayman 2 process.
+///
+/// ## Details:
+/// * A run starts when the race's level starts running, and finishes when the end of the race is
+///   reached (see [`RaceEvent::Finished`](../races/enum.RaceEvent.html#variant.Finished)). If the
+///   level stops or the race restarts first (e.g. because the countdown ran out, or the game went
+///   back to the menu), the run is thrown away.
+/// * A split is recorded whenever the countdown goes up, which is what happens when a checkpoint
+///   is reached.
+pub struct SplitRecorder {
+    timer: RaceTimer,
+    splits: Option<Vec<f32>>,
+    last_time: f32,
+}
+
+impl SplitRecorder {
+    /// Start recording splits in `race`, in the Rayman 2 process given by `r2pid`.
+    pub fn attach(r2pid: Pid, race: Race) -> SplitRecorder {
+        SplitRecorder{timer: RaceTimer::attach(r2pid, race), splits: None, last_time: 0.0}
+    }
+
+    /// Get the splits recorded so far in the current run (if one is under way).
+    pub fn current_splits(&self) -> Option<&[f32]> {
+        self.splits.as_deref()
+    }
+
+    /// Record a split now, in the current run (if one is under way).
+    pub fn split(&mut self) {
+        if let Some(splits) = self.splits.as_mut() {
+            splits.push(self.last_time);
+        }
+    }
+
+    /// Read the race timer, and record any splits since this was last called. This should be
+    /// called regularly (e.g. a few times a second) for the splits to be accurate.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug the game (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Some` [`Run`](struct.Run.html) if one has just finished, or `None`
+    ///   otherwise.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn poll(&mut self) -> Result<Option<Run>> {
+        for event in self.timer.poll()? {
+            if let Some(run) = self.handle(event) {
+                return Ok(Some(run));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Record any split from `event` (e.g. from a [`RaceStream`](../asynchronous/struct.RaceStream.html)
+    /// following the same race), rather than reading the timer with [`poll()`](#method.poll).
+    ///
+    /// ## Returns:
+    /// * Returns `Some` [`Run`](struct.Run.html) if one has just finished, or `None` otherwise.
+    pub fn handle(&mut self, event: RaceEvent) -> Option<Run> {
+        match event {
+            RaceEvent::Started(state) => {
+                self.splits = Some(Vec::new());
+                self.last_time = state.time;
+            },
+            RaceEvent::TimeChanged{new, ..} => {self.last_time = new;},
+            RaceEvent::CountdownChanged{old, new} => {
+                if new > old {
+                    self.split();
+                }
+            },
+            RaceEvent::Finished(state) => {
+                let mut splits = self.splits.take()?;
+                splits.push(state.time);
+                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+                return Some(Run{timestamp, race: self.timer.race(), splits});
+            },
+            RaceEvent::Stopped => {self.splits = None;},
+        }
+        None
+    }
+}
+
+/// A log of finished runs, kept in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunLog {
+    path: PathBuf,
+    runs: Vec<Run>,
+}
+
+impl RunLog {
+    /// Open the log at `path`, reading the runs already in it (if it exists).
+    ///
+    /// ## Details:
+    /// * Lines which can't be read as runs are skipped.
+    ///
+    /// ## Returns:
+    /// * On success, returns the `RunLog`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the file exists but can't be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RunLog> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => {return Err(Error::from(err).context(format!("Couldn't read run log {}", path.display())));},
+        };
+        let runs = text.lines().filter_map(Run::from_line).collect();
+        Ok(RunLog{path, runs})
+    }
+
+    /// Add `run` to the log, writing it to the end of the file.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the file can't be written.
+    pub fn record(&mut self, run: Run) -> Result<()> {
+        let written = OpenOptions::new().create(true).append(true).open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", run.to_line()));
+        if let Err(err) = written {
+            return Err(Error::from(err).context(format!("Couldn't write to run log {}", self.path.display())));
+        }
+        self.runs.push(run);
+        Ok(())
+    }
+
+    /// Get all the runs of `race` in the log, oldest first.
+    pub fn runs(&self, race: Race) -> impl Iterator<Item = &Run> {
+        self.runs.iter().filter(move |run| run.race == race && run.time().is_some())
+    }
+
+    /// Get the fastest run of `race`, if there are any.
+    pub fn personal_best(&self, race: Race) -> Option<&Run> {
+        self.runs(race).min_by(|a, b| a.time().partial_cmp(&b.time()).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Get the mean time (in milliseconds) of the runs of `race`, if there are any.
+    pub fn average(&self, race: Race) -> Option<f32> {
+        let times: Vec<f32> = self.runs(race).filter_map(Run::time).collect();
+        match times.len() {
+            0 => None,
+            n => Some(times.iter().sum::<f32>() / n as f32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod splits_tests {
+    use super::*;
+
+    #[test]
+    fn can_keep_run_log() {
+        let path = std::env::temp_dir().join(format!("walkoflife-splits-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut log = RunLog::open(&path).unwrap();
+        assert_eq!(log.personal_best(Race::WalkOfLife), None);
+        log.record(Run{timestamp: 1, race: Race::WalkOfLife, splits: vec![10000.0, 25000.0, 60000.0]}).unwrap();
+        log.record(Run{timestamp: 2, race: Race::WalkOfLife, splits: vec![9000.0, 24000.0, 58000.0]}).unwrap();
+        log.record(Run{timestamp: 3, race: Race::WalkOfPower, splits: vec![30000.0]}).unwrap();
+
+        let log = RunLog::open(&path).unwrap();
+        assert_eq!(log.runs(Race::WalkOfLife).count(), 2);
+        assert_eq!(log.personal_best(Race::WalkOfLife).map(|run| run.timestamp), Some(2));
+        assert_eq!(log.average(Race::WalkOfLife), Some(59000.0));
+        assert_eq!(log.personal_best(Race::WalkOfLife).unwrap().segments(), vec![9000.0, 15000.0, 34000.0]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn only_records_finished_runs() {
+        use crate::races::RaceState;
+        // Nothing can have this PID, but nothing is read from it here.
+        let mut recorder = SplitRecorder::attach(Pid::from_raw(i32::MAX), Race::WalkOfLife);
+        let mut run = |events: &[RaceEvent]| events.iter().filter_map(|&event| recorder.handle(event)).collect::<Vec<Run>>();
+
+        // Through a checkpoint, and then back to the menu with time still left.
+        let aborted = run(&[
+            RaceEvent::Started(RaceState{countdown: 30, time: 0.0}),
+            RaceEvent::TimeChanged{old: 0.0, new: 9207.0},
+            RaceEvent::CountdownChanged{old: 22, new: 31},
+            RaceEvent::TimeChanged{old: 9207.0, new: 10197.0},
+            RaceEvent::Stopped,
+        ]);
+        assert!(aborted.is_empty());
+
+        let finished = run(&[
+            RaceEvent::Started(RaceState{countdown: 30, time: 0.0}),
+            RaceEvent::TimeChanged{old: 0.0, new: 9207.0},
+            RaceEvent::CountdownChanged{old: 22, new: 31},
+            RaceEvent::TimeChanged{old: 9207.0, new: 107915.0},
+            RaceEvent::Finished(RaceState{countdown: 25, time: 107915.0}),
+            RaceEvent::Stopped,
+        ]);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].splits, vec![9207.0, 107915.0]);
+    }
+}
@@ -0,0 +1,118 @@
+/*!
+  Declarations of some of Rayman 2's engine structures, with `#[derive(EngineStruct)]`, so that
+  they can be read in one go (with [`RemotePtr::load()`](../remote/struct.RemotePtr.html#method.load)
+  or [`read_struct()`](../memory/fn.read_struct.html)) instead of field by field with magic
+  offsets.
+
+  Only the fields we know about are declared, so the sizes here may be smaller than the real
+  structures.
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{EngineStruct,remote::RemotePtr,relocation::address,constants::OFF_ENGINE_STRUCTURE};
+
+/// The engine structure, at `OFF_ENGINE_STRUCTURE`.
+#[derive(EngineStruct, Debug, Clone, PartialEq)]
+pub struct EngineStructure {
+    /// The mode the engine is in - see [`EngineMode`](../utils/enum.EngineMode.html).
+    #[offset(0x0)]
+    pub mode: u8,
+    /// The name of the current level, NUL-terminated.
+    #[offset(0x1F)]
+    pub level_name: [u8; 16],
+}
+
+/// Get the engine structure in the memory of the Rayman 2 process given by `r2pid`.
+pub fn engine_structure(r2pid: Pid) -> RemotePtr<EngineStructure> {
+    RemotePtr::new(address(r2pid, OFF_ENGINE_STRUCTURE))
+}
+
+/// An entry in a family's default objects table (0x14 bytes long).
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct ObjectTableEntry {
+    /// The physical object's visual set (through an extra pointer).
+    #[offset(0x4)]
+    pub visual_set: RemotePtr<RemotePtr<VisualSet>>,
+}
+
+/// The visual set of a physical object: its levels of detail.
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct VisualSet {
+    /// The number of levels of detail.
+    #[offset(0x4)]
+    pub num_of_lod: i16,
+    /// The type of visual (`0` for meshes).
+    #[offset(0x6)]
+    pub visual_type: i16,
+    /// The array of visuals, one for each level of detail.
+    #[offset(0xC)]
+    pub lod_visuals: RemotePtr<RemotePtr<MeshHeader>>,
+}
+
+/// The header of a mesh.
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct MeshHeader {
+    /// The vertices (x, y, z).
+    #[offset(0x0)]
+    pub vertices: RemotePtr<[f32; 3]>,
+    /// The types of the sub-blocks.
+    #[offset(0x10)]
+    pub sub_block_types: RemotePtr<u16>,
+    /// The sub-blocks.
+    #[offset(0x14)]
+    pub sub_blocks: RemotePtr<RemotePtr<()>>,
+    /// The number of vertices.
+    #[offset(0x2C)]
+    pub num_vertices: i16,
+    /// The number of sub-blocks.
+    #[offset(0x2E)]
+    pub num_sub_blocks: i16,
+}
+
+/// The start of a family: the things shared by all the objects of one kind.
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct FamilyHeader {
+    /// The index of the family in the families table (see
+    /// [`read_object_types()`](../utils/fn.read_object_types.html)).
+    #[offset(0xC)]
+    pub index: u32,
+    /// The default objects table, with the family's physical objects.
+    #[offset(0x1C)]
+    pub default_objects: RemotePtr<ObjectsTable>,
+}
+
+/// A family's table of physical objects.
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct ObjectsTable {
+    /// The first entry.
+    #[offset(0x4)]
+    pub entries: RemotePtr<ObjectTableEntry>,
+    /// The number of entries.
+    #[offset(0xC)]
+    pub num_entries: u32,
+}
+
+/// A sub-block of a mesh made of indexed triangles (sub-block type `1`).
+#[derive(EngineStruct, Debug, Clone, Copy, PartialEq)]
+pub struct TriangleElement {
+    /// The material (textures, etc.).
+    #[offset(0x0)]
+    pub material: RemotePtr<()>,
+    /// The number of triangles.
+    #[offset(0x4)]
+    pub num_triangles: u16,
+    /// The number of texture coordinates.
+    #[offset(0x6)]
+    pub num_uvs: u16,
+    /// The triangles, as indices into the mesh's vertices.
+    #[offset(0x8)]
+    pub triangles: RemotePtr<[u16; 3]>,
+    /// For each triangle, the indices of the texture coordinates of its corners.
+    #[offset(0xC)]
+    pub uv_indices: RemotePtr<[u16; 3]>,
+    /// The texture coordinates (u, v).
+    #[offset(0x14)]
+    pub uvs: RemotePtr<[f32; 2]>,
+}
@@ -0,0 +1,543 @@
+/*!
+  Typed pointers to the structures hanging off a super-object in Rayman 2's engine hierarchy,
+  so that the pointer topology (which structure points to which, and at what offset) lives in one
+  place, and a mind pointer can't be passed where a super-object pointer is expected.
+  */
+
+extern crate nix;
+
+use nix::unistd::Pid;
+use crate::{memory::get_pointer_path,remote::RemotePtr,constants::paths,error::Result};
+
+/// A pointer to a super-object in Rayman 2's memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SuperObjectPtr(pub usize);
+
+/// A pointer to the mind of a super-object's perso (i.e. its AI state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MindPtr(pub usize);
+
+/// A pointer to the DSG memory of a mind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DsgMemPtr(pub usize);
+
+/// A pointer to the dynamics (i.e. physics state) of a super-object's perso.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DynamicsPtr(pub usize);
+
+/// A pointer to an AI Model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AiModelPtr(pub usize);
+
+/// A 4x4 transform matrix, row by row, with the translation in the last column (so that a point
+/// `p` moves to `M * [p, 1]`).
+pub type Mat4 = [f32; 16];
+
+/// The 4x4 identity matrix.
+pub const MAT4_IDENTITY: Mat4 = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+
+/// Build a 4x4 matrix from the parts of a super-object's matrix: its `position`, and its
+/// `rotation` and `scale` (3x3 matrices, row by row), which are applied scale first.
+pub fn compose_matrix(position: [f32; 3], rotation: [f32; 9], scale: [f32; 9]) -> Mat4 {
+    let mut matrix = MAT4_IDENTITY;
+    for row in 0..3 {
+        for col in 0..3 {
+            matrix[4*row + col] = (0..3).map(|k| rotation[3*row + k] * scale[3*k + col]).sum();
+        }
+        matrix[4*row + 3] = position[row];
+    }
+    matrix
+}
+
+/// Get the translation (x, y, z) from a 4x4 `matrix`.
+pub fn matrix_translation(matrix: &Mat4) -> [f32; 3] {
+    [matrix[3], matrix[7], matrix[11]]
+}
+
+/// One of the 32 custom bits of a super-object's perso: flags which the game's AI scripts check
+/// and set to change how the object behaves.
+///
+/// ## Details:
+/// * The meanings of the bits aren't recorded in the game itself, and the ones found by the
+///   community haven't been checked here, so bits are only given by number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomBit(u8);
+
+impl CustomBit {
+    /// How many custom bits there are.
+    pub const COUNT: u8 = 32;
+
+    /// Get the bit with the number `bit`.
+    ///
+    /// ## Returns:
+    /// * On success, returns the bit.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if `bit` isn't less than [`COUNT`](#associatedconstant.COUNT).
+    pub fn new(bit: u8) -> Result<CustomBit> {
+        match bit < CustomBit::COUNT {
+            true => Ok(CustomBit(bit)),
+            false => Err(format!("There is no custom bit {} (they go from 0 to {})", bit, CustomBit::COUNT - 1).into()),
+        }
+    }
+
+    /// Get the number of this bit (0 to 31).
+    pub fn bit(self) -> u8 {
+        self.0
+    }
+
+    /// Get the mask of this bit in the custom bits.
+    pub fn mask(self) -> u32 {
+        1 << self.0
+    }
+
+    /// Check if this bit is set in `bits`.
+    pub fn is_set(self, bits: u32) -> bool {
+        bits & self.mask() != 0
+    }
+
+    /// Get the bit with the given `name`, which is its number, with or without `bit` in front
+    /// (e.g. `"bit12"` or `"12"`), ignoring case.
+    pub fn from_name(name: &str) -> Option<CustomBit> {
+        let name = name.to_ascii_lowercase();
+        let number = name.strip_prefix("bit").unwrap_or(&name);
+        CustomBit::new(number.parse().ok()?).ok()
+    }
+}
+
+impl SuperObjectPtr {
+    /// Get the mind of this super-object's perso, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the mind.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn mind(self, r2pid: Pid) -> Result<MindPtr> {
+        match paths::MIND.resolve_from(r2pid, self.0) {
+            Ok(ptr) => Ok(MindPtr(ptr)),
+            Err(err) => Err(err.context("Unable to get Mind")),
+        }
+    }
+
+    /// Get a pointer to the custom bits of this super-object's perso, in the process given by
+    /// `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the custom bits.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn custom_bits(self, r2pid: Pid) -> Result<usize> {
+        match paths::CUSTOM_BITS.resolve_from(r2pid, self.0) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get Custom Bits")),
+        }
+    }
+
+    /// Get a pointer to the position (three `f32`s: x, y, z) in the global matrix of this
+    /// super-object, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the position.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn position_ptr(self, r2pid: Pid) -> Result<RemotePtr<[f32; 3]>> {
+        // Super-object -> global matrix, which has a type (u32) and then the position.
+        Ok(RemotePtr::new(self.global_matrix(r2pid)? + 4))
+    }
+
+    /// Get a pointer to the rotation (nine `f32`s: a 3x3 matrix, row by row) in the global matrix
+    /// of this super-object, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the rotation.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn rotation_ptr(self, r2pid: Pid) -> Result<RemotePtr<[f32; 9]>> {
+        // The rotation comes straight after the position.
+        Ok(RemotePtr::new(self.global_matrix(r2pid)? + 0x10))
+    }
+
+    /// Get a pointer to the scale (nine `f32`s: a 3x3 matrix, row by row) in the global matrix of
+    /// this super-object, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the scale.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn scale_ptr(self, r2pid: Pid) -> Result<RemotePtr<[f32; 9]>> {
+        // The scale comes straight after the rotation.
+        Ok(RemotePtr::new(self.global_matrix(r2pid)? + 0x34))
+    }
+
+    fn global_matrix(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, self.0 + 0x20, None) {
+            Ok(0) => Err("Super-object has no global matrix".into()),
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get global matrix")),
+        }
+    }
+
+    /// Get the dynamics of this super-object's perso, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the dynamics.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails, or if the perso has no dynamics.
+    pub fn dynamics(self, r2pid: Pid) -> Result<DynamicsPtr> {
+        // Super-object -> perso -> dynam -> dynamics
+        match get_pointer_path(r2pid, self.0 + 4, Some(&vec![8, 0])) {
+            Ok(0) => Err("Object has no dynamics".into()),
+            Ok(ptr) => Ok(DynamicsPtr(ptr)),
+            Err(err) => Err(err.context("Unable to get Dynamics")),
+        }
+    }
+
+    /// Get the next brother of this super-object in the hierarchy, in the process given by
+    /// `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the next brother, or `None` if this is the last one.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn next_brother(self, r2pid: Pid) -> Result<Option<SuperObjectPtr>> {
+        match get_pointer_path(r2pid, self.0 + 0x14, None) {
+            Ok(0) => Ok(None),
+            Ok(ptr) => Ok(Some(SuperObjectPtr(ptr))),
+            Err(err) => Err(err.context("Unable to get next brother")),
+        }
+    }
+}
+
+impl MindPtr {
+    /// Get the AI Model used by this mind, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the AI Model.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn ai_model(self, r2pid: Pid) -> Result<AiModelPtr> {
+        match get_pointer_path(r2pid, self.0, None) {
+            Ok(ptr) => Ok(AiModelPtr(ptr)),
+            Err(err) => Err(err.context("Unable to get AI Model pointer")),
+        }
+    }
+
+    /// Get the index of the currently-active normal behaviour (comport) of this mind, in the
+    /// process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns the index of the active comport.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn active_normal_behaviour(self, r2pid: Pid) -> Result<usize> {
+        // Mind -> normal intelligence, which has the current comport at 0x8.
+        match get_pointer_path(r2pid, self.0 + 4, Some(&vec![0x8])) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get Active Normal Behaviour")),
+        }
+    }
+
+    /// Switch the currently-active normal behaviour (comport) of this mind to the one at
+    /// `behaviour`, in the process given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * `behaviour` should be a pointer to one of the normal behaviours of this mind's AI Model
+    ///   (see [`AiModel::normal_behaviour_list()`](../aimodel/struct.AiModel.html#method.normal_behaviour_list)).
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read or write fails.
+    pub fn set_active_normal_behaviour(self, r2pid: Pid, behaviour: usize) -> Result<()> {
+        let intelligence = match get_pointer_path(r2pid, self.0 + 4, None) {
+            Ok(ptr) => ptr,
+            Err(err) => {return Err(err.context("Unable to get normal intelligence"));},
+        };
+        match RemotePtr::<u32>::new(intelligence + 0x8).write(r2pid, behaviour as u32) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.context("Unable to set Active Normal Behaviour")),
+        }
+    }
+
+    /// Get the DSG memory of this mind, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the DSG memory.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails, or if the mind has no DSG memory.
+    pub fn dsg_mem(self, r2pid: Pid) -> Result<DsgMemPtr> {
+        match get_pointer_path(r2pid, self.0 + 0xC, None) {
+            Ok(0) => Err("Object has no DSG memory".into()),
+            Ok(ptr) => Ok(DsgMemPtr(ptr)),
+            Err(err) => Err(err.context("Unable to get DsgMem pointer")),
+        }
+    }
+}
+
+impl DsgMemPtr {
+    /// Get a pointer to the current DSG memory buffer, in the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the buffer.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn buffer(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, self.0 + 8, None) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get DSG memory buffer")),
+        }
+    }
+
+    /// Get a pointer to the DsgVar structure (i.e. the variable layout) for this DSG memory, in
+    /// the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the DsgVar.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn dsg_var(self, r2pid: Pid) -> Result<usize> {
+        // The DsgMem points to the AI Model's pointer to its DsgVar, rather than to the DsgVar itself.
+        match get_pointer_path(r2pid, self.0, Some(&vec![0])) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get DsgVar pointer")),
+        }
+    }
+}
+
+impl DynamicsPtr {
+    /// Get a pointer to the speed (three `f32`s: x, y, z, in units per second) that the object
+    /// had in the last frame, in the dynamics' base block.
+    pub fn speed_ptr(self) -> RemotePtr<[f32; 3]> {
+        // Object type, ID card, flags, end flags, five reals, then the imposed and proposed speeds.
+        RemotePtr::new(self.0 + 0x3C)
+    }
+}
+
+impl AiModelPtr {
+    /// Get a pointer to the vector of normal behaviours (comports) in this AI Model, in the
+    /// process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the vector of normal behaviours.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn normal_behaviours_ptr(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, self.0, None) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get AI Model Normal Behaviours pointer")),
+        }
+    }
+
+    /// Get a pointer to the vector of reflex behaviours in this AI Model, in the process given by
+    /// `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the vector of reflex behaviours, or `0` if the AI Model
+    ///   has none.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn reflex_behaviours_ptr(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, self.0 + 4, None) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get AI Model Reflex Behaviours pointer")),
+        }
+    }
+
+    /// Get a pointer to the DsgVar structure (i.e. the DSG variable layout) of this AI Model, in
+    /// the process given by `r2pid`.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the DsgVar, or `0` if the AI Model has no DSG variables.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn dsg_var(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, self.0 + 8, None) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context("Unable to get AI Model DsgVar pointer")),
+        }
+    }
+}
+
+/// A super-object in a running Rayman 2 process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SuperObject {
+    pub r2pid: Pid,
+    pub ptr: SuperObjectPtr,
+}
+
+impl SuperObject {
+    /// Refer to the super-object at `ptr` in the process given by `r2pid`.
+    pub fn new(r2pid: Pid, ptr: usize) -> SuperObject {
+        SuperObject{r2pid, ptr: SuperObjectPtr(ptr)}
+    }
+
+    /// Get the mind of this super-object - see [`SuperObjectPtr::mind()`](struct.SuperObjectPtr.html#method.mind).
+    pub fn mind(&self) -> Result<MindPtr> {
+        self.ptr.mind(self.r2pid)
+    }
+
+    /// Get the AI Model used by this super-object - see [`MindPtr::ai_model()`](struct.MindPtr.html#method.ai_model).
+    pub fn ai_model(&self) -> Result<AiModelPtr> {
+        self.mind()?.ai_model(self.r2pid)
+    }
+
+    /// Get the DSG memory of this super-object - see [`MindPtr::dsg_mem()`](struct.MindPtr.html#method.dsg_mem).
+    pub fn dsg_mem(&self) -> Result<DsgMemPtr> {
+        self.mind()?.dsg_mem(self.r2pid)
+    }
+
+    /// Get a pointer to the DSG variable at byte `offset` in this super-object's DSG memory.
+    ///
+    /// ## Requirements:
+    /// * You need to know the `offset` of the DSG variable you want. You can find this in Raymap
+    ///   by clicking "Print DsgVar from Mind->DsgMem" under the "Perso Behaviour" component of
+    ///   the object you're interested in.
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the desired DSG variable.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn dsg_var_ptr(&self, offset: usize) -> Result<usize> {
+        match self.dsg_mem()?.buffer(self.r2pid) {
+            Ok(ptr) => Ok(ptr + offset),
+            Err(err) => Err(err.context("Unable to get DSG Var pointer")),
+        }
+    }
+
+    /// Get a pointer to the custom bits of this super-object - see
+    /// [`SuperObjectPtr::custom_bits()`](struct.SuperObjectPtr.html#method.custom_bits).
+    pub fn custom_bits(&self) -> Result<usize> {
+        self.ptr.custom_bits(self.r2pid)
+    }
+
+    /// Read the custom bits of this super-object - see
+    /// [`CustomBit`](struct.CustomBit.html).
+    pub fn read_custom_bits(&self) -> Result<u32> {
+        RemotePtr::<u32>::new(self.custom_bits()?).read(self.r2pid)
+    }
+
+    /// Set or clear one of the custom bits of this super-object.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read or write fails.
+    ///
+    /// ## Details:
+    /// * The other bits are left as they are, but the game may change any of them again at any
+    ///   time.
+    pub fn set_custom_bit(&self, bit: CustomBit, value: bool) -> Result<()> {
+        let ptr = RemotePtr::<u32>::new(self.custom_bits()?);
+        let bits = ptr.read(self.r2pid)?;
+        let bits = match value {
+            true => bits | bit.mask(),
+            false => bits & !bit.mask(),
+        };
+        match ptr.write(self.r2pid, bits) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.context(format!("Unable to set custom bit {}", bit.bit()))),
+        }
+    }
+
+    /// Get the index of the currently-active behaviour (comport) of this super-object - see
+    /// [`MindPtr::active_normal_behaviour()`](struct.MindPtr.html#method.active_normal_behaviour).
+    pub fn active_normal_behaviour(&self) -> Result<usize> {
+        self.mind()?.active_normal_behaviour(self.r2pid)
+    }
+
+    /// Switch the currently-active behaviour (comport) of this super-object - see
+    /// [`MindPtr::set_active_normal_behaviour()`](struct.MindPtr.html#method.set_active_normal_behaviour).
+    pub fn set_active_normal_behaviour(&self, behaviour: usize) -> Result<()> {
+        self.mind()?.set_active_normal_behaviour(self.r2pid, behaviour)
+    }
+
+    /// Read the position of this super-object - see
+    /// [`SuperObjectPtr::position_ptr()`](struct.SuperObjectPtr.html#method.position_ptr).
+    pub fn position(&self) -> Result<[f32; 3]> {
+        self.ptr.position_ptr(self.r2pid)?.read(self.r2pid)
+    }
+
+    /// Move this super-object to `position` - see
+    /// [`SuperObjectPtr::position_ptr()`](struct.SuperObjectPtr.html#method.position_ptr).
+    pub fn set_position(&self, position: [f32; 3]) -> Result<()> {
+        self.ptr.position_ptr(self.r2pid)?.write(self.r2pid, position)
+    }
+
+    /// Read the rotation of this super-object - see
+    /// [`SuperObjectPtr::rotation_ptr()`](struct.SuperObjectPtr.html#method.rotation_ptr).
+    pub fn rotation(&self) -> Result<[f32; 9]> {
+        self.ptr.rotation_ptr(self.r2pid)?.read(self.r2pid)
+    }
+
+    /// Turn this super-object to `rotation` - see
+    /// [`SuperObjectPtr::rotation_ptr()`](struct.SuperObjectPtr.html#method.rotation_ptr).
+    pub fn set_rotation(&self, rotation: [f32; 9]) -> Result<()> {
+        self.ptr.rotation_ptr(self.r2pid)?.write(self.r2pid, rotation)
+    }
+
+    /// Read the whole transform of this super-object (position, rotation and scale) as a 4x4
+    /// matrix - see [`compose_matrix()`](fn.compose_matrix.html).
+    pub fn matrix(&self) -> Result<Mat4> {
+        // The position, rotation and scale are all together, so read them in one go.
+        let parts = match self.ptr.position_ptr(self.r2pid)?.cast::<[f32; 21]>().read(self.r2pid) {
+            Ok(parts) => parts,
+            Err(err) => {return Err(err.context("Unable to read global matrix"));},
+        };
+        Ok(compose_matrix(std::array::from_fn(|i| parts[i]), std::array::from_fn(|i| parts[3 + i]), std::array::from_fn(|i| parts[12 + i])))
+    }
+
+    /// Overwrite the whole transform of this super-object with the 4x4 `matrix`.
+    ///
+    /// ## Details:
+    /// * The rotation and scale aren't separated again: the top-left 3x3 part of `matrix` is
+    ///   written as the rotation, and the scale is set to the identity. The bottom row is ignored.
+    pub fn set_matrix(&self, matrix: &Mat4) -> Result<()> {
+        let mut parts = [0.0; 21];
+        parts[..3].copy_from_slice(&matrix_translation(matrix));
+        for row in 0..3 {
+            parts[3 + 3*row..6 + 3*row].copy_from_slice(&matrix[4*row..4*row + 3]);
+            parts[12 + 4*row] = 1.0;
+        }
+        match self.ptr.position_ptr(self.r2pid)?.cast::<[f32; 21]>().write(self.r2pid, parts) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.context("Unable to write global matrix")),
+        }
+    }
+
+    /// Read the speed of this super-object in the last frame - see
+    /// [`DynamicsPtr::speed_ptr()`](struct.DynamicsPtr.html#method.speed_ptr).
+    pub fn speed(&self) -> Result<[f32; 3]> {
+        self.ptr.dynamics(self.r2pid)?.speed_ptr().read(self.r2pid)
+    }
+
+    /// Get the next brother of this super-object in the hierarchy, if there is one.
+    pub fn next_brother(&self) -> Result<Option<SuperObject>> {
+        Ok(self.ptr.next_brother(self.r2pid)?.map(|ptr| SuperObject{r2pid: self.r2pid, ptr}))
+    }
+}
+
+#[cfg(test)]
+mod superobject_tests {
+    use super::*;
+
+    #[test]
+    fn can_name_custom_bits() {
+        assert_eq!(CustomBit::from_name("bit12"), Some(CustomBit::new(12).unwrap()));
+        assert_eq!(CustomBit::from_name("BIT5"), CustomBit::from_name("5"));
+        assert_eq!(CustomBit::from_name("bit32"), None);
+        assert_eq!(CustomBit::from_name("invincible"), None);
+        assert_eq!(CustomBit::new(12).unwrap().mask(), 0x1000);
+        assert!(CustomBit::new(0).unwrap().is_set(CustomBit::new(0).unwrap().mask() | 2));
+        assert!(CustomBit::new(40).is_err());
+    }
+
+    #[test]
+    fn can_compose_matrices() {
+        // A quarter turn about z, doubling x first.
+        let rotation = [0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let scale = [2.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let matrix = compose_matrix([5.0, 6.0, 7.0], rotation, scale);
+        assert_eq!(matrix, [0.0, -1.0, 0.0, 5.0, 2.0, 0.0, 0.0, 6.0, 0.0, 0.0, 1.0, 7.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(matrix_translation(&matrix), [5.0, 6.0, 7.0]);
+        assert_eq!(compose_matrix([0.0; 3], rotation, [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])[..3], rotation[..3]);
+    }
+}
@@ -0,0 +1,160 @@
+/*!
+  Stopping the game altogether (with `SIGSTOP`) and starting it again (with `SIGCONT`), so that
+  several writes can be made between two frames, e.g. moving Rayman along with his speed and
+  sector, without the engine running halfway through.
+
+  This isn't the same as the game's own pause menu (see
+  [`Rayman2Process::is_paused()`](../process/struct.Rayman2Process.html#method.is_paused)): a
+  suspended game doesn't do anything at all, including drawing or reading input, until it's
+  resumed.
+  */
+
+extern crate nix;
+
+use std::{fs,thread,time::{Duration,Instant}};
+use nix::{unistd::Pid,sys::signal::{kill,Signal}};
+use crate::{discovery::parse_stat,error::{Error,Result}};
+
+// How long to wait for every thread in the process to stop, before giving up.
+const SUSPEND_TIMEOUT: Duration = Duration::from_secs(1);
+// How long to wait between checks that the process has stopped.
+const SUSPEND_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+/// Check if every thread of the process given by `pid` is stopped (e.g. by
+/// [`suspend()`](fn.suspend.html), or by a debugger).
+pub fn is_suspended(pid: Pid) -> bool {
+    let tasks = match fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(tasks) => tasks,
+        Err(_) => {return false;},
+    };
+    let mut states = tasks.map(|task| {
+        let stat = fs::read_to_string(task.ok()?.path().join("stat")).ok()?;
+        parse_stat(&stat).map(|(_, state)| state)
+    }).peekable();
+    states.peek().is_some() && states.all(|state| matches!(state, Some('T') | Some('t')))
+}
+
+/// Stop the process given by `pid`, and wait until all its threads have stopped.
+///
+/// ## Requirements:
+/// * We need to be allowed to send signals to `pid` (e.g. by running as the same user).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the signal can't be sent, or the process doesn't stop within a second (in which case it
+///   may still stop later, so [`resume()`](fn.resume.html) it to be sure).
+pub fn suspend(pid: Pid) -> Result<()> {
+    if let Err(err) = kill(pid, Signal::SIGSTOP) {
+        return Err(Error::from(err).context(format!("Couldn't stop process {}", pid)));
+    }
+    // The signal is only delivered to each thread when it next runs.
+    let start = Instant::now();
+    while !is_suspended(pid) {
+        if start.elapsed() > SUSPEND_TIMEOUT {
+            return Err(format!("Process {} didn't stop in time", pid).into());
+        }
+        thread::sleep(SUSPEND_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Start the process given by `pid` again, after [`suspend()`](fn.suspend.html).
+///
+/// ## Requirements:
+/// * We need to be allowed to send signals to `pid` (e.g. by running as the same user).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the signal can't be sent.
+pub fn resume(pid: Pid) -> Result<()> {
+    match kill(pid, Signal::SIGCONT) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't resume process {}", pid))),
+    }
+}
+
+/// Keeps a process suspended until it's [`release()`](#method.release)d or dropped.
+///
+/// ## Details:
+/// * If the process was already stopped when the guard was made (e.g. by the user, or by another
+///   guard), it's left stopped afterwards.
+pub struct SuspendGuard {
+    pid: Pid,
+    // Whether to resume the process afterwards.
+    resume: bool,
+}
+
+impl SuspendGuard {
+    /// Suspend the process given by `pid` - see [`suspend()`](fn.suspend.html).
+    ///
+    /// ## Returns:
+    /// * On success, returns a guard which resumes the process when it's dropped.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the process can't be suspended (in which case it's resumed straight away).
+    pub fn new(pid: Pid) -> Result<SuspendGuard> {
+        if is_suspended(pid) {
+            return Ok(SuspendGuard{pid, resume: false});
+        }
+        if let Err(err) = suspend(pid) {
+            let _ = resume(pid);
+            return Err(err);
+        }
+        Ok(SuspendGuard{pid, resume: true})
+    }
+
+    /// Resume the process now, rather than when the guard is dropped.
+    ///
+    /// ## Returns:
+    /// * On success, returns `Ok(())`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the process can't be resumed.
+    pub fn release(mut self) -> Result<()> {
+        match std::mem::replace(&mut self.resume, false) {
+            true => resume(self.pid),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        if self.resume {
+            let _ = resume(self.pid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod suspend_tests {
+    use super::*;
+    use nix::{sys::wait::waitpid,unistd::{fork,ForkResult}};
+
+    #[test]
+    fn can_suspend_processes() {
+        match fork().expect("Fork failed") {
+            ForkResult::Parent { child, .. } => {
+                assert!(!is_suspended(child));
+                {
+                    let _guard = SuspendGuard::new(child).unwrap();
+                    assert!(is_suspended(child));
+                    // Already stopped, so this one leaves it that way.
+                    SuspendGuard::new(child).unwrap().release().unwrap();
+                    assert!(is_suspended(child));
+                }
+                let start = Instant::now();
+                while is_suspended(child) && start.elapsed() < SUSPEND_TIMEOUT {
+                    thread::sleep(SUSPEND_POLL_INTERVAL);
+                }
+                assert!(!is_suspended(child));
+
+                kill(child, Signal::SIGKILL).unwrap();
+                waitpid(child, None).unwrap();
+            },
+            ForkResult::Child => loop {
+                thread::sleep(Duration::from_millis(1));
+            },
+        }
+    }
+}
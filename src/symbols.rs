@@ -0,0 +1,151 @@
+/*!
+  Symbol maps for Rayman 2's executable, so that addresses can be shown with the names of the
+  engine functions they belong to (as found by the community, or exported from Ghidra).
+  */
+
+use std::{collections::{BTreeMap,HashMap},path::Path};
+use crate::{config::parse_hex,error::{Error,Result}};
+
+/// A map from addresses in Rayman 2's memory to the names of the functions (or globals) there.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolMap {
+    by_address: BTreeMap<usize, String>,
+    by_name: HashMap<String, usize>,
+}
+
+impl SymbolMap {
+    /// Create an empty symbol map.
+    pub fn new() -> SymbolMap {
+        Default::default()
+    }
+
+    /// Parse a symbol map from `text`.
+    ///
+    /// ## Details:
+    /// * Each line should contain an address (in hex, with or without a `0x` prefix) and a name,
+    ///   separated by whitespace or a comma, in either order. Quotes around either are ignored, so
+    ///   a CSV export of Ghidra's symbol table (`"Name","Location",...`) works as-is.
+    /// * Blank lines, and lines starting with `#` or `//`, are skipped.
+    ///
+    /// ## Returns:
+    /// * On success, returns the parsed `SymbolMap`.
+    /// * Returns an `Err` variant describing the first line that couldn't be
+    ///   parsed on failure.
+    pub fn parse(text: &str) -> Result<SymbolMap> {
+        let mut ret = SymbolMap::new();
+
+        for (lineno, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+
+            let fields: Vec<&str> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|field| field.trim_matches('"'))
+                .filter(|field| !field.is_empty())
+                .collect();
+            if fields.len() < 2 {
+                return Err(format!("Line {} of symbol map has too few fields", lineno + 1).into());
+            }
+
+            // Ghidra puts the name first, most other tools put the address first.
+            let (name, address) = match (parse_address(fields[0]), parse_address(fields[1])) {
+                (Some(address), _) => (fields[1], address),
+                (None, Some(address)) => (fields[0], address),
+                (None, None) => {
+                    if lineno == 0 {
+                        continue; // Probably a CSV header...
+                    }
+                    return Err(format!("Line {} of symbol map has no address", lineno + 1).into());
+                },
+            };
+            ret.insert(address, name);
+        }
+
+        Ok(ret)
+    }
+
+    /// Load a symbol map from the file at `path` - see [`parse()`](#method.parse) for the format.
+    ///
+    /// ## Returns:
+    /// * On success, returns the loaded `SymbolMap`.
+    /// * Returns an `Err` variant describing what went wrong on failure.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SymbolMap> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(text) => SymbolMap::parse(&text),
+            Err(err) => Err(Error::from(err).context(format!("Couldn't read symbol map {}", path.as_ref().display()))),
+        }
+    }
+
+    /// Add a symbol called `name` at `address`, replacing any existing symbol at that address.
+    pub fn insert(&mut self, address: usize, name: &str) {
+        if let Some(old_name) = self.by_address.insert(address, name.into()) {
+            self.by_name.remove(&old_name);
+        }
+        self.by_name.insert(name.into(), address);
+    }
+
+    /// Get the number of symbols in the map.
+    pub fn len(&self) -> usize {
+        self.by_address.len()
+    }
+
+    /// Check if the map has no symbols in it.
+    pub fn is_empty(&self) -> bool {
+        self.by_address.is_empty()
+    }
+
+    /// Look up the address of the symbol called `name`, if there is one.
+    pub fn address_of(&self, name: &str) -> Option<usize> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Find the symbol containing `address`, i.e. the closest one at or below it.
+    ///
+    /// ## Returns:
+    /// * If there is such a symbol, returns its name and the offset of `address` from it.
+    pub fn resolve(&self, address: usize) -> Option<(&str, usize)> {
+        self.by_address
+            .range(..=address)
+            .next_back()
+            .map(|(&start, name)| (name.as_str(), address - start))
+    }
+
+    /// Describe `address` for humans, as e.g. `fn_vPlayerDead+0x1c (0x4031ac)`, or just as a hex
+    /// address if there's no symbol below it.
+    pub fn annotate(&self, address: usize) -> String {
+        match self.resolve(address) {
+            Some((name, 0)) => format!("{} ({:#x})", name, address),
+            Some((name, offset)) => format!("{}+{:#x} ({:#x})", name, offset, address),
+            None => format!("{:#x}", address),
+        }
+    }
+}
+
+// Unprefixed addresses need at least six digits, so that names like `add` aren't mistaken for them.
+fn parse_address(field: &str) -> Option<usize> {
+    match field.strip_prefix("0x").or_else(|| field.strip_prefix("0X")) {
+        Some(digits) => parse_hex(digits),
+        None if field.len() >= 6 => parse_hex(field),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod symbol_tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_symbol_maps() {
+        let map = SymbolMap::parse("\"Name\",\"Location\",\"Type\"\n\
+                                    \"fn_vEngine\",\"00401000\",\"Function\"\n\
+                                    # A comment\n\
+                                    0x00401200 fn_vChooseTheGoodDesInit\n").unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.address_of("fn_vEngine"), Some(0x401000));
+        assert_eq!(map.resolve(0x401010), Some(("fn_vEngine", 0x10)));
+        assert_eq!(map.annotate(0x401200), "fn_vChooseTheGoodDesInit (0x401200)");
+        assert_eq!(map.annotate(0x400000), "0x400000");
+    }
+}
@@ -0,0 +1,174 @@
+/*!
+  A snapshot of the interesting bits of the game's state (the level, the race timers, the frame
+  rate and positions), as a [`TelemetryFrame`](struct.TelemetryFrame.html) which can be written
+  out as a line of JSON, for overlays and dashboards to read without parsing free text.
+  */
+
+extern crate nix;
+
+use std::{fmt::Write,time::{SystemTime,UNIX_EPOCH}};
+use nix::unistd::Pid;
+use crate::{memory::read_prims,races::RaceState,utils::get_current_level_name,relocation::{address,resolve_path},constants::{paths,OFF_FRAMERATE},dsg::DsgValue,error::Result};
+
+/// The state of the game at one moment.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TelemetryFrame {
+    /// When the frame was read, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The name of the current level.
+    pub level: String,
+    /// The race countdown (in seconds), if a race is running.
+    pub countdown: Option<i32>,
+    /// The race timer (in milliseconds), if a race is running.
+    pub timer: Option<f32>,
+    /// The game's frame rate.
+    pub framerate: f32,
+    /// The positions (x, y, z) of things in the level, by name. The main character's is called
+    /// `main_character`.
+    pub positions: Vec<(String, [f32; 3])>,
+    /// Any other DSG variables sampled, by name (these aren't read by
+    /// [`read()`](#method.read), but can be added afterwards).
+    pub variables: Vec<(String, DsgValue)>,
+}
+
+impl TelemetryFrame {
+    /// Read the current level, frame rate and main character's position from the Rayman 2 process
+    /// given by `r2pid`, along with the `race` timers (if one is running - see
+    /// [`RaceTimer::read()`](../races/struct.RaceTimer.html#method.read)).
+    ///
+    /// ## Details:
+    /// * The main character's position is left out if it can't be read (e.g. while the level is
+    ///   loading).
+    ///
+    /// ## Returns:
+    /// * On success, returns the `TelemetryFrame`.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the level name or frame rate can't be read.
+    pub fn read(r2pid: Pid, race: Option<RaceState>) -> Result<TelemetryFrame> {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_millis() as u64).unwrap_or(0);
+        let level = get_current_level_name(r2pid)?;
+        let framerate = match read_prims::<f32>(r2pid, address(r2pid, OFF_FRAMERATE), 1) {
+            Ok(vec) => vec[0],
+            Err(err) => {return Err(err.context("Couldn't read frame rate"));},
+        };
+
+        let mut positions = Vec::new();
+        let main_character = resolve_path(r2pid, &paths::MAIN_CHAR_POSITION)
+            .and_then(|off_position| read_prims::<f32>(r2pid, off_position, 3));
+        if let Ok(position) = main_character {
+            positions.push(("main_character".to_string(), [position[0], position[1], position[2]]));
+        }
+
+        Ok(TelemetryFrame{
+            timestamp_ms,
+            level,
+            countdown: race.map(|state| state.countdown),
+            timer: race.map(|state| state.time),
+            framerate,
+            positions,
+            variables: Vec::new(),
+        })
+    }
+
+    /// Write the frame as a JSON object on one line (without a newline at the end), e.g.:
+    ///
+    /// ```text
+    /// {"timestamp_ms":1700000000000,"level":"ly_10","countdown":87,"timer":12345.6,"framerate":60,"positions":{"main_character":[1.5,-2,30.25]}}
+    /// ```
+    ///
+    /// ## Details:
+    /// * Missing values, and numbers which JSON can't represent (infinities and NaNs), are written
+    ///   as `null`.
+    /// * If there are any `variables`, they're added at the end as an object, e.g.
+    ///   `"variables":{"countdown":87,"speed":[1.5,0,0]}`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(json, "{{\"timestamp_ms\":{},\"level\":{},\"countdown\":{},\"timer\":{},\"framerate\":{},\"positions\":{{",
+                       self.timestamp_ms,
+                       json_string(&self.level),
+                       self.countdown.map_or("null".to_string(), |countdown| countdown.to_string()),
+                       self.timer.map_or("null".to_string(), json_number),
+                       json_number(self.framerate));
+        for (i, (name, position)) in self.positions.iter().enumerate() {
+            let coords: Vec<String> = position.iter().map(|&coord| json_number(coord)).collect();
+            let _ = write!(json, "{}{}:[{}]", if i == 0 {""} else {","}, json_string(name), coords.join(","));
+        }
+        json.push('}');
+        if !self.variables.is_empty() {
+            json.push_str(",\"variables\":{");
+            for (i, (name, value)) in self.variables.iter().enumerate() {
+                let _ = write!(json, "{}{}:{}", if i == 0 {""} else {","}, json_string(name), dsg_json(value));
+            }
+            json.push('}');
+        }
+        json.push('}');
+        json
+    }
+}
+
+// Write a DSG variable's value as JSON: pointers as numbers, and anything that can't be decoded as
+// an array of its bytes.
+pub(crate) fn dsg_json(value: &DsgValue) -> String {
+    match value {
+        DsgValue::Boolean(val) => val.to_string(),
+        DsgValue::Float(val) => json_number(*val),
+        DsgValue::Vector(vector) => format!("[{}]", vector.iter().map(|&coord| json_number(coord)).collect::<Vec<String>>().join(",")),
+        DsgValue::Pointer(ptr) => ptr.to_string(),
+        DsgValue::Raw(bytes) => format!("[{}]", bytes.iter().map(|byte| byte.to_string()).collect::<Vec<String>>().join(",")),
+        // All the integers are written the same way.
+        other => other.to_string(),
+    }
+}
+
+pub(crate) fn json_number(value: f32) -> String {
+    match value.is_finite() {
+        true => value.to_string(),
+        false => "null".to_string(),
+    }
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {let _ = write!(json, "\\u{:04x}", c as u32);},
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod telemetry_tests {
+    use super::*;
+
+    #[test]
+    fn can_write_json() {
+        let frame = TelemetryFrame{
+            timestamp_ms: 1234,
+            level: "ly_\"10\"".to_string(),
+            countdown: Some(87),
+            timer: None,
+            framerate: f32::NAN,
+            positions: vec![("main_character".to_string(), [1.5, -2.0, 30.25]), ("camera".to_string(), [0.0; 3])],
+            variables: Vec::new(),
+        };
+        assert_eq!(frame.to_json(), "{\"timestamp_ms\":1234,\"level\":\"ly_\\\"10\\\"\",\"countdown\":87,\"timer\":null,\"framerate\":null,\
+                                     \"positions\":{\"main_character\":[1.5,-2,30.25],\"camera\":[0,0,0]}}");
+
+        let frame = TelemetryFrame{
+            variables: vec![("countdown".to_string(), DsgValue::Int(-3)), ("speed".to_string(), DsgValue::Vector([1.5, f32::INFINITY, 0.0])),
+                            ("flag".to_string(), DsgValue::Boolean(true)), ("list".to_string(), DsgValue::Raw(vec![1, 255]))],
+            ..TelemetryFrame::default()
+        };
+        assert_eq!(frame.to_json(), "{\"timestamp_ms\":0,\"level\":\"\",\"countdown\":null,\"timer\":null,\"framerate\":0,\"positions\":{},\
+                                     \"variables\":{\"countdown\":-3,\"speed\":[1.5,null,0],\"flag\":true,\"list\":[1,255]}}");
+    }
+}
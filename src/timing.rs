@@ -0,0 +1,163 @@
+/*!
+  Keeping in step with the game's frames, by watching the engine's frame counter, rather than
+  polling on the clock (which misses some frames and reads others twice).
+
+  [`wait_for_next_frame()`](fn.wait_for_next_frame.html) blocks until the next frame, and a
+  [`FrameTicker`](struct.FrameTicker.html) is an iterator which yields once per frame, e.g. to
+  drive a [`replay::Recorder`](../replay/struct.Recorder.html) or a ghost.
+  */
+
+extern crate nix;
+
+use std::{thread,time::{Duration,Instant}};
+use nix::unistd::Pid;
+use crate::{remote::RemotePtr,relocation::address,constants::OFF_FRAME_COUNTER,error::Result};
+
+/// How long to wait between reads of the frame counter: well under a frame, even at high frame
+/// rates.
+pub const FRAME_POLL_INTERVAL: Duration = Duration::from_micros(250);
+
+/// How long to wait for a new frame before giving up, by default. The engine keeps running frames
+/// while the game is paused, so this only runs out if the game has stopped altogether (e.g. it's
+/// frozen, or stopped in a debugger).
+pub const DEFAULT_FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The frame counter, in the process given by `r2pid`.
+fn frame_counter(r2pid: Pid) -> RemotePtr<u32> {
+    RemotePtr::new(address(r2pid, OFF_FRAME_COUNTER))
+}
+
+/// Get the number of frames the engine has run, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the frame number.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_frame_counter(r2pid: Pid) -> Result<u32> {
+    match frame_counter(r2pid).read(r2pid) {
+        Ok(frame) => Ok(frame),
+        Err(err) => Err(err.context("Couldn't read frame counter")),
+    }
+}
+
+/// Block until the engine starts a new frame, in the Rayman 2 process given by `r2pid`, waiting
+/// for up to [`DEFAULT_FRAME_TIMEOUT`](constant.DEFAULT_FRAME_TIMEOUT.html).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the new frame number.
+/// * Returns an `Err` variant describing what went wrong,
+///   if no new frame starts in time, or if the memory read fails.
+pub fn wait_for_next_frame(r2pid: Pid) -> Result<u32> {
+    let frame = get_frame_counter(r2pid)?;
+    wait_for_frame_after(r2pid, frame, FRAME_POLL_INTERVAL, DEFAULT_FRAME_TIMEOUT)
+}
+
+// Wait for the frame counter to move on from `frame`.
+fn wait_for_frame_after(r2pid: Pid, frame: u32, poll_interval: Duration, timeout: Duration) -> Result<u32> {
+    let start = Instant::now();
+    loop {
+        let now = get_frame_counter(r2pid)?;
+        if now != frame {
+            return Ok(now);
+        }
+        if start.elapsed() >= timeout {
+            return Err(format!("No new frame in {} ms", timeout.as_millis()).into());
+        }
+        thread::sleep(poll_interval);
+    }
+}
+
+/// A frame, as yielded by a [`FrameTicker`](struct.FrameTicker.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// The engine's frame number.
+    pub number: u32,
+    /// The number of frames which went by since the last one yielded, without being seen (e.g.
+    /// because the thread wasn't scheduled in time).
+    pub missed: u32,
+}
+
+impl Frame {
+    // Work out the frame `number`, given that the last one seen was `last`.
+    pub(crate) fn after(last: Option<u32>, number: u32) -> Frame {
+        let missed = match last {
+            Some(last) => number.wrapping_sub(last).saturating_sub(1),
+            None => 0,
+        };
+        Frame{number, missed}
+    }
+}
+
+/// An iterator which yields once per frame of the game, as each frame starts.
+///
+/// ## Details:
+/// * Each item is a [`Frame`](struct.Frame.html), or an `Err` variant if no new frame started
+///   before the timeout (see [`with_timeout()`](#method.with_timeout)), or if the memory read
+///   failed. The iterator never ends by itself, so stop it with `take()`, `take_while()`, etc.
+pub struct FrameTicker {
+    r2pid: Pid,
+    last: Option<u32>,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl FrameTicker {
+    /// Start ticking with the frames of the Rayman 2 process given by `r2pid`. The first item is
+    /// the next frame to start after this.
+    pub fn new(r2pid: Pid) -> FrameTicker {
+        FrameTicker{r2pid, last: None, poll_interval: FRAME_POLL_INTERVAL, timeout: DEFAULT_FRAME_TIMEOUT}
+    }
+
+    /// Set how long to wait for each frame before giving up. This is
+    /// [`DEFAULT_FRAME_TIMEOUT`](constant.DEFAULT_FRAME_TIMEOUT.html) by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> FrameTicker {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait between reads of the frame counter. This is
+    /// [`FRAME_POLL_INTERVAL`](constant.FRAME_POLL_INTERVAL.html) by default.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> FrameTicker {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Block until the next frame - the same as `next()`, but without the `Option`.
+    pub fn tick(&mut self) -> Result<Frame> {
+        let from = match self.last {
+            Some(last) => last,
+            None => get_frame_counter(self.r2pid)?,
+        };
+        let number = wait_for_frame_after(self.r2pid, from, self.poll_interval, self.timeout)?;
+        let frame = Frame::after(self.last, number);
+        self.last = Some(number);
+        Ok(frame)
+    }
+}
+
+impl Iterator for FrameTicker {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Result<Frame>> {
+        Some(self.tick())
+    }
+}
+
+#[cfg(test)]
+mod timing_tests {
+    use super::*;
+
+    #[test]
+    fn can_count_missed_frames() {
+        assert_eq!(Frame::after(None, 100), Frame{number: 100, missed: 0});
+        assert_eq!(Frame::after(Some(100), 101), Frame{number: 101, missed: 0});
+        assert_eq!(Frame::after(Some(100), 104), Frame{number: 104, missed: 3});
+        assert_eq!(Frame::after(Some(u32::MAX), 1), Frame{number: 1, missed: 1});
+    }
+}
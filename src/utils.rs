@@ -5,78 +5,30 @@
 
 extern crate nix;
 
-use std::{process::Command,collections::HashMap};
-use nix::{libc::pid_t,unistd::Pid};
-use crate::{memory::{read_prims,read_string,get_pointer_path},constants::*};
-
-fn find_rayman2_pidof() -> Result<Pid,&'static str> {
-    if let Ok(out) = Command::new("pidof").arg("Rayman2.exe").output() {
-        if let Ok(strout) = String::from_utf8(out.stdout) {
-            if let Some(firstline) = strout.lines().next() {
-                if let Ok(num) = firstline.parse::<pid_t>() {
-                    Ok(Pid::from_raw(num))
-                } else {
-                    Err("No numerical output from pidof")
-                }
-            } else {
-                Err("Got no output from pidof")
-            }
-        } else {
-            Err("Failed to parse output of pidof")
-        }
-    } else {
-        Err("Failed to run pidof")
-    }
-}
-
-fn find_rayman2_pgrep() -> Result<Pid,&'static str> {
-    if let Ok(out) = Command::new("pgrep").arg("Rayman2.exe").output() {
-        if let Ok(strout) = String::from_utf8(out.stdout) {
-            if let Some(firstline) = strout.lines().next() {
-                if let Ok(num) = firstline.parse::<pid_t>() {
-                    Ok(Pid::from_raw(num))
-                } else {
-                    Err("No numerical output from pgrep")
-                }
-            } else {
-                Err("Got no output from pgrep")
-            }
-        } else {
-            Err("Failed to parse output of pgrep")
-        }
-    } else {
-        Err("Failed to run pgrep")
-    }
-}
+use std::{process::Command,collections::HashMap,thread::{self,JoinHandle}};
+use nix::unistd::Pid;
+use crate::{memory::{read_prims,write_prims,read_string,read_batch,decode_string,get_pointer_path},remote::RemotePtr,superobject::{SuperObject,SuperObjectPtr,CustomBit,Mat4},dsg::{DsgValue,DsgVarEntry,read_dsg_mem_layout},search::{NamePattern,SuperObjectMatch},geometry,openspace::{self,SuperObjectHeader},aimodel::{AiModel,Behaviour,BehaviourId,BehaviourNames,get_ai_model_name},constants::*,discovery::{find_attach_by_name,RAYMAN2_NAMES},relocation::{self,address},error::{Error,Result}};
 
 /// Find the PID of the currently-running `Rayman2.exe` process.
 ///
 /// ## Requirements:
-/// * Rayman 2 needs to be running, and the filename used to launch it needs to be `Rayman2.exe`.
-/// * Either `pidof` or `pgrep` needs to be in the `PATH` of this program's environment.
-/// (Preferably the latter.)
+/// * Rayman 2 needs to be running, and the filename used to launch it needs to be `Rayman2.exe`
+///   (in any case). To look for other names, use
+///   [`find_attach_by_name()`](../discovery/fn.find_attach_by_name.html).
 ///
 /// ## Returns:
 /// * On success (i.e. if the PID was found), returns a
-/// [nix::unistd::Pid](../../nix/unistd/struct.Pid.html) corresponding to the running Rayman 2
-/// process.
-/// * Returns an `Err` variant with a text description of what went wrong on failure.
-pub fn find_attach_rayman2() -> Result<Pid,String> {
-    match find_rayman2_pidof() {
-        Ok(pid) => Ok(pid),
-        Err(err) => {
-            println!("Couldn't find Rayman 2 with pidof - {}", err);
-            print!("Trying pgrep instead... ");
-            
-            match find_rayman2_pgrep() {
-                Ok(pid) => {
-                    println!("OK!");
-                    Ok(pid)
-                },
-                Err(err) => Err(err.into()),
-            }
-        },
-    }
+///   [nix::unistd::Pid](../../nix/unistd/struct.Pid.html) corresponding to the running Rayman 2
+///   process.
+/// * Returns an `Err` variant describing what went wrong on failure.
+///
+/// ## Details:
+/// * Where the game's executable is loaded is looked up again (see
+///   [`relocation::detect()`](../relocation/fn.detect.html)), in case the game was restarted.
+pub fn find_attach_rayman2() -> Result<Pid> {
+    let pid = find_attach_by_name(RAYMAN2_NAMES)?;
+    relocation::detect(pid, RAYMAN2_NAMES);
+    Ok(pid)
 }
 
 /// Get the environment of the process given by `r2pid`, as a `HashMap`.
@@ -86,20 +38,20 @@ pub fn find_attach_rayman2() -> Result<Pid,String> {
 ///
 /// ## Returns:
 /// * On success, returns a
-/// [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html)
-/// with keys corresponding to environment variables and values equal to their values.
-/// * Returns an `Err` variant with a text description of what went wrong on failure.
-pub fn get_environment(r2pid:Pid) -> Result<HashMap<String,String>, String> {
+///   [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html)
+///   with keys corresponding to environment variables and values equal to their values.
+/// * Returns an `Err` variant describing what went wrong on failure.
+pub fn get_environment(r2pid:Pid) -> Result<HashMap<String,String>> {
     let env_buf = match std::fs::read(format!("/proc/{}/environ", r2pid)) {
         Ok(buf) => buf,
         Err(err) => {
-            return Err(format!("Unable to open Rayman 2's environment file: {:?}", err));
+            return Err(Error::from(err).context("Unable to open Rayman 2's environment file"));
         },
     };
 
     let mut ret = HashMap::new();
     let mut buf_iter = env_buf.into_iter().peekable();
-    while buf_iter.peek() != None {
+    while buf_iter.peek().is_some() {
         let key = match String::from_utf8(
             buf_iter.by_ref()
             .take_while(|&x| x != b'=') // Everything before the first equals sign is the key.
@@ -122,29 +74,82 @@ pub fn get_environment(r2pid:Pid) -> Result<HashMap<String,String>, String> {
 }
 
 /// Send some fake X11 input to the display given by `disp`, using the `xte` program from
-/// [`xautomation`](https://www.hoopajoo.net/projects/xautomation.html). This is used to implement
-/// auto-strafing when the down button is pressed in FPS mode.
+/// [`xautomation`](https://www.hoopajoo.net/projects/xautomation.html). This can be used to hold the
+/// strafe button in FPS mode - see [`fpsmode::StrafeInput`](../fpsmode/enum.StrafeInput.html).
+///
+/// ## Details:
+/// * This is only available with the `xte` feature. Otherwise, use an
+///   [`input::VirtualPad`](../input/struct.VirtualPad.html), which doesn't need X11.
 ///
 /// ## Requirements:
 /// * Rayman 2 should be running on the X display given in `disp`.
 /// * `xte` needs to be in the `PATH` of this program's environment.
 /// * `command` should be a valid option for `xte` - see 
-/// [its man page](https://linux.die.net/man/1/xte) for details.
+///   [its man page](https://linux.die.net/man/1/xte) for details.
 ///
 /// ## Returns:
 /// * On success, returns `Ok(())`.
-/// * Returns an `Err` variant with a text description of what went wrong on failure.
-pub fn send_input(disp: &str, command: &str) -> Result<(), String> {
+/// * Returns an `Err` variant describing what went wrong on failure.
+#[cfg(feature = "xte")]
+pub fn send_input(disp: &str, command: &str) -> Result<()> {
     if let Err(err) = Command::new("xte")
-        .args(&["-x", &disp, command])
+        .args(["-x", disp, command])
             .spawn() {
-                Err(format!("Couldn't send input to Rayman 2 with xte: {:?}", err))
+                Err(Error::from(err).context("Couldn't send input to Rayman 2 with xte"))
             }
     else {
         Ok(())
     }
 }
 
+/// Play the audio file at `path` as a short cue (e.g. a beep at the right moment to jump), through
+/// PipeWire using `pw-play`, or through PulseAudio using `paplay` if that doesn't work. This blocks
+/// until the cue has finished - see [`play_audio_cue_in_background()`](fn.play_audio_cue_in_background.html)
+/// for a version which doesn't.
+///
+/// ## Requirements:
+/// * Either `pw-play` or `paplay` needs to be in the `PATH` of this program's environment.
+///   (Preferably the former.)
+/// * `path` should be a sound file that the player understands (e.g. a WAV or an OGG file).
+///
+/// ## Returns:
+/// * On success (i.e. if one of the players played the cue), returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if neither player could be started, or both exited with an error (e.g. because there's no
+///   sound server running).
+pub fn play_audio_cue(path: &str) -> Result<()> {
+    let pw_err = match play_with("pw-play", path) {
+        Ok(()) => {return Ok(());},
+        Err(err) => err,
+    };
+    match play_with("paplay", path) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(err.context(format!("Couldn't play audio cue {} ({}), or with paplay", path, pw_err))),
+    }
+}
+
+// Play `path` with `player`, waiting for it to finish, so that it doesn't linger as a zombie.
+fn play_with(player: &str, path: &str) -> Result<()> {
+    match Command::new(player).arg(path).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} failed: {}", player, status).into()),
+        Err(err) => Err(Error::from(err).context(format!("Couldn't run {}", player))),
+    }
+}
+
+/// Play the audio file at `path` as a short cue on another thread, so that the caller (e.g. a
+/// watcher reacting to the game) doesn't have to wait for it - see
+/// [`play_audio_cue()`](fn.play_audio_cue.html).
+///
+/// ## Returns:
+/// * Returns a handle to the thread playing the cue, which gives what
+///   [`play_audio_cue()`](fn.play_audio_cue.html) returned when it's joined. If it's dropped
+///   instead, the cue still plays, and the player is still waited for.
+pub fn play_audio_cue_in_background(path: &str) -> JoinHandle<Result<()>> {
+    let path = path.to_string();
+    thread::spawn(move || play_audio_cue(&path))
+}
+
 /// Read the name of the level currently open in the Rayman 2 process given by `r2pid`.
 ///
 /// ## Requirements:
@@ -152,12 +157,185 @@ pub fn send_input(disp: &str, command: &str) -> Result<(), String> {
 ///
 /// ## Returns:
 /// * On success, returns the level name as a `String`.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_current_level_name(r2pid:Pid) -> Result<String,String> {
-    match read_string(r2pid, OFF_LEVEL_NAME, 16) {
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_current_level_name(r2pid:Pid) -> Result<String> {
+    match read_string(r2pid, address(r2pid, OFF_LEVEL_NAME), 16) {
         Ok(name) => Ok(name),
-        Err(err) => Err(format!("Couldn't read level name: {:?}", err)),
+        Err(err) => Err(err.context("Couldn't read level name")),
+    }
+}
+
+/// The mode the engine is in, as found at `OFF_ENGINE_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngineMode {
+    Invalid,
+    Initialise,
+    Deinitialise,
+    InitialiseGameLoop,
+    DeinitialiseGameLoop,
+    EnterLevel,
+    ChangeLevel,
+    DeadLoop,
+    PlayerDead,
+    Playing,
+    StoppingProgram,
+    Unknown(u8),
+}
+
+impl From<u8> for EngineMode {
+    fn from(mode: u8) -> EngineMode {
+        match mode {
+            0 => EngineMode::Invalid,
+            1 => EngineMode::Initialise,
+            2 => EngineMode::Deinitialise,
+            3 => EngineMode::InitialiseGameLoop,
+            4 => EngineMode::DeinitialiseGameLoop,
+            5 => EngineMode::EnterLevel,
+            6 => EngineMode::ChangeLevel,
+            7 => EngineMode::DeadLoop,
+            8 => EngineMode::PlayerDead,
+            9 => EngineMode::Playing,
+            10 => EngineMode::StoppingProgram,
+            other => EngineMode::Unknown(other),
+        }
+    }
+}
+
+impl EngineMode {
+    /// Check if a level is up and running, i.e. if it's safe to read things like the hierarchy
+    /// and DSG variables. In other modes (e.g. while a level is loading), they may be garbage.
+    pub fn is_playing(self) -> bool {
+        self == EngineMode::Playing
+    }
+}
+
+/// Read the mode the engine is in, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the `EngineMode`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_engine_mode(r2pid: Pid) -> Result<EngineMode> {
+    match RemotePtr::<u8>::new(address(r2pid, OFF_ENGINE_MODE)).read(r2pid) {
+        Ok(mode) => Ok(EngineMode::from(mode)),
+        Err(err) => Err(err.context("Couldn't read engine mode")),
+    }
+}
+
+/// Check if the game is paused (e.g. in the pause menu), in the Rayman 2 process given by
+/// `r2pid`.
+///
+/// ## Details:
+/// * This is a heuristic: the game counts as paused if the engine is in
+///   [`EngineMode::Playing`](enum.EngineMode.html) but no game time passed in the last frame.
+///
+/// ## Returns:
+/// * On success, returns `true` if the game is paused.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn is_paused(r2pid: Pid) -> Result<bool> {
+    if !get_engine_mode(r2pid)?.is_playing() {
+        return Ok(false);
+    }
+    Ok(get_delta_t(r2pid)? == 0)
+}
+
+/// Get the length of the last frame, in milliseconds, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Returns:
+/// * On success, returns the frame time (`0` if the game is paused).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_delta_t(r2pid: Pid) -> Result<u32> {
+    match RemotePtr::<u32>::new(address(r2pid, OFF_DELTA_T)).read(r2pid) {
+        Ok(delta_t) => Ok(delta_t),
+        Err(err) => Err(err.context("Couldn't read frame time")),
+    }
+}
+
+/// Get the main character (i.e. Rayman) in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns a handle on the main character's super-object, which can be used to get
+///   its [`position()`](../superobject/struct.SuperObject.html#method.position),
+///   [`speed()`](../superobject/struct.SuperObject.html#method.speed), etc.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails or there is no main character (e.g. in a menu).
+pub fn get_main_character(r2pid: Pid) -> Result<SuperObject> {
+    match RemotePtr::<RemotePtr<()>>::new(address(r2pid, OFF_MAIN_CHAR)).follow(r2pid) {
+        Ok(ptr) if ptr.is_null() => Err("There is no main character".into()),
+        Ok(ptr) => Ok(SuperObject::new(r2pid, ptr.addr())),
+        Err(err) => Err(err.context("Couldn't get main character")),
+    }
+}
+
+// Find Rayman's hit points, via the pointer at `OFF_HEALTH_PTR_1`. The maximum follows them.
+fn get_health_ptr(r2pid: Pid) -> Result<RemotePtr<u8>> {
+    match RemotePtr::<RemotePtr<()>>::new(address(r2pid, OFF_HEALTH_PTR_1)).follow(r2pid) {
+        Ok(ptr) if ptr.is_null() => Err("Health pointer is null (is a level loaded?)".into()),
+        Ok(ptr) => Ok(ptr.field(0x245)),
+        Err(err) => Err(err.context("Couldn't follow health pointer")),
+    }
+}
+
+/// Read Rayman's current health, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the number of hit points Rayman has.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_health(r2pid: Pid) -> Result<u8> {
+    match get_health_ptr(r2pid)?.read(r2pid) {
+        Ok(health) => Ok(health),
+        Err(err) => Err(err.context("Couldn't read health")),
+    }
+}
+
+/// Read the most health Rayman can currently have, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns the maximum number of hit points.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_max_health(r2pid: Pid) -> Result<u8> {
+    match get_health_ptr(r2pid)?.offset(1).read(r2pid) {
+        Ok(max_health) => Ok(max_health),
+        Err(err) => Err(err.context("Couldn't read maximum health")),
+    }
+}
+
+/// Set Rayman's health, in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * `value` can't be more than [`get_max_health()`](fn.get_max_health.html).
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if `value` is out of range or the memory access fails.
+pub fn set_health(r2pid: Pid, value: u8) -> Result<()> {
+    let off_health = get_health_ptr(r2pid)?;
+    let max_health = get_max_health(r2pid)?;
+    if value > max_health {
+        return Err(format!("Health {} is more than the maximum ({})", value, max_health).into());
+    }
+    match off_health.write(r2pid, value) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context("Couldn't write health")),
     }
 }
 
@@ -170,12 +348,12 @@ pub fn get_current_level_name(r2pid:Pid) -> Result<String,String> {
 ///
 /// ## Returns:
 /// * On success, returns the index of the given family.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_family_index(r2pid: Pid, off_family: usize) -> Result<usize, String> {
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_family_index(r2pid: Pid, off_family: usize) -> Result<usize> {
     match get_pointer_path(r2pid, off_family + 0xC, None) {
         Ok(ptr) => Ok(ptr),
-        Err(err) => Err(format!("Couldn't get family index: {:?}", err))
+        Err(err) => Err(err.context("Couldn't get family index"))
     }
 }
 
@@ -188,25 +366,25 @@ pub fn get_family_index(r2pid: Pid, off_family: usize) -> Result<usize, String>
 ///
 /// ## Returns:
 /// * On success, returns a
-/// [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
+///   [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
 ///     * The keys are pointers to the PO meshes in the given family.
 ///     * The values are `Vec<f32>`s containing all the vertices of the meshes as of when they were
-///     read from memory. Of course, each group of three floats in the vector is a single vertex.
+///       read from memory. Of course, each group of three floats in the vector is a single vertex.
 ///     * Note that you can skip certain POs in the family by specifying their `indices`.
-///     Alternatively, you can choose to keep only certain POs by specifying `keep_instead = true`.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_family_po_vert_offsets(r2pid:Pid, offset_family:usize, keep_instead:bool, indices:&Vec<usize>) -> Result<HashMap<usize,Vec<f32>>, String> {
+///       Alternatively, you can choose to keep only certain POs by specifying `keep_instead = true`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_family_po_vert_offsets(r2pid:Pid, offset_family:usize, keep_instead:bool, indices:&[usize]) -> Result<HashMap<usize,Vec<f32>>> {
     let mut ret = HashMap::new();
 
     let off_default_objects_table = match get_pointer_path(r2pid, offset_family + 0x1C, None) {
         Ok(ptr) => ptr,
-        Err(err) => {return Err(format!("Couldn't get default object table offset: {:?}", err));},
+        Err(err) => {return Err(err.context("Couldn't get default object table offset"));},
     };
 
     let (first_entry, num_entries) = match read_prims::<u32>(r2pid, off_default_objects_table+4, 3) {
         Ok(vec) => (vec[0] as usize, vec[2] as usize), // Want the pointers at off_default_objects_table + 0x4 and + 0xC
-        Err(err) => {return Err(format!("Couldn't find address or number of entries in object table: {:?}", err));},
+        Err(err) => {return Err(err.context("Couldn't find address or number of entries in object table"));},
     };
 
     for i in 0..num_entries {
@@ -242,17 +420,17 @@ pub fn get_family_po_vert_offsets(r2pid:Pid, offset_family:usize, keep_instead:b
 
             /*let num_sub_blocks = match read_prims::<i16>(r2pid, off_first_mesh_num_sub_blocks, 1) {
                 Ok(vec) => vec[0],
-                Err(err) => {return Err(format!("Couldn't get number of subblocks: {:?}", err));}
+                Err(err) => {return Err(err.context("Couldn't get number of subblocks"));}
             };*/
             let num_verts = match read_prims::<i16>(r2pid, off_first_mesh_num_vertices, 1) {
                 Ok(vec) => vec[0],
-                Err(err) => {return Err(format!("Couldn't get number of vertices: {:?}", err));}
+                Err(err) => {return Err(err.context("Couldn't get number of vertices"));}
             };
 
             // Each vertex is naturally three floats
             let all_verts = match read_prims::<f32>(r2pid, off_verts, 3 * num_verts as usize) {
                 Ok(vec) => vec,
-                Err(err) => {return Err(format!("Couldn't get vertex positions: {:?}", err));}
+                Err(err) => {return Err(err.context("Couldn't get vertex positions"));}
             };
             ret.insert(off_verts, all_verts); // Put vectors in the HashMap - it'll be more efficient...
         }
@@ -271,33 +449,124 @@ pub fn get_family_po_vert_offsets(r2pid:Pid, offset_family:usize, keep_instead:b
 ///
 /// ## Returns:
 /// * A `Vec<String>` with `len()` equal to `num_names`. This is guaranteed, but it may contain
-/// blanks or repeats if the function input was not sane.
+///   blanks or repeats if the function input was not sane.
 pub fn read_object_names_table(r2pid: Pid, off_names_first: usize, num_names: usize) -> Vec<String> {
+    // Walk the list first, reading each entry's next pointer and name pointer together...
     let mut cur_offset = off_names_first;
-    let mut ret = Vec::with_capacity(num_names);
+    let mut off_names = Vec::with_capacity(num_names);
 
     for _j in 0..num_names {
-        let res_off_names_next = get_pointer_path(r2pid, cur_offset, None);
-
-        if let Ok(off_name) = get_pointer_path(r2pid, cur_offset + 0xC, None) {
-            ret.push(
-                match read_string(r2pid, off_name, 64) {
-                    Ok(name) => name,
-                    Err(_) => "".into(),
-                });
-        } else {
-            // I'm guessing this can also fail with impunity...
-            ret.push("".into());
+        // Each entry has the next one at 0x0, and its name at 0xC.
+        let entry = read_prims::<u32>(r2pid, cur_offset, 4).unwrap_or_default();
+        // I'm guessing this can also fail with impunity...
+        off_names.push(entry.get(3).map(|&off_name| off_name as usize));
+
+        if let Some(&off_names_next) = entry.first() {
+            if off_names_next > 0 {
+                cur_offset = off_names_next as usize;
+            }
+        }
+    }
+
+    // ...and then read all the names in one go.
+    let regions: Vec<(usize, usize)> = off_names.iter()
+        .map(|off_name| match off_name {
+            Some(off_name) => (*off_name, 64),
+            None => (0, 0),
+        })
+        .collect();
+    match read_batch(r2pid, &regions) {
+        Ok(names) => names.into_iter()
+            .map(|bytes| decode_string(bytes).unwrap_or_default())
+            .collect(),
+        Err(_) => vec![String::new(); num_names],
+    }
+}
+
+/// One of the three object-type tables, as read by [`read_object_types()`](fn.read_object_types.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectTable {
+    /// The family names.
+    Families,
+    /// The AI Model names.
+    AiModels,
+    /// The super-object names.
+    SuperObjectNames,
+}
+
+impl ObjectTable {
+    // Where the table's header (first entry, last entry, number of entries) is.
+    fn header(self, r2pid: Pid) -> usize {
+        address(r2pid, OFF_OBJECT_TYPES) + 12 * match self {
+            ObjectTable::Families => 0,
+            ObjectTable::AiModels => 1,
+            ObjectTable::SuperObjectNames => 2,
+        }
+    }
+}
+
+/// An iterator over the names in one of the object-type tables, which reads each entry only when
+/// it's needed - see [`object_names()`](fn.object_names.html).
+///
+/// ## Details:
+/// * As with [`read_object_names_table()`](fn.read_object_names_table.html), an entry which can't
+///   be read gives a blank name, rather than stopping the iterator.
+#[derive(Debug, Clone)]
+pub struct ObjectNames {
+    r2pid: Pid,
+    cur_offset: usize,
+    remaining: usize,
+}
+
+impl Iterator for ObjectNames {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
 
-        if let Ok(off_names_next) = res_off_names_next {
+        // Each entry has the next one at 0x0, and its name at 0xC.
+        let entry = read_prims::<u32>(self.r2pid, self.cur_offset, 4).unwrap_or_default();
+        if let Some(&off_names_next) = entry.first() {
             if off_names_next > 0 {
-                cur_offset = off_names_next;
+                self.cur_offset = off_names_next as usize;
             }
         }
+        Some(match entry.get(3) {
+            Some(&off_name) => read_prims::<u8>(self.r2pid, off_name as usize, 64)
+                .and_then(decode_string)
+                .unwrap_or_default(),
+            None => String::new(),
+        })
     }
 
-    ret
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for ObjectNames {}
+
+/// Go through the names in one of the object-type `table`s of the Rayman 2 process given by
+/// `r2pid`, reading them one at a time. This is quicker than
+/// [`read_object_types()`](fn.read_object_types.html) if only the first few names are needed.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+///
+/// ## Returns:
+/// * On success, returns an [`ObjectNames`](struct.ObjectNames.html) iterator, which gives the
+///   names in index order.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the table's header can't be read.
+pub fn object_names(r2pid: Pid, table: ObjectTable) -> Result<ObjectNames> {
+    match read_prims::<u32>(r2pid, table.header(r2pid), 3) {
+        Ok(ref vec) if vec.len() == 3 => Ok(ObjectNames{r2pid, cur_offset: vec[0] as usize, remaining: vec[2] as usize}),
+        Ok(_) => Err(format!("Unable to read {:?} table header", table).into()),
+        Err(err) => Err(err.context(format!("Unable to read {:?} table header", table))),
+    }
 }
 
 /// Read all the object types in the engine hierarchy of Rayman 2 process given by `r2pid`.
@@ -307,20 +576,21 @@ pub fn read_object_names_table(r2pid: Pid, off_names_first: usize, num_names: us
 ///
 /// ## Returns:
 /// * On success, returns an array of three `Vec<String>`s. The first one contains the family
-/// names, the second one contains the AI Model names, and the third contains the super-object
-/// names.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn read_object_types(r2pid: Pid) -> Result<[Vec<String>; 3], String> {
+///   names, the second one contains the AI Model names, and the third contains the super-object
+///   names.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn read_object_types(r2pid: Pid) -> Result<[Vec<String>; 3]> {
     let mut iter = ["family", "AI Model", "super-object"]
         .iter()
         .enumerate()
-        .map(|(i, desc)| {
-            let off_names_header = OFF_OBJECT_TYPES + i*12;
+        .map(|(i, desc)| -> Result<Vec<String>> {
+            let off_names_header = address(r2pid, OFF_OBJECT_TYPES) + i*12;
             let (off_names_first, _off_names_last, num_names) = 
                 match read_prims::<u32>(r2pid, off_names_header, 3) {
-                    Ok(vec) => (vec[0] as usize, vec[1] as usize, vec[2] as usize),
-                    Err(err) => {return Err(format!("Unable to read {} names: {:?}", desc, err));},
+                    Ok(ref vec) if vec.len() == 3 => (vec[0] as usize, vec[1] as usize, vec[2] as usize),
+                    Ok(_) => {return Err(format!("Unable to read {} names: short read of the table header", desc).into());},
+                    Err(err) => {return Err(err.context(format!("Unable to read {} names", desc)));},
                 };
 
             Ok(read_object_names_table(r2pid, off_names_first, num_names))
@@ -336,109 +606,373 @@ pub fn read_object_types(r2pid: Pid) -> Result<[Vec<String>; 3], String> {
     ])
 }
 
+/// The parts of the engine hierarchy which the walkers (e.g.
+/// [`get_active_super_object_names()`](fn.get_active_super_object_names.html)) can start from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum World {
+    /// The dynamic world, with all the objects currently being run.
+    #[default]
+    ActiveDynamic,
+    /// The inactive dynamic world, with the objects which have been taken out of the level for
+    /// now (e.g. because they're too far away, or haven't been spawned yet).
+    InactiveDynamic,
+    /// The father sector, i.e. the static world, whose children are the sectors of the level.
+    Static,
+}
+
+impl World {
+    /// Get the pointer to this part of the hierarchy, in Rayman 2's memory (one of the `OFF_*`
+    /// constants - see [`relocation::address()`](../relocation/fn.address.html) for where it
+    /// really is in a particular process).
+    pub fn offset(self) -> usize {
+        match self {
+            World::ActiveDynamic => OFF_DYNAM_WORLD,
+            World::InactiveDynamic => OFF_INACTIVE_DYNAM_WORLD,
+            World::Static => OFF_FATHER_SECTOR,
+        }
+    }
+
+    /// Get the super-object at the root of this part of the hierarchy, in the Rayman 2 process
+    /// given by `r2pid`.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the super-object.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn root(self, r2pid: Pid) -> Result<usize> {
+        match get_pointer_path(r2pid, address(r2pid, self.offset()), None) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context(format!("Couldn't get super-object for {}", self))),
+        }
+    }
+
+    /// Get the first child of the super-object at the root of this part of the hierarchy, in the
+    /// Rayman 2 process given by `r2pid` - this is where the walkers start by default.
+    ///
+    /// ## Requirements:
+    /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+    ///
+    /// ## Returns:
+    /// * On success, returns a pointer to the first child.
+    /// * Returns an `Err` variant describing what went wrong,
+    ///   if the memory read fails.
+    pub fn first_super_object(self, r2pid: Pid) -> Result<usize> {
+        let path = match self {
+            World::ActiveDynamic => paths::DYNAMIC_WORLD_FIRST,
+            World::InactiveDynamic => paths::INACTIVE_DYNAMIC_WORLD_FIRST,
+            World::Static => paths::FATHER_SECTOR_FIRST,
+        };
+        match relocation::resolve_path(r2pid, &path) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => Err(err.context(format!("Couldn't get super-object for {}", self))),
+        }
+    }
+}
+
+impl std::fmt::Display for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            World::ActiveDynamic => "dynamic world",
+            World::InactiveDynamic => "inactive dynamic world",
+            World::Static => "father sector",
+        })
+    }
+}
+
 /// Get the names and memory locations of all active super-objects in the engine hierarchy of the
-/// Rayman 2 process given by `r2pid`, starting from a given `super_object` pointer (or the dynamic
-/// world itself if that is set to 0).
+/// Rayman 2 process given by `r2pid`, starting from a given `super_object` pointer (or the start of
+/// the given `world` if that is set to 0).
 ///
 /// ## Requirements:
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 /// * You need to know the list of super-object names in the hierarchy and pass it via the argument
-/// `object_names`. This list can be obtained with
-/// [`read_object_types()`](fn.read_object_types.html)`.unwrap()[2]`.
+///   `object_names`. This list can be obtained with
+///   [`read_object_types()`](fn.read_object_types.html)`.unwrap()[2]`.
 ///
 /// ## Returns:
 /// * On success, returns a
-/// [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
+///   [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
 ///     * The keys are the names of the super-objects.
 ///     * The values are pointers to the super-objects in Rayman 2's memory.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_active_super_object_names(r2pid: Pid, object_names: &Vec<String>, super_object: usize) -> Result<HashMap<String,usize>, String> {
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_active_super_object_names(r2pid: Pid, object_names: &[String], world: World, super_object: usize) -> Result<HashMap<String,usize>> {
     let mut ret = HashMap::new();
-    let super_object = match super_object {
-        0 => {
-            let off_dynam_world = 0x500FD0;
-            match get_pointer_path(r2pid, off_dynam_world, Some(&vec![8])) {
-                Ok(ptr) => ptr,
-                Err(err) => {return Err(format!("Couldn't get super-object for dynamic world: {:?}", err));},
-            }
-        },
-        val => val,
+    let brothers = read_brothers(r2pid, start_super_object(r2pid, world, super_object)?)?;
+    let types = read_type_indices(r2pid, &brothers)?;
+
+    for (brother, type_indices) in brothers.iter().zip(types) {
+        let name_index = match type_indices {
+            Some([_, _, index]) => index,
+            None => {break;},
+        };
+        let name = match object_names.get(name_index) {
+            Some(namestr) => namestr.to_string(),
+            None => format!("unknown_{}", brother.ptr),
+        };
+        ret.insert(name, brother.ptr);
+    }
+
+    Ok(ret)
+}
+
+/// An active instance of a family, as found by
+/// [`get_active_family_instances()`](fn.get_active_family_instances.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FamilyInstance {
+    /// The name of the super-object.
+    pub name: String,
+    /// Pointer to the super-object in Rayman 2's memory.
+    pub super_object: usize,
+    /// The position of the super-object (x, y, z), or `None` if it has no global matrix.
+    pub position: Option<[f32; 3]>,
+}
+
+/// Get all the active instances of the family called `family` (e.g. all the cages, or all the
+/// rings) in the engine hierarchy of the Rayman 2 process given by `r2pid`, starting from a given
+/// `super_object` pointer (or the start of the given `world` if that is set to 0).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to pass the tables returned by [`read_object_types()`](fn.read_object_types.html),
+///   to look up the family and the names of its instances.
+///
+/// ## Returns:
+/// * On success, returns a `Vec` of the [`FamilyInstance`](struct.FamilyInstance.html)s, in the
+///   order they appear in the hierarchy.
+/// * Returns an `Err` variant describing what went wrong,
+///   if there is no family called `family`, or if the memory read fails.
+pub fn get_active_family_instances(r2pid: Pid, object_types: &[Vec<String>; 3], family: &str, world: World, super_object: usize) -> Result<Vec<FamilyInstance>> {
+    let family_index = match object_types[0].iter().position(|name| name == family) {
+        Some(index) => index,
+        None => {return Err(format!("No family called {}", family).into());},
     };
+    let brothers = read_brothers(r2pid, start_super_object(r2pid, world, super_object)?)?;
+    let types = read_type_indices(r2pid, &brothers)?;
 
-    let mut next_brother = super_object;
+    let instances: Vec<(&SuperObjectHeader, usize)> = brothers.iter().zip(types)
+        .map_while(|(brother, type_indices)| type_indices.map(|indices| (brother, indices)))
+        .filter(|(_, [family, _, _])| *family == family_index)
+        .map(|(brother, [_, _, name_index])| (brother, name_index))
+        .collect();
 
-    loop {
-        if next_brother != 0 {
-            let name_index = match get_pointer_path(r2pid, next_brother + 4, Some(&vec![4, 8])) {
-                Ok(ptr) => ptr,
-                Err(_) => {break;},
-            };
-            let name = match object_names.get(name_index) {
+    // The global matrix has a type (u32) and then the position.
+    let regions: Vec<(usize, usize)> = instances.iter()
+        .map(|(brother, _)| match brother.matrix {
+            0 => (0, 0),
+            matrix => (matrix + 4, 12),
+        })
+        .collect();
+    let positions = read_batch(r2pid, &regions)?;
+
+    Ok(instances.into_iter().zip(positions)
+        .map(|((brother, name_index), bytes)| FamilyInstance{
+            name: match object_types[2].get(name_index) {
                 Some(namestr) => namestr.to_string(),
-                None => format!("unknown_{}", next_brother),
-            };
-            ret.insert(name, next_brother);
-        } else {
-            break;
-        }
+                None => format!("unknown_{}", brother.ptr),
+            },
+            super_object: brother.ptr,
+            position: match bytes.len() {
+                12 => Some(std::array::from_fn(|i| f32::from_le_bytes([bytes[4*i], bytes[4*i + 1], bytes[4*i + 2], bytes[4*i + 3]]))),
+                _ => None,
+            },
+        })
+        .collect())
+}
 
-        next_brother = match get_pointer_path(r2pid, next_brother + 0x14, None) {
-            Ok(ptr) => ptr,
-            Err(_) => {break;},
+/// A super-object in the engine hierarchy, along with everything under it, as found by
+/// [`walk_hierarchy()`](fn.walk_hierarchy.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyNode {
+    /// The name of the super-object (or `unknown_<pointer>` if it isn't an actor, or isn't in the
+    /// names table).
+    pub name: String,
+    /// Pointer to the super-object in Rayman 2's memory.
+    pub ptr: usize,
+    /// The name of the AI Model, if it's an actor.
+    pub ai_model: Option<String>,
+    /// The name of the family, if it's an actor.
+    pub family: Option<String>,
+    /// The children of the super-object, in order.
+    pub children: Vec<HierarchyNode>,
+}
+
+impl HierarchyNode {
+    /// Go through this node and everything under it, depth-first.
+    pub fn iter(&self) -> impl Iterator<Item = &HierarchyNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+}
+
+/// The deepest [`walk_hierarchy()`](fn.walk_hierarchy.html) will go, in case the hierarchy is
+/// garbage (e.g. while a level is loading).
+const MAX_HIERARCHY_DEPTH: usize = 64;
+
+/// Walk the engine hierarchy of the Rayman 2 process given by `r2pid`, starting from the `root`
+/// super-object (or the root of the given `world` if that is set to 0), and following the children of
+/// every super-object, not just the brothers.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to pass the tables returned by [`read_object_types()`](fn.read_object_types.html),
+///   to look up the names.
+///
+/// ## Returns:
+/// * On success, returns the [`HierarchyNode`](struct.HierarchyNode.html) for `root`, with
+///   everything under it.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err(Display, level = "debug"),
+                                                   fields(pid = %r2pid, world = ?world, root = tracing::field::Empty, nodes = tracing::field::Empty)))]
+pub fn walk_hierarchy(r2pid: Pid, object_types: &[Vec<String>; 3], world: World, root: usize) -> Result<HierarchyNode> {
+    let root = match root {
+        0 => world.root(r2pid)?,
+        val => val,
+    };
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("root", format_args!("{:#x}", root));
+    let first_child = match read_prims::<u32>(r2pid, root + 8, 1) {
+        Ok(vec) if !vec.is_empty() => vec[0] as usize,
+        Ok(_) => 0,
+        Err(err) => {return Err(err.context("Couldn't read hierarchy root"));},
+    };
+
+    let mut root_node = match walk_brothers(r2pid, object_types, root, 0)?.into_iter().next() {
+        Some(node) => node,
+        None => {return Err("Couldn't read hierarchy root".into());},
+    };
+    // Only the root itself, not its brothers.
+    root_node.children = match first_child {
+        0 => Vec::new(),
+        first_child => walk_brothers(r2pid, object_types, first_child, 1)?,
+    };
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("nodes", root_node.iter().count());
+    Ok(root_node)
+}
+
+/// Find all the actors in the engine hierarchy of the Rayman 2 process given by `r2pid` whose
+/// names match `pattern`, in both the active and the inactive dynamic worlds.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to pass the tables returned by [`read_object_types()`](fn.read_object_types.html),
+///   to look up the names.
+///
+/// ## Returns:
+/// * On success, returns a `Vec` of the
+///   [`SuperObjectMatch`](../search/struct.SuperObjectMatch.html)es, in the order they appear in
+///   the hierarchy (active world first).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn find_super_objects(r2pid: Pid, object_types: &[Vec<String>; 3], pattern: &NamePattern) -> Result<Vec<SuperObjectMatch>> {
+    let mut ret = Vec::new();
+    for &world in &[World::ActiveDynamic, World::InactiveDynamic] {
+        let root = walk_hierarchy(r2pid, object_types, world, 0)?;
+        ret.extend(root.iter()
+            .filter(|node| node.ai_model.is_some() && pattern.matches(&node.name))
+            .map(|node| SuperObjectMatch{
+                name: node.name.clone(),
+                ptr: node.ptr,
+                ai_model: node.ai_model.clone(),
+                family: node.family.clone(),
+                world,
+            }));
+    }
+    Ok(ret)
+}
+
+// Make nodes for the brothers starting at `first`, and everything under them.
+fn walk_brothers(r2pid: Pid, object_types: &[Vec<String>; 3], first: usize, depth: usize) -> Result<Vec<HierarchyNode>> {
+    let brothers = read_brothers(r2pid, first)?;
+    let types = read_type_indices(r2pid, &brothers)?;
+
+    let mut ret = Vec::with_capacity(brothers.len());
+    for (brother, type_indices) in brothers.iter().zip(types) {
+        let (name, ai_model, family) = match type_indices {
+            Some([family_index, model_index, name_index]) if brother.kind == openspace::RAYMAN2.super_object_type_perso => (
+                object_types[2].get(name_index).cloned(),
+                Some(object_types[1].get(model_index).cloned().unwrap_or_else(|| format!("unknown_{}", brother.ptr))),
+                Some(object_types[0].get(family_index).cloned().unwrap_or_else(|| format!("unknown_{}", brother.ptr))),
+            ),
+            _ => (None, None, None),
+        };
+        let children = match brother.first_child {
+            0 => Vec::new(),
+            _ if depth >= MAX_HIERARCHY_DEPTH => Vec::new(),
+            first_child => walk_brothers(r2pid, object_types, first_child, depth + 1)?,
         };
+        ret.push(HierarchyNode{
+            name: name.unwrap_or_else(|| format!("unknown_{}", brother.ptr)),
+            ptr: brother.ptr,
+            ai_model,
+            family,
+            children,
+        });
     }
 
     Ok(ret)
 }
 
+/// Get the super-object a walker should start from: `super_object`, or the first super-object in
+/// `world` if that is 0.
+fn start_super_object(r2pid: Pid, world: World, super_object: usize) -> Result<usize> {
+    match super_object {
+        0 => world.first_super_object(r2pid),
+        val => Ok(val),
+    }
+}
+
+/// Walk the brothers starting from `super_object`, reading each one's header - see
+/// [`openspace::read_brothers()`](../openspace/fn.read_brothers.html).
+fn read_brothers(r2pid: Pid, super_object: usize) -> Result<Vec<SuperObjectHeader>> {
+    openspace::read_brothers(r2pid, &openspace::RAYMAN2, super_object)
+}
+
+/// Read the family, AI Model and super-object name indices of all the `brothers` at once - see
+/// [`openspace::read_object_indices()`](../openspace/fn.read_object_indices.html).
+fn read_type_indices(r2pid: Pid, brothers: &[SuperObjectHeader]) -> Result<Vec<Option<[usize; 3]>>> {
+    openspace::read_object_indices(r2pid, &openspace::RAYMAN2, brothers)
+}
+
 /// Get the names of AI Models and lists of memory locations of all corresponding active super-objects
 /// in the engine hierarchy of the Rayman 2 process given by `r2pid`, starting from a given
-/// `super_object` pointer (or the dynamic world itself if that is set to 0).
+/// `super_object` pointer (or the start of the given `world` if that is set to 0).
 ///
 /// ## Requirements:
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 /// * You need to know the list of AI Model names in the hierarchy and pass it via the argument
-/// `object_names`. This list can be obtained with
-/// [`read_object_types()`](fn.read_object_types.html)`.unwrap()[1]`.
+///   `object_names`. This list can be obtained with
+///   [`read_object_types()`](fn.read_object_types.html)`.unwrap()[1]`.
 ///
 /// ## Returns:
 /// * On success, returns a
-/// [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
+///   [`HashMap`](https://doc.rust-lang.org/std/collections/struct.HashMap.html).
 ///     * The keys are the names of the AI Models.
 ///     * The values are vectors of pointers to the corresponding super-objects in Rayman 2's memory.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_active_super_object_ai_model_names(r2pid: Pid, ai_model_names: &Vec<String>, super_object: usize) -> Result<HashMap<String,Vec<usize>>,String> {
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_active_super_object_ai_model_names(r2pid: Pid, ai_model_names: &[String], world: World, super_object: usize) -> Result<HashMap<String,Vec<usize>>> {
     let mut ret: HashMap<String,Vec<usize>> = HashMap::new();
-    let super_object = match super_object {
-        0 => {
-            let off_dynam_world = 0x500FD0;
-            match get_pointer_path(r2pid, off_dynam_world, Some(&vec![8])) {
-                Ok(ptr) => ptr,
-                Err(err) => {return Err(format!("Couldn't get super-object for dynamic world: {:?}", err));},
-            }
-        },
-        val => val,
-    };
+    let super_object = start_super_object(r2pid, world, super_object)?;
 
     let mut next_brother = super_object;
 
     loop {
         if next_brother != 0 {
-            let name_index = match get_pointer_path(r2pid, next_brother + 4, Some(&vec![4, 4])) {
-                Ok(ptr) => ptr,
+            let name = match get_ai_model_name(r2pid, next_brother, ai_model_names) {
+                Ok(name) => name,
                 Err(_) => {break;},
             };
-            let name = match ai_model_names.get(name_index) {
-                Some(namestr) => namestr.to_string(),
-                None => format!("unknown_{}", next_brother),
-            };
-            if ret.contains_key(&name) {
-                ret.get_mut(&name).unwrap().push(next_brother);
-            } else {
-                ret.insert(name, vec![next_brother]);
-            }
+            ret.entry(name).or_default().push(next_brother);
         } else {
             break;
         }
@@ -452,6 +986,51 @@ pub fn get_active_super_object_ai_model_names(r2pid: Pid, ai_model_names: &Vec<S
     Ok(ret)
 }
 
+/// Find the global script object of the current level (usually called `global`) in the engine
+/// hierarchy of the Rayman 2 process given by `r2pid`, which holds things like the countdown in
+/// the Walk of Life.
+///
+/// ## Details:
+/// * If there is an active super-object called `global` (in any case), that's the one.
+/// * Otherwise, the first super-object whose AI Model has `global` in its name is used.
+/// * Failing that, the super-object whose AI Model has the most DSG variables is used, since the
+///   global object keeps track of much more than any actor does.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to pass the tables returned by [`read_object_types()`](fn.read_object_types.html).
+///
+/// ## Returns:
+/// * On success, returns the name of the global object, and a pointer to it.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails, or if no candidate could be found at all.
+pub fn find_global_object(r2pid: Pid, object_types: &[Vec<String>; 3]) -> Result<(String, usize)> {
+    let active_super_objects = get_active_super_object_names(r2pid, &object_types[2], World::ActiveDynamic, 0)?;
+    if let Some((name, &super_object)) = active_super_objects.iter().find(|(name, _)| name.eq_ignore_ascii_case("global")) {
+        return Ok((name.to_string(), super_object));
+    }
+
+    // Look at the objects in a fixed order, so that the same one is found every time.
+    let mut candidates: Vec<(&String, usize)> = active_super_objects.iter().map(|(name, &ptr)| (name, ptr)).collect();
+    candidates.sort();
+    let ai_model_names: Vec<Option<String>> = candidates.iter()
+        .map(|&(_, super_object)| get_ai_model_name(r2pid, super_object, &object_types[1]).ok())
+        .collect();
+
+    if let Some(((name, super_object), _)) = candidates.iter().zip(ai_model_names.iter())
+        .find(|(_, model)| model.as_ref().is_some_and(|model| model.to_lowercase().contains("global"))) {
+        return Ok((name.to_string(), *super_object));
+    }
+
+    // Plenty of objects have no mind or no DSG memory - they just don't count.
+    match candidates.iter()
+        .filter_map(|&(name, super_object)| Some((read_dsg_mem_layout(r2pid, super_object).ok()?.vars.len(), name, super_object)))
+        .max_by_key(|&(num_vars, _, _)| num_vars) {
+        Some((_, name, super_object)) => Ok((name.to_string(), super_object)),
+        None => Err("Couldn't find the global object".into()),
+    }
+}
+
 /// Get a pointer to the mind object of the given `super_object`
 /// in the Rayman 2 process given by `r2pid`.
 ///
@@ -461,13 +1040,10 @@ pub fn get_active_super_object_ai_model_names(r2pid: Pid, ai_model_names: &Vec<S
 ///
 /// ## Returns:
 /// * On success, returns a pointer to the mind object for the given super-object.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_mind(r2pid: Pid, super_object: usize) -> Result<usize, String> {
-    match get_pointer_path(r2pid, super_object + 4, Some(&vec![0xC, 0])) {
-        Ok(ptr) => Ok(ptr),
-        Err(err) => Err(format!("Unable to get Mind: {:?}", err)),
-    }
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_mind(r2pid: Pid, super_object: usize) -> Result<usize> {
+    Ok(SuperObjectPtr(super_object).mind(r2pid)?.0)
 }
 
 /// Get the index of the currently-active behaviour (comport) on the given `super_object`
@@ -479,14 +1055,42 @@ pub fn get_mind(r2pid: Pid, super_object: usize) -> Result<usize, String> {
 ///
 /// ## Returns:
 /// * On success, returns the index of the active comport.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_active_normal_behaviour(r2pid: Pid, super_object: usize) -> Result<usize, String> {
-    let off_mind = get_mind(r2pid, super_object)?;
-    match get_pointer_path(r2pid, off_mind + 4, Some(&vec![0x8])) {
-        Ok(ptr) => Ok(ptr),
-        Err(err) => Err(format!("Unable to get Active Normal Behaviour: {:?}", err)),
-    }
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_active_normal_behaviour(r2pid: Pid, super_object: usize) -> Result<usize> {
+    SuperObject::new(r2pid, super_object).active_normal_behaviour()
+}
+
+/// Switch the currently-active behaviour (comport) on the given `super_object` in the Rayman 2
+/// process given by `r2pid`, e.g. to reset an object's logic.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+/// * `behaviour` is an index or a name (see [`BehaviourId`](../aimodel/enum.BehaviourId.html)),
+///   e.g. `0.into()` or `"GRP_TimerCourse#0".into()`.
+/// * `ai_model_names` is the second table returned by
+///   [`read_object_types()`](fn.read_object_types.html).
+/// * Names are looked up in `names`, if given (see
+///   [`BehaviourNames`](../aimodel/struct.BehaviourNames.html)).
+///
+/// ## Returns:
+/// * On success, returns the behaviour which is now active.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the AI Model has no such behaviour, or if the memory read or write fails.
+///
+/// ## Details:
+/// * Only the current comport is changed: the object's rules start running from the top of the
+///   new one on its next frame, but anything the old one had set up (e.g. DSG variables) is left
+///   as it was.
+pub fn set_active_normal_behaviour(r2pid: Pid, super_object: usize, behaviour: BehaviourId, ai_model_names: &[String], names: Option<&BehaviourNames>) -> Result<Behaviour> {
+    let model = AiModel::read(r2pid, super_object, ai_model_names)?;
+    let behaviour = match model.find_normal_behaviour(behaviour, names) {
+        Some(found) => found,
+        None => {return Err(format!("{} has no behaviour {:?}", model.name, behaviour).into());},
+    };
+    SuperObject::new(r2pid, super_object).set_active_normal_behaviour(behaviour.ptr)?;
+    Ok(behaviour)
 }
 
 /// Get a pointer to a certain DSG variable on the given `super_object`
@@ -495,19 +1099,92 @@ pub fn get_active_normal_behaviour(r2pid: Pid, super_object: usize) -> Result<us
 /// ## Requirements:
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 /// * You need to give a pointer to a valid super-object.
-/// * You need to know the `offset` of the DSG variable you want (can't currently address DSG
-/// variables by index). You can find this in Raymap by clicking "Print DsgVar from Mind->DsgMem"
-/// under the "Perso Behaviour" component of the object you're interested in.
+/// * You need to know the `offset` of the DSG variable you want (to address DSG variables by
+///   index instead, use [`get_dsg_var_by_index()`](fn.get_dsg_var_by_index.html)). You can find
+///   this in Raymap by clicking "Print DsgVar from Mind->DsgMem" under the "Perso Behaviour"
+///   component of the object you're interested in.
 ///
 /// ## Returns:
 /// * On success, returns a pointer to the desired DSG variable.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_dsg_var_ptr(r2pid: Pid, super_object: usize, offset: usize) -> Result<usize, String> {
-    let off_mind = get_mind(r2pid, super_object)?;
-    match get_pointer_path(r2pid, off_mind + 0xC, Some(&vec![8])) {
-        Ok(ptr) => Ok(ptr + offset),
-        Err(err) => Err(format!("Unable to get DSG Var pointer: {:?}", err)),
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_dsg_var_ptr(r2pid: Pid, super_object: usize, offset: usize) -> Result<usize> {
+    SuperObject::new(r2pid, super_object).dsg_var_ptr(offset)
+}
+
+/// Read a DSG variable on the given `super_object` by its index, in the Rayman 2 process given by
+/// `r2pid`, decoding it according to its type.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object, which has DSG memory.
+/// * `index` is the number in the names Raymap gives the variables, e.g. `30` for `Int_30`.
+///
+/// ## Returns:
+/// * On success, returns the decoded [`DsgValue`](../dsg/enum.DsgValue.html).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails or there is no variable with the given `index`.
+pub fn get_dsg_var_by_index(r2pid: Pid, super_object: usize, index: usize) -> Result<DsgValue> {
+    let layout = read_dsg_mem_layout(r2pid, super_object)?;
+    let range = match layout.var_range(index) {
+        Some(range) => range,
+        None => {return Err(format!("No DSG variable with index {} (object has {})", index, layout.vars.len()).into());},
+    };
+
+    match read_prims::<u8>(r2pid, layout.buffer + range.start, range.len()) {
+        Ok(bytes) => Ok(DsgValue::decode(layout.vars[index].var_type, &bytes)),
+        Err(err) => Err(err.context(format!("Unable to read DSG variable {}", index))),
+    }
+}
+
+/// Read every DSG variable on the given `super_object` in the Rayman 2 process given by `r2pid`,
+/// e.g. to find out which one holds a timer or a flag.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object, which has DSG memory.
+///
+/// ## Returns:
+/// * On success, returns a [`DsgVarEntry`](../dsg/struct.DsgVarEntry.html) for each variable, in
+///   index order.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn dump_dsg_mem(r2pid: Pid, super_object: usize) -> Result<Vec<DsgVarEntry>> {
+    let layout = read_dsg_mem_layout(r2pid, super_object)?;
+    match read_prims::<u8>(r2pid, layout.buffer, layout.size) {
+        Ok(buffer) => Ok(layout.decode_all(&buffer)),
+        Err(err) => Err(err.context("Unable to read DSG memory buffer")),
+    }
+}
+
+/// Write a DSG variable on the given `super_object` by its index, in the Rayman 2 process given by
+/// `r2pid`, checking that `value` is of the right type first.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object, which has DSG memory.
+/// * `index` is the number in the names Raymap gives the variables, e.g. `30` for `Int_30`.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if there is no variable with the given `index`, if `value` is the wrong type for it (or
+///   doesn't fit), or if the memory read or write fails.
+pub fn set_dsg_var_by_index(r2pid: Pid, super_object: usize, index: usize, value: &DsgValue) -> Result<()> {
+    let layout = read_dsg_mem_layout(r2pid, super_object)?;
+    let range = match layout.var_range(index) {
+        Some(range) => range,
+        None => {return Err(format!("No DSG variable with index {} (object has {})", index, layout.vars.len()).into());},
+    };
+    let var_type = layout.vars[index].var_type;
+    let bytes = value.encode();
+    if !value.is_type(var_type) || bytes.len() > range.len() {
+        return Err(format!("Can't write {:?} to DSG variable {} of type {:?}", value, index, var_type).into());
+    }
+
+    match write_prims(r2pid, layout.buffer + range.start, &bytes) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.context(format!("Unable to write DSG variable {}", index))),
     }
 }
 
@@ -520,13 +1197,117 @@ pub fn get_dsg_var_ptr(r2pid: Pid, super_object: usize, offset: usize) -> Result
 ///
 /// ## Returns:
 /// * On success, returns a pointer to the custom bits.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_custom_bits_ptr(r2pid: Pid, super_object: usize) -> Result<usize, String> {
-    match get_pointer_path(r2pid, super_object + 4, Some(&vec![4])) {
-        Ok(ptr) => Ok(ptr + 0x24),
-        Err(err) => Err(format!("Unable to get Custom Bits: {:?}", err)),
-    }
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_custom_bits_ptr(r2pid: Pid, super_object: usize) -> Result<usize> {
+    SuperObject::new(r2pid, super_object).custom_bits()
+}
+
+/// Read the transform (position, rotation and scale) of the given `super_object` in the Rayman 2
+/// process given by `r2pid`, as a 4x4 matrix.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns the [`Mat4`](../superobject/type.Mat4.html) (row by row, with the
+///   translation in the last column).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the super-object has no global matrix, or if the memory read fails.
+pub fn get_so_matrix(r2pid: Pid, super_object: usize) -> Result<Mat4> {
+    SuperObject::new(r2pid, super_object).matrix()
+}
+
+/// Overwrite the transform of the given `super_object` in the Rayman 2 process given by `r2pid`
+/// with a 4x4 `matrix` - see
+/// [`SuperObject::set_matrix()`](../superobject/struct.SuperObject.html#method.set_matrix).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the super-object has no global matrix, or if the memory write fails.
+pub fn set_so_matrix(r2pid: Pid, super_object: usize, matrix: &Mat4) -> Result<()> {
+    SuperObject::new(r2pid, super_object).set_matrix(matrix)
+}
+
+/// Read the translation (i.e. the position: x, y, z) of the given `super_object` in the Rayman 2
+/// process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns the position.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the super-object has no global matrix, or if the memory read fails.
+pub fn get_so_translation(r2pid: Pid, super_object: usize) -> Result<[f32; 3]> {
+    SuperObject::new(r2pid, super_object).position()
+}
+
+/// Move the given `super_object` in the Rayman 2 process given by `r2pid` to `position`, leaving
+/// its rotation and scale alone (e.g. to teleport it).
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the super-object has no global matrix, or if the memory write fails.
+pub fn set_so_translation(r2pid: Pid, super_object: usize, position: [f32; 3]) -> Result<()> {
+    SuperObject::new(r2pid, super_object).set_position(position)
+}
+
+/// Get the distance between the positions of two super-objects, `from` and `to`, in the Rayman 2
+/// process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give pointers to valid super-objects.
+///
+/// ## Returns:
+/// * On success, returns the distance.
+/// * Returns an `Err` variant describing what went wrong,
+///   if either super-object has no global matrix, or if the memory read fails.
+pub fn get_so_distance(r2pid: Pid, from: usize, to: usize) -> Result<f32> {
+    Ok(geometry::distance(get_so_translation(r2pid, from)?, get_so_translation(r2pid, to)?))
+}
+
+/// Read the custom bits of the given `super_object` in the Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns the custom bits - check them with
+///   [`CustomBit::is_set()`](../superobject/struct.CustomBit.html#method.is_set).
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_custom_bits(r2pid: Pid, super_object: usize) -> Result<u32> {
+    SuperObject::new(r2pid, super_object).read_custom_bits()
+}
+
+/// Set (if `value` is `true`) or clear one of the custom bits of the given `super_object` in the
+/// Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+///
+/// ## Returns:
+/// * On success, returns `Ok(())`.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read or write fails.
+pub fn set_custom_bit(r2pid: Pid, super_object: usize, bit: CustomBit, value: bool) -> Result<()> {
+    SuperObject::new(r2pid, super_object).set_custom_bit(bit, value)
 }
 
 /// Get a pointer to the AI Model used by the given `super_object`
@@ -538,15 +1319,10 @@ pub fn get_custom_bits_ptr(r2pid: Pid, super_object: usize) -> Result<usize, Str
 ///
 /// ## Returns:
 /// * On success, returns a pointer to the AI Model.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_ai_model(r2pid: Pid, super_object: usize) -> Result<usize, String> {
-    let off_mind = get_mind(r2pid, super_object)?;
-    //match get_pointer_path(r2pid, super_object + 4, Some(&vec![0xC, 0, 0])) {
-    match get_pointer_path(r2pid, off_mind, None) {
-        Ok(ptr) => Ok(ptr),
-        Err(err) => Err(format!("Unable to get AI Model pointer: {:?}", err)),
-    }
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_ai_model(r2pid: Pid, super_object: usize) -> Result<usize> {
+    Ok(SuperObject::new(r2pid, super_object).ai_model()?.0)
 }
 
 /// Get a pointer to the vector of normal behaviours (comports) in the AI Model used by the given `super_object`
@@ -558,34 +1334,52 @@ pub fn get_ai_model(r2pid: Pid, super_object: usize) -> Result<usize, String> {
 ///
 /// ## Returns:
 /// * On success, returns a pointer to the vector of normal behaviours.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_ai_model_normal_behaviours_ptr(r2pid: Pid, super_object: usize) -> Result<usize, String> {
-    let ai_model = get_ai_model(r2pid, super_object)?;
-    match get_pointer_path(r2pid, ai_model, None) {
-        Ok(ptr) => Ok(ptr),
-        Err(err) => Err(format!("Unable to get AI Model Normal Behaviours pointer: {:?}", err)),
-    }
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_ai_model_normal_behaviours_ptr(r2pid: Pid, super_object: usize) -> Result<usize> {
+    SuperObject::new(r2pid, super_object).ai_model()?.normal_behaviours_ptr(r2pid)
 }
 
-/// Get a list of pointers to the normal behaviours (comports) in the AI Model used by the given `super_object`
+/// Get a list of the normal behaviours (comports) in the AI Model used by the given `super_object`
 /// in the Rayman 2 process given by `r2pid`.
 ///
 /// ## Requirements:
 /// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
 /// * You need to give a pointer to a valid super-object.
+/// * `ai_model_names` is the second table returned by
+///   [`read_object_types()`](fn.read_object_types.html).
+/// * The behaviours are named from `names`, if given (see
+///   [`BehaviourNames`](../aimodel/struct.BehaviourNames.html)).
 ///
 /// ## Returns:
-/// * On success, returns a `Vec<usize>` of pointers to the normal behaviours.
-/// * Returns an `Err` variant with a text description of what went wrong,
-/// if the memory read fails.
-pub fn get_ai_model_normal_behaviours_list(r2pid: Pid, super_object: usize) -> Result<Vec<usize>, String> {
-    let offset = get_ai_model_normal_behaviours_ptr(r2pid, super_object)?;
-    let (off_first_entry, num_entries) = match read_prims::<u32>(r2pid, offset, 2) {
-        Ok(vec) => (vec[0] as usize, vec[1] as usize),
-        Err(err) => {return Err(format!("Unable to get entries in AI Model Normal Behaviours List: {:?}", err));},
-    };
+/// * On success, returns a `Vec` of [`Behaviour`](../aimodel/struct.Behaviour.html)s, in index
+///   order.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails.
+pub fn get_ai_model_normal_behaviours_list(r2pid: Pid, super_object: usize, ai_model_names: &[String], names: Option<&BehaviourNames>) -> Result<Vec<Behaviour>> {
+    Ok(AiModel::read(r2pid, super_object, ai_model_names)?.normal_behaviour_list(names))
+}
 
-    // Each entry takes up 12 bytes.
-    Ok((0..num_entries).map(|i| off_first_entry + 12*i).collect())
+/// Get the name of the currently-active behaviour (comport) on the given `super_object` in the
+/// Rayman 2 process given by `r2pid`.
+///
+/// ## Requirements:
+/// * We need to have permissions to debug `pid` (e.g. with `CAP_SYS_PTRACE`).
+/// * You need to give a pointer to a valid super-object.
+/// * `ai_model_names` is the second table returned by
+///   [`read_object_types()`](fn.read_object_types.html).
+/// * The behaviour is named from `names`, if given (see
+///   [`BehaviourNames`](../aimodel/struct.BehaviourNames.html)).
+///
+/// ## Returns:
+/// * On success, returns the name of the active comport.
+/// * Returns an `Err` variant describing what went wrong,
+///   if the memory read fails, or if the active comport isn't one of the AI Model's.
+pub fn get_active_normal_behaviour_name(r2pid: Pid, super_object: usize, ai_model_names: &[String], names: Option<&BehaviourNames>) -> Result<String> {
+    let model = AiModel::read(r2pid, super_object, ai_model_names)?;
+    let active = get_active_normal_behaviour(r2pid, super_object)?;
+    match model.active_normal_behaviour(active, names) {
+        Some(behaviour) => Ok(behaviour.name),
+        None => Err(format!("Active behaviour {:#x} isn't one of {}'s", active, model.name).into()),
+    }
 }
@@ -0,0 +1,639 @@
+/*!
+  Background watchers which poll Rayman 2's memory, so that tools can react to things changing
+  in the game without writing their own polling loops.
+  */
+
+extern crate nix;
+
+use std::{fmt,thread,time::Duration,collections::VecDeque,sync::{Arc,Mutex,atomic::{AtomicBool,Ordering},mpsc::{channel,Receiver}}};
+use nix::unistd::Pid;
+#[cfg(any(feature = "metrics", feature = "server"))]
+use std::net::{TcpListener,TcpStream,ToSocketAddrs};
+#[cfg(any(feature = "metrics", feature = "server"))]
+use crate::error::Error;
+use crate::{memory::read_prims,utils::{get_current_level_name,find_attach_rayman2,get_engine_mode,get_delta_t,get_main_character},error::Result};
+
+// A background polling thread, which can be asked to stop.
+pub(crate) struct Poller {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Poller {
+    // Call `tick` every `interval` until asked to stop, or until it fails.
+    pub(crate) fn spawn<F>(interval: Duration, mut tick: F) -> Poller
+        where F: FnMut() -> Result<()> + Send + 'static {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let result = tick();
+                #[cfg(feature = "tracing")]
+                if let Err(err) = &result {
+                    tracing::debug!(error = %err, "Watcher stopped");
+                }
+                result?;
+                thread::sleep(interval);
+            }
+            Ok(())
+        });
+
+        Poller{stop, handle: Some(handle)}
+    }
+
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.handle.take() {
+            Some(handle) => match handle.join() {
+                Ok(result) => result,
+                Err(_) => Err("Watcher thread panicked".into()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// How often `serve()` checks for new connections.
+#[cfg(any(feature = "metrics", feature = "server"))]
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+// Listen on `address`, and hand each connection to `handle` on a thread of its own, so that a slow
+// client can't hold up the others (or the accepting). `what` describes the clients, for the error
+// if the address can't be listened on. Accepting stops when the `Poller` is stopped or dropped.
+#[cfg(any(feature = "metrics", feature = "server"))]
+pub(crate) fn serve<A, F>(address: A, what: &str, handle: F) -> Result<Poller>
+    where A: ToSocketAddrs, F: Fn(TcpStream) + Send + Sync + 'static {
+    let listener = match TcpListener::bind(address).and_then(|listener| listener.set_nonblocking(true).map(|_| listener)) {
+        Ok(listener) => listener,
+        Err(err) => {return Err(Error::from(err).context(format!("Couldn't listen for {}", what)));},
+    };
+
+    let handle = Arc::new(handle);
+    Ok(Poller::spawn(ACCEPT_INTERVAL, move || {
+        while let Ok((stream, _)) = listener.accept() {
+            let handle = handle.clone();
+            // A client going wrong shouldn't stop the server.
+            thread::spawn(move || {
+                if stream.set_nonblocking(false).is_ok() {
+                    handle(stream);
+                }
+            });
+        }
+        Ok(())
+    }))
+}
+
+/// Something that happened to the current level, as seen by a
+/// [`LevelWatcher`](struct.LevelWatcher.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelEvent {
+    /// A level with the given name has been entered (or was already open when watching started).
+    LevelEntered(String),
+    /// The level with the given name has been left.
+    LevelExited(String),
+    /// The game went away and a new Rayman 2 process, with the given PID, has been attached to
+    /// (see [`set_reattach()`](struct.LevelWatcher.html#method.set_reattach)). The level that was
+    /// open is reported as left first.
+    NewGameInstance(Pid),
+}
+
+/// A background thread which polls the name of the current level in a Rayman 2 process, and
+/// reports when it changes.
+///
+/// Watching stops when the `LevelWatcher` is dropped, or when reading the level name fails (e.g.
+/// because the game has quit) - unless reattaching is turned on with
+/// [`set_reattach()`](#method.set_reattach).
+pub struct LevelWatcher {
+    events: Option<Receiver<LevelEvent>>,
+    reattach: Arc<AtomicBool>,
+    poller: Poller,
+}
+
+impl LevelWatcher {
+    /// Start watching the level in the Rayman 2 process given by `r2pid`, checking every
+    /// `interval`. Events are delivered over a channel - see [`events()`](#method.events).
+    pub fn spawn(r2pid: Pid, interval: Duration) -> LevelWatcher {
+        let (sender, receiver) = channel();
+        let mut ret = LevelWatcher::with_callback(r2pid, interval, move |event| {
+            // If nobody's listening any more, that's their problem...
+            let _ = sender.send(event);
+        });
+        ret.events = Some(receiver);
+        ret
+    }
+
+    /// Start watching the level in the Rayman 2 process given by `r2pid`, checking every
+    /// `interval`, and calling `callback` (on the background thread) for each event.
+    pub fn with_callback<F>(r2pid: Pid, interval: Duration, mut callback: F) -> LevelWatcher
+        where F: FnMut(LevelEvent) + Send + 'static {
+        let mut r2pid = r2pid;
+        let mut current: Option<String> = None;
+        let reattach = Arc::new(AtomicBool::new(false));
+        let thread_reattach = reattach.clone();
+
+        let poller = Poller::spawn(interval, move || {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("level_watcher_tick", pid = %r2pid, level = ?current).entered();
+            let level_name = match get_current_level_name(r2pid) {
+                Ok(name) => name,
+                Err(err) if err.is_process_gone() && thread_reattach.load(Ordering::Relaxed) => {
+                    if let Some(old_name) = current.take() {
+                        callback(LevelEvent::LevelExited(old_name));
+                    }
+                    match find_attach_rayman2() {
+                        Ok(pid) if pid != r2pid => {
+                            r2pid = pid;
+                            callback(LevelEvent::NewGameInstance(pid));
+                        },
+                        _ => {},
+                    }
+                    return Ok(());
+                },
+                Err(err) => {return Err(err);},
+            };
+            if current.as_ref() != Some(&level_name) {
+                if let Some(old_name) = current.take() {
+                    callback(LevelEvent::LevelExited(old_name));
+                }
+                callback(LevelEvent::LevelEntered(level_name.clone()));
+                current = Some(level_name);
+            }
+            Ok(())
+        });
+
+        LevelWatcher{events: None, reattach, poller}
+    }
+
+    /// Choose whether to keep watching when the game goes away, by waiting for a new Rayman 2
+    /// process and attaching to that instead. Off by default.
+    ///
+    /// ## Details:
+    /// * A [`LevelEvent::NewGameInstance`](enum.LevelEvent.html#variant.NewGameInstance) event
+    ///   marks the point where the new process was attached to.
+    pub fn set_reattach(&self, reattach: bool) {
+        self.reattach.store(reattach, Ordering::Relaxed);
+    }
+
+    /// Get the channel the events are delivered on.
+    ///
+    /// ## Details:
+    /// * The channel disconnects when the background thread stops.
+    /// * If the watcher was made with [`with_callback()`](#method.with_callback), there is no
+    ///   channel, and this returns `None`.
+    pub fn events(&self) -> Option<&Receiver<LevelEvent>> {
+        self.events.as_ref()
+    }
+
+    /// Stop watching, and wait for the background thread to finish.
+    ///
+    /// ## Returns:
+    /// * If the thread stopped because it was asked to, returns `Ok(())`.
+    /// * If it had already stopped because of an error, returns an `Err` variant with a text
+    ///   description of what went wrong.
+    pub fn stop(mut self) -> Result<()> {
+        self.poller.stop()
+    }
+}
+
+/// Something that happened to a value, as seen by a [`ValueWatcher`](struct.ValueWatcher.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueEvent<T> {
+    /// The value was read for the first time.
+    Initial(T),
+    /// The value changed from `old` to `new`.
+    Changed { old: T, new: T },
+    /// The game went away and a new Rayman 2 process, with the given PID, has been attached to
+    /// (see [`set_reattach()`](struct.ValueWatcher.html#method.set_reattach)). Events after this
+    /// one come from the new process, but are still compared with the last value from the old one.
+    NewGameInstance(Pid),
+}
+
+/// Where a [`ValueWatcher`](struct.ValueWatcher.html) should read its value from.
+pub enum AddressSource {
+    /// Always the same address (e.g. something in the engine structure).
+    Fixed(usize),
+    /// An address which has to be looked up (e.g. a DSG variable), and may move when the level is
+    /// reloaded. The closure is called again whenever the level changes or a read fails.
+    Resolver(Box<dyn FnMut(Pid) -> Result<usize> + Send>),
+}
+
+/// The unit a watched value is measured in, for displaying it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// A plain number (e.g. a count).
+    #[default]
+    None,
+    /// Seconds.
+    Seconds,
+    /// Milliseconds.
+    Milliseconds,
+    /// Metres per second.
+    MetresPerSecond,
+    /// A flag, which is shown as `true` if the value isn't zero.
+    Boolean,
+}
+
+impl Unit {
+    /// Get the symbol shown after values in this unit (e.g. `m/s`), or an empty string if there
+    /// isn't one.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::None | Unit::Boolean => "",
+            Unit::Seconds => "s",
+            Unit::Milliseconds => "ms",
+            Unit::MetresPerSecond => "m/s",
+        }
+    }
+}
+
+/// How a watched value should be displayed: its unit, and how many decimal places to show. This
+/// is kept with the watcher (see [`ValueWatcher::with_format()`](struct.ValueWatcher.html#method.with_format)),
+/// so that everything showing the value shows it the same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ValueFormat {
+    /// The unit the value is measured in.
+    pub unit: Unit,
+    /// The number of decimal places to show.
+    pub precision: usize,
+}
+
+impl ValueFormat {
+    /// Make a format for values in `unit`, with `precision` decimal places.
+    pub const fn new(unit: Unit, precision: usize) -> ValueFormat {
+        ValueFormat{unit, precision}
+    }
+
+    /// Format `value`, followed by the unit's symbol (if it has one).
+    pub fn format(&self, value: f64) -> String {
+        match self.unit {
+            Unit::Boolean => (value != 0.0).to_string(),
+            Unit::None => format!("{:.*}", self.precision, value),
+            unit => format!("{:.*} {}", self.precision, value, unit.symbol()),
+        }
+    }
+
+    /// Make a heading for a value called `name` (e.g. for a column in a table), with the unit's
+    /// symbol in brackets (if it has one).
+    pub fn header(&self, name: &str) -> String {
+        match self.unit.symbol() {
+            "" => name.to_string(),
+            symbol => format!("{} ({})", name, symbol),
+        }
+    }
+}
+
+/// A background thread which polls a value in a Rayman 2 process, and reports when it changes.
+///
+/// Watching stops when the `ValueWatcher` is dropped, or when the process can no longer be read
+/// at all (e.g. because the game has quit) - unless reattaching is turned on with
+/// [`set_reattach()`](#method.set_reattach). Failing to resolve or read the value itself isn't
+/// fatal, since that happens routinely while levels are loading.
+pub struct ValueWatcher<T> {
+    events: Option<Receiver<ValueEvent<T>>>,
+    last: Arc<Mutex<Option<T>>>,
+    reattach: Arc<AtomicBool>,
+    format: ValueFormat,
+    poller: Poller,
+}
+
+impl<T: Copy + PartialEq + Send + 'static> ValueWatcher<T> {
+    /// Start watching the value at `address` in the Rayman 2 process given by `r2pid`, checking
+    /// every `interval`. Events are delivered over a channel - see [`events()`](#method.events).
+    pub fn at(r2pid: Pid, address: usize, interval: Duration) -> ValueWatcher<T> {
+        ValueWatcher::spawn(r2pid, AddressSource::Fixed(address), interval)
+    }
+
+    /// Start watching the value whose address is given by `resolver` in the Rayman 2 process given
+    /// by `r2pid`, checking every `interval`. Events are delivered over a channel - see
+    /// [`events()`](#method.events).
+    pub fn resolved<F>(r2pid: Pid, resolver: F, interval: Duration) -> ValueWatcher<T>
+        where F: FnMut(Pid) -> Result<usize> + Send + 'static {
+        ValueWatcher::spawn(r2pid, AddressSource::Resolver(Box::new(resolver)), interval)
+    }
+
+    /// Start watching the value at `source` in the Rayman 2 process given by `r2pid`, checking
+    /// every `interval`. Events are delivered over a channel - see [`events()`](#method.events).
+    pub fn spawn(r2pid: Pid, source: AddressSource, interval: Duration) -> ValueWatcher<T> {
+        let (sender, receiver) = channel();
+        let mut ret = ValueWatcher::with_callback(r2pid, source, interval, move |event| {
+            let _ = sender.send(event);
+        });
+        ret.events = Some(receiver);
+        ret
+    }
+
+    /// Start watching the value at `source` in the Rayman 2 process given by `r2pid`, checking
+    /// every `interval`, and calling `callback` (on the background thread) for each event.
+    pub fn with_callback<F>(r2pid: Pid, mut source: AddressSource, interval: Duration, mut callback: F) -> ValueWatcher<T>
+        where F: FnMut(ValueEvent<T>) + Send + 'static {
+        let mut r2pid = r2pid;
+        let mut level: Option<String> = None;
+        let mut address: Option<usize> = None;
+        let last = Arc::new(Mutex::new(None));
+        let thread_last = last.clone();
+        let reattach = Arc::new(AtomicBool::new(false));
+        let thread_reattach = reattach.clone();
+
+        let poller = Poller::spawn(interval, move || {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("value_watcher_tick", pid = %r2pid, level = ?level,
+                                             address = address.map(|address| format!("{:#x}", address))).entered();
+            // If we can't even read the level name, the process is probably gone.
+            let level_name = match get_current_level_name(r2pid) {
+                Ok(name) => name,
+                Err(err) if err.is_process_gone() && thread_reattach.load(Ordering::Relaxed) => {
+                    // Wait for the game to come back, keeping the last value we saw.
+                    match find_attach_rayman2() {
+                        Ok(pid) if pid != r2pid => {
+                            r2pid = pid;
+                            level = None;
+                            address = None;
+                            callback(ValueEvent::NewGameInstance(pid));
+                        },
+                        _ => {},
+                    }
+                    return Ok(());
+                },
+                Err(err) => {return Err(err);},
+            };
+            if level.as_ref() != Some(&level_name) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(from = ?level, to = %level_name, "Level changed, so resolving the address again");
+                address = None;
+                level = Some(level_name);
+            }
+
+            if address.is_none() {
+                address = match &mut source {
+                    AddressSource::Fixed(address) => Some(*address),
+                    AddressSource::Resolver(resolver) => {
+                        let resolved = resolver(r2pid);
+                        #[cfg(feature = "tracing")]
+                        if let Err(err) = &resolved {
+                            tracing::debug!(error = %err, "Couldn't resolve the address");
+                        }
+                        resolved.ok()
+                    },
+                };
+            }
+
+            if let Some(off_value) = address {
+                match read_prims::<T>(r2pid, off_value, 1) {
+                    Ok(ref vec) if !vec.is_empty() => {
+                        let new = vec[0];
+                        let mut last = thread_last.lock().unwrap();
+                        match *last {
+                            None => callback(ValueEvent::Initial(new)),
+                            Some(old) if old != new => callback(ValueEvent::Changed{old, new}),
+                            _ => {},
+                        }
+                        *last = Some(new);
+                    },
+                    _ => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(address = format_args!("{:#x}", off_value), "Couldn't read the value, so resolving the address again");
+                        address = None; // Try resolving it again next time.
+                    },
+                }
+            }
+            Ok(())
+        });
+
+        ValueWatcher{events: None, last, reattach, format: ValueFormat::default(), poller}
+    }
+
+    /// Choose whether to keep watching when the game goes away, by waiting for a new Rayman 2
+    /// process and attaching to that instead. Off by default.
+    ///
+    /// ## Details:
+    /// * A [`ValueEvent::NewGameInstance`](enum.ValueEvent.html#variant.NewGameInstance) event
+    ///   marks the point where the new process was attached to.
+    /// * The last value read from the old process is kept, so the first value from the new one
+    ///   is reported as a change from it (if it's different), rather than as a new initial value.
+    /// * An `AddressSource::Resolver` is called again with the new PID.
+    pub fn set_reattach(&self, reattach: bool) {
+        self.reattach.store(reattach, Ordering::Relaxed);
+    }
+
+    /// Get the last value read, if any. This is kept after the watcher stops, and across
+    /// reattaching to a new process.
+    pub fn last_value(&self) -> Option<T> {
+        *self.last.lock().unwrap()
+    }
+
+    /// Set how the value should be displayed. By default, it's a plain number with no decimal
+    /// places.
+    pub fn with_format(mut self, format: ValueFormat) -> ValueWatcher<T> {
+        self.format = format;
+        self
+    }
+
+    /// Get how the value should be displayed - see [`with_format()`](#method.with_format).
+    pub fn format(&self) -> ValueFormat {
+        self.format
+    }
+
+    /// Get the last value read, if any, formatted as given to [`with_format()`](#method.with_format).
+    pub fn display_last_value(&self) -> Option<String>
+        where T: Into<f64> {
+        self.last_value().map(|value| self.format.format(value.into()))
+    }
+
+    /// Get the channel the events are delivered on.
+    ///
+    /// ## Details:
+    /// * The channel disconnects when the background thread stops.
+    /// * If the watcher was made with [`with_callback()`](#method.with_callback), there is no
+    ///   channel, and this returns `None`.
+    pub fn events(&self) -> Option<&Receiver<ValueEvent<T>>> {
+        self.events.as_ref()
+    }
+
+    /// Stop watching, and wait for the background thread to finish.
+    ///
+    /// ## Returns:
+    /// * If the thread stopped because it was asked to, returns `Ok(())`.
+    /// * If it had already stopped because of an error, returns an `Err` variant with a text
+    ///   description of what went wrong.
+    pub fn stop(mut self) -> Result<()> {
+        self.poller.stop()
+    }
+}
+
+/// Settings for a [`FrameSpikeWatcher`](struct.FrameSpikeWatcher.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSpikeConfig {
+    /// Frames longer than this many milliseconds count as spikes.
+    pub threshold_ms: u32,
+    /// How many of the frames before a spike to report along with it.
+    pub history: usize,
+    /// How often to check the frame time. This should be well under a frame, or frames will be
+    /// missed.
+    pub interval: Duration,
+}
+
+impl Default for FrameSpikeConfig {
+    fn default() -> FrameSpikeConfig {
+        // Three frames at 60 FPS.
+        FrameSpikeConfig{threshold_ms: 50, history: 30, interval: Duration::from_millis(4)}
+    }
+}
+
+/// A frame which took longer than it should have, as seen by a
+/// [`FrameSpikeWatcher`](struct.FrameSpikeWatcher.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSpike {
+    /// The length of the frame, in milliseconds.
+    pub delta_t: u32,
+    /// The level it happened in.
+    pub level: String,
+    /// Where Rayman was at the time, if that could be read.
+    pub position: Option<[f32; 3]>,
+    /// The lengths of the frames before it, in milliseconds, oldest first.
+    pub recent: Vec<u32>,
+}
+
+impl fmt::Display for FrameSpike {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ms frame in {}", self.delta_t, self.level)?;
+        if let Some([x, y, z]) = self.position {
+            write!(f, " at ({}, {}, {})", x, y, z)?;
+        }
+        write!(f, " (previous frames: {:?} ms)", self.recent)
+    }
+}
+
+/// A summary of the frames seen by a [`FrameSpikeWatcher`](struct.FrameSpikeWatcher.html).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameSpikeSummary {
+    /// The number of frames seen. This is approximate, since several frames of exactly the same
+    /// length in a row look like one.
+    pub frames: u64,
+    /// The number of spikes.
+    pub spikes: u64,
+    /// The longest frame seen, in milliseconds.
+    pub worst_ms: u32,
+}
+
+/// A background thread which watches the frame time (`OFF_DELTA_T`) in a Rayman 2 process, and
+/// reports frames which take too long, along with what was going on at the time, to help track
+/// down stutters.
+///
+/// Frames are only counted while the level is being played (not while paused or loading).
+/// Watching stops when the `FrameSpikeWatcher` is dropped, or when the process can no longer be
+/// read.
+pub struct FrameSpikeWatcher {
+    events: Option<Receiver<FrameSpike>>,
+    summary: Arc<Mutex<FrameSpikeSummary>>,
+    poller: Poller,
+}
+
+impl FrameSpikeWatcher {
+    /// Start watching the frame time in the Rayman 2 process given by `r2pid`, with the given
+    /// `config`. Spikes are delivered over a channel - see [`events()`](#method.events).
+    pub fn spawn(r2pid: Pid, config: FrameSpikeConfig) -> FrameSpikeWatcher {
+        let (sender, receiver) = channel();
+        let mut ret = FrameSpikeWatcher::with_callback(r2pid, config, move |spike| {
+            let _ = sender.send(spike);
+        });
+        ret.events = Some(receiver);
+        ret
+    }
+
+    /// Start watching the frame time in the Rayman 2 process given by `r2pid`, with the given
+    /// `config`, and calling `callback` (on the background thread) for each spike.
+    pub fn with_callback<F>(r2pid: Pid, config: FrameSpikeConfig, mut callback: F) -> FrameSpikeWatcher
+        where F: FnMut(FrameSpike) + Send + 'static {
+        let summary = Arc::new(Mutex::new(FrameSpikeSummary::default()));
+        let thread_summary = summary.clone();
+        let mut recent: VecDeque<u32> = VecDeque::with_capacity(config.history + 1);
+
+        let poller = Poller::spawn(config.interval, move || {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("frame_spike_watcher_tick", pid = %r2pid).entered();
+            if !get_engine_mode(r2pid)?.is_playing() {
+                recent.clear();
+                return Ok(());
+            }
+            let delta_t = get_delta_t(r2pid)?;
+            // Paused, or still the same frame as last time.
+            if delta_t == 0 || recent.back() == Some(&delta_t) {
+                return Ok(());
+            }
+
+            let is_spike = delta_t > config.threshold_ms;
+            {
+                let mut summary = thread_summary.lock().unwrap();
+                summary.frames += 1;
+                summary.worst_ms = summary.worst_ms.max(delta_t);
+                if is_spike {
+                    summary.spikes += 1;
+                }
+            }
+            if is_spike {
+                callback(FrameSpike{
+                    delta_t,
+                    level: get_current_level_name(r2pid)?,
+                    position: get_main_character(r2pid).and_then(|rayman| rayman.position()).ok(),
+                    recent: recent.iter().copied().collect(),
+                });
+            }
+
+            recent.push_back(delta_t);
+            if recent.len() > config.history {
+                recent.pop_front();
+            }
+            Ok(())
+        });
+
+        FrameSpikeWatcher{events: None, summary, poller}
+    }
+
+    /// Get a summary of the frames seen so far. This is kept after the watcher stops.
+    pub fn summary(&self) -> FrameSpikeSummary {
+        *self.summary.lock().unwrap()
+    }
+
+    /// Get the channel the spikes are delivered on.
+    ///
+    /// ## Details:
+    /// * The channel disconnects when the background thread stops.
+    /// * If the watcher was made with [`with_callback()`](#method.with_callback), there is no
+    ///   channel, and this returns `None`.
+    pub fn events(&self) -> Option<&Receiver<FrameSpike>> {
+        self.events.as_ref()
+    }
+
+    /// Stop watching, and wait for the background thread to finish.
+    ///
+    /// ## Returns:
+    /// * If the thread stopped because it was asked to, returns `Ok(())`.
+    /// * If it had already stopped because of an error, returns an `Err` variant with a text
+    ///   description of what went wrong.
+    pub fn stop(mut self) -> Result<()> {
+        self.poller.stop()
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+
+    #[test]
+    fn can_format_values() {
+        let timer = ValueFormat::new(Unit::Seconds, 2);
+        assert_eq!(timer.format(1.5), "1.50 s");
+        assert_eq!(timer.header("timer"), "timer (s)");
+        assert_eq!(ValueFormat::new(Unit::MetresPerSecond, 1).format(12.34), "12.3 m/s");
+        assert_eq!(ValueFormat::new(Unit::Boolean, 0).format(1.0), "true");
+        assert_eq!(ValueFormat::default().format(30.0), "30");
+        assert_eq!(ValueFormat::default().header("countdown"), "countdown");
+    }
+}